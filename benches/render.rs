@@ -0,0 +1,45 @@
+//! Benchmarks of rendering large ANSI art, where every cell has its own colors.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use fromansi::{HtmlOptions, ansi_to_rexpaint, parse_ansi, rexpaint_to_ansi};
+use std::fmt::Write;
+use std::hint::black_box;
+
+/// Builds art `width` cells wide and `height` tall, changing colors on every cell.
+fn art(width: usize, height: usize) -> String {
+    let mut art = String::new();
+    for y in 0..height {
+        for x in 0..width {
+            let (fg, bg) = ((x * 7 + y) % 256, (x + y * 3) % 16);
+            write!(
+                &mut art,
+                "\x1b[38;5;{fg};48;5;{bg}m{}",
+                ['░', '▒', '▓', '█'][x % 4]
+            )
+            .unwrap();
+        }
+        art.push_str("\x1b[0m\n");
+    }
+    art
+}
+
+fn render(c: &mut Criterion) {
+    let art = art(160, 500);
+    let text = parse_ansi(&art);
+    let xp = ansi_to_rexpaint(&art).unwrap();
+
+    c.bench_function("to_html", |b| b.iter(|| black_box(&text).to_html()));
+    c.bench_function("to_html_compact", |b| {
+        let options = HtmlOptions {
+            compact: true,
+            ..Default::default()
+        };
+        b.iter(|| black_box(&text).to_html_with(&options));
+    });
+    c.bench_function("rexpaint_to_ansi", |b| {
+        b.iter(|| rexpaint_to_ansi(black_box(&xp)).unwrap());
+    });
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
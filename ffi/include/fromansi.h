@@ -0,0 +1,43 @@
+/*
+ * C interface to fromansi, built with `cargo build --release -p fromansi-ffi`.
+ * Link against libfromansi_ffi.so (libfromansi_ffi.dylib on macOS, fromansi_ffi.dll on
+ * Windows).
+ */
+#ifndef FROMANSI_H
+#define FROMANSI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Parsed ANSI text. */
+typedef struct FromansiText FromansiText;
+
+/*
+ * Parses `len` bytes of ANSI text. Invalid UTF-8 is replaced with U+FFFD.
+ * Returns NULL if `input` is NULL and `len` is not zero.
+ * Free the result with fromansi_text_free.
+ */
+FromansiText *fromansi_parse(const uint8_t *input, size_t len);
+
+/*
+ * Renders parsed text as a <pre> fragment, or a complete page with its CSS if
+ * `standalone` is nonzero. Returns NULL if `text` is NULL or contains a NUL
+ * character. Free the result with fromansi_string_free.
+ */
+char *fromansi_to_html(const FromansiText *text, int32_t standalone);
+
+/* Frees text returned by fromansi_parse. Does nothing if `text` is NULL. */
+void fromansi_text_free(FromansiText *text);
+
+/* Frees a string returned by fromansi. Does nothing if `string` is NULL. */
+void fromansi_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FROMANSI_H */
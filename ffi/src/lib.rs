@@ -0,0 +1,135 @@
+//! A C interface for linking fromansi into programs not written in Rust.
+//!
+//! Text is parsed into an opaque [`FromansiText`] handle and rendered into strings owned
+//! by fromansi; both must be given back to their free function. The declarations are in
+//! `include/fromansi.h`.
+//!
+//! This is its own crate so that only programs using the C interface build the shared
+//! library.
+
+use fromansi::{HtmlDocumentOptions, HtmlOptions, StyledText, parse_ansi};
+use std::ffi::{CString, c_char};
+use std::slice;
+
+/// Parsed text, handed to C callers as an opaque pointer.
+pub struct FromansiText(StyledText);
+
+/// Parses `len` bytes of ANSI text at `input`.
+///
+/// Invalid UTF-8 is replaced with U+FFFD. Returns null if `input` is null and `len` is not
+/// zero. The result must be freed with [`fromansi_text_free`].
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes, or may be null if `len` is zero.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fromansi_parse(input: *const u8, len: usize) -> *mut FromansiText {
+    let bytes = if len == 0 {
+        &[][..]
+    } else if input.is_null() {
+        return std::ptr::null_mut();
+    } else {
+        // SAFETY: the caller guarantees `input` points to `len` readable bytes
+        unsafe { slice::from_raw_parts(input, len) }
+    };
+    let text = parse_ansi(&String::from_utf8_lossy(bytes));
+    Box::into_raw(Box::new(FromansiText(text)))
+}
+
+/// Renders parsed text as HTML, as a `<pre>` fragment or, if `standalone` is nonzero, a
+/// complete page with its CSS.
+///
+/// Returns a NUL-terminated string that must be freed with [`fromansi_string_free`], or
+/// null if `text` is null or the text contains a NUL character.
+///
+/// # Safety
+///
+/// `text` must be null or a pointer returned by [`fromansi_parse`] that has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fromansi_to_html(
+    text: *const FromansiText,
+    standalone: i32,
+) -> *mut c_char {
+    // SAFETY: the caller guarantees `text` is null or a live handle from `fromansi_parse`
+    let Some(FromansiText(text)) = (unsafe { text.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    let options = HtmlOptions::default();
+    let html = if standalone == 0 {
+        text.to_html_with(&options)
+    } else {
+        text.to_html_document(&options, &HtmlDocumentOptions::default())
+    };
+    CString::new(html).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Frees text returned by [`fromansi_parse`]. Does nothing if `text` is null.
+///
+/// # Safety
+///
+/// `text` must be null or a pointer returned by [`fromansi_parse`] that has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fromansi_text_free(text: *mut FromansiText) {
+    if !text.is_null() {
+        // SAFETY: the caller guarantees `text` came from `Box::into_raw` in `fromansi_parse`
+        drop(unsafe { Box::from_raw(text) });
+    }
+}
+
+/// Frees a string returned by fromansi. Does nothing if `string` is null.
+///
+/// # Safety
+///
+/// `string` must be null or a pointer returned by a fromansi function that has not been
+/// freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fromansi_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: the caller guarantees `string` came from `CString::into_raw`
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_parse_and_render() {
+        let input = b"\x1b[31mred\x1b[0m";
+        unsafe {
+            let text = fromansi_parse(input.as_ptr(), input.len());
+            assert!(!text.is_null());
+
+            let html = fromansi_to_html(text, 0);
+            assert_eq!(
+                CStr::from_ptr(html).to_str().unwrap(),
+                "<pre><span class=\"fg1\">red</span></pre>"
+            );
+            fromansi_string_free(html);
+
+            let page = fromansi_to_html(text, 1);
+            assert!(
+                CStr::from_ptr(page)
+                    .to_bytes()
+                    .starts_with(b"<!DOCTYPE html>")
+            );
+            fromansi_string_free(page);
+            fromansi_text_free(text);
+        }
+    }
+
+    #[test]
+    fn test_null_handling() {
+        unsafe {
+            assert!(fromansi_parse(std::ptr::null(), 3).is_null());
+            let empty = fromansi_parse(std::ptr::null(), 0);
+            assert!(!empty.is_null());
+            fromansi_text_free(empty);
+            assert!(fromansi_to_html(std::ptr::null(), 0).is_null());
+            fromansi_text_free(std::ptr::null_mut());
+            fromansi_string_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{Color, Style};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// Palette indexes of the DOS text colors, which put blue where ANSI puts red.
+const DOS_TO_ANSI: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+static PCBOARD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[([0-9;]*)m|@X([0-9A-Fa-f]{2})").unwrap());
+
+static WILDCAT_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[([0-9;]*)m|@([0-9A-Fa-f]{2})@").unwrap());
+
+/// Inline color codes from BBS software, read alongside ANSI escape sequences.
+///
+/// Each code holds a DOS text attribute as two hex digits, background first: `@X1F` and
+/// `@1F@` are both bright white on blue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorCodes {
+    /// No color codes, only ANSI escape sequences.
+    #[default]
+    None,
+    /// `PCBoard` codes, such as `@X1F`.
+    PcBoard,
+    /// Wildcat! codes, such as `@1F@`.
+    Wildcat,
+}
+
+impl ColorCodes {
+    /// Returns a regex matching SGR sequences, with their parameters in group 1, and these
+    /// color codes, with their attribute in group 2.
+    pub(crate) fn regex(self) -> &'static Regex {
+        match self {
+            ColorCodes::None => &crate::ANSI_REGEX,
+            ColorCodes::PcBoard => &PCBOARD_REGEX,
+            ColorCodes::Wildcat => &WILDCAT_REGEX,
+        }
+    }
+}
+
+/// Applies a DOS text attribute byte to `style`: the low nibble is the foreground, the next
+/// three bits the background, and the high bit makes text blink.
+///
+/// Other attributes of `style`, such as bold, are kept.
+pub(crate) fn apply_attribute(style: &mut Style, attribute: u8) {
+    let dos_color = |bits: u8| Color::Indexed(DOS_TO_ANSI[usize::from(bits & 7)] + (bits & 8));
+    style.fg_color = Some(dos_color(attribute & 0x0f));
+    style.bg_color = Some(dos_color((attribute >> 4) & 7));
+    style.blink = attribute & 0x80 != 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParseOptions, parse_ansi_with};
+
+    #[test]
+    fn test_color_codes() {
+        let options = ParseOptions {
+            color_codes: ColorCodes::PcBoard,
+            ..Default::default()
+        };
+        let text = parse_ansi_with("@X1FHi \x1b[1m@X8Cthere@X@", &options);
+        assert_eq!(text.to_plain_text(), "Hi there@X@");
+        let style = &text.segments[0].style;
+        assert_eq!(
+            (style.fg_color.clone(), style.bg_color.clone()),
+            (Some(Color::Indexed(15)), Some(Color::Indexed(4)))
+        );
+        let style = &text.segments[1].style;
+        assert!(style.bold && style.blink);
+        assert_eq!(style.fg_color, Some(Color::Indexed(9)));
+
+        let options = ParseOptions {
+            color_codes: ColorCodes::Wildcat,
+            ..Default::default()
+        };
+        let text = parse_ansi_with("@0E@yellow @X1F", &options);
+        assert_eq!(text.to_plain_text(), "yellow @X1F");
+        assert_eq!(text.segments[0].style.fg_color, Some(Color::Indexed(11)));
+
+        // Without color codes selected, they are plain text
+        assert_eq!(
+            parse_ansi_with("@X1F", &ParseOptions::default()).to_plain_text(),
+            "@X1F"
+        );
+    }
+}
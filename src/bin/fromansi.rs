@@ -1,10 +1,56 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use error_stack::fmt::ColorMode;
 use error_stack::{Report, ResultExt};
-use fromansi::{ansi_to_rexpaint, generate_css, parse_ansi, rexpaint_to_ansi};
+use fromansi::{
+    AnsiOptions, ClassPrefix, Codepage, Color, ColorBlindness, ColorCodes, ColorDepth, ColorMap,
+    CombiningPolicy, DiffGranularity, EmojiPolicy, HtmlDocumentOptions, HtmlLayout, HtmlOptions,
+    InputFormat, LayerSelection, ParseOptions, Recording, RexImportOptions, RexOptions, Screen,
+    SectionMarker, Style, StyledText, SvgOptions, Theme, TransparencyPolicy, WideCharPolicy,
+    decode_input, decode_input_as, frames_to_html, frames_to_svg, generate_css_with_prefix,
+    parse_ansi, parse_ansi_with, rexpaint_to_ansi_with, strip_ansi, xp_to_html_with,
+};
+use regex::RegexBuilder;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[cfg(feature = "crossterm")]
+use crossterm::cursor::{Hide, MoveTo, Show};
+#[cfg(feature = "crossterm")]
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+#[cfg(feature = "crossterm")]
+use crossterm::style::{Attribute, Print, SetAttribute};
+#[cfg(feature = "crossterm")]
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+#[cfg(feature = "crossterm")]
+use crossterm::{QueueableCommand, execute};
+#[cfg(feature = "gif")]
+use fromansi::frames_to_gif;
+#[cfg(feature = "parallel")]
+use fromansi::parse_ansi_parallel;
+#[cfg(feature = "png")]
+use fromansi::{FontAtlas, PngOptions, xp_to_png_with};
+#[cfg(feature = "image")]
+use fromansi::{ImageOptions, RgbaImage};
+#[cfg(feature = "pty")]
+use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "crossterm")]
+use regex::Regex;
+#[cfg(feature = "pty")]
+use std::ffi::OsString;
+#[cfg(feature = "crossterm")]
+use std::io::IsTerminal;
+#[cfg(feature = "crossterm")]
+use std::panic;
 use wherror::Error;
 
 #[derive(Parser)]
@@ -14,41 +60,925 @@ struct Args {
     /// Input file (reads from stdin if not provided) - for terminal output
     input: Option<PathBuf>,
 
+    /// Only print these lines, keeping the styles set before them (`N:M`, `N:`, `:M`, or
+    /// `N`; counted from 1, with negative numbers counting back from the end). Only used
+    /// without a subcommand; `html` takes its own --lines
+    #[arg(long, value_parser = parse_line_range, allow_hyphen_values = true)]
+    lines: Option<LineRange>,
+
+    /// Output file (writes to stdout if not provided)
+    #[arg(short, long, global = true)]
+    output: Option<PathBuf>,
+
+    /// Convert each input file separately, writing the results to this directory. Inputs
+    /// must not share a file name without its extension
+    #[arg(long, global = true, conflicts_with = "output")]
+    out_dir: Option<PathBuf>,
+
+    /// Color theme for HTML, SVG, and PNG output: `light`, `dark`, or a terminal color
+    /// scheme file with `foreground`, `background`, and `color0`-`color15` lines
+    #[arg(long, global = true, value_name = "NAME|FILE")]
+    theme: Option<String>,
+
+    /// Recolor for readers who need high contrast: no backgrounds, and saturated colors with
+    /// at least 7:1 contrast (WCAG AAA) on a black page, or a white one with `--theme light`
+    #[arg(long, global = true)]
+    high_contrast: bool,
+
+    /// Show blinking text with a bright background, as ANSI art drawn for iCE colors
+    /// expects (art whose SAUCE record asks for it gets this without the flag)
+    #[arg(long, global = true)]
+    ice_colors: bool,
+
+    /// How input bytes are decoded: detected per input, or forced to UTF-8 or CP437 (so
+    /// high-bit bytes become box-drawing and shade characters)
+    #[arg(long, global = true, default_value = "auto")]
+    encoding: InputEncoding,
+
+    /// Lay the input out N columns wide, wrapping long lines and following cursor movement
+    /// like a DOS terminal (CP437 art is laid out at its SAUCE width or 80 columns anyway)
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..=1000)
+    )]
+    art_width: Option<u16>,
+
+    /// Read BBS color codes in the input as well as ANSI escape sequences, such as `@X1F`
+    /// for `PCBoard` or `@1F@` for Wildcat!
+    #[arg(long, global = true, value_name = "SYSTEM")]
+    color_codes: Option<BbsCodes>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
-#[derive(Subcommand)]
+#[derive(Clone, Subcommand)]
 enum Commands {
     /// Generate HTML output
-    Html {
-        /// Input file (reads from stdin if not provided)
-        input: Option<PathBuf>,
+    Html(HtmlArgs),
+    /// Print the parsed text as JSON
+    Json {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
 
-        /// Output type
-        #[arg(short, long, default_value = "fragment")]
-        output: HtmlOutputType,
+        /// Indent the output for readability
+        #[arg(long)]
+        pretty: bool,
 
-        /// Filter out cells of a specific color (hex format, e.g., #000000)
+        /// Group the segments by line
         #[arg(long)]
-        filter: Option<String>,
+        lines: bool,
     },
-    /// Convert `RexPaint` file to ANSI text
-    Rex {
-        /// Input file (reads from stdin if not provided)
-        input: Option<PathBuf>,
+    /// Generate a block suitable for embedding in Markdown documents
+    Markdown {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
     },
-    /// Convert ANSI text to `RexPaint` file
-    ToRex {
-        /// Input file (reads from stdin if not provided)
-        input: Option<PathBuf>,
+    /// Generate Pango markup
+    Pango {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+    },
+    /// Generate Typst markup
+    Typst {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+    },
+    /// Generate a Discord ANSI code block
+    Discord {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+    },
+    /// Generate an XML document with one element per segment
+    Xml {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+    },
+    /// Render the text as an SVG image, like a terminal screenshot
+    Svg(SvgArgs),
+    /// Render the text as a PNG image with a bitmap font, like a terminal screenshot
+    #[cfg(feature = "png")]
+    Png(PngArgs),
+    /// Replay an asciinema recording as an animated SVG, self-playing HTML page, or GIF
+    Cast(CastArgs),
+    /// Re-emit the text as clean ANSI, downgrading colors to the given depth
+    Ansi {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+
+        /// Color depth of the output
+        #[arg(long, default_value = "truecolor")]
+        depth: CliColorDepth,
+    },
+    /// Replace colors, e.g. to fix unreadable colors in another tool's output
+    Recolor {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+
+        /// Replace one color with another (hex format or palette index, e.g., #000000=#1e1e1e
+        /// or 1=9). May be repeated.
+        #[arg(long = "map", value_name = "FROM=TO", required = true)]
+        maps: Vec<String>,
+
+        /// Output format
+        #[arg(long, default_value = "ansi")]
+        to: TextOutputFormat,
+    },
+    /// Reduce the colors to a smaller palette and re-emit the text as ANSI
+    Quantize {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
 
-        /// Output file path
-        #[arg(short, long)]
-        output: PathBuf,
+        /// Color depth to reduce to
+        #[arg(long, default_value = "256")]
+        depth: CliColorDepth,
     },
+    /// Show how the colors look with color blindness, or adjust them to be easier to tell apart
+    Colorblind(ColorblindArgs),
+    /// Compare the text of two inputs, ignoring styles, and show the differences in color
+    Diff(DiffArgs),
+    /// Reflow the text to a column width, keeping its styles
+    Wrap {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+
+        /// Maximum line width in columns
+        #[arg(long)]
+        width: usize,
+
+        /// Output format
+        #[arg(long, default_value = "ansi")]
+        to: TextOutputFormat,
+    },
+    /// Print the lines whose plain text matches a pattern, keeping their colors
+    Grep(GrepArgs),
+    /// Remove all escape sequences and print the plain text
+    Strip {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+    },
+    /// Convert `RexPaint` file to ANSI text
+    Rex(RexArgs),
+    /// Convert ANSI text to `RexPaint` file
+    ToRex(ToRexArgs),
+    /// Convert a PNG or JPEG image to truecolor ANSI text drawn with half blocks
+    #[cfg(feature = "image")]
+    #[command(name = "img2ansi")]
+    ImageToAnsi(ImageArgs),
+    /// Convert a PNG or JPEG image to a `RexPaint` file, matching each cell to a CP437 glyph
+    #[cfg(feature = "image")]
+    #[command(name = "img2rex")]
+    ImageToRex(ImageToRexArgs),
     /// Generate CSS styles
-    Css,
+    Css {
+        /// Prefix for generated CSS class names
+        #[arg(long, value_parser = parse_class_prefix, default_value = "")]
+        class_prefix: ClassPrefix,
+    },
+    /// Run a command in a pseudo-terminal, so it writes colors, and render its output
+    #[cfg(feature = "pty")]
+    Exec(ExecArgs),
+    /// Preview the input as a web page that reloads whenever an input file changes
+    Serve(ServeArgs),
+    /// Page through the input in the terminal, with search
+    #[cfg(feature = "crossterm")]
+    View(ViewArgs),
+    /// Print color and style statistics
+    Stats {
+        /// Input files or glob patterns (reads from stdin if not provided)
+        input: Vec<PathBuf>,
+    },
+}
+
+impl Commands {
+    /// Returns the input files of the subcommand, or `None` if it takes no input.
+    fn input_mut(&mut self) -> Option<&mut Vec<PathBuf>> {
+        match self {
+            Commands::Html(HtmlArgs { input, .. })
+            | Commands::Svg(SvgArgs { input, .. })
+            | Commands::Cast(CastArgs { input, .. })
+            | Commands::Serve(ServeArgs { input, .. })
+            | Commands::Grep(GrepArgs { input, .. })
+            | Commands::Colorblind(ColorblindArgs { input, .. })
+            | Commands::Rex(RexArgs { input, .. })
+            | Commands::ToRex(ToRexArgs { input, .. })
+            | Commands::Json { input, .. }
+            | Commands::Markdown { input }
+            | Commands::Pango { input }
+            | Commands::Typst { input }
+            | Commands::Discord { input }
+            | Commands::Xml { input }
+            | Commands::Ansi { input, .. }
+            | Commands::Quantize { input, .. }
+            | Commands::Recolor { input, .. }
+            | Commands::Wrap { input, .. }
+            | Commands::Strip { input }
+            | Commands::Stats { input } => Some(input),
+            #[cfg(feature = "png")]
+            Commands::Png(PngArgs { input, .. }) => Some(input),
+            #[cfg(feature = "image")]
+            Commands::ImageToAnsi(ImageArgs { input, .. })
+            | Commands::ImageToRex(ImageToRexArgs { input, .. }) => Some(input),
+            #[cfg(feature = "crossterm")]
+            Commands::View(ViewArgs { input, .. }) => Some(input),
+            #[cfg(feature = "pty")]
+            Commands::Exec(_) => None,
+            Commands::Diff(_) | Commands::Css { .. } => None,
+        }
+    }
+
+    /// Returns the file extension for the subcommand's output.
+    fn extension(&self) -> &'static str {
+        match self {
+            Commands::Html(_) | Commands::Serve(_) => "html",
+            Commands::Json { .. } => "json",
+            Commands::Markdown { .. } => "md",
+            Commands::Pango { .. } => "pango",
+            Commands::Typst { .. } => "typ",
+            Commands::Discord { .. } | Commands::Strip { .. } | Commands::Stats { .. } => "txt",
+            Commands::Xml { .. } => "xml",
+            Commands::Svg(_) => "svg",
+            #[cfg(feature = "png")]
+            Commands::Png(_) => "png",
+            Commands::Cast(args) => match args.to {
+                CastOutputFormat::Svg => "svg",
+                CastOutputFormat::Html => "html",
+                #[cfg(feature = "gif")]
+                CastOutputFormat::Gif => "gif",
+            },
+            Commands::Ansi { .. } | Commands::Quantize { .. } | Commands::Grep(_) => "ans",
+            #[cfg(feature = "crossterm")]
+            Commands::View(_) => "ans",
+            Commands::Rex(args) => match args.to {
+                RexOutputFormat::Ansi => "ans",
+                RexOutputFormat::Html => "html",
+                #[cfg(feature = "png")]
+                RexOutputFormat::Png => "png",
+            },
+            Commands::ToRex(_) => "xp",
+            #[cfg(feature = "image")]
+            Commands::ImageToAnsi(_) => "ans",
+            #[cfg(feature = "image")]
+            Commands::ImageToRex(_) => "xp",
+            Commands::Diff(DiffArgs { to, .. })
+            | Commands::Colorblind(ColorblindArgs { to, .. })
+            | Commands::Wrap { to, .. }
+            | Commands::Recolor { to, .. } => match to {
+                TextOutputFormat::Ansi => "ans",
+                TextOutputFormat::Html => "html",
+            },
+            #[cfg(feature = "pty")]
+            Commands::Exec(args) => match args.to {
+                ExecOutputFormat::Ansi => "ans",
+                ExecOutputFormat::Html => "html",
+                ExecOutputFormat::Svg => "svg",
+                ExecOutputFormat::Json => "json",
+                ExecOutputFormat::Markdown => "md",
+                ExecOutputFormat::Text => "txt",
+            },
+            Commands::Css { .. } => "css",
+        }
+    }
+}
+
+/// Arguments for the `html` subcommand.
+#[derive(Clone, clap::Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct HtmlArgs {
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Whether to generate a fragment or an entire webpage
+    #[arg(short, long, default_value = "fragment")]
+    mode: HtmlOutputType,
+
+    /// Filter out cells of a specific color (hex format, e.g., #000000)
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Replace one color with another (hex format or palette index, e.g., #808080=#000000 or
+    /// 1=9). May be repeated.
+    #[arg(long, value_name = "FROM=TO")]
+    remap: Vec<String>,
+
+    /// Pass text through without HTML-escaping it (only for trusted input)
+    #[arg(long)]
+    raw: bool,
+
+    /// Use inline styles instead of CSS classes (e.g., for HTML email)
+    #[arg(long)]
+    inline_styles: bool,
+
+    /// Prefix for generated CSS class names
+    #[arg(long, value_parser = parse_class_prefix, default_value = "")]
+    class_prefix: ClassPrefix,
+
+    /// Wrap each line in an element with an `L<N>` id for deep-linking
+    #[arg(long)]
+    line_anchors: bool,
+
+    /// Show a gutter of line numbers linking to each line (implies --line-anchors)
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// Merge adjacent identically styled spans and drop spans without styling
+    #[arg(long)]
+    compact: bool,
+
+    /// Annotate spans with the SGR parameters that produced them (`data-sgr`)
+    #[arg(long)]
+    data_sgr: bool,
+
+    /// Lay out the text as a fixed-size character grid (for ANSI art)
+    #[arg(long)]
+    grid: bool,
+
+    /// Fold CI log groups (GitHub Actions `::group::`, Azure Pipelines `##[group]` and
+    /// `##[section]`) into collapsible sections
+    #[arg(long)]
+    fold_groups: bool,
+
+    /// Start a collapsible section at each line matching this regex, named after its
+    /// `name` group or the whole line, lasting until the next match. May be repeated.
+    #[arg(long = "section", value_name = "REGEX")]
+    sections: Vec<String>,
+
+    /// Read section markers from a JSON file: an array of `{"start": REGEX, "end": REGEX}`
+    /// objects, where `end` is optional
+    #[arg(long, value_name = "FILE")]
+    section_file: Option<PathBuf>,
+
+    /// Put a table of contents linking to the collapsible sections before the output
+    #[arg(long)]
+    toc: bool,
+
+    /// Link `path/to/file.rs:12:5` references to this URL template, filling in `{path}`,
+    /// `{line}`, and `{column}` (e.g. `vscode://file/{path}:{line}:{column}`)
+    #[arg(long, value_name = "TEMPLATE")]
+    file_links: Option<String>,
+
+    /// Give each line `data-line` and `data-offset` attributes with its line number and the
+    /// byte offset where it starts in the input (as decoded to UTF-8)
+    #[arg(long)]
+    source_positions: bool,
+
+    /// Only render these lines, keeping the styles set before them (`N:M`, `N:`, `:M`, or
+    /// `N`; counted from 1, with negative numbers counting back from the end)
+    #[arg(long, value_parser = parse_line_range, allow_hyphen_values = true)]
+    lines: Option<LineRange>,
+
+    /// Split the output into standalone pages of this many lines, linked to each other.
+    /// Needs -o; later pages are written next to it as `NAME-2.html`, `NAME-3.html`, ...
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    page_lines: Option<u64>,
+
+    /// Title of standalone pages, also read out by screen readers as the name of the output
+    #[arg(long, default_value = "Terminal output")]
+    label: String,
+
+    /// Language of standalone pages as a BCP 47 tag, used by screen readers to pick a voice
+    #[arg(long, default_value = "en")]
+    lang: String,
+
+    /// Add a link to standalone pages to download the output as plain text, which is easier
+    /// to follow with a screen reader
+    #[arg(long)]
+    plain_text_link: bool,
+
+    /// Add a toolbar to standalone pages to find text, show only errors, warnings, or one
+    /// color, and jump to the next error (needs scripting)
+    #[arg(long)]
+    toolbar: bool,
+
+    /// Add buttons to standalone pages to copy a line, the selection, or the whole output
+    /// as plain text (needs scripting)
+    #[arg(long)]
+    copy_buttons: bool,
+
+    /// Add a link to standalone pages to download the output as ANSI escape sequences
+    #[arg(long)]
+    ansi_link: bool,
+
+    /// Give standalone pages this dark theme as well as --theme (`light` if not given),
+    /// with a button to switch between them that remembers the choice
+    #[arg(long, value_name = "NAME|FILE")]
+    dark_theme: Option<String>,
+}
+
+/// Arguments for the `to-rex` subcommand.
+#[derive(Clone, clap::Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct ToRexArgs {
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Foreground color for text without one (hex format)
+    #[arg(long, value_parser = parse_color, default_value = "#ffffff")]
+    fg: Color,
+
+    /// Background color for text without one (hex format)
+    #[arg(long, value_parser = parse_color, default_value = "#000000")]
+    bg: Color,
+
+    /// Write text without a background color using `RexPaint`'s transparent key color, so
+    /// the art can be layered over other layers
+    #[arg(long)]
+    transparent_background: bool,
+
+    /// Canvas width in cells (defaults to the longest line)
+    #[arg(long)]
+    width: Option<usize>,
+
+    /// Write backgrounds and glyphs to separate layers for easier editing
+    #[arg(long)]
+    split_layers: bool,
+
+    /// Wrap lines longer than the width instead of clipping them
+    #[arg(long, requires = "width")]
+    wrap: bool,
+
+    /// Canvas height in cells (defaults to the number of lines)
+    #[arg(long)]
+    height: Option<usize>,
+
+    /// Glyph used for characters without a CP437 equivalent
+    #[arg(long, default_value_t = '?')]
+    replacement: char,
+
+    /// Use the replacement glyph instead of the closest CP437 glyph for characters such as
+    /// rounded box corners and accented letters
+    #[arg(long)]
+    exact_glyphs: bool,
+
+    /// Glyph mapping: `wingdings`, `control`, or a file listing the 256 glyphs in order
+    #[arg(long, default_value = "wingdings", value_parser = parse_codepage)]
+    codepage: Codepage,
+
+    /// How characters two columns wide, such as CJK ideographs, are written
+    #[arg(long, default_value = "pad")]
+    wide_chars: CliWideChars,
+
+    /// How emoji are written: "first-char", "blank", or a character to write in their place
+    #[arg(long, default_value = "first-char", value_parser = parse_emoji)]
+    emoji: EmojiPolicy,
+
+    /// How letters with combining marks are written
+    #[arg(long, default_value = "precompose")]
+    combining: CliCombining,
+}
+
+/// How characters two columns wide are written to `RexPaint`.
+#[derive(Clone, Copy, ValueEnum)]
+enum CliWideChars {
+    /// The glyph in the first of two cells, with the second left blank
+    Pad,
+    /// The glyph in both cells
+    Fill,
+    /// The glyph in one cell, moving the rest of the line left
+    Narrow,
+}
+
+/// How letters with combining marks are written to a cell grid.
+#[derive(Clone, Copy, ValueEnum)]
+enum CliCombining {
+    /// The precomposed character, such as é, where there is one
+    Precompose,
+    /// The letter without its marks
+    Drop,
+}
+
+impl From<CliCombining> for CombiningPolicy {
+    fn from(combining: CliCombining) -> Self {
+        match combining {
+            CliCombining::Precompose => CombiningPolicy::Precompose,
+            CliCombining::Drop => CombiningPolicy::Drop,
+        }
+    }
+}
+
+impl From<CliWideChars> for WideCharPolicy {
+    fn from(wide_chars: CliWideChars) -> Self {
+        match wide_chars {
+            CliWideChars::Pad => WideCharPolicy::Pad,
+            CliWideChars::Fill => WideCharPolicy::Fill,
+            CliWideChars::Narrow => WideCharPolicy::Narrow,
+        }
+    }
+}
+
+/// Arguments for the `diff` subcommand.
+#[derive(Clone, clap::Args)]
+struct DiffArgs {
+    /// The original file
+    old: PathBuf,
+
+    /// The changed file
+    new: PathBuf,
+
+    /// Compare words instead of whole lines
+    #[arg(long)]
+    words: bool,
+
+    /// Output format
+    #[arg(long, default_value = "ansi")]
+    to: TextOutputFormat,
+}
+
+/// The output format for subcommands that transform styled text.
+#[derive(Clone, ValueEnum)]
+enum TextOutputFormat {
+    /// Text with ANSI escape sequences
+    Ansi,
+    /// An HTML fragment
+    Html,
+}
+
+/// Arguments for the `grep` subcommand.
+#[derive(Clone, clap::Args)]
+struct GrepArgs {
+    /// Regular expression matched against the plain text of each line
+    pattern: String,
+
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Highlight the matches in bold red
+    #[arg(long)]
+    highlight: bool,
+
+    /// Match regardless of case
+    #[arg(short, long)]
+    ignore_case: bool,
+
+    /// Print the lines that do not match instead
+    #[arg(short = 'v', long)]
+    invert_match: bool,
+}
+
+/// Arguments for the `colorblind` subcommand.
+#[derive(Clone, clap::Args)]
+struct ColorblindArgs {
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Kind of color blindness
+    #[arg(long, default_value = "deuteranopia")]
+    kind: CliColorBlindness,
+
+    /// Adjust the colors to be easier to tell apart instead of showing how they look
+    #[arg(long)]
+    fix: bool,
+
+    /// Output format
+    #[arg(long, default_value = "ansi")]
+    to: TextOutputFormat,
+}
+
+/// Arguments for the `svg` subcommand.
+#[derive(Clone, clap::Args)]
+struct SvgArgs {
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// CSS font family list
+    #[arg(long)]
+    font_family: Option<String>,
+
+    /// Font size in pixels
+    #[arg(long, default_value_t = 14.0)]
+    font_size: f64,
+
+    /// Space around the text in pixels
+    #[arg(long, default_value_t = 16.0)]
+    padding: f64,
+
+    /// Make blinking text and the cursor blink
+    #[arg(long)]
+    animate_blink: bool,
+
+    /// Run the input through a terminal emulator and draw the cursor where it is left
+    #[arg(long)]
+    cursor: bool,
+}
+
+/// Arguments for the `img2ansi` subcommand.
+#[cfg(feature = "image")]
+#[derive(Clone, clap::Args)]
+struct ImageArgs {
+    /// PNG or JPEG image file (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Width of the output in columns; smaller images keep their size
+    #[arg(long, default_value_t = 80, value_parser = clap::value_parser!(u16).range(1..))]
+    columns: u16,
+}
+
+/// Arguments for the `img2rex` subcommand.
+#[cfg(feature = "image")]
+#[derive(Clone, clap::Args)]
+struct ImageToRexArgs {
+    /// PNG or JPEG image file (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Width of the canvas in cells; smaller images keep a cell per pixel
+    #[arg(long, default_value_t = 80, value_parser = clap::value_parser!(u16).range(1..))]
+    columns: u16,
+
+    /// Font atlas image with a 16x16 grid of glyphs to match cells against (defaults to
+    /// `RexPaint`'s 14x14 font)
+    #[arg(long)]
+    font: Option<PathBuf>,
+}
+
+/// Arguments for the `cast` subcommand.
+#[derive(Clone, clap::Args)]
+struct CastArgs {
+    /// Recording files or glob patterns in asciicast v2 or v3 format (reads from stdin if
+    /// not provided)
+    input: Vec<PathBuf>,
+
+    /// Output format
+    #[arg(long, default_value = "svg")]
+    to: CastOutputFormat,
+
+    /// Most frames per second; output written faster is merged into one frame
+    #[arg(long, default_value_t = 30.0)]
+    fps: f64,
+
+    /// Longest pause between frames in seconds (defaults to the recording's own limit)
+    #[arg(long)]
+    idle_time_limit: Option<f64>,
+}
+
+/// The output format for the `cast` subcommand.
+#[derive(Clone, ValueEnum)]
+enum CastOutputFormat {
+    /// An SVG image animated with SMIL
+    Svg,
+    /// A standalone HTML page that plays the frames with a script
+    Html,
+    /// A GIF animation drawn with a CP437 font
+    #[cfg(feature = "gif")]
+    Gif,
+}
+
+/// Arguments for the `exec` subcommand.
+#[cfg(feature = "pty")]
+#[derive(Clone, clap::Args)]
+struct ExecArgs {
+    /// Output format
+    #[arg(long, default_value = "ansi")]
+    to: ExecOutputFormat,
+
+    /// Width of the pseudo-terminal in columns
+    #[arg(long, default_value_t = 80)]
+    cols: u16,
+
+    /// Height of the pseudo-terminal in rows
+    #[arg(long, default_value_t = 24)]
+    rows: u16,
+
+    /// The command to run and its arguments, after `--`
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    command: Vec<OsString>,
+}
+
+/// The output format for the `exec` subcommand.
+#[cfg(feature = "pty")]
+#[derive(Clone, ValueEnum)]
+enum ExecOutputFormat {
+    /// The output as captured, with line endings normalized
+    Ansi,
+    /// An HTML fragment
+    Html,
+    /// An SVG image, like a terminal screenshot
+    Svg,
+    /// Segments with their colors and attributes
+    Json,
+    /// A block for embedding in Markdown documents
+    Markdown,
+    /// The plain text, with every escape sequence removed
+    Text,
+}
+
+/// Arguments for the `serve` subcommand.
+#[derive(Clone, clap::Args)]
+struct ServeArgs {
+    /// Input files or glob patterns (reads from stdin once if not provided)
+    input: Vec<PathBuf>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on (0 picks a free port)
+    #[arg(short, long, default_value_t = 8000)]
+    port: u16,
+}
+
+/// Arguments for the `view` subcommand.
+#[cfg(feature = "crossterm")]
+#[derive(Clone, clap::Args)]
+struct ViewArgs {
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Color depth to show colors in, instead of the one `COLORTERM` and `TERM` report
+    #[arg(long)]
+    color_depth: Option<CliColorDepth>,
+}
+
+/// Arguments for the `png` subcommand.
+#[cfg(feature = "png")]
+#[derive(Clone, clap::Args)]
+struct PngArgs {
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Size of each font pixel in image pixels
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=16))]
+    scale: u16,
+
+    /// Font atlas image with a 16x16 grid of glyphs (defaults to `RexPaint`'s 14x14 font)
+    #[arg(long)]
+    font: Option<PathBuf>,
+
+    /// How letters with combining marks are written
+    #[arg(long, default_value = "precompose")]
+    combining: CliCombining,
+}
+
+/// Arguments for the `rex` subcommand.
+#[derive(Clone, clap::Args)]
+struct RexArgs {
+    /// Input files or glob patterns (reads from stdin if not provided)
+    input: Vec<PathBuf>,
+
+    /// Output format
+    #[arg(long, default_value = "ansi")]
+    to: RexOutputFormat,
+
+    /// Convert only this layer (0 is the bottom layer)
+    #[arg(long, conflicts_with = "exclude_layer")]
+    layer: Option<usize>,
+
+    /// Leave out this layer. May be repeated.
+    #[arg(long)]
+    exclude_layer: Vec<usize>,
+
+    /// Composite the layers with layer 0 on top
+    #[arg(long)]
+    reverse_layers: bool,
+
+    /// Glyph mapping: `wingdings`, `control`, or a file listing the 256 glyphs in order
+    #[arg(long, default_value = "wingdings", value_parser = parse_codepage)]
+    codepage: Codepage,
+
+    /// How to draw transparent cells: `default` (no background), `skip`, or a background
+    /// color (hex format, e.g., #000000)
+    #[arg(long, default_value = "default", value_parser = parse_transparency)]
+    transparency: TransparencyPolicy,
+
+    /// Colors used in ANSI output
+    #[arg(long, default_value = "truecolor")]
+    color_depth: CliColorDepth,
+
+    /// Write runs of cells with the same colors as one escape sequence in ANSI output
+    #[arg(long)]
+    coalesce: bool,
+
+    /// Leave out empty cells at the end of each line in ANSI output
+    #[arg(long)]
+    trim_trailing: bool,
+
+    /// Font atlas image with a 16x16 grid of glyphs for PNG output (defaults to `RexPaint`'s
+    /// 14x14 font)
+    #[cfg(feature = "png")]
+    #[arg(long)]
+    font: Option<PathBuf>,
+}
+
+/// The output format for `RexPaint` conversion.
+#[derive(Clone, ValueEnum)]
+enum RexOutputFormat {
+    /// Text with ANSI escape sequences, with the layers flattened
+    Ansi,
+    /// An HTML grid of cells that keeps each layer and its transparent cells
+    Html,
+    /// A PNG image drawn with a CP437 font
+    #[cfg(feature = "png")]
+    Png,
+}
+
+/// The number of colors to use in ANSI output.
+#[derive(Clone, ValueEnum)]
+enum CliColorDepth {
+    /// 24-bit RGB colors
+    #[value(alias = "true")]
+    Truecolor,
+    /// The 256-color palette
+    #[value(name = "256")]
+    Ansi256,
+    /// The 16-color palette
+    #[value(name = "16")]
+    Ansi16,
+}
+
+impl From<CliColorDepth> for ColorDepth {
+    fn from(depth: CliColorDepth) -> Self {
+        match depth {
+            CliColorDepth::Truecolor => ColorDepth::TrueColor,
+            CliColorDepth::Ansi256 => ColorDepth::Ansi256,
+            CliColorDepth::Ansi16 => ColorDepth::Ansi16,
+        }
+    }
+}
+
+/// A form of color blindness for the `colorblind` subcommand.
+#[derive(Clone, Copy, ValueEnum)]
+enum CliColorBlindness {
+    /// Red-blind
+    Protanopia,
+    /// Green-blind
+    Deuteranopia,
+    /// Blue-blind
+    Tritanopia,
+}
+
+impl From<CliColorBlindness> for ColorBlindness {
+    fn from(kind: CliColorBlindness) -> Self {
+        match kind {
+            CliColorBlindness::Protanopia => ColorBlindness::Protanopia,
+            CliColorBlindness::Deuteranopia => ColorBlindness::Deuteranopia,
+            CliColorBlindness::Tritanopia => ColorBlindness::Tritanopia,
+        }
+    }
+}
+
+/// A `--lines` selection of whole lines.
+///
+/// Lines are counted from 1 and both ends are inclusive; negative numbers count back from
+/// the last line, so `-1` is the last line.
+#[derive(Clone, Copy)]
+struct LineRange {
+    start: Option<isize>,
+    end: Option<isize>,
+}
+
+impl LineRange {
+    /// Returns the zero-based range of lines selected from text with `count` lines.
+    fn resolve(self, count: usize) -> Range<usize> {
+        let start = match self.start {
+            Some(start) if start < 0 => count.saturating_sub(start.unsigned_abs()),
+            Some(start) => start.unsigned_abs() - 1,
+            None => 0,
+        };
+        let end = match self.end {
+            Some(end) if end < 0 => count.saturating_sub(end.unsigned_abs() - 1),
+            Some(end) => end.unsigned_abs(),
+            None => count,
+        };
+        start..end.max(start)
+    }
+
+    /// Returns the selected lines of `text`.
+    fn slice(self, text: &StyledText) -> StyledText {
+        text.slice_lines(self.resolve(text.split_lines().len()))
+    }
+}
+
+/// How input bytes are decoded.
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum InputEncoding {
+    /// Detect the format of each input
+    #[default]
+    Auto,
+    /// UTF-8 ANSI text
+    Utf8,
+    /// CP437 ANSI art or logs from DOS-era tools
+    Cp437,
+    /// BIN screen dumps of character and attribute byte pairs
+    Bin,
+}
+
+/// BBS software whose inline color codes are read.
+#[derive(Clone, Copy, ValueEnum)]
+enum BbsCodes {
+    /// `@X` followed by the background and foreground as hex digits
+    Pcboard,
+    /// The background and foreground as hex digits between `@` signs
+    Wildcat,
+}
+
+impl From<BbsCodes> for ColorCodes {
+    fn from(codes: BbsCodes) -> Self {
+        match codes {
+            BbsCodes::Pcboard => ColorCodes::PcBoard,
+            BbsCodes::Wildcat => ColorCodes::Wildcat,
+        }
+    }
 }
 
 /// The output type for HTML rendering.
@@ -65,23 +995,140 @@ enum HtmlOutputType {
 #[error(debug)]
 pub struct AppError;
 
-fn read_text_input(input: Option<PathBuf>) -> Result<String, Report<AppError>> {
-    if let Some(input_path) = input {
-        fs::read_to_string(&input_path)
-            .change_context(AppError)
-            .attach_with(|| format!("failed to read input file '{}'", input_path.display()))
-    } else {
-        let mut buffer = String::new();
+/// How input is interpreted, as set by the global flags.
+struct InputOptions {
+    /// Set by `--encoding`.
+    encoding: InputEncoding,
+    /// Set by `--ice-colors`.
+    ice_colors: bool,
+    /// Set by `--art-width`.
+    art_width: Option<usize>,
+    /// Set by `--color-codes`.
+    color_codes: ColorCodes,
+    /// The theme set by `--high-contrast`.
+    high_contrast: Option<Theme>,
+}
+
+impl InputOptions {
+    /// The options that parse input as these options read it.
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            ice_colors: self.ice_colors,
+            color_codes: self.color_codes,
+            ..Default::default()
+        }
+    }
+
+    /// Recolors parsed input for `--high-contrast`.
+    fn recolor(&self, text: StyledText) -> StyledText {
+        match &self.high_contrast {
+            Some(theme) => text.high_contrast(theme),
+            None => text,
+        }
+    }
+
+    /// Whether parsing the input with these options changes it, so it cannot be passed
+    /// through as it was read.
+    fn changes_input(&self) -> bool {
+        self.ice_colors || self.color_codes != ColorCodes::None || self.high_contrast.is_some()
+    }
+}
+
+/// Reads the input files one after another, or stdin if there are none, as ANSI text.
+///
+/// Each input is converted from whichever format it is detected to be, so `RexPaint`
+/// images, recordings, and CP437 art can be used anywhere ANSI text can.
+fn read_text_input(
+    input: Vec<PathBuf>,
+    options: &InputOptions,
+) -> Result<String, Report<AppError>> {
+    // BIN dumps are raw bytes with nothing to detect, so in auto mode a `.bin` file is
+    // taken to be one
+    let decode = |data: &[u8], path: Option<&Path>| match options.encoding {
+        InputEncoding::Auto
+            if path
+                .and_then(Path::extension)
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("bin")) =>
+        {
+            decode_input_as(data, InputFormat::Bin)
+        }
+        InputEncoding::Auto => decode_input(data),
+        InputEncoding::Utf8 => decode_input_as(data, InputFormat::Ansi),
+        InputEncoding::Cp437 => decode_input_as(data, InputFormat::Cp437),
+        InputEncoding::Bin => decode_input_as(data, InputFormat::Bin),
+    };
+    let mut text = String::new();
+    if input.is_empty() {
+        let mut buffer = Vec::new();
         io::stdin()
-            .read_to_string(&mut buffer)
+            .read_to_end(&mut buffer)
             .change_context(AppError)
             .attach("failed to read stdin")?;
-        Ok(buffer)
+        text = decode(&buffer, None)
+            .change_context(AppError)
+            .attach("failed to convert stdin")?;
+    }
+    for input_path in input {
+        let contents = fs::read(&input_path)
+            .change_context(AppError)
+            .attach_with(|| format!("failed to read input file '{}'", input_path.display()))?;
+        let contents = decode(&contents, Some(&input_path))
+            .change_context(AppError)
+            .attach_with(|| format!("failed to convert input file '{}'", input_path.display()))?;
+        text.push_str(&contents);
+    }
+
+    if let Some(width) = options.art_width {
+        let mut canvas = Screen::canvas(width);
+        canvas.feed(&expand_color_codes(&text, options));
+        text = canvas.to_styled_text().to_ansi(&AnsiOptions::default());
     }
+    Ok(text)
 }
 
-fn read_binary_input(input: Option<PathBuf>) -> Result<Vec<u8>, Report<AppError>> {
-    if let Some(input_path) = input {
+/// Replaces the color codes in `text` with the escape sequences they stand for, for
+/// screens that lay text out and only read escape sequences.
+fn expand_color_codes<'a>(text: &'a str, options: &InputOptions) -> Cow<'a, str> {
+    if options.color_codes == ColorCodes::None {
+        return Cow::Borrowed(text);
+    }
+    let parse_options = ParseOptions {
+        color_codes: options.color_codes,
+        ..Default::default()
+    };
+    Cow::Owned(parse_ansi_with(text, &parse_options).to_ansi(&AnsiOptions::default()))
+}
+
+/// Parses ANSI text read by [`read_text_input`] as `options` say.
+///
+/// With the `parallel` feature, large inputs are parsed on several threads.
+fn parse_input(input: &str, options: &InputOptions) -> StyledText {
+    parse_input_with(input, options, &options.parse_options())
+}
+
+/// Parses ANSI text read by [`read_text_input`] using `parse_options`, like
+/// [`parse_input`].
+///
+/// `parse_options` should start from [`InputOptions::parse_options`].
+fn parse_input_with(
+    input: &str,
+    options: &InputOptions,
+    parse_options: &ParseOptions,
+) -> StyledText {
+    #[cfg(feature = "parallel")]
+    let text = parse_ansi_parallel(input, parse_options);
+    #[cfg(not(feature = "parallel"))]
+    let text = parse_ansi_with(input, parse_options);
+    options.recolor(text)
+}
+
+/// Reads a single binary input file, or stdin if there is none.
+fn read_binary_input(input: Vec<PathBuf>) -> Result<Vec<u8>, Report<AppError>> {
+    if input.len() > 1 {
+        return Err(Report::new(AppError)
+            .attach("binary input cannot be combined; use --out-dir to convert each file"));
+    }
+    if let Some(input_path) = input.into_iter().next() {
         fs::read(&input_path)
             .change_context(AppError)
             .attach_with(|| format!("failed to read input file '{}'", input_path.display()))
@@ -95,59 +1142,1459 @@ fn read_binary_input(input: Option<PathBuf>) -> Result<Vec<u8>, Report<AppError>
     }
 }
 
-fn main() -> Result<(), Report<AppError>> {
-    let args = Args::parse();
-    Report::set_color_mode(ColorMode::Color);
+/// Parses `FROM=TO` color mappings, where each color is `#RRGGBB` or a palette index.
+fn parse_color_map(entries: &[String]) -> Result<ColorMap, Report<AppError>> {
+    let color = |value: &str| {
+        Color::from_hex(value).or_else(|| value.parse::<u8>().ok().map(Color::Indexed))
+    };
+    let mut map = ColorMap::new();
+    for entry in entries {
+        let (from, to) = entry
+            .split_once('=')
+            .and_then(|(from, to)| Some((color(from)?, color(to)?)))
+            .ok_or_else(|| Report::new(AppError))
+            .attach_with(|| {
+                format!(
+                    "invalid color mapping '{entry}', expected FROM=TO with #RRGGBB colors or \
+                     palette indexes 0-255"
+                )
+            })?;
+        map.insert(from, to);
+    }
+    Ok(map)
+}
+
+/// Collects the section markers given by `--section` and `--section-file`, checking that
+/// their patterns are valid.
+fn read_section_markers(
+    starts: &[String],
+    file: Option<&Path>,
+) -> Result<Vec<SectionMarker>, Report<AppError>> {
+    let mut markers = starts
+        .iter()
+        .map(|start| SectionMarker::new(start, None).change_context(AppError))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(file) = file {
+        let contents = fs::read_to_string(file)
+            .change_context(AppError)
+            .attach_with(|| format!("failed to read section file '{}'", file.display()))?;
+        let from_file: Vec<SectionMarker> = serde_json::from_str(&contents)
+            .change_context(AppError)
+            .attach_with(|| format!("invalid section file '{}'", file.display()))?;
+        markers.extend(from_file);
+    }
+    Ok(markers)
+}
 
-    // Handle output
-    match args.command {
-        None => {
-            let input = read_text_input(args.input)?;
-            print!("{input}");
+/// Parses a `--lines` argument.
+fn parse_line_range(value: &str) -> Result<LineRange, String> {
+    let line = |number: &str| -> Result<Option<isize>, String> {
+        if number.is_empty() {
+            return Ok(None);
         }
-        Some(Commands::Html {
-            input,
-            output,
-            filter,
-        }) => {
-            let input = read_text_input(input)?;
-            let parsed = parse_ansi(&input);
-            let html = parsed.to_html_with_filter(filter.as_deref());
-            match output {
-                HtmlOutputType::Fragment => {
-                    println!("{html}");
+        match number.parse::<isize>() {
+            Ok(0) | Err(_) => Err(format!(
+                "invalid line '{number}', expected a nonzero line number"
+            )),
+            Ok(number) => Ok(Some(number)),
+        }
+    };
+    if let Some((start, end)) = value.split_once(':') {
+        Ok(LineRange {
+            start: line(start)?,
+            end: line(end)?,
+        })
+    } else {
+        let number = line(value)?;
+        Ok(LineRange {
+            start: number,
+            end: number,
+        })
+    }
+}
+
+/// Parses a `#RRGGBB` color argument.
+fn parse_color(value: &str) -> Result<Color, String> {
+    Color::from_hex(value).ok_or_else(|| format!("invalid color '{value}', expected #RRGGBB"))
+}
+
+/// Parses a `--class-prefix` argument.
+fn parse_class_prefix(value: &str) -> Result<ClassPrefix, String> {
+    ClassPrefix::new(value)
+        .map_err(|_| format!("invalid class prefix '{value}', expected one such as 'ansi-'"))
+}
+
+/// Parses a `--codepage` argument, reading custom glyph lists from a file.
+///
+/// Line breaks in the file are ignored, so the glyphs may be split across lines.
+fn parse_codepage(value: &str) -> Result<Codepage, String> {
+    match value {
+        "wingdings" => Ok(Codepage::Wingdings),
+        "control" => Ok(Codepage::Control),
+        path => {
+            let contents = fs::read_to_string(path)
+                .map_err(|err| format!("failed to read codepage file '{path}': {err}"))?;
+            let glyphs: Vec<char> = contents
+                .chars()
+                .filter(|&ch| ch != '\n' && ch != '\r')
+                .collect();
+            if glyphs.len() != 256 {
+                return Err(format!(
+                    "codepage file '{path}' has {} glyphs, expected 256",
+                    glyphs.len()
+                ));
+            }
+            Ok(Codepage::Custom(glyphs))
+        }
+    }
+}
+
+/// Parses a `--transparency` argument.
+fn parse_transparency(value: &str) -> Result<TransparencyPolicy, String> {
+    match value {
+        "default" => Ok(TransparencyPolicy::TerminalDefault),
+        "skip" => Ok(TransparencyPolicy::Skip),
+        color => parse_color(color).map(TransparencyPolicy::Background),
+    }
+}
+
+/// Parses an `--emoji` argument.
+fn parse_emoji(value: &str) -> Result<EmojiPolicy, String> {
+    match value {
+        "first-char" => Ok(EmojiPolicy::FirstChar),
+        "blank" => Ok(EmojiPolicy::Blank),
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Ok(EmojiPolicy::ReplaceWith(ch)),
+                _ => Err(format!(
+                    "expected first-char, blank, or one character, got '{value}'"
+                )),
+            }
+        }
+    }
+}
+
+/// Renders the input as an SVG image.
+fn svg_command(
+    args: SvgArgs,
+    input_options: &InputOptions,
+    theme: Option<Theme>,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_text_input(args.input, input_options)?;
+    let mut options = SvgOptions {
+        font_size: args.font_size,
+        padding: args.padding,
+        animate_blink: args.animate_blink,
+        ..Default::default()
+    };
+    if let Some(theme) = theme {
+        options.theme = theme;
+    }
+    if let Some(font_family) = args.font_family {
+        options.font_family = font_family;
+    }
+    let mut text = parse_input(&input, input_options);
+    if args.cursor {
+        // Lay the input out on a canvas as wide as its widest line, with each newline
+        // returning to the first column as it does when written to a terminal
+        let mut screen = Screen::canvas(text.width().max(1));
+        let input = expand_color_codes(&input, input_options);
+        screen.feed(&input.replace("\r\n", "\n").replace('\n', "\r\n"));
+        text = screen.to_styled_text();
+        if input_options.ice_colors {
+            text = text.ice_colors();
+        }
+        text = input_options.recolor(text);
+        options.cursor = screen.cursor_visible().then(|| screen.cursor());
+    }
+    Ok(text.to_svg_with(&options).into_bytes())
+}
+
+/// Replays an asciinema recording and renders it as an animation.
+fn cast_command(args: CastArgs, theme: Option<Theme>) -> Result<Vec<u8>, Report<AppError>> {
+    if args.fps <= 0.0 {
+        return Err(Report::new(AppError).attach("--fps must be greater than 0"));
+    }
+    let input = read_binary_input(args.input)?;
+    let mut recording = Recording::parse(&String::from_utf8_lossy(&input))
+        .change_context(AppError)
+        .attach("invalid asciinema recording")?;
+    if args.idle_time_limit.is_some() {
+        recording.idle_time_limit = args.idle_time_limit;
+    }
+    let frames = recording.frames(1.0 / args.fps);
+
+    let output = match args.to {
+        CastOutputFormat::Svg => {
+            let options = SvgOptions {
+                theme: theme.unwrap_or_else(Theme::dark),
+                ..Default::default()
+            };
+            frames_to_svg(&frames, &options).into_bytes()
+        }
+        CastOutputFormat::Html => {
+            let document = HtmlDocumentOptions {
+                light: theme.unwrap_or_else(Theme::dark),
+                dark: None,
+                ..Default::default()
+            };
+            let html = frames_to_html(&frames, &HtmlOptions::default(), &document);
+            format!("{html}\n").into_bytes()
+        }
+        #[cfg(feature = "gif")]
+        CastOutputFormat::Gif => {
+            let options = PngOptions {
+                theme: theme.unwrap_or_else(Theme::dark),
+                ..Default::default()
+            };
+            frames_to_gif(&frames, &options)
+                .change_context(AppError)
+                .attach("GIF rendering failed")?
+        }
+    };
+    Ok(output)
+}
+
+/// Runs a command in a pseudo-terminal and renders what it wrote.
+///
+/// Programs see a terminal of the given size, so they keep their colors, progress bars,
+/// and other terminal-only output. The command's input is the pseudo-terminal, not ours.
+#[cfg(feature = "pty")]
+fn exec_command(args: ExecArgs, theme: Option<Theme>) -> Result<Vec<u8>, Report<AppError>> {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: args.rows,
+            cols: args.cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|err| Report::new(AppError).attach(format!("{err:#}")))
+        .attach("failed to open a pseudo-terminal")?;
+
+    let mut command = CommandBuilder::from_argv(args.command);
+    let current_dir = std::env::current_dir()
+        .change_context(AppError)
+        .attach("failed to read the current directory")?;
+    command.cwd(current_dir);
+    if command.get_env("TERM").is_none() {
+        command.env("TERM", "xterm-256color");
+    }
+    let program = command.get_argv()[0].to_string_lossy().into_owned();
+    let mut child = pair
+        .slave
+        .spawn_command(command)
+        .map_err(|err| Report::new(AppError).attach(format!("{err:#}")))
+        .attach_with(|| format!("failed to run '{program}'"))?;
+    // Our copy of the terminal would keep the output open after the command exits
+    drop(pair.slave);
+
+    let mut reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|err| Report::new(AppError).attach(format!("{err:#}")))
+        .attach("failed to read from the pseudo-terminal")?;
+    let capture = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buffer = [0; 8192];
+        // Reading fails instead of ending on some platforms once the command exits
+        while let Ok(length @ 1..) = reader.read(&mut buffer) {
+            output.extend_from_slice(&buffer[..length]);
+        }
+        output
+    });
+    let status = child
+        .wait()
+        .change_context(AppError)
+        .attach_with(|| format!("failed to wait for '{program}'"))?;
+    drop(pair.master);
+    let output = capture
+        .join()
+        .map_err(|_| Report::new(AppError).attach("failed to capture the command's output"))?;
+    if !status.success() {
+        eprintln!(
+            "warning: '{program}' exited with code {}",
+            status.exit_code()
+        );
+    }
+
+    // Terminals turn every newline into a carriage return and newline
+    let output = String::from_utf8_lossy(&output).replace("\r\n", "\n");
+    let text = parse_ansi(&output);
+    let rendered = match args.to {
+        ExecOutputFormat::Ansi => output,
+        ExecOutputFormat::Html => format!("{}\n", text.to_html()),
+        ExecOutputFormat::Svg => {
+            let options = SvgOptions {
+                theme: theme.unwrap_or_else(Theme::dark),
+                ..Default::default()
+            };
+            text.to_svg_with(&options)
+        }
+        ExecOutputFormat::Json => format!("{}\n", text.normalize().to_json()),
+        ExecOutputFormat::Markdown => format!("{}\n", text.normalize().to_markdown()),
+        ExecOutputFormat::Text => text.to_plain_text(),
+    };
+    Ok(rendered.into_bytes())
+}
+
+/// Script added to preview pages that reloads the page when the input's version changes.
+const RELOAD_SCRIPT: &str = "<script>(() => { const version = \"{version}\"; \
+    setInterval(async () => { try { const response = await fetch(\"/version\"); \
+    if ((await response.text()) !== version) { location.reload(); } } catch (err) {} }, 1000); \
+    })();</script>";
+
+/// Serves a preview of the input over HTTP until the process is stopped.
+///
+/// Files are read again for every page load, and the page polls `/version` to reload
+/// itself when they change.
+fn serve_command(
+    args: &ServeArgs,
+    input_options: &InputOptions,
+    theme: Option<Theme>,
+) -> Result<Vec<u8>, Report<AppError>> {
+    // Standard input can only be read once, so it is kept for every page load
+    let stdin = if args.input.is_empty() {
+        Some(read_text_input(Vec::new(), input_options)?)
+    } else {
+        None
+    };
+    let document = match theme {
+        Some(theme) => HtmlDocumentOptions {
+            light: theme,
+            dark: None,
+            ..Default::default()
+        },
+        None => HtmlDocumentOptions::default(),
+    };
+
+    let listener = TcpListener::bind((args.host.as_str(), args.port))
+        .change_context(AppError)
+        .attach_with(|| format!("failed to listen on {}:{}", args.host, args.port))?;
+    let address = listener
+        .local_addr()
+        .change_context(AppError)
+        .attach("failed to read the server address")?;
+    eprintln!("Serving a preview at http://{address}/ (press Ctrl+C to stop)");
+
+    thread::scope(|scope| {
+        for stream in listener.incoming().flatten() {
+            let (input, stdin, document) = (&args.input, stdin.as_deref(), &document);
+            scope.spawn(move || {
+                if let Err(err) = serve_request(&stream, input, stdin, input_options, document) {
+                    eprintln!("warning: failed to answer a preview request: {err}");
                 }
-                HtmlOutputType::Standalone => {
-                    let css = generate_css();
-                    let full_html = format!(
-                        "<!DOCTYPE html><html><head><style>{css}</style></head><body>{html}</body></html>"
-                    );
-                    println!("{full_html}");
+            });
+        }
+    });
+    Ok(Vec::new())
+}
+
+/// Answers one HTTP request for the preview page or its version.
+fn serve_request(
+    stream: &TcpStream,
+    input: &[PathBuf],
+    stdin: Option<&str>,
+    input_options: &InputOptions,
+    document: &HtmlDocumentOptions,
+) -> io::Result<()> {
+    // Browsers may open connections ahead of time and never use them
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let version = input_version(input);
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (status, content_type, body) = match path {
+        "/" => {
+            let text = match stdin {
+                Some(text) => Ok(text.to_string()),
+                None => read_text_input(input.to_vec(), input_options),
+            };
+            // Errors are shown on the page, which reloads once the input is fixed
+            let (status, styled) = match text {
+                Ok(text) => ("200 OK", parse_input(&text, input_options)),
+                Err(report) => (
+                    "500 Internal Server Error",
+                    parse_ansi(&format!("{report:?}")),
+                ),
+            };
+            let html = styled.to_html_document(&HtmlOptions::default(), document);
+            let script = RELOAD_SCRIPT.replace("{version}", &version);
+            let html = html.replacen("</body>", &format!("{script}</body>"), 1);
+            (status, "text/html; charset=utf-8", html)
+        }
+        "/version" => ("200 OK", "text/plain; charset=utf-8", version),
+        _ => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "not found".to_string(),
+        ),
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Returns a string that changes whenever one of `files` is modified, created, or deleted.
+fn input_version(files: &[PathBuf]) -> String {
+    let versions: Vec<String> = files
+        .iter()
+        .map(
+            |path| match fs::metadata(path).and_then(|meta| Ok((meta.modified()?, meta.len()))) {
+                Ok((modified, len)) => {
+                    let modified = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+                    format!("{}-{len}", modified.as_nanos())
                 }
+                Err(_) => "missing".to_string(),
+            },
+        )
+        .collect();
+    versions.join(",")
+}
+
+/// Shows the input in a pager until it is closed.
+///
+/// Without a terminal to show it on, the input is printed as with `ansi`, as `less` does.
+#[cfg(feature = "crossterm")]
+fn view_command(args: ViewArgs, input_options: &InputOptions) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_text_input(args.input, input_options)?;
+    let text = parse_input(&input, input_options);
+    let depth = args
+        .color_depth
+        .map_or_else(ColorDepth::detect, ColorDepth::from);
+    let mut stdout = io::stdout();
+    if !stdout.is_terminal() {
+        let options = AnsiOptions {
+            color_depth: depth,
+            ..Default::default()
+        };
+        return Ok(text.to_ansi(&options).into_bytes());
+    }
+
+    let size = terminal::size()
+        .change_context(AppError)
+        .attach("failed to read the terminal size")?;
+    let mut pager = Pager::new(&text, depth, size);
+    terminal::enable_raw_mode()
+        .change_context(AppError)
+        .attach("failed to set up the terminal")?;
+    // The terminal is restored even if the pager fails, or panics
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+        default_hook(info);
+    }));
+    let result = execute!(stdout, EnterAlternateScreen, Hide).and_then(|()| pager.run(&mut stdout));
+    let restored = execute!(stdout, Show, LeaveAlternateScreen).and(terminal::disable_raw_mode());
+    drop(panic::take_hook());
+    result
+        .and(restored)
+        .change_context(AppError)
+        .attach("failed to show the pager")?;
+    Ok(Vec::new())
+}
+
+/// A scrollable, searchable view of styled text in the terminal.
+#[cfg(feature = "crossterm")]
+struct Pager {
+    /// The lines of the input.
+    source: Vec<StyledText>,
+    /// The input wrapped to the width of the screen, one entry per row.
+    rows: Vec<StyledText>,
+    /// The plain text of each row, for searching.
+    plain_rows: Vec<String>,
+    /// The line of the input each row belongs to.
+    row_lines: Vec<usize>,
+    /// The first row on screen.
+    top: usize,
+    /// The width and height of the screen.
+    size: (u16, u16),
+    depth: ColorDepth,
+    /// The last pattern searched for.
+    search: Option<Regex>,
+    /// The row of the last match, where searching continues from.
+    matched: Option<usize>,
+    /// The pattern being typed after `/`, while the prompt is open.
+    prompt: Option<String>,
+    /// A message shown in place of the status until the next key.
+    message: Option<String>,
+}
+
+#[cfg(feature = "crossterm")]
+impl Pager {
+    fn new(text: &StyledText, depth: ColorDepth, size: (u16, u16)) -> Self {
+        let mut pager = Pager {
+            source: text.split_lines(),
+            rows: Vec::new(),
+            plain_rows: Vec::new(),
+            row_lines: Vec::new(),
+            top: 0,
+            size,
+            depth,
+            search: None,
+            matched: None,
+            prompt: None,
+            message: None,
+        };
+        pager.resize(size);
+        pager
+    }
+
+    /// Draws the screen and handles input until the pager is closed.
+    fn run(&mut self, out: &mut impl Write) -> io::Result<()> {
+        loop {
+            // The screen is written at once, so it does not flicker while drawing
+            let mut screen = Vec::new();
+            self.draw(&mut screen)?;
+            out.write_all(&screen)?;
+            out.flush()?;
+            let open = match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => self.key(key),
+                Event::Resize(columns, rows) => {
+                    self.resize((columns, rows));
+                    true
+                }
+                _ => true,
+            };
+            if !open {
+                return Ok(());
             }
         }
-        Some(Commands::Rex { input }) => {
-            let data = read_binary_input(input)?;
-            let ansi = rexpaint_to_ansi(&data)
-                .change_context(AppError)
-                .attach("RexPaint conversion failed")?;
-            print!("{ansi}");
+    }
+
+    /// Wraps the input to a new screen size, keeping the line at the top in view.
+    fn resize(&mut self, size: (u16, u16)) {
+        let top_line = self.row_lines.get(self.top).copied().unwrap_or(0);
+        self.size = size;
+        self.rows.clear();
+        self.row_lines.clear();
+        for (idx, line) in self.source.iter().enumerate() {
+            let mut rows = line.wrap(usize::from(size.0)).split_lines();
+            if rows.is_empty() {
+                rows.push(StyledText::default());
+            }
+            self.row_lines.extend(std::iter::repeat_n(idx, rows.len()));
+            self.rows.extend(rows);
+        }
+        self.plain_rows = self.rows.iter().map(StyledText::to_plain_text).collect();
+        self.matched = None;
+        let top = self.row_lines.partition_point(|&line| line < top_line);
+        self.top = top.min(self.max_top());
+    }
+
+    /// Returns the number of rows of text on screen, above the status line.
+    fn page_height(&self) -> usize {
+        usize::from(self.size.1.saturating_sub(1)).max(1)
+    }
+
+    /// Returns the top row that shows the end of the input at the bottom of the screen.
+    fn max_top(&self) -> usize {
+        self.rows.len().saturating_sub(self.page_height())
+    }
+
+    /// Handles a key press, returning `false` once the pager should close.
+    fn key(&mut self, key: KeyEvent) -> bool {
+        self.message = None;
+        if let Some(prompt) = &mut self.prompt {
+            match key.code {
+                KeyCode::Enter => {
+                    let pattern = mem::take(prompt);
+                    self.prompt = None;
+                    self.start_search(&pattern);
+                }
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Backspace if prompt.is_empty() => self.prompt = None,
+                KeyCode::Backspace => {
+                    prompt.pop();
+                }
+                KeyCode::Char(ch) => prompt.push(ch),
+                _ => {}
+            }
+            return true;
+        }
+
+        let page = self.page_height();
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Enter | KeyCode::Char('j') => self.scroll_down(1),
+            KeyCode::Up | KeyCode::Char('k') => self.top = self.top.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ' | 'f') => self.scroll_down(page),
+            KeyCode::PageUp | KeyCode::Char('b') => self.top = self.top.saturating_sub(page),
+            KeyCode::Char('d') => self.scroll_down(page / 2),
+            KeyCode::Char('u') => self.top = self.top.saturating_sub(page / 2),
+            KeyCode::Home | KeyCode::Char('g') => self.top = 0,
+            KeyCode::End | KeyCode::Char('G') => self.top = self.max_top(),
+            KeyCode::Char('/') => self.prompt = Some(String::new()),
+            KeyCode::Char('n') => self.find(true),
+            KeyCode::Char('N') => self.find(false),
+            _ => {}
+        }
+        true
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        self.top = (self.top + rows).min(self.max_top());
+    }
+
+    /// Searches for `pattern` from the top of the screen, ignoring case unless the pattern
+    /// has uppercase letters. An empty pattern repeats the last search.
+    fn start_search(&mut self, pattern: &str) {
+        if !pattern.is_empty() {
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(!pattern.chars().any(char::is_uppercase))
+                .build();
+            let Ok(regex) = regex else {
+                self.message = Some(format!("Invalid pattern: {pattern}"));
+                return;
+            };
+            self.search = Some(regex);
+            self.matched = None;
         }
-        Some(Commands::ToRex { input, output }) => {
-            let input_text = read_text_input(input)?;
-            let xp_data = ansi_to_rexpaint(&input_text)
+        self.find(true);
+    }
+
+    /// Scrolls to the next row matching the search, or the previous one, wrapping around
+    /// at the end of the input.
+    fn find(&mut self, forward: bool) {
+        let Some(regex) = &self.search else {
+            self.message = Some("No previous search".to_string());
+            return;
+        };
+        let count = self.rows.len();
+        let start = match self.matched {
+            Some(row) if forward => row + 1,
+            Some(row) => row + count - 1,
+            None => self.top,
+        };
+        let found = (0..count)
+            .map(|step| {
+                if forward {
+                    (start + step) % count
+                } else {
+                    (start + count - step) % count
+                }
+            })
+            .find(|&row| regex.is_match(&self.plain_rows[row]));
+        match found {
+            Some(row) => {
+                self.matched = Some(row);
+                self.top = row.min(self.max_top());
+            }
+            None => self.message = Some("Pattern not found".to_string()),
+        }
+    }
+
+    /// Draws the rows on screen and the status line below them.
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let page = self.page_height();
+        // Matches stand out as in `less`
+        let highlight = Style {
+            fg_color: Some(Color::Indexed(0)),
+            bg_color: Some(Color::Indexed(3)),
+            ..Default::default()
+        };
+        let options = AnsiOptions {
+            color_depth: self.depth,
+            ..Default::default()
+        };
+        for (screen_row, row) in (0..page).zip(self.top..) {
+            out.queue(MoveTo(0, u16::try_from(screen_row).unwrap_or(u16::MAX)))?;
+            out.queue(Clear(ClearType::UntilNewLine))?;
+            let ansi = match (self.rows.get(row), &self.search) {
+                (Some(text), Some(regex)) => text.highlight(regex, &highlight).to_ansi(&options),
+                (Some(text), None) => text.to_ansi(&options),
+                // Rows past the end are marked, as in `less` and `vi`
+                (None, _) => "~".to_string(),
+            };
+            out.queue(Print(ansi))?;
+        }
+
+        let status = if let Some(prompt) = &self.prompt {
+            format!("/{prompt}")
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else {
+            let last_row = (self.top + page).min(self.rows.len()).saturating_sub(1);
+            let first = self.row_lines.get(self.top).map_or(0, |line| line + 1);
+            let last = self.row_lines.get(last_row).map_or(0, |line| line + 1);
+            let percent = if self.rows.is_empty() {
+                100
+            } else {
+                (last_row + 1) * 100 / self.rows.len()
+            };
+            format!(
+                "lines {first}-{last} of {} ({percent}%)   q quit  / search  n/N next/previous",
+                self.source.len()
+            )
+        };
+        let width = usize::from(self.size.0);
+        let status: String = format!("{status:<width$}").chars().take(width).collect();
+        out.queue(MoveTo(0, self.size.1.saturating_sub(1)))?;
+        out.queue(SetAttribute(Attribute::Reverse))?;
+        out.queue(Print(status))?;
+        out.queue(SetAttribute(Attribute::Reset))?;
+        Ok(())
+    }
+}
+
+/// Converts a `RexPaint` file to ANSI text or HTML.
+fn rex_command(args: RexArgs) -> Result<Vec<u8>, Report<AppError>> {
+    let data = read_binary_input(args.input)?;
+    let layers = match args.layer {
+        Some(idx) => LayerSelection::Only(idx),
+        None if !args.exclude_layer.is_empty() => LayerSelection::Exclude(args.exclude_layer),
+        None => LayerSelection::All,
+    };
+    let options = RexImportOptions {
+        layers,
+        reverse_layers: args.reverse_layers,
+        codepage: args.codepage,
+        transparency: args.transparency,
+        color_depth: args.color_depth.into(),
+        coalesce: args.coalesce,
+        trim_trailing: args.trim_trailing,
+    };
+    let output = match args.to {
+        RexOutputFormat::Ansi => rexpaint_to_ansi_with(&data, &options).map(String::into_bytes),
+        RexOutputFormat::Html => xp_to_html_with(&data, &HtmlOptions::default(), &options)
+            .map(|html| format!("{html}\n").into_bytes()),
+        #[cfg(feature = "png")]
+        RexOutputFormat::Png => xp_to_png_with(&data, &options, &read_font(args.font)?),
+    };
+    output
+        .change_context(AppError)
+        .attach("RexPaint conversion failed")
+}
+
+/// Renders the input as a PNG image.
+#[cfg(feature = "png")]
+fn png_command(
+    args: PngArgs,
+    input_options: &InputOptions,
+    theme: Option<Theme>,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_text_input(args.input, input_options)?;
+    let options = PngOptions {
+        font: read_font(args.font)?,
+        scale: usize::from(args.scale),
+        theme: theme.unwrap_or_else(Theme::dark),
+        combining: args.combining.into(),
+    };
+    parse_input(&input, input_options)
+        .to_png_with(&options)
+        .change_context(AppError)
+        .attach("PNG rendering failed")
+}
+
+/// Loads the font atlas at `path`, or `RexPaint`'s default font if no path is given.
+#[cfg(feature = "png")]
+fn read_font(path: Option<PathBuf>) -> Result<FontAtlas, Report<AppError>> {
+    let Some(path) = path else {
+        return Ok(FontAtlas::default());
+    };
+    let font = fs::read(&path)
+        .change_context(AppError)
+        .attach_with(|| format!("failed to read font '{}'", path.display()))?;
+    FontAtlas::from_png(&font)
+        .change_context(AppError)
+        .attach("invalid font atlas")
+}
+
+/// Compares two inputs and renders their differences.
+fn diff_command(args: DiffArgs, input_options: &InputOptions) -> Result<Vec<u8>, Report<AppError>> {
+    let old = parse_input(
+        &read_text_input(vec![args.old], input_options)?,
+        input_options,
+    );
+    let new = parse_input(
+        &read_text_input(vec![args.new], input_options)?,
+        input_options,
+    );
+    let granularity = if args.words {
+        DiffGranularity::Word
+    } else {
+        DiffGranularity::Line
+    };
+    Ok(render_text(&old.diff(&new, granularity), &args.to))
+}
+
+/// Renders styled text in a [`TextOutputFormat`].
+fn render_text(text: &StyledText, format: &TextOutputFormat) -> Vec<u8> {
+    let output = match format {
+        TextOutputFormat::Ansi => text.to_ansi(&AnsiOptions::default()),
+        TextOutputFormat::Html => format!("{}\n", text.to_html()),
+    };
+    output.into_bytes()
+}
+
+/// Simulates color blindness on the input, or adjusts its colors for it.
+fn colorblind_command(
+    args: ColorblindArgs,
+    input_options: &InputOptions,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_text_input(args.input, input_options)?;
+    let parsed = parse_input(&input, input_options);
+    let kind = args.kind.into();
+    let recolored = if args.fix {
+        parsed.daltonize(kind)
+    } else {
+        parsed.simulate_colorblindness(kind)
+    };
+    Ok(render_text(&recolored, &args.to))
+}
+
+/// Prints the lines of the input that match a pattern.
+fn grep_command(args: GrepArgs, input_options: &InputOptions) -> Result<Vec<u8>, Report<AppError>> {
+    let regex = RegexBuilder::new(&args.pattern)
+        .case_insensitive(args.ignore_case)
+        .build()
+        .change_context(AppError)
+        .attach_with(|| format!("invalid pattern '{}'", args.pattern))?;
+    let highlight = Style {
+        fg_color: Some(Color::Indexed(1)),
+        bold: true,
+        ..Default::default()
+    };
+
+    let input = read_text_input(args.input, input_options)?;
+    let parsed = parse_input(&input, input_options);
+    let lines = parsed.split_lines();
+    // The last line only ends in a newline if the input does
+    let ends_with_newline = parsed
+        .segments
+        .iter()
+        .rev()
+        .find(|segment| !segment.text.is_empty())
+        .is_some_and(|segment| segment.text.ends_with('\n'));
+    let line_count = lines.len();
+    let mut selected = Vec::new();
+    let mut terminated = false;
+    for (idx, line) in lines.into_iter().enumerate() {
+        if regex.is_match(&line.to_plain_text()) == args.invert_match {
+            continue;
+        }
+        let line = if args.highlight && !args.invert_match {
+            line.highlight(&regex, &highlight)
+        } else {
+            line
+        };
+        selected.push(line.to_ansi(&AnsiOptions::default()));
+        terminated = idx + 1 < line_count || ends_with_newline;
+    }
+    let mut output = selected.join("\n");
+    if terminated {
+        output.push('\n');
+    }
+    Ok(output.into_bytes())
+}
+
+/// Loads the theme named by `--theme`, reading color schemes from a file.
+fn load_theme(theme: Option<&str>) -> Result<Option<Theme>, Report<AppError>> {
+    let theme = match theme {
+        None => return Ok(None),
+        Some("light") => Theme::light(),
+        Some("dark") => Theme::dark(),
+        Some(path) => {
+            let contents = fs::read_to_string(path)
                 .change_context(AppError)
-                .attach("ANSI to RexPaint conversion failed")?;
-            fs::write(&output, xp_data)
+                .attach_with(|| format!("failed to read theme file '{path}'"))?;
+            Theme::parse(&contents)
                 .change_context(AppError)
-                .attach_with(|| format!("failed to write output file '{}'", output.display()))?;
+                .attach_with(|| format!("invalid theme file '{path}'"))?
         }
-        Some(Commands::Css) => {
-            let css = generate_css();
-            println!("{css}");
-            // No debug for CSS since no input parsed
+    };
+    Ok(Some(theme))
+}
+
+/// Prints the parsed input as JSON, as one document or one line of JSON per input line.
+fn json_command(
+    input: Vec<PathBuf>,
+    input_options: &InputOptions,
+    pretty: bool,
+    lines: bool,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_text_input(input, input_options)?;
+    let parsed = parse_input(&input, input_options).normalize();
+    let json = match (lines, pretty) {
+        (false, false) => parsed.to_json(),
+        (false, true) => parsed.to_json_pretty(),
+        (true, false) => parsed.to_json_lines(),
+        (true, true) => parsed.to_json_lines_pretty(),
+    };
+    Ok(format!("{json}\n").into_bytes())
+}
+
+/// Converts the input to a `RexPaint` file.
+fn to_rex_command(
+    args: ToRexArgs,
+    input_options: &InputOptions,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let input_text = read_text_input(args.input, input_options)?;
+    if let Some(width) = args.width
+        && !args.wrap
+    {
+        let longest = parse_input(&input_text, input_options).width();
+        if longest > width {
+            eprintln!(
+                "warning: lines up to {longest} columns wide were clipped to {width}; \
+                 use --wrap to keep them"
+            );
         }
     }
+    let options = RexOptions {
+        default_fg: args.fg,
+        default_bg: args.bg,
+        transparent_background: args.transparent_background,
+        width: args.width,
+        wrap: args.wrap,
+        split_layers: args.split_layers,
+        height: args.height,
+        replacement: args.replacement,
+        codepage: args.codepage,
+        approximate_glyphs: !args.exact_glyphs,
+        wide_chars: args.wide_chars.into(),
+        emoji: args.emoji,
+        combining: args.combining.into(),
+    };
+    let parse_options = ParseOptions {
+        source_spans: true,
+        ..input_options.parse_options()
+    };
+    let (xp, diagnostics) = parse_input_with(&input_text, input_options, &parse_options)
+        .to_rexpaint_with_diagnostics(&options);
+    for diagnostic in diagnostics {
+        eprintln!("warning: {diagnostic}");
+    }
+    let mut output = Vec::new();
+    xp.write(&mut output)
+        .change_context(AppError)
+        .attach("ANSI to RexPaint conversion failed")?;
+    Ok(output)
+}
+
+/// Converts an image to ANSI text drawn with half blocks.
+#[cfg(feature = "image")]
+fn image_to_ansi_command(args: ImageArgs) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_binary_input(args.input)?;
+    let image = RgbaImage::decode(&input)
+        .change_context(AppError)
+        .attach("failed to decode image")?;
+    let options = ImageOptions {
+        columns: usize::from(args.columns),
+        ..Default::default()
+    };
+    Ok(image
+        .to_half_blocks(&options)
+        .to_ansi(&AnsiOptions::default())
+        .into_bytes())
+}
+
+/// Converts an image to a `RexPaint` file by matching each cell to a glyph.
+#[cfg(feature = "image")]
+fn image_to_rex_command(args: ImageToRexArgs) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_binary_input(args.input)?;
+    let image = RgbaImage::decode(&input)
+        .change_context(AppError)
+        .attach("failed to decode image")?;
+    let options = ImageOptions {
+        columns: usize::from(args.columns),
+        font: read_font(args.font)?,
+    };
+    let mut output = Vec::new();
+    image
+        .to_rexpaint(&options)
+        .write(&mut output)
+        .change_context(AppError)
+        .attach("failed to write RexPaint file")?;
+    Ok(output)
+}
 
+/// Renders the input as an HTML fragment or standalone page.
+fn html_command(
+    args: HtmlArgs,
+    input_options: &InputOptions,
+    theme: Option<Theme>,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let mode = args.mode.clone();
+    let (parsed, options, document_options) = prepare_html(args, input_options, theme)?;
+    let html = match mode {
+        HtmlOutputType::Fragment => parsed.to_html_with(&options),
+        HtmlOutputType::Standalone => parsed.to_html_document(&options, &document_options),
+    };
+    Ok(format!("{html}\n").into_bytes())
+}
+
+/// Renders the input as linked standalone pages of `--page-lines` lines each, writing the
+/// first page to `output` and the rest next to it.
+fn html_pages_command(
+    args: HtmlArgs,
+    output: &Path,
+    input_options: &InputOptions,
+    theme: Option<Theme>,
+) -> Result<(), Report<AppError>> {
+    let lines_per_page = args.page_lines.map_or(usize::MAX, |lines| {
+        usize::try_from(lines).unwrap_or(usize::MAX)
+    });
+    let (parsed, options, document_options) = prepare_html(args, input_options, theme)?;
+
+    let stem = output
+        .file_stem()
+        .unwrap_or(output.as_os_str())
+        .to_string_lossy();
+    let extension = output
+        .extension()
+        .map_or_else(|| "html".into(), |extension| extension.to_string_lossy());
+    let page_name = |page: usize| {
+        if page == 0 {
+            format!("{stem}.{extension}")
+        } else {
+            format!("{stem}-{}.{extension}", page + 1)
+        }
+    };
+    let pages = parsed.to_html_pages(lines_per_page, page_name, &options, &document_options);
+    for (page, html) in pages.iter().enumerate() {
+        let path = output.with_file_name(page_name(page));
+        write_output(Some(path), format!("{html}\n").as_bytes())?;
+    }
     Ok(())
 }
+
+/// Parses the input of the `html` subcommand and returns it with the rendering options
+/// given by `args`.
+fn prepare_html(
+    args: HtmlArgs,
+    input_options: &InputOptions,
+    theme: Option<Theme>,
+) -> Result<(StyledText, HtmlOptions, HtmlDocumentOptions), Report<AppError>> {
+    let HtmlArgs {
+        input,
+        mode: _,
+        filter,
+        remap,
+        raw,
+        inline_styles,
+        class_prefix,
+        line_anchors,
+        line_numbers,
+        compact,
+        data_sgr,
+        grid,
+        fold_groups,
+        sections,
+        section_file,
+        toc,
+        file_links,
+        source_positions,
+        lines,
+        page_lines: _,
+        label,
+        lang,
+        plain_text_link,
+        toolbar,
+        copy_buttons,
+        ansi_link,
+        dark_theme,
+    } = args;
+    let input = read_text_input(input, input_options)?;
+    let color_map = parse_color_map(&remap)?;
+    // The ANSI download keeps the input's own escape sequences
+    let parse_options = ParseOptions {
+        source_spans: source_positions,
+        raw_escapes: ansi_link,
+        ..input_options.parse_options()
+    };
+    let mut parsed = parse_input_with(&input, input_options, &parse_options)
+        .remap_colors(&color_map)
+        .normalize();
+    // Line numbers of an excerpt start where it does in the full text
+    let mut first_line_number = 1;
+    if let Some(lines) = lines {
+        let range = lines.resolve(parsed.split_lines().len());
+        first_line_number += range.start;
+        parsed = parsed.slice_lines(range);
+    }
+    let dark_theme = load_theme(dark_theme.as_deref())?;
+    // With two themes the palette is left to the page, which switches between them
+    if let Some(theme) = &theme
+        && dark_theme.is_none()
+    {
+        parsed = parsed.resolve_colors(theme);
+    }
+    let options = HtmlOptions {
+        filter,
+        escape: !raw,
+        inline_styles,
+        class_prefix,
+        line_anchors,
+        line_numbers,
+        first_line_number,
+        compact,
+        data_sgr,
+        layout: if grid {
+            HtmlLayout::Grid
+        } else {
+            HtmlLayout::Flow
+        },
+        fold_groups,
+        sections: read_section_markers(&sections, section_file.as_deref())?,
+        table_of_contents: toc,
+        file_links,
+        source_positions,
+        ..Default::default()
+    };
+    let document_options = HtmlDocumentOptions {
+        label,
+        lang,
+        plain_text_link,
+        toolbar,
+        copy_buttons,
+        ansi_link,
+        ..Default::default()
+    };
+    let document_options = match (theme, dark_theme) {
+        (theme, Some(dark)) => HtmlDocumentOptions {
+            light: theme.unwrap_or_else(Theme::light),
+            dark: Some(dark),
+            theme_toggle: true,
+            ..document_options
+        },
+        (Some(theme), None) => HtmlDocumentOptions {
+            light: theme,
+            dark: None,
+            ..document_options
+        },
+        (None, None) => document_options,
+    };
+    Ok((parsed, options, document_options))
+}
+
+fn main() -> Result<(), Report<AppError>> {
+    let args = Args::parse();
+    Report::set_color_mode(ColorMode::Color);
+
+    let mut theme = load_theme(args.theme.as_deref())?;
+    let high_contrast = args.high_contrast.then(|| {
+        let light = theme
+            .as_ref()
+            .is_some_and(|theme| theme.background.relative_luminance() > 0.5);
+        if light {
+            Theme::high_contrast_light()
+        } else {
+            Theme::high_contrast_dark()
+        }
+    });
+    if high_contrast.is_some() {
+        theme.clone_from(&high_contrast);
+    }
+    let input_options = InputOptions {
+        encoding: args.encoding,
+        ice_colors: args.ice_colors,
+        art_width: args.art_width.map(usize::from),
+        color_codes: args.color_codes.map(ColorCodes::from).unwrap_or_default(),
+        high_contrast,
+    };
+    let mut command = args.command;
+    if let Some(patterns) = command.as_mut().and_then(Commands::input_mut) {
+        *patterns = expand_globs(patterns)?;
+    }
+
+    // `html -o` chose the kind of page before `-o` named the output file
+    if let Some(Commands::Html(_)) = command
+        && let Some(mode) = args.output.as_deref().and_then(Path::to_str)
+        && matches!(mode, "standalone" | "fragment")
+    {
+        return Err(Report::new(AppError).attach(format!(
+            "-o names the output file; use --mode {mode} to write a {mode} page, or \
+             -o ./{mode} to write a file named '{mode}'"
+        )));
+    }
+    // Subcommands would ignore the excerpt rather than print it
+    if args.lines.is_some() && command.is_some() {
+        return Err(Report::new(AppError).attach(
+            "--lines before a subcommand only applies when printing the input without one; \
+             html takes its own --lines after the subcommand",
+        ));
+    }
+    if let Some(out_dir) = args.out_dir {
+        return batch(command, &out_dir, &input_options, theme.as_ref());
+    }
+    if let Some(Commands::Html(html_args)) = command.as_ref()
+        && html_args.page_lines.is_some()
+    {
+        let Some(output) = args.output else {
+            return Err(Report::new(AppError).attach("--page-lines needs an output file (-o)"));
+        };
+        return html_pages_command(html_args.clone(), &output, &input_options, theme);
+    }
+    let output = run(command, args.input, args.lines, &input_options, theme)?;
+    write_output(args.output, &output)
+}
+
+/// Prints the input as ANSI text, or the excerpt of it given by `--lines`.
+///
+/// Input is passed through as it was read unless parsing it would change it.
+fn print_command(
+    input: Option<PathBuf>,
+    lines: Option<LineRange>,
+    input_options: &InputOptions,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let input = read_text_input(input.into_iter().collect(), input_options)?;
+    let output = match lines {
+        Some(lines) => lines
+            .slice(&parse_input(&input, input_options))
+            .to_ansi(&AnsiOptions::default()),
+        None if !input_options.changes_input() => input,
+        None => parse_input(&input, input_options).to_ansi(&AnsiOptions::default()),
+    };
+    Ok(output.into_bytes())
+}
+
+/// Runs `command`, or passes `input` through if there is no command, and returns the
+/// output.
+fn run(
+    command: Option<Commands>,
+    input: Option<PathBuf>,
+    lines: Option<LineRange>,
+    input_options: &InputOptions,
+    theme: Option<Theme>,
+) -> Result<Vec<u8>, Report<AppError>> {
+    let output = match command {
+        None => print_command(input, lines, input_options)?,
+        Some(Commands::Html(html_args)) => html_command(html_args, input_options, theme)?,
+        Some(Commands::Json {
+            input,
+            pretty,
+            lines,
+        }) => json_command(input, input_options, pretty, lines)?,
+        Some(Commands::Markdown { input }) => {
+            let input = read_text_input(input, input_options)?;
+            let markdown = parse_input(&input, input_options).normalize().to_markdown();
+            format!("{markdown}\n").into_bytes()
+        }
+        Some(Commands::Pango { input }) => {
+            let input = read_text_input(input, input_options)?;
+            parse_input(&input, input_options)
+                .normalize()
+                .to_pango()
+                .into_bytes()
+        }
+        Some(Commands::Typst { input }) => {
+            let input = read_text_input(input, input_options)?;
+            let typst = parse_input(&input, input_options).normalize().to_typst();
+            format!("{typst}\n").into_bytes()
+        }
+        Some(Commands::Discord { input }) => {
+            let input = read_text_input(input, input_options)?;
+            let discord = parse_input(&input, input_options).normalize().to_discord();
+            format!("{discord}\n").into_bytes()
+        }
+        Some(Commands::Xml { input }) => {
+            let input = read_text_input(input, input_options)?;
+            parse_input(&input, input_options)
+                .normalize()
+                .to_xml()
+                .into_bytes()
+        }
+        Some(Commands::Svg(svg_args)) => svg_command(svg_args, input_options, theme)?,
+        #[cfg(feature = "png")]
+        Some(Commands::Png(png_args)) => png_command(png_args, input_options, theme)?,
+        Some(Commands::Cast(cast_args)) => cast_command(cast_args, theme)?,
+        Some(Commands::Ansi { input, depth }) => {
+            let input_text = read_text_input(input, input_options)?;
+            let options = AnsiOptions {
+                color_depth: depth.into(),
+                ..Default::default()
+            };
+            parse_input(&input_text, input_options)
+                .to_ansi(&options)
+                .into_bytes()
+        }
+        Some(Commands::Recolor { input, maps, to }) => {
+            let map = parse_color_map(&maps)?;
+            let input = read_text_input(input, input_options)?;
+            render_text(&parse_input(&input, input_options).remap_colors(&map), &to)
+        }
+        Some(Commands::Quantize { input, depth }) => {
+            let input = read_text_input(input, input_options)?;
+            parse_input(&input, input_options)
+                .quantize(depth.into())
+                .to_ansi(&AnsiOptions::default())
+                .into_bytes()
+        }
+        Some(Commands::Colorblind(colorblind_args)) => {
+            colorblind_command(colorblind_args, input_options)?
+        }
+        Some(Commands::Diff(diff_args)) => diff_command(diff_args, input_options)?,
+        Some(Commands::Wrap { input, width, to }) => {
+            let input = read_text_input(input, input_options)?;
+            render_text(&parse_input(&input, input_options).wrap(width), &to)
+        }
+        Some(Commands::Grep(grep_args)) => grep_command(grep_args, input_options)?,
+        Some(Commands::Strip { input }) => {
+            let input = read_text_input(input, input_options)?;
+            strip_ansi(&expand_color_codes(&input, input_options)).into_bytes()
+        }
+        Some(Commands::Rex(rex_args)) => rex_command(rex_args)?,
+        Some(Commands::ToRex(to_rex_args)) => to_rex_command(to_rex_args, input_options)?,
+        #[cfg(feature = "image")]
+        Some(Commands::ImageToAnsi(image_args)) => image_to_ansi_command(image_args)?,
+        #[cfg(feature = "image")]
+        Some(Commands::ImageToRex(image_args)) => image_to_rex_command(image_args)?,
+        Some(Commands::Css { class_prefix }) => {
+            // No input is parsed for CSS
+            let css = generate_css_with_prefix(&class_prefix);
+            format!("{css}\n").into_bytes()
+        }
+        #[cfg(feature = "pty")]
+        Some(Commands::Exec(exec_args)) => exec_command(exec_args, theme)?,
+        Some(Commands::Serve(serve_args)) => serve_command(&serve_args, input_options, theme)?,
+        #[cfg(feature = "crossterm")]
+        Some(Commands::View(view_args)) => view_command(view_args, input_options)?,
+        Some(Commands::Stats { input }) => {
+            let input = read_text_input(input, input_options)?;
+            let stats = parse_input(&input, input_options).stats();
+            format!("{stats}\n").into_bytes()
+        }
+    };
+    Ok(output)
+}
+
+/// Converts each input of `command` separately, writing the results to `out_dir` with the
+/// extension of the output format.
+fn batch(
+    command: Option<Commands>,
+    out_dir: &Path,
+    input_options: &InputOptions,
+    theme: Option<&Theme>,
+) -> Result<(), Report<AppError>> {
+    let Some(mut command) = command else {
+        return Err(Report::new(AppError).attach("--out-dir needs a subcommand"));
+    };
+    if matches!(command, Commands::Serve(_)) {
+        return Err(Report::new(AppError).attach("serve does not write output files"));
+    }
+    #[cfg(feature = "crossterm")]
+    if matches!(command, Commands::View(_)) {
+        return Err(Report::new(AppError).attach("view does not write output files"));
+    }
+    let Some(inputs) = command.input_mut().map(mem::take) else {
+        return Err(Report::new(AppError).attach("this subcommand does not take input files"));
+    };
+    if inputs.is_empty() {
+        return Err(Report::new(AppError).attach("--out-dir needs at least one input file"));
+    }
+
+    let extension = command.extension();
+    let output_path = |input: &Path| {
+        let stem = input
+            .file_stem()
+            .unwrap_or(input.as_os_str())
+            .to_string_lossy();
+        out_dir.join(format!("{stem}.{extension}"))
+    };
+    // Inputs with the same name in different directories, or with different extensions,
+    // would write the same output file
+    let mut outputs: HashMap<PathBuf, &Path> = HashMap::new();
+    for input in &inputs {
+        if let Some(other) = outputs.insert(output_path(input), input) {
+            return Err(Report::new(AppError).attach(format!(
+                "'{}' and '{}' would both be written to '{}'",
+                other.display(),
+                input.display(),
+                output_path(input).display()
+            )));
+        }
+    }
+
+    fs::create_dir_all(out_dir)
+        .change_context(AppError)
+        .attach_with(|| format!("failed to create output directory '{}'", out_dir.display()))?;
+
+    let convert = |input: PathBuf| {
+        let output_path = output_path(&input);
+        let mut file_command = command.clone();
+        if let Some(file_input) = file_command.input_mut() {
+            *file_input = vec![input.clone()];
+        }
+        if let Commands::Html(html_args) = &file_command
+            && html_args.page_lines.is_some()
+        {
+            return html_pages_command(
+                html_args.clone(),
+                &output_path,
+                input_options,
+                theme.cloned(),
+            )
+            .attach_with(|| format!("failed to convert '{}'", input.display()));
+        }
+        let output = run(
+            Some(file_command),
+            None,
+            None,
+            input_options,
+            theme.cloned(),
+        )
+        .attach_with(|| format!("failed to convert '{}'", input.display()))?;
+        write_output(Some(output_path), &output)
+    };
+    // Files are independent, so with the `parallel` feature they are converted at once
+    #[cfg(feature = "parallel")]
+    return inputs.into_par_iter().try_for_each(convert);
+    #[cfg(not(feature = "parallel"))]
+    inputs.into_iter().try_for_each(convert)
+}
+
+/// Expands the glob patterns among `patterns` into the files they match, keeping other
+/// paths as they are.
+///
+/// Shells usually expand globs before the program sees them, but not every shell does.
+fn expand_globs(patterns: &[PathBuf]) -> Result<Vec<PathBuf>, Report<AppError>> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let pattern_str = pattern.to_string_lossy();
+        if !pattern_str.contains(['*', '?', '[']) {
+            paths.push(pattern.clone());
+            continue;
+        }
+        let matches: Vec<PathBuf> = glob::glob(&pattern_str)
+            .change_context(AppError)
+            .attach_with(|| format!("invalid glob pattern '{pattern_str}'"))?
+            .filter_map(Result::ok)
+            .collect();
+        if matches.is_empty() {
+            return Err(Report::new(AppError).attach(format!("no files match '{pattern_str}'")));
+        }
+        paths.extend(matches);
+    }
+    Ok(paths)
+}
+
+/// Writes `output` to the file at `path`, or to stdout if no path is given.
+///
+/// The bytes are written as-is, so binary formats are not mangled by shell redirection.
+fn write_output(path: Option<PathBuf>, output: &[u8]) -> Result<(), Report<AppError>> {
+    match path {
+        Some(path) => fs::write(&path, output)
+            .change_context(AppError)
+            .attach_with(|| format!("failed to write output file '{}'", path.display())),
+        None => io::stdout()
+            .lock()
+            .write_all(output)
+            .change_context(AppError)
+            .attach("failed to write to stdout"),
+    }
+}
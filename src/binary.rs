@@ -0,0 +1,94 @@
+use crate::StyledText;
+use error_stack::{Report, ResultExt};
+use wherror::Error;
+
+/// Error raised when styled text cannot be encoded to or decoded from a binary format.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct SerializationError;
+
+impl StyledText {
+    /// Encodes the styled text as `MessagePack`.
+    ///
+    /// Fields are written by name, so the data stays readable if fields are added later.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text cannot be encoded.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, Report<SerializationError>> {
+        rmp_serde::to_vec_named(self)
+            .change_context(SerializationError)
+            .attach("failed to encode styled text as MessagePack")
+    }
+
+    /// Decodes styled text previously encoded with [`to_msgpack`](Self::to_msgpack).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` is not valid `MessagePack` for styled text.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(data: &[u8]) -> Result<StyledText, Report<SerializationError>> {
+        rmp_serde::from_slice(data)
+            .change_context(SerializationError)
+            .attach("failed to decode styled text from MessagePack")
+    }
+
+    /// Encodes the styled text as CBOR.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text cannot be encoded.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Result<Vec<u8>, Report<SerializationError>> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(self, &mut buffer)
+            .change_context(SerializationError)
+            .attach("failed to encode styled text as CBOR")?;
+        Ok(buffer)
+    }
+
+    /// Decodes styled text previously encoded with [`to_cbor`](Self::to_cbor).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` is not valid CBOR for styled text.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(data: &[u8]) -> Result<StyledText, Report<SerializationError>> {
+        ciborium::from_reader(data)
+            .change_context(SerializationError)
+            .attach("failed to decode styled text from CBOR")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParseOptions, parse_ansi_with};
+
+    fn sample() -> StyledText {
+        let options = ParseOptions {
+            source_spans: true,
+            ..Default::default()
+        };
+        parse_ansi_with("a\x1b[1;31mb\x1b[0m\n\x1b[48;2;1;2;3mc", &options)
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        let styled_text = sample();
+        let data = styled_text.to_msgpack().unwrap();
+        assert_eq!(StyledText::from_msgpack(&data).unwrap(), styled_text);
+        assert!(StyledText::from_msgpack(b"\xc1").is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_round_trip() {
+        let styled_text = sample();
+        let data = styled_text.to_cbor().unwrap();
+        assert_eq!(StyledText::from_cbor(&data).unwrap(), styled_text);
+        assert!(StyledText::from_cbor(b"\xff").is_err());
+    }
+}
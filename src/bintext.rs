@@ -0,0 +1,86 @@
+use crate::bbs::apply_attribute;
+use crate::{Codepage, Sauce, Segment, Style, StyledText};
+
+/// Width in columns of BIN art without a SAUCE width, the width of a DOS text screen.
+const DEFAULT_BIN_WIDTH: usize = 80;
+
+/// Reads a BIN screen dump: raw text-mode video memory, a character byte and an attribute
+/// byte per cell, row after row.
+///
+/// Rows are as wide as the SAUCE record declares, or 80 columns. Characters are decoded
+/// from CP437 with the pictographs DOS draws for control codes. The attribute's high bit
+/// makes text blink, or gives it a bright background if the SAUCE record asks for iCE
+/// colors. A partial row at the end is kept.
+#[must_use]
+pub fn parse_bin(data: &[u8]) -> StyledText {
+    let sauce = Sauce::parse(data);
+    let columns = sauce
+        .as_ref()
+        .and_then(Sauce::width)
+        .unwrap_or(DEFAULT_BIN_WIDTH);
+
+    let mut segments = Vec::new();
+    for row in Sauce::strip_record(data).chunks(columns * 2) {
+        for cell in row.chunks_exact(2) {
+            let mut style = Style::default();
+            apply_attribute(&mut style, cell[1]);
+            segments.push(Segment {
+                text: Codepage::Wingdings.decode(cell[0]).to_string(),
+                style,
+                source: None,
+                escapes: None,
+            });
+        }
+        segments.push(Segment {
+            text: "\n".to_string(),
+            style: Style::default(),
+            source: None,
+            escapes: None,
+        });
+    }
+
+    let text = StyledText { segments }.normalize();
+    if sauce.as_ref().is_some_and(Sauce::ice_colors) {
+        text.ice_colors()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::sauce::tests::sauce_record;
+
+    /// Builds a SAUCE record for BIN art `width` columns wide.
+    fn bin_sauce(width: u8, flags: u8) -> Vec<u8> {
+        let mut record = sauce_record(0, 0, flags);
+        let start = record.len() - 128;
+        record[start + 94] = 5;
+        record[start + 95] = width / 2;
+        record
+    }
+
+    #[test]
+    fn test_parse_bin() {
+        // Red on blue, then bright white on black, then a blinking smiley
+        let data = [b'A', 0x14, b'B', 0x0f, 0x01, 0x87, b'C', 0x07];
+        let text = parse_bin(&[data.to_vec(), bin_sauce(2, 0)].concat());
+        assert_eq!(text.to_plain_text(), "AB\n☺C\n");
+        let style = &text.segments[0].style;
+        assert_eq!(
+            (style.fg_color.clone(), style.bg_color.clone()),
+            (Some(Color::Indexed(1)), Some(Color::Indexed(4)))
+        );
+        assert_eq!(text.segments[1].style.fg_color, Some(Color::Indexed(15)));
+        assert!(text.segments[3].style.blink);
+
+        // Without a SAUCE record, rows are 80 columns
+        assert_eq!(parse_bin(&data).to_plain_text(), "AB☺C\n");
+
+        let ice = parse_bin(&[data.to_vec(), bin_sauce(2, 1)].concat());
+        assert!(!ice.segments[3].style.blink);
+        assert_eq!(ice.segments[3].style.bg_color, Some(Color::Indexed(8)));
+    }
+}
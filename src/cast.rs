@@ -0,0 +1,261 @@
+use crate::grid::{Cell, Grid};
+use crate::{Color, Screen, StyledText};
+use error_stack::{Report, ResultExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wherror::Error;
+
+/// How long the last frame of a recording is shown before an animation starts over, in
+/// seconds.
+pub(crate) const FINAL_FRAME_SECONDS: f64 = 2.0;
+
+/// Error raised when an asciinema recording cannot be read.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct CastError;
+
+/// A terminal session recorded by asciinema, in the asciicast v2 or v3 format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    /// Width of the terminal in columns.
+    pub width: usize,
+    /// Height of the terminal in rows.
+    pub height: usize,
+    /// Longest pause between events in seconds, if the recording limits idle time.
+    pub idle_time_limit: Option<f64>,
+    /// Output written to the terminal, with the time in seconds from the start of the
+    /// recording that it was written at.
+    pub output: Vec<(f64, String)>,
+}
+
+/// The screen at one point of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    /// Time in seconds from the start of the recording at which the frame is shown.
+    pub time: f64,
+    /// The contents of the screen.
+    pub text: StyledText,
+    /// Width of the screen in columns.
+    pub width: usize,
+    /// Height of the screen in rows.
+    pub height: usize,
+    /// The cursor position as a zero-based `(column, row)`, or `None` if it is hidden.
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl Recording {
+    /// Parses an asciicast v2 or v3 recording.
+    ///
+    /// Only output events are kept; input, markers, and resizes are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header or an event is not valid asciicast.
+    pub fn parse(data: &str) -> Result<Self, Report<CastError>> {
+        let mut lines = data.lines().enumerate().filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        });
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| Report::new(CastError).attach("recording is empty"))?;
+        let header: Value = serde_json::from_str(header)
+            .change_context(CastError)
+            .attach("invalid header")?;
+
+        let version = header["version"].as_u64();
+        let (width, height) = match version {
+            Some(2) => (&header["width"], &header["height"]),
+            Some(3) => (&header["term"]["cols"], &header["term"]["rows"]),
+            _ => {
+                return Err(Report::new(CastError).attach(format!(
+                    "unsupported asciicast version {}",
+                    header["version"]
+                )));
+            }
+        };
+        let size = |value: &Value| {
+            value
+                .as_u64()
+                .and_then(|size| usize::try_from(size).ok())
+                .ok_or_else(|| Report::new(CastError).attach("header is missing the terminal size"))
+        };
+        let mut recording = Recording {
+            width: size(width)?,
+            height: size(height)?,
+            idle_time_limit: header["idle_time_limit"].as_f64(),
+            output: Vec::new(),
+        };
+
+        // Version 3 stores the time since the previous event instead of since the start
+        let mut time = 0.0;
+        for (line_idx, line) in lines {
+            let (event_time, code, data): (f64, String, String) = serde_json::from_str(line)
+                .change_context(CastError)
+                .attach_with(|| format!("invalid event on line {}", line_idx + 1))?;
+            time = if version == Some(3) {
+                time + event_time
+            } else {
+                event_time
+            };
+            if code == "o" {
+                recording.output.push((time, data));
+            }
+        }
+        Ok(recording)
+    }
+
+    /// Replays the recording on a [`Screen`] and returns the frames it showed.
+    ///
+    /// Output written within `frame_interval` seconds of the last frame is merged into the
+    /// next one, so fast-scrolling output does not produce a frame per write, and pauses
+    /// are shortened to the recording's idle time limit. The first frame is the blank
+    /// screen at time zero.
+    #[must_use]
+    pub fn frames(&self, frame_interval: f64) -> Vec<Frame> {
+        let mut screen = Screen::new(self.width, self.height);
+        let mut frames = vec![snapshot(&screen, 0.0)];
+        let (mut time, mut last_event) = (0.0, 0.0);
+        for (event_time, data) in &self.output {
+            let gap = (event_time - last_event).max(0.0);
+            last_event = *event_time;
+            time += self.idle_time_limit.map_or(gap, |limit| gap.min(limit));
+
+            screen.feed(data);
+            let frame = snapshot(&screen, time);
+            match frames.last_mut() {
+                // Too soon for a new frame, so the last one shows this output instead
+                Some(last) if time - last.time < frame_interval => {
+                    *last = Frame {
+                        time: last.time,
+                        ..frame
+                    };
+                }
+                Some(last) if last.text == frame.text && last.cursor == frame.cursor => {}
+                _ => frames.push(frame),
+            }
+        }
+        frames
+    }
+}
+
+impl Frame {
+    /// Returns the contents of the screen with the cursor, if visible, drawn as a cell with
+    /// reversed colors.
+    #[must_use]
+    pub fn text_with_cursor(&self) -> StyledText {
+        let Some((column, row)) = self.cursor else {
+            return self.text.clone();
+        };
+        let mut grid = Grid::from_styled(&self.text);
+        if grid.rows.len() <= row {
+            grid.rows.resize_with(row + 1, Vec::new);
+        }
+        let cells = &mut grid.rows[row];
+        if cells.len() <= column {
+            cells.resize_with(column + 1, Cell::blank);
+        }
+        let mut column = column;
+        while column > 0 && cells[column].is_continuation() {
+            column -= 1;
+        }
+        // Default colors are filled in from the palette, since reversing them is invisible
+        // in formats that leave the page colors to the viewer
+        let style = &mut cells[column].style;
+        style.fg_color.get_or_insert(Color::Indexed(7));
+        style.bg_color.get_or_insert(Color::Indexed(0));
+        style.reverse = !style.reverse;
+        grid.into_styled()
+    }
+}
+
+/// Captures the current state of `screen` as a frame shown at `time`.
+fn snapshot(screen: &Screen, time: f64) -> Frame {
+    Frame {
+        time,
+        text: screen.to_styled_text(),
+        width: screen.width(),
+        height: screen.height(),
+        cursor: screen.cursor_visible().then(|| screen.cursor()),
+    }
+}
+
+/// Returns how long each frame is shown for, in seconds.
+///
+/// The last frame is held for [`FINAL_FRAME_SECONDS`] so looping animations pause on it.
+pub(crate) fn frame_durations(frames: &[Frame]) -> Vec<f64> {
+    frames
+        .windows(2)
+        .map(|pair| pair[1].time - pair[0].time)
+        .chain(frames.last().map(|_| FINAL_FRAME_SECONDS))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAST_V2: &str = r#"{"version": 2, "width": 10, "height": 2, "idle_time_limit": 1.0}
+[0.5, "o", "$ ls\r\n"]
+[0.6, "i", "q"]
+[5.0, "o", "\u001b[32mfile\u001b[0m"]
+[5.01, "o", "!"]
+"#;
+
+    #[test]
+    fn test_parse_v2() {
+        let recording = Recording::parse(CAST_V2).unwrap();
+        assert_eq!((recording.width, recording.height), (10, 2));
+        assert_eq!(recording.idle_time_limit, Some(1.0));
+        assert_eq!(recording.output.len(), 3);
+        assert_eq!(
+            recording.output[1],
+            (5.0, "\x1b[32mfile\x1b[0m".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_v3_relative_times() {
+        let cast = "{\"version\": 3, \"term\": {\"cols\": 4, \"rows\": 1}}\n\
+                    # a comment\n\
+                    [0.5, \"o\", \"a\"]\n[0.25, \"o\", \"b\"]\n";
+        let recording = Recording::parse(cast).unwrap();
+        assert_eq!((recording.width, recording.height), (4, 1));
+        assert_eq!(
+            recording.output,
+            vec![(0.5, "a".to_string()), (0.75, "b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Recording::parse("").is_err());
+        assert!(Recording::parse("{\"version\": 1}").is_err());
+        assert!(
+            Recording::parse("{\"version\": 2, \"width\": 1, \"height\": 1}\nnot json").is_err()
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let frames = Recording::parse(CAST_V2).unwrap().frames(0.1);
+        let times: Vec<f64> = frames.iter().map(|frame| frame.time).collect();
+        // The 4.4 second pause is cut to the 1 second idle time limit, and "!" lands in the
+        // same frame as "file"
+        assert_eq!(times, vec![0.0, 0.5, 1.5]);
+        assert_eq!(frames[2].text.to_plain_text(), "$ ls\nfile!\n");
+        assert_eq!(frames[2].cursor, Some((5, 1)));
+        let cursor = frames[2].text_with_cursor();
+        assert_eq!(cursor.to_plain_text(), "$ ls\nfile! \n");
+        assert!(
+            cursor
+                .segments
+                .iter()
+                .any(|segment| segment.text == " " && segment.style.reverse)
+        );
+        assert_eq!(
+            frame_durations(&frames),
+            vec![0.5, 1.0, FINAL_FRAME_SECONDS]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{Color, StyledText};
+use serde::{Deserialize, Serialize};
+
+/// A form of color vision deficiency in which one kind of cone is missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorBlindness {
+    /// No red cones: reds look dark and are confused with greens.
+    Protanopia,
+    /// No green cones, the most common form: reds and greens are confused.
+    Deuteranopia,
+    /// No blue cones: blues are confused with greens and yellows with pinks.
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    /// Returns the matrix from Machado, Oliveira, and Fernandes (2009) that maps a linear
+    /// RGB color to the color seen with this deficiency at full severity.
+    fn simulation(self) -> [[f64; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [
+                [0.152_286, 1.052_583, -0.204_868],
+                [0.114_503, 0.786_281, 0.099_216],
+                [-0.003_882, -0.048_116, 1.051_998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367_322, 0.860_646, -0.227_968],
+                [0.280_085, 0.672_501, 0.047_413],
+                [-0.011_820, 0.042_940, 0.968_881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255_528, -0.076_749, -0.178_779],
+                [-0.078_411, 0.930_809, 0.147_602],
+                [0.004_733, 0.691_367, 0.303_900],
+            ],
+        }
+    }
+
+    /// Returns the matrix that moves the difference the deficiency hides onto channels that
+    /// are still seen.
+    fn correction(self) -> [[f64; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia | ColorBlindness::Deuteranopia => {
+                [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]]
+            }
+            ColorBlindness::Tritanopia => [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]],
+        }
+    }
+}
+
+impl Color {
+    /// Returns the color as someone with `kind` of color blindness sees it.
+    ///
+    /// The result is always an RGB color, as few simulated colors are in the palette.
+    #[must_use]
+    pub fn simulate_colorblindness(&self, kind: ColorBlindness) -> Color {
+        let linear = rgb_channels(self).map(to_linear);
+        from_channels(multiply(kind.simulation(), linear).map(from_linear))
+    }
+
+    /// Adjusts the color so that it stays distinct from others for someone with `kind` of
+    /// color blindness, a technique known as daltonization.
+    ///
+    /// The part of the color the deficiency hides is moved onto channels that are still
+    /// seen, so red and green, for example, differ in brightness and blueness as well.
+    #[must_use]
+    pub fn daltonize(&self, kind: ColorBlindness) -> Color {
+        let original = rgb_channels(self);
+        let simulated = rgb_channels(&self.simulate_colorblindness(kind));
+        let lost = [0, 1, 2].map(|channel| original[channel] - simulated[channel]);
+        let shift = multiply(kind.correction(), lost);
+        from_channels([0, 1, 2].map(|channel| original[channel] + shift[channel]))
+    }
+}
+
+impl StyledText {
+    /// Replaces every color with how someone with `kind` of color blindness sees it, as
+    /// [`Color::simulate_colorblindness`] does.
+    ///
+    /// Useful to check whether a color scheme still tells things apart. Default colors are
+    /// left unchanged.
+    #[must_use]
+    pub fn simulate_colorblindness(&self, kind: ColorBlindness) -> StyledText {
+        self.map_colors(|color| color.simulate_colorblindness(kind))
+    }
+
+    /// Adjusts every color to be easier to tell apart with `kind` of color blindness, as
+    /// [`Color::daltonize`] does.
+    ///
+    /// Default colors are left unchanged.
+    #[must_use]
+    pub fn daltonize(&self, kind: ColorBlindness) -> StyledText {
+        self.map_colors(|color| color.daltonize(kind))
+    }
+
+    /// Replaces each foreground and background color with `f` applied to it.
+    fn map_colors(&self, f: impl Fn(&Color) -> Color) -> StyledText {
+        let mut styled_text = self.clone();
+        for segment in &mut styled_text.segments {
+            for color in [&mut segment.style.fg_color, &mut segment.style.bg_color] {
+                if let Some(mapped) = color.as_ref().map(&f) {
+                    *color = Some(mapped);
+                }
+            }
+        }
+        styled_text
+    }
+}
+
+/// Returns the red, green, and blue channels of `color`, from 0 to 1.
+fn rgb_channels(color: &Color) -> [f64; 3] {
+    let (r, g, b) = color.to_rgb();
+    [r, g, b].map(|channel| f64::from(channel) / 255.0)
+}
+
+/// Builds an RGB color from channels from 0 to 1, clamping those out of range.
+fn from_channels(channels: [f64; 3]) -> Color {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let [r, g, b] = channels.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Color::Rgb(r, g, b)
+}
+
+/// Converts an sRGB channel to linear light.
+fn to_linear(channel: f64) -> f64 {
+    if channel <= 0.040_45 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light channel to sRGB.
+fn from_linear(channel: f64) -> f64 {
+    let channel = channel.clamp(0.0, 1.0);
+    if channel <= 0.003_130_8 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Multiplies a column vector by a matrix.
+fn multiply(matrix: [[f64; 3]; 3], vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row.iter().zip(vector).map(|(a, b)| a * b).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::rgb_distance;
+
+    #[test]
+    fn test_simulate_colorblindness() {
+        // Grays look the same to everyone
+        for kind in [
+            ColorBlindness::Protanopia,
+            ColorBlindness::Deuteranopia,
+            ColorBlindness::Tritanopia,
+        ] {
+            let gray = Color::Rgb(128, 128, 128).simulate_colorblindness(kind);
+            assert!(rgb_distance(gray.to_rgb(), (128, 128, 128)) <= 3);
+        }
+
+        let text = crate::parse_ansi("\x1b[31;42mred on green\x1b[0m plain");
+        let simulated = text.simulate_colorblindness(ColorBlindness::Deuteranopia);
+        let style = &simulated.segments[0].style;
+        let (fg, bg) = (
+            style.fg_color.as_ref().unwrap(),
+            style.bg_color.as_ref().unwrap(),
+        );
+        assert!(matches!(fg, Color::Rgb(..)));
+        assert!(rgb_distance(fg.to_rgb(), bg.to_rgb()) < rgb_distance((205, 0, 0), (0, 205, 0)));
+        assert_eq!(simulated.segments[1].style.fg_color, None);
+    }
+
+    #[test]
+    fn test_daltonize_separates_confused_colors() {
+        // Green looks like yellow without red or green cones, and like cyan without blue ones
+        for (kind, confused) in [
+            (ColorBlindness::Protanopia, Color::Indexed(3)),
+            (ColorBlindness::Deuteranopia, Color::Indexed(3)),
+            (ColorBlindness::Tritanopia, Color::Indexed(6)),
+        ] {
+            let green = Color::Indexed(2);
+            let seen = |color: &Color| color.simulate_colorblindness(kind).to_rgb();
+            let before = rgb_distance(seen(&green), seen(&confused));
+            let after = rgb_distance(
+                seen(&green.daltonize(kind)),
+                seen(&confused.daltonize(kind)),
+            );
+            assert!(after > before * 10, "{kind:?}: {after} <= {before} * 10");
+        }
+    }
+}
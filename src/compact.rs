@@ -0,0 +1,162 @@
+use crate::{Segment, Style, StyleId, StyleTable, StyledText};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Styled text stored as one text buffer, with each segment a byte range of the buffer and
+/// the id of its style in a shared [`StyleTable`].
+///
+/// Converting from [`StyledText`] replaces a `String` and a [`Style`] per segment with a
+/// range and an id, which saves an allocation per segment for text that is kept around and
+/// rendered many times. Source spans and the raw escape sequences of
+/// [`Segment::escapes`] are not kept, so converting back loses them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompactStyledText {
+    text: String,
+    spans: Vec<(Range<usize>, StyleId)>,
+    styles: StyleTable,
+}
+
+impl CompactStyledText {
+    /// Creates empty text.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a segment of `text` in `style`.
+    pub fn push(&mut self, text: &str, style: &Style) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        let id = self.styles.intern(style);
+        self.spans.push((start..self.text.len(), id));
+    }
+
+    /// Returns the text of every segment, without styling.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the byte range of the text and the style id of each segment.
+    #[must_use]
+    pub fn spans(&self) -> &[(Range<usize>, StyleId)] {
+        &self.spans
+    }
+
+    /// Returns the table of styles the segments refer to.
+    #[must_use]
+    pub fn styles(&self) -> &StyleTable {
+        &self.styles
+    }
+
+    /// Returns the number of segments.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Returns `true` if there are no segments.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Iterates over the segments as their text and style.
+    ///
+    /// Spans outside the text or style table, which only malformed deserialized data can
+    /// have, are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Style)> {
+        self.spans.iter().filter_map(|(range, id)| {
+            let text = self.text.get(range.clone())?;
+            Some((text, self.styles.get(*id)?))
+        })
+    }
+
+    /// Converts back to [`StyledText`], with a segment per span.
+    #[must_use]
+    pub fn to_styled_text(&self) -> StyledText {
+        StyledText {
+            segments: self
+                .iter()
+                .map(|(text, style)| Segment {
+                    text: text.to_string(),
+                    style: style.clone(),
+                    source: None,
+                    escapes: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&StyledText> for CompactStyledText {
+    fn from(text: &StyledText) -> Self {
+        let mut compact = CompactStyledText {
+            text: String::with_capacity(
+                text.segments.iter().map(|segment| segment.text.len()).sum(),
+            ),
+            spans: Vec::with_capacity(text.segments.len()),
+            styles: StyleTable::new(),
+        };
+        for segment in &text.segments {
+            compact.push(&segment.text, &segment.style);
+        }
+        compact
+    }
+}
+
+impl From<&CompactStyledText> for StyledText {
+    fn from(compact: &CompactStyledText) -> Self {
+        compact.to_styled_text()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, parse_ansi};
+
+    #[test]
+    fn test_compact_round_trip() {
+        let text = parse_ansi("plain \x1b[31mred\x1b[0m again \x1b[31;1mbold\x1b[0m\n");
+        let compact = CompactStyledText::from(&text);
+        assert_eq!(compact.text(), "plain red again bold\n");
+        assert_eq!(compact.len(), text.segments.len());
+        // The plain style is stored once for both plain segments
+        assert_eq!(compact.styles().len(), 3);
+        assert_eq!(compact.spans()[1].0, 6..9);
+        assert_eq!(
+            compact.iter().nth(1),
+            Some((
+                "red",
+                &Style {
+                    fg_color: Some(Color::Indexed(1)),
+                    ..Default::default()
+                }
+            ))
+        );
+        assert_eq!(StyledText::from(&compact), text);
+    }
+
+    #[test]
+    fn test_compact_serde_round_trip() {
+        let mut compact = CompactStyledText::new();
+        compact.push("a", &Style::default());
+        compact.push(
+            "b",
+            &Style {
+                bold: true,
+                ..Default::default()
+            },
+        );
+        let json = serde_json::to_string(&compact).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CompactStyledText>(&json).unwrap(),
+            compact
+        );
+
+        let malformed = r#"{"text": "a", "spans": [[{"start": 0, "end": 5}, 0]], "styles": []}"#;
+        let malformed: CompactStyledText = serde_json::from_str(malformed).unwrap();
+        assert_eq!(malformed.iter().count(), 0);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{Segment, Style, StyledText};
+use std::ops::{Add, AddAssign};
+
+impl StyledText {
+    /// Appends `text` with the given style.
+    ///
+    /// If the last segment already has the same style, the text is merged into it instead
+    /// of creating a new segment. Empty text is ignored.
+    pub fn push_str_styled(&mut self, text: &str, style: Style) {
+        if text.is_empty() {
+            return;
+        }
+        match self.segments.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => self.segments.push(Segment {
+                text: text.to_string(),
+                style,
+                source: None,
+                escapes: None,
+            }),
+        }
+    }
+}
+
+impl Extend<Segment> for StyledText {
+    fn extend<T: IntoIterator<Item = Segment>>(&mut self, iter: T) {
+        self.segments.extend(iter);
+    }
+}
+
+impl Add for StyledText {
+    type Output = StyledText;
+
+    fn add(mut self, rhs: StyledText) -> StyledText {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for StyledText {
+    fn add_assign(&mut self, rhs: StyledText) {
+        self.extend(rhs.segments);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_str_styled_merges_same_style() {
+        let mut styled_text = StyledText::default();
+        styled_text.push_str_styled("Hello", Style::default());
+        styled_text.push_str_styled(" World", Style::default());
+        styled_text.push_str_styled("", Style::default());
+        assert_eq!(
+            styled_text.segments,
+            vec![Segment {
+                text: "Hello World".to_string(),
+                style: Style::default(),
+                source: None,
+                escapes: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_add_concatenates_segments() {
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        let mut left = StyledText::default();
+        left.push_str_styled("a", Style::default());
+        let mut right = StyledText::default();
+        right.push_str_styled("b", bold.clone());
+
+        let combined = left + right;
+        assert_eq!(
+            combined.segments,
+            vec![
+                Segment {
+                    text: "a".to_string(),
+                    style: Style::default(),
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: "b".to_string(),
+                    style: bold,
+                    source: None,
+                    escapes: None,
+                },
+            ]
+        );
+    }
+}
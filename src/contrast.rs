@@ -0,0 +1,144 @@
+use crate::{Color, StyledText, Theme};
+
+/// The contrast ratio WCAG level AAA asks of normal text.
+pub(crate) const AAA_CONTRAST: f64 = 7.0;
+
+impl Color {
+    /// Returns the relative luminance of the color as WCAG defines it, from 0 for black to
+    /// 1 for white.
+    #[must_use]
+    pub fn relative_luminance(&self) -> f64 {
+        let (r, g, b) = self.to_rgb();
+        let linear = |channel: u8| {
+            let channel = f64::from(channel) / 255.0;
+            if channel <= 0.040_45 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+
+    /// Returns the WCAG contrast ratio between this color and `other`, from 1 for the same
+    /// color to 21 for black and white.
+    #[must_use]
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let (a, b) = (self.relative_luminance(), other.relative_luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Returns the most saturated color of the same hue that has at least `ratio` contrast
+    /// with `background`.
+    ///
+    /// The color is fully saturated and then lightened on dark backgrounds, or darkened on
+    /// light ones, only as far as needed. Grays become white or black. If even white or
+    /// black falls short of `ratio`, that is returned.
+    #[must_use]
+    pub fn with_contrast(&self, background: &Color, ratio: f64) -> Color {
+        let white = Color::Rgb(255, 255, 255);
+        let black = Color::Rgb(0, 0, 0);
+        let target = if background.contrast_ratio(&white) >= background.contrast_ratio(&black) {
+            white
+        } else {
+            black
+        };
+
+        let (r, g, b) = self.to_rgb();
+        let (min, max) = (r.min(g).min(b), r.max(g).max(b));
+        if min == max {
+            return target;
+        }
+        let saturate = |channel: u8| f64::from(channel - min) * 255.0 / f64::from(max - min);
+        let saturated = [saturate(r), saturate(g), saturate(b)];
+        let (tr, tg, tb) = target.to_rgb();
+        let target_channels = [tr, tg, tb].map(f64::from);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let mix = |amount: f64| {
+            let [r, g, b] = [0, 1, 2].map(|channel| {
+                let from = saturated[channel];
+                (from + (target_channels[channel] - from) * amount).round() as u8
+            });
+            Color::Rgb(r, g, b)
+        };
+
+        // Moving toward the target only ever raises the contrast, so search for the least
+        // amount that is enough
+        if mix(0.0).contrast_ratio(background) >= ratio {
+            return mix(0.0);
+        }
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..16 {
+            let middle = f64::midpoint(low, high);
+            if mix(middle).contrast_ratio(background) >= ratio {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        mix(high)
+    }
+}
+
+impl StyledText {
+    /// Recolors the text for readers who need high contrast, drawn on the background of
+    /// `theme`, such as [`Theme::high_contrast_dark`].
+    ///
+    /// Backgrounds are removed, so all text sits on the theme's background, and every
+    /// foreground is made saturated with at least 7:1 contrast against it, as WCAG level
+    /// AAA asks, using [`Color::with_contrast`]. Dim text is drawn at full strength.
+    #[must_use]
+    pub fn high_contrast(&self, theme: &Theme) -> StyledText {
+        let mut styled_text = self.clone();
+        for segment in &mut styled_text.segments {
+            let style = &mut segment.style;
+            style.bg_color = None;
+            style.dim = false;
+            style.fg_color = style.fg_color.as_ref().map(|color| {
+                theme
+                    .resolve(color)
+                    .with_contrast(&theme.background, AAA_CONTRAST)
+            });
+        }
+        styled_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contrast_ratio() {
+        let (black, white) = (Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255));
+        assert!((black.contrast_ratio(&white) - 21.0).abs() < 1e-9);
+        assert!((white.contrast_ratio(&white) - 1.0).abs() < 1e-9);
+        assert_eq!(Color::Indexed(8).with_contrast(&black, AAA_CONTRAST), white);
+
+        // Blue is lightened on black, yellow darkened on white, and both keep their hue
+        let (r, g, b) = Color::Indexed(4)
+            .with_contrast(&black, AAA_CONTRAST)
+            .to_rgb();
+        assert!(b == 255 && r == g && r > 0);
+        let (r, g, b) = Color::Indexed(11)
+            .with_contrast(&white, AAA_CONTRAST)
+            .to_rgb();
+        assert!(r == g && b == 0 && r < 255);
+    }
+
+    #[test]
+    fn test_high_contrast() {
+        let text = crate::parse_ansi(
+            "\x1b[31;47mred\x1b[0;2;38;2;30;30;90m dim navy\x1b[0;93;44m yellow\x1b[0m plain",
+        );
+        for theme in [Theme::high_contrast_dark(), Theme::high_contrast_light()] {
+            let high_contrast = text.high_contrast(&theme);
+            for segment in &high_contrast.segments {
+                let style = &segment.style;
+                assert_eq!((style.bg_color.as_ref(), style.dim), (None, false));
+                let fg = style.fg_color.as_ref().unwrap_or(&theme.foreground);
+                assert!(fg.contrast_ratio(&theme.background) >= AAA_CONTRAST);
+            }
+        }
+    }
+}
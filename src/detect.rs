@@ -0,0 +1,287 @@
+use crate::{AnsiOptions, Recording, Sauce, Screen, parse_bin, parse_tundra, rexpaint_to_ansi};
+use codepage_437::{CP437_CONTROL, FromCp437};
+use error_stack::{Report, ResultExt};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use wherror::Error;
+
+/// Width in columns of the DOS screens that CP437 art without a SAUCE width was drawn on.
+const DEFAULT_ART_WIDTH: usize = 80;
+
+/// Most bytes a `RexPaint` image may decompress to, so a gzip stream that is not an image
+/// cannot make the reader allocate whatever its first bytes happen to claim.
+const MAX_REXPAINT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Error raised when input cannot be converted to ANSI text.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct InputError;
+
+/// The kinds of input that can be converted to ANSI text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputFormat {
+    /// UTF-8 text with ANSI escape sequences.
+    Ansi,
+    /// ANSI art encoded in code page 437, as made by DOS-era art tools, possibly with a
+    /// [`Sauce`] record.
+    Cp437,
+    /// A gzip-compressed `RexPaint` image.
+    RexPaint,
+    /// An asciinema recording in the asciicast v2 or v3 format.
+    Asciicast,
+    /// A BIN screen dump of character and attribute byte pairs, read by [`parse_bin`].
+    Bin,
+    /// A `TundraDraw` file of 24-bit color ANSI art, read by [`parse_tundra`].
+    Tundra,
+}
+
+/// How the bytes of ANSI text are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Encoding {
+    /// UTF-8, with invalid bytes replaced by U+FFFD.
+    #[default]
+    Utf8,
+    /// Code page 437, so bytes from 0x80 up decode to the box-drawing, shade, and accented
+    /// characters DOS showed for them.
+    Cp437,
+}
+
+impl Encoding {
+    /// Decodes `data` as text in this encoding.
+    #[must_use]
+    pub fn decode(self, data: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(data).into_owned(),
+            Encoding::Cp437 => String::from_cp437(data.to_vec(), &CP437_CONTROL),
+        }
+    }
+}
+
+impl InputFormat {
+    /// Guesses the format of `data` from its contents.
+    ///
+    /// Gzip data is taken to be `RexPaint`, data with the `TundraDraw` header to be
+    /// `TundraDraw`, data with a SAUCE record for binary text to be BIN, and data with any
+    /// other SAUCE record, a DOS end-of-file character at its end or before a SAUCE record,
+    /// or at least 1% of its bytes not UTF-8 to be CP437. Otherwise, UTF-8 starting with an
+    /// asciicast header is taken to be a recording, and anything else, such as a log with a
+    /// stray byte, to be ANSI text.
+    /// BIN dumps without a SAUCE record look like any other binary data, so they are only
+    /// read as BIN when asked for with [`decode_input_as`].
+    #[must_use]
+    pub fn detect(data: &[u8]) -> InputFormat {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            return InputFormat::RexPaint;
+        }
+        if data.starts_with(b"\x18TUNDRA24") {
+            return InputFormat::Tundra;
+        }
+        if Sauce::parse(data).is_some_and(|sauce| sauce.data_type == 5) {
+            return InputFormat::Bin;
+        }
+        let end_of_file =
+            data.last() == Some(&0x1a) || data.windows(6).any(|window| window == b"\x1aSAUCE");
+        if end_of_file || Sauce::parse(data).is_some() {
+            return InputFormat::Cp437;
+        }
+        let invalid: usize = data.utf8_chunks().map(|chunk| chunk.invalid().len()).sum();
+        if invalid * 100 >= data.len() {
+            return InputFormat::Cp437;
+        }
+        let Ok(text) = std::str::from_utf8(data) else {
+            return InputFormat::Ansi;
+        };
+        let header = text.trim_start().lines().next().unwrap_or_default();
+        let is_asciicast = header.starts_with('{')
+            && serde_json::from_str::<serde_json::Value>(header)
+                .is_ok_and(|header| matches!(header["version"].as_u64(), Some(2 | 3)));
+        if is_asciicast {
+            InputFormat::Asciicast
+        } else {
+            InputFormat::Ansi
+        }
+    }
+}
+
+/// Converts input in any [`InputFormat`] to ANSI text, detecting the format with
+/// [`InputFormat::detect`].
+///
+/// `RexPaint` images are flattened like [`rexpaint_to_ansi`], and recordings are replayed
+/// to the screen they end on. CP437 art stops at its end-of-file character, and is laid
+/// out at the width its SAUCE record declares, or 80 columns, wrapping like a DOS terminal.
+/// Blinking text in art flagged for iCE colors gets a bright background instead, as
+/// [`StyledText::ice_colors`](crate::StyledText::ice_colors) does.
+///
+/// # Errors
+///
+/// Returns an error if the input looks like a `RexPaint` image or recording but cannot be
+/// read as one.
+pub fn decode_input(data: &[u8]) -> Result<String, Report<InputError>> {
+    decode_input_as(data, InputFormat::detect(data))
+}
+
+/// Converts input in the given [`InputFormat`] to ANSI text, like [`decode_input`] but
+/// without detecting the format.
+///
+/// # Errors
+///
+/// Returns an error if the input is not a valid `RexPaint` image or recording when
+/// `format` says it is one.
+pub fn decode_input_as(data: &[u8], format: InputFormat) -> Result<String, Report<InputError>> {
+    match format {
+        InputFormat::Ansi => Ok(Encoding::Utf8.decode(data)),
+        InputFormat::Cp437 => {
+            // Anything after the end-of-file character is metadata, not art
+            let art = Encoding::Cp437.decode(Sauce::strip(data));
+            let sauce = Sauce::parse(data);
+            let ice_colors = sauce.as_ref().is_some_and(Sauce::ice_colors);
+            let width = sauce
+                .as_ref()
+                .and_then(Sauce::width)
+                .unwrap_or(DEFAULT_ART_WIDTH);
+            let mut canvas = Screen::canvas(width);
+            canvas.feed(&art);
+            let text = canvas.to_styled_text();
+            let text = if ice_colors { text.ice_colors() } else { text };
+            Ok(text.to_ansi(&AnsiOptions::default()))
+        }
+        InputFormat::RexPaint => {
+            if !is_rexpaint(data) {
+                return Err(Report::new(InputError).attach("invalid RexPaint image"));
+            }
+            rexpaint_to_ansi(data)
+                .change_context(InputError)
+                .attach("invalid RexPaint image")
+        }
+        InputFormat::Asciicast => {
+            let recording = Recording::parse(&String::from_utf8_lossy(data))
+                .change_context(InputError)
+                .attach("invalid asciinema recording")?;
+            let mut screen = Screen::new(recording.width, recording.height);
+            for (_, output) in &recording.output {
+                screen.feed(output);
+            }
+            Ok(screen.to_styled_text().to_ansi(&AnsiOptions::default()))
+        }
+        InputFormat::Bin => Ok(parse_bin(data).to_ansi(&AnsiOptions::default())),
+        InputFormat::Tundra => Ok(parse_tundra(data)
+            .change_context(InputError)
+            .attach("invalid TundraDraw file")?
+            .to_ansi(&AnsiOptions::default())),
+    }
+}
+
+/// Whether `data` decompresses to a `RexPaint` image whose layers fit in its contents.
+///
+/// `XpFile::read` reserves room for as many cells as the header declares before reading
+/// any, so this checks the header first.
+fn is_rexpaint(data: &[u8]) -> bool {
+    let mut contents = Vec::new();
+    let read = GzDecoder::new(data)
+        .take(MAX_REXPAINT_SIZE + 1)
+        .read_to_end(&mut contents);
+    if read.is_err() || contents.len() as u64 > MAX_REXPAINT_SIZE {
+        return false;
+    }
+    let word = |offset: usize| {
+        contents
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    // The only version RexPaint writes is -1
+    if contents.get(..4) != Some(&(-1i32).to_le_bytes()[..]) {
+        return false;
+    }
+    let Some(layers) = word(4).filter(|&layers| layers > 0) else {
+        return false;
+    };
+    let mut offset = 8;
+    for _ in 0..layers {
+        let (Some(width), Some(height)) = (word(offset), word(offset + 4)) else {
+            return false;
+        };
+        // Each cell is a 4-byte glyph and two 3-byte colors
+        let end = width
+            .checked_mul(height)
+            .and_then(|cells| cells.checked_mul(10))
+            .and_then(|size| size.checked_add(offset + 8));
+        match end {
+            Some(end) if end <= contents.len() => offset = end,
+            _ => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sauce::tests::sauce_record;
+    use crate::{ansi_to_rexpaint, strip_ansi};
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    const CAST: &[u8] =
+        b"{\"version\": 2, \"width\": 4, \"height\": 2}\n[0.1, \"o\", \"ab\\r\\ncd\"]\n";
+
+    #[test]
+    fn test_detect() {
+        let xp = ansi_to_rexpaint("\x1b[31mhi\x1b[0m").unwrap();
+        assert_eq!(InputFormat::detect(&xp), InputFormat::RexPaint);
+        assert_eq!(InputFormat::detect(CAST), InputFormat::Asciicast);
+        assert_eq!(
+            InputFormat::detect(b"\x18TUNDRA24\x02A\0\xff\0\0"),
+            InputFormat::Tundra
+        );
+        assert_eq!(
+            InputFormat::detect(b"{\"not\": \"a recording\"}"),
+            InputFormat::Ansi
+        );
+        assert_eq!(
+            InputFormat::detect(b"\x1b[31mred\x1b[0m"),
+            InputFormat::Ansi
+        );
+        assert_eq!(
+            InputFormat::detect(b"\x1b[31m\xdb\xdb\x1b[0m"),
+            InputFormat::Cp437
+        );
+        assert_eq!(InputFormat::detect(b"art\x1a"), InputFormat::Cp437);
+        // A stray byte or end-of-file character in a UTF-8 log does not make it CP437
+        let log = [b"\xe2\x9c\x93 ok\x1a\n".repeat(50), b"\xff\n".to_vec()].concat();
+        assert_eq!(InputFormat::detect(&log), InputFormat::Ansi);
+    }
+
+    #[test]
+    fn test_decode_input() {
+        let xp = ansi_to_rexpaint("\x1b[31mhi\x1b[0m").unwrap();
+        assert_eq!(strip_ansi(&decode_input(&xp).unwrap()), "hi\n");
+        assert_eq!(
+            decode_input(b"\x1b[31m\xdb\xb0\x1b[0m\r\n\x1aSAUCE00").unwrap(),
+            "\x1b[0;31m█░\x1b[0m\n"
+        );
+        // Without a SAUCE width, art wraps at 80 columns
+        let long_line = [vec![0xdb; 100], b"\x1a".to_vec()].concat();
+        let lines: Vec<usize> = strip_ansi(&decode_input(&long_line).unwrap())
+            .lines()
+            .map(|line| line.chars().count())
+            .collect();
+        assert_eq!(lines, vec![80, 20]);
+        assert_eq!(strip_ansi(&decode_input(CAST).unwrap()), "ab\ncd\n");
+        // Art is wrapped at the width its SAUCE record declares
+        let art = [b"\xdb\xdb\xdb\xb0\xb0\xb0".to_vec(), sauce_record(3, 2, 0)].concat();
+        assert_eq!(strip_ansi(&decode_input(&art).unwrap()), "███\n░░░\n");
+        let art = [b"\x1b[5;41mX".to_vec(), sauce_record(0, 0, 1)].concat();
+        assert_eq!(decode_input(&art).unwrap(), "\x1b[0;101mX\x1b[0m\n");
+        assert!(decode_input(b"\x1f\x8bnot gzip").is_err());
+        // Gzipped text is rejected before its first bytes are read as an image size
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(b"hello world\n").unwrap();
+        assert!(decode_input(&gzipped.finish().unwrap()).is_err());
+        assert_eq!(
+            decode_input_as("é".as_bytes(), InputFormat::Cp437).unwrap(),
+            "├⌐\n"
+        );
+    }
+}
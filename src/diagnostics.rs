@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+use wherror::Error;
+
+/// Error raised when strict parsing finds a problem in the input.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct ParseError;
+
+/// A problem found in the input while parsing, returned by
+/// [`parse_ansi_with_diagnostics`](crate::parse_ansi_with_diagnostics).
+///
+/// Parsing goes on past every problem, so these explain where the output differs from what
+/// the input asked for rather than why parsing failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// The byte range of the parsed text with the problem, such as a whole escape
+    /// sequence. This is an offset into the string that was parsed, which differs from the
+    /// bytes of a file that was decoded from another encoding such as CP437.
+    pub range: Range<usize>,
+    /// What the problem is.
+    pub kind: DiagnosticKind,
+}
+
+/// The kinds of problems reported in a [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+    /// A palette index or color channel above 255 in an SGR sequence, clamped or ignored
+    /// as [`ParseOptions::out_of_range`](crate::ParseOptions::out_of_range) says.
+    ParamOutOfRange {
+        /// The parameter as it was written.
+        value: u32,
+    },
+    /// A CSI, OSC, or DCS escape sequence that is not SGR, handled as
+    /// [`ParseOptions::unknown_sequences`](crate::ParseOptions::unknown_sequences) says.
+    UnknownSequence,
+    /// A character whose combining marks were left out when writing it to a cell grid,
+    /// as [`RexOptions::combining`](crate::RexOptions::combining) says.
+    DroppedCombiningMarks,
+}
+
+/// What happens to a palette index or color channel above 255 in an SGR sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OutOfRangePolicy {
+    /// Use 255 in its place.
+    #[default]
+    Clamp,
+    /// Ignore the color it is part of, leaving the color as it was.
+    Reject,
+}
+
+/// What happens to CSI, OSC, and DCS escape sequences the parser does not understand, such
+/// as cursor movement or window titles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum UnknownSequencePolicy {
+    /// Keep the sequence in the text as it was, so rendering it as ANSI loses nothing.
+    #[default]
+    KeepRaw,
+    /// Leave the sequence out of the text.
+    Drop,
+    /// Put this character in the text in place of the sequence.
+    ReplaceWith(char),
+    /// Fail [`try_parse_ansi_with`](crate::try_parse_ansi_with). Other parse functions
+    /// leave the sequence out, as with [`UnknownSequencePolicy::Drop`].
+    Error,
+}
+
+impl OutOfRangePolicy {
+    /// Returns `value` as a byte, or what the policy puts in its place if it is too large.
+    pub(crate) fn apply(self, value: u32) -> Option<u8> {
+        match (u8::try_from(value), self) {
+            (Ok(value), _) => Some(value),
+            (Err(_), OutOfRangePolicy::Clamp) => Some(u8::MAX),
+            (Err(_), OutOfRangePolicy::Reject) => None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Range { start, end } = self.range;
+        match &self.kind {
+            DiagnosticKind::ParamOutOfRange { value } => {
+                write!(
+                    f,
+                    "offsets {start}..{end}: color parameter {value} is above 255"
+                )
+            }
+            DiagnosticKind::UnknownSequence => {
+                write!(f, "offsets {start}..{end}: unknown escape sequence")
+            }
+            DiagnosticKind::DroppedCombiningMarks => {
+                write!(f, "offsets {start}..{end}: combining marks were dropped")
+            }
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{Color, Segment, Style, StyledText};
+use serde::{Deserialize, Serialize};
+
+/// The unit text is compared in by [`StyledText::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DiffGranularity {
+    /// Whole lines, shown one per line with `-`, `+`, or ` ` in front.
+    #[default]
+    Line,
+    /// Words and the whitespace between them, shown inline.
+    Word,
+}
+
+/// Whether a piece of a diff is in both texts or only one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffTag {
+    /// Text found in both the old and new text.
+    Equal,
+    /// Text only found in the old text.
+    Removed,
+    /// Text only found in the new text.
+    Added,
+}
+
+/// A run of text from a diff that is equal, removed, or added as a whole.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffChunk {
+    /// How the text differs.
+    pub tag: DiffTag,
+    /// The text, with lines keeping their newlines.
+    pub text: String,
+}
+
+impl StyledText {
+    /// Compares the plain text of `self` against `other`, ignoring styles.
+    ///
+    /// Adjacent pieces with the same tag are merged, so the chunks alternate between tags.
+    /// Removed text comes before added text where the two texts differ. Texts that differ
+    /// in more than a thousand lines or words are not compared closely: what lies between
+    /// their common start and end is shown as removed, then added.
+    #[must_use]
+    pub fn diff_chunks(&self, other: &StyledText, granularity: DiffGranularity) -> Vec<DiffChunk> {
+        let (old, new) = (self.to_plain_text(), other.to_plain_text());
+        let tokenize = match granularity {
+            DiffGranularity::Line => line_tokens,
+            DiffGranularity::Word => word_tokens,
+        };
+        let (old_tokens, new_tokens) = (tokenize(&old), tokenize(&new));
+
+        let mut chunks: Vec<DiffChunk> = Vec::new();
+        for (tag, token) in diff_tokens(&old_tokens, &new_tokens) {
+            match chunks.last_mut() {
+                Some(chunk) if chunk.tag == tag => chunk.text.push_str(token),
+                _ => chunks.push(DiffChunk {
+                    tag,
+                    text: token.to_string(),
+                }),
+            }
+        }
+        chunks
+    }
+
+    /// Compares the plain text of `self` against `other` and returns the differences as
+    /// colorized text, ready to render to ANSI, HTML, or any other format.
+    ///
+    /// Removed text is red and added text green. Line diffs mark each line with `-`, `+`,
+    /// or a space, like `diff -u`; word diffs are shown inline with removed words struck
+    /// through.
+    #[must_use]
+    pub fn diff(&self, other: &StyledText, granularity: DiffGranularity) -> StyledText {
+        let removed = Style {
+            fg_color: Some(Color::Indexed(1)),
+            strikethrough: granularity == DiffGranularity::Word,
+            ..Default::default()
+        };
+        let added = Style {
+            fg_color: Some(Color::Indexed(2)),
+            ..Default::default()
+        };
+
+        let mut segments = Vec::new();
+        for chunk in self.diff_chunks(other, granularity) {
+            let (marker, style) = match chunk.tag {
+                DiffTag::Equal => (' ', Style::default()),
+                DiffTag::Removed => ('-', removed.clone()),
+                DiffTag::Added => ('+', added.clone()),
+            };
+            let text = match granularity {
+                DiffGranularity::Line => {
+                    let mut text = String::new();
+                    for line in line_tokens(&chunk.text) {
+                        text.push(marker);
+                        text.push_str(line);
+                        if !line.ends_with('\n') {
+                            text.push('\n');
+                        }
+                    }
+                    text
+                }
+                DiffGranularity::Word => chunk.text,
+            };
+            segments.push(Segment {
+                text,
+                style,
+                source: None,
+                escapes: None,
+            });
+        }
+        StyledText { segments }.normalize()
+    }
+}
+
+/// Splits `text` into lines, keeping their newlines.
+fn line_tokens(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Splits `text` into alternating runs of whitespace and non-whitespace.
+fn word_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous = None;
+    for (idx, ch) in text.char_indices() {
+        let whitespace = ch.is_whitespace();
+        if previous.is_some_and(|previous| previous != whitespace) {
+            tokens.push(&text[start..idx]);
+            start = idx;
+        }
+        previous = Some(whitespace);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Finds a shortest edit script from `old` to `new` with Myers' algorithm, returning every
+/// token tagged with how it changed.
+fn diff_tokens<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(DiffTag, &'a str)> {
+    // Common prefixes and suffixes are cheap to find and keep the search small
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ops: Vec<(DiffTag, &str)> = old[..prefix]
+        .iter()
+        .map(|token| (DiffTag::Equal, *token))
+        .collect();
+    ops.extend(myers(old_middle, new_middle));
+    ops.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|token| (DiffTag::Equal, *token)),
+    );
+    ops
+}
+
+/// The most edits [`myers`] looks for before giving up on a minimal diff. The search
+/// keeps O(D²) state for D edits, so this bounds it to a few megabytes.
+const MAX_EDITS: isize = 1000;
+
+/// The core of Myers' O(ND) diff algorithm.
+///
+/// Texts more than [`MAX_EDITS`] edits apart are shown as all of `old` removed and all
+/// of `new` added instead.
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+fn myers<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(DiffTag, &'a str)> {
+    let (old_len, new_len) = (old.len() as isize, new.len() as isize);
+    let max = old_len + new_len;
+    let index = |k: isize| (k + max + 1) as usize;
+
+    // The furthest x reached on each diagonal k = x - y. Before every round d, the
+    // diagonals it can read, -d - 1 to d + 1, are recorded for the backtrack.
+    let mut furthest = vec![0; 2 * (max as usize) + 3];
+    let mut trace = Vec::new();
+    let mut found = false;
+    'search: for d in 0..=max.min(MAX_EDITS) {
+        trace.push(furthest[index(-d - 1)..=index(d + 1)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[index(k - 1)] < furthest[index(k + 1)]) {
+                furthest[index(k + 1)]
+            } else {
+                furthest[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < old_len && y < new_len && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index(k)] = x;
+            if x >= old_len && y >= new_len {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return old
+            .iter()
+            .map(|token| (DiffTag::Removed, *token))
+            .chain(new.iter().map(|token| (DiffTag::Added, *token)))
+            .collect();
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (old_len, new_len);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| furthest[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push((DiffTag::Equal, old[(x - 1) as usize]));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push((DiffTag::Added, new[(y - 1) as usize]));
+            } else {
+                ops.push((DiffTag::Removed, old[(x - 1) as usize]));
+            }
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ansi;
+    use std::fmt::Write;
+
+    fn chunk(tag: DiffTag, text: &str) -> DiffChunk {
+        DiffChunk {
+            tag,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff_chunks_by_line_ignores_styles() {
+        let old = parse_ansi("same\n\x1b[31mold\x1b[0m\nend\n");
+        let new = parse_ansi("\x1b[1msame\x1b[0m\nnew\nend\nextra");
+        assert_eq!(
+            old.diff_chunks(&new, DiffGranularity::Line),
+            vec![
+                chunk(DiffTag::Equal, "same\n"),
+                chunk(DiffTag::Removed, "old\n"),
+                chunk(DiffTag::Added, "new\n"),
+                chunk(DiffTag::Equal, "end\n"),
+                chunk(DiffTag::Added, "extra"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_chunks_by_word() {
+        let old = parse_ansi("the quick brown fox");
+        let new = parse_ansi("the slow brown  fox jumps");
+        assert_eq!(
+            old.diff_chunks(&new, DiffGranularity::Word),
+            vec![
+                chunk(DiffTag::Equal, "the "),
+                chunk(DiffTag::Removed, "quick"),
+                chunk(DiffTag::Added, "slow"),
+                chunk(DiffTag::Equal, " brown"),
+                chunk(DiffTag::Removed, " "),
+                chunk(DiffTag::Added, "  "),
+                chunk(DiffTag::Equal, "fox"),
+                chunk(DiffTag::Added, " jumps"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_chunks_gives_up_on_large_differences() {
+        let lines = |word: &str| {
+            let mut text = "start\n".to_string();
+            for n in 0..800 {
+                writeln!(&mut text, "{word} {n}").unwrap();
+            }
+            text + "end\n"
+        };
+        let (old, new) = (parse_ansi(&lines("old")), parse_ansi(&lines("new")));
+        let chunks = old.diff_chunks(&new, DiffGranularity::Line);
+        let tags: Vec<_> = chunks.iter().map(|chunk| chunk.tag).collect();
+        assert_eq!(
+            tags,
+            vec![
+                DiffTag::Equal,
+                DiffTag::Removed,
+                DiffTag::Added,
+                DiffTag::Equal
+            ]
+        );
+        assert!(chunks[1].text.starts_with("old 0\n") && chunks[1].text.ends_with("old 799\n"));
+
+        // Below the limit the diff stays minimal
+        let old = parse_ansi("a\nb\nc\nd\n");
+        let new = parse_ansi("b\nx\nd\ne\n");
+        let tags: Vec<_> = old
+            .diff_chunks(&new, DiffGranularity::Line)
+            .iter()
+            .map(|chunk| (chunk.tag, chunk.text.clone()))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                (DiffTag::Removed, "a\n".to_string()),
+                (DiffTag::Equal, "b\n".to_string()),
+                (DiffTag::Removed, "c\n".to_string()),
+                (DiffTag::Added, "x\n".to_string()),
+                (DiffTag::Equal, "d\n".to_string()),
+                (DiffTag::Added, "e\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_renders_markers() {
+        let diff = parse_ansi("a\nb").diff(&parse_ansi("a\nc\n"), DiffGranularity::Line);
+        assert_eq!(diff.to_plain_text(), " a\n-b\n+c\n");
+        assert_eq!(diff.segments[1].style.fg_color, Some(Color::Indexed(1)));
+        assert_eq!(diff.segments[2].style.fg_color, Some(Color::Indexed(2)));
+        assert!(
+            parse_ansi("")
+                .diff(&parse_ansi(""), DiffGranularity::Word)
+                .segments
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{Style, StyledText};
+
+impl StyledText {
+    /// Returns a copy with the blink attribute removed from every segment.
+    #[must_use]
+    pub fn strip_blink(&self) -> StyledText {
+        self.map_styles(|style| style.blink = false)
+    }
+
+    /// Returns a copy with the hidden attribute removed, making concealed text visible.
+    #[must_use]
+    pub fn strip_hidden(&self) -> StyledText {
+        self.map_styles(|style| style.hidden = false)
+    }
+
+    /// Returns a copy with every background color removed.
+    ///
+    /// Reversed segments have their colors swapped first so that the visible foreground
+    /// is kept.
+    #[must_use]
+    pub fn strip_backgrounds(&self) -> StyledText {
+        self.map_styles(|style| {
+            resolve_reverse(style);
+            style.bg_color = None;
+        })
+    }
+
+    /// Returns a copy that keeps only foreground and background colors.
+    ///
+    /// All text attributes are removed. Reversed segments have their colors swapped so
+    /// the text looks the same without the reverse attribute.
+    #[must_use]
+    pub fn colors_only(&self) -> StyledText {
+        self.map_styles(|style| {
+            resolve_reverse(style);
+            *style = Style {
+                fg_color: style.fg_color.take(),
+                bg_color: style.bg_color.take(),
+                ..Default::default()
+            };
+        })
+    }
+
+    /// Applies `f` to the style of every segment of a copy of this text.
+    fn map_styles(&self, f: impl Fn(&mut Style)) -> StyledText {
+        let mut styled_text = self.clone();
+        for segment in &mut styled_text.segments {
+            f(&mut segment.style);
+        }
+        styled_text
+    }
+}
+
+/// Swaps the foreground and background colors of a reversed style and clears the flag.
+fn resolve_reverse(style: &mut Style) {
+    if style.reverse {
+        std::mem::swap(&mut style.fg_color, &mut style.bg_color);
+        style.reverse = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Style, parse_ansi};
+
+    #[test]
+    fn test_strip_blink_keeps_other_attributes() {
+        let parsed = parse_ansi("\x1b[1;5;31mx");
+        let style = &parsed.strip_blink().segments[0].style;
+        assert!(!style.blink);
+        assert!(style.bold);
+        assert_eq!(style.fg_color, Some(Color::Indexed(1)));
+    }
+
+    #[test]
+    fn test_strip_backgrounds_resolves_reverse() {
+        let parsed = parse_ansi("\x1b[7;31;44mx");
+        let style = &parsed.strip_backgrounds().segments[0].style;
+        assert_eq!(
+            style,
+            &Style {
+                fg_color: Some(Color::Indexed(4)),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_colors_only() {
+        let parsed = parse_ansi("\x1b[1;3;4;8;31;42mx");
+        let style = &parsed.colors_only().segments[0].style;
+        assert_eq!(
+            style,
+            &Style {
+                fg_color: Some(Color::Indexed(1)),
+                bg_color: Some(Color::Indexed(2)),
+                ..Default::default()
+            }
+        );
+    }
+}
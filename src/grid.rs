@@ -0,0 +1,294 @@
+use crate::width::grapheme_width;
+use crate::{Style, StyledText};
+use std::borrow::Cow;
+
+/// A single display column of a [`Grid`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Cell {
+    /// The grapheme shown in this cell, or an empty string if the cell is covered by the
+    /// wide grapheme in the previous cell. Blank and covered cells borrow their text, so
+    /// large grids of them do not allocate per cell.
+    pub text: Cow<'static, str>,
+    /// The style applied to the cell.
+    pub style: Style,
+}
+
+impl Cell {
+    /// Creates a blank cell with the default style.
+    pub fn blank() -> Self {
+        Cell {
+            text: Cow::Borrowed(" "),
+            style: Style::default(),
+        }
+    }
+
+    /// Returns `true` if this cell is covered by a wide grapheme to its left.
+    pub fn is_continuation(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+/// Styled text laid out as rows of display-column cells.
+///
+/// Wide graphemes occupy their first cell and leave continuation cells after it, so every
+/// cell index within a row corresponds directly to a display column.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Grid {
+    pub rows: Vec<Vec<Cell>>,
+    /// Whether the source text ended with a newline.
+    pub trailing_newline: bool,
+}
+
+impl Grid {
+    /// Lays out styled text as a grid, one row per line.
+    pub fn from_styled(styled_text: &StyledText) -> Self {
+        let rows = styled_text
+            .split_lines()
+            .iter()
+            .map(|line| {
+                let mut row: Vec<Cell> = Vec::new();
+                for (grapheme, style) in line.joined_graphemes_styled() {
+                    let columns = grapheme_width(&grapheme);
+                    if columns == 0 {
+                        if let Some(last) = row.iter_mut().rev().find(|c| !c.is_continuation()) {
+                            last.text.to_mut().push_str(&grapheme);
+                        }
+                        continue;
+                    }
+                    row.push(Cell {
+                        text: Cow::Owned(grapheme.into_owned()),
+                        style: style.clone(),
+                    });
+                    for _ in 1..columns {
+                        row.push(Cell {
+                            text: Cow::Borrowed(""),
+                            style: style.clone(),
+                        });
+                    }
+                }
+                row
+            })
+            .collect();
+
+        Grid {
+            rows,
+            trailing_newline: styled_text
+                .segments
+                .iter()
+                .rev()
+                .find(|segment| !segment.text.is_empty())
+                .is_some_and(|segment| segment.text.ends_with('\n')),
+        }
+    }
+
+    /// Returns the width of the widest row.
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Pads every row with blank cells up to `width` columns.
+    pub fn pad_to(&mut self, width: usize) {
+        for row in &mut self.rows {
+            row.resize_with(width.max(row.len()), Cell::blank);
+        }
+    }
+
+    /// Writes `cells` starting at the given position, growing the grid as needed.
+    ///
+    /// `cells` should hold a whole grapheme, including its continuation cells. Wide
+    /// graphemes in the grid that are partially overwritten are replaced with blanks so no
+    /// orphaned halves remain.
+    pub fn put(&mut self, x: usize, y: usize, cells: Vec<Cell>) {
+        let end = x + cells.len();
+        if self.rows.len() <= y {
+            self.rows.resize_with(y + 1, Vec::new);
+        }
+        let row = &mut self.rows[y];
+        if row.len() < end {
+            row.resize_with(end, Cell::blank);
+        }
+
+        let mut start = x;
+        while start > 0 && row[start].is_continuation() {
+            start -= 1;
+            row[start].text = Cow::Borrowed(" ");
+        }
+        let mut next = end;
+        while next < row.len() && row[next].is_continuation() {
+            row[next].text = Cow::Borrowed(" ");
+            next += 1;
+        }
+
+        row.splice(x..end, cells);
+    }
+
+    /// Converts the grid back into styled text, joining rows with newlines.
+    pub fn into_styled(self) -> StyledText {
+        let mut styled_text = StyledText::default();
+        let row_count = self.rows.len();
+        for (idx, row) in self.rows.into_iter().enumerate() {
+            for cell in row {
+                styled_text.push_str_styled(&cell.text, cell.style);
+            }
+            if idx + 1 < row_count || self.trailing_newline {
+                styled_text.push_str_styled("\n", Style::default());
+            }
+        }
+        styled_text
+    }
+}
+
+impl StyledText {
+    /// Stamps `other` on top of this text with its top-left corner at column `x`, line `y`.
+    ///
+    /// Both texts are treated as grids of display cells. Every non-space cell of `other`
+    /// replaces the corresponding cell of this text, while spaces let the base show
+    /// through. The base grows with blank cells if the overlay extends past it.
+    #[must_use]
+    pub fn overlay(&self, other: &StyledText, x: usize, y: usize) -> StyledText {
+        let mut base = Grid::from_styled(self);
+        let top = Grid::from_styled(other);
+
+        for (row_idx, row) in top.rows.into_iter().enumerate() {
+            let mut columns = row.into_iter().enumerate().peekable();
+            while let Some((col_idx, cell)) = columns.next() {
+                if cell.text == " " || cell.is_continuation() {
+                    continue;
+                }
+                let mut cells = vec![cell];
+                while let Some((_, continuation)) =
+                    columns.next_if(|(_, next)| next.is_continuation())
+                {
+                    cells.push(continuation);
+                }
+                base.put(x + col_idx, y + row_idx, cells);
+            }
+        }
+
+        base.into_styled()
+    }
+
+    /// Mirrors the text left to right, treating it as a grid padded to its widest line.
+    ///
+    /// Wide graphemes are kept intact rather than having their cells swapped.
+    #[must_use]
+    pub fn flip_horizontal(&self) -> StyledText {
+        let mut grid = Grid::from_styled(self);
+        grid.pad_to(grid.width());
+
+        for row in &mut grid.rows {
+            let mut graphemes: Vec<Vec<Cell>> = Vec::new();
+            for cell in row.drain(..) {
+                match graphemes.last_mut() {
+                    Some(last) if cell.is_continuation() => last.push(cell),
+                    _ => graphemes.push(vec![cell]),
+                }
+            }
+            row.extend(graphemes.into_iter().rev().flatten());
+        }
+
+        grid.into_styled()
+    }
+
+    /// Mirrors the text top to bottom, reversing the order of its lines.
+    #[must_use]
+    pub fn flip_vertical(&self) -> StyledText {
+        let mut grid = Grid::from_styled(self);
+        grid.rows.reverse();
+        grid.into_styled()
+    }
+
+    /// Rotates the text 90 degrees clockwise, treating it as a grid padded to its widest
+    /// line.
+    ///
+    /// Wide graphemes cannot be represented once rotated, so every cell they covered
+    /// becomes a space with the grapheme's style.
+    #[must_use]
+    pub fn rotate90(&self) -> StyledText {
+        let mut grid = Grid::from_styled(self);
+        let width = grid.width();
+        grid.pad_to(width);
+
+        let mut rows: Vec<Vec<Cell>> = vec![Vec::with_capacity(grid.rows.len()); width];
+        for row in grid.rows.iter().rev() {
+            for (column, cell) in row.iter().enumerate() {
+                let wide = cell.is_continuation()
+                    || row.get(column + 1).is_some_and(Cell::is_continuation);
+                let mut cell = cell.clone();
+                if wide {
+                    cell.text = Cow::Borrowed(" ");
+                }
+                rows[column].push(cell);
+            }
+        }
+
+        Grid {
+            rows,
+            trailing_newline: grid.trailing_newline,
+        }
+        .into_styled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Style, parse_ansi, strip_ansi};
+
+    #[test]
+    fn test_overlay_replaces_non_space_cells() {
+        let base = parse_ansi("......\n......\n");
+        let label = parse_ansi("\x1b[1mA B\x1b[0m");
+        let result = base.overlay(&label, 2, 1);
+
+        assert_eq!(result.to_plain_text(), "......\n..A.B.\n");
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        assert!(
+            result
+                .segments
+                .iter()
+                .any(|segment| segment.text == "A" && segment.style == bold)
+        );
+    }
+
+    #[test]
+    fn test_overlay_grows_base() {
+        let base = parse_ansi("ab");
+        let result = base.overlay(&parse_ansi("X"), 3, 1);
+        assert_eq!(strip_ansi(&result.to_string()), "ab\n   X");
+    }
+
+    #[test]
+    fn test_flip_horizontal_pads_and_mirrors() {
+        let text = parse_ansi("ab\nc日");
+        assert_eq!(text.flip_horizontal().to_plain_text(), " ba\n日c");
+    }
+
+    #[test]
+    fn test_flip_vertical() {
+        let text = parse_ansi("ab\ncd\n");
+        assert_eq!(text.flip_vertical().to_plain_text(), "cd\nab\n");
+    }
+
+    #[test]
+    fn test_rotate90_clockwise() {
+        let text = parse_ansi("ab\ncd\nef");
+        assert_eq!(text.rotate90().to_plain_text(), "eca\nfdb");
+    }
+
+    #[test]
+    fn test_overlay_wide_chars() {
+        let base = parse_ansi("....");
+        let result = base.overlay(&parse_ansi("日"), 1, 0);
+        assert_eq!(result.to_plain_text(), ".日.");
+    }
+
+    #[test]
+    fn test_overlay_blanks_split_wide_chars() {
+        let base = parse_ansi("日本");
+        let result = base.overlay(&parse_ansi("X"), 1, 0);
+        assert_eq!(result.to_plain_text(), " X本");
+    }
+}
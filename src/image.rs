@@ -0,0 +1,515 @@
+use crate::{Color, FontAtlas, Segment, Style, StyledText};
+use error_stack::{Report, ResultExt};
+use rexpaint::{XpColor, XpFile};
+use smart_default::SmartDefault;
+use std::io::Cursor;
+use wherror::Error;
+
+/// Pixels at least this opaque are drawn; fainter ones are left transparent.
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Error raised when an image cannot be decoded.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct ImageError;
+
+/// A raster image decoded to 8-bit RGBA pixels, for converting to text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: usize,
+    height: usize,
+    /// Pixels row by row, from the top left.
+    pixels: Vec<[u8; 4]>,
+}
+
+/// Options for converting an image to text.
+#[derive(Debug, Clone, PartialEq, Eq, SmartDefault)]
+pub struct ImageOptions {
+    /// Width of the text in columns. Images are shrunk to fit, but never made wider than a
+    /// column per pixel.
+    #[default(80)]
+    pub columns: usize,
+    /// Font whose glyph shapes are matched when converting to `RexPaint`. Defaults to
+    /// `RexPaint`'s 14x14 CP437 font.
+    pub font: FontAtlas,
+}
+
+impl RgbaImage {
+    /// Creates an image from its pixels, row by row from the top left, or returns `None`
+    /// if there are not `width * height` of them.
+    #[must_use]
+    pub fn new(width: usize, height: usize, pixels: Vec<[u8; 4]>) -> Option<Self> {
+        (width.checked_mul(height) == Some(pixels.len())).then_some(RgbaImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes a PNG or JPEG image, telling them apart by their signatures.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is neither format or cannot be decoded.
+    pub fn decode(data: &[u8]) -> Result<Self, Report<ImageError>> {
+        if data.starts_with(b"\x89PNG") {
+            decode_png(data)
+        } else if data.starts_with(&[0xff, 0xd8]) {
+            decode_jpeg(data)
+        } else {
+            Err(Report::new(ImageError).attach("image is not a PNG or JPEG"))
+        }
+    }
+
+    /// The width of the image in pixels.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the RGBA color of pixel (`x`, `y`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is outside the image.
+    #[must_use]
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        assert!(x < self.width && y < self.height, "pixel outside the image");
+        self.pixels[y * self.width + x]
+    }
+
+    /// Scales the image to `width` by `height` pixels, averaging the pixels that each new
+    /// pixel covers.
+    ///
+    /// Colors are weighted by opacity, so transparent pixels do not darken their neighbors.
+    #[must_use]
+    pub fn resize(&self, width: usize, height: usize) -> RgbaImage {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let rows = source_range(y, height, self.height);
+            for x in 0..width {
+                let columns = source_range(x, width, self.width);
+                let (mut sums, mut count) = ([0u64; 4], 0u64);
+                for source_y in rows.clone() {
+                    for source_x in columns.clone() {
+                        let [r, g, b, a] = self.pixels[source_y * self.width + source_x];
+                        let alpha = u64::from(a);
+                        sums[0] += u64::from(r) * alpha;
+                        sums[1] += u64::from(g) * alpha;
+                        sums[2] += u64::from(b) * alpha;
+                        sums[3] += alpha;
+                        count += 1;
+                    }
+                }
+                let channel = |sum: u64| {
+                    u8::try_from(sum.checked_div(sums[3]).unwrap_or(0)).unwrap_or(u8::MAX)
+                };
+                let alpha =
+                    u8::try_from(sums[3].checked_div(count).unwrap_or(0)).unwrap_or(u8::MAX);
+                pixels.push([channel(sums[0]), channel(sums[1]), channel(sums[2]), alpha]);
+            }
+        }
+        RgbaImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Converts the image to truecolor text drawn with half-block characters, two pixels
+    /// to a cell: `▀` in the top pixel's color over the bottom pixel's color as background.
+    ///
+    /// The image is scaled to [`ImageOptions::columns`] columns, keeping its aspect ratio
+    /// for cells twice as tall as they are wide. Transparent pixels are left to the
+    /// terminal's default background.
+    #[must_use]
+    pub fn to_half_blocks(&self, options: &ImageOptions) -> StyledText {
+        let columns = self.columns(options);
+        let pixel_rows = self.scaled_height(columns);
+        let image = self.resize(columns, pixel_rows);
+        let color = |x: usize, y: usize| {
+            let [r, g, b, a] = image
+                .pixels
+                .get(y * columns + x)
+                .filter(|_| y < pixel_rows)?;
+            (*a >= ALPHA_THRESHOLD).then_some(Color::Rgb(*r, *g, *b))
+        };
+
+        let mut segments = Vec::new();
+        for row in 0..pixel_rows.div_ceil(2) {
+            for x in 0..columns {
+                let (text, fg_color, bg_color) = match (color(x, row * 2), color(x, row * 2 + 1)) {
+                    (None, None) => (" ", None, None),
+                    (None, bottom) => ("▄", bottom, None),
+                    (top, bottom) => ("▀", top, bottom),
+                };
+                segments.push(Segment {
+                    text: text.to_string(),
+                    style: Style {
+                        fg_color,
+                        bg_color,
+                        ..Default::default()
+                    },
+                    source: None,
+                    escapes: None,
+                });
+            }
+            segments.push(Segment {
+                text: "\n".to_string(),
+                style: Style::default(),
+                source: None,
+                escapes: None,
+            });
+        }
+        StyledText { segments }.normalize()
+    }
+
+    /// Converts the image to a `RexPaint` file, choosing for each cell the glyph of
+    /// [`ImageOptions::font`] and the foreground and background colors that draw its
+    /// pixels most closely.
+    ///
+    /// The image is scaled to [`ImageOptions::columns`] cells, keeping its aspect ratio for
+    /// the font's glyph size. Cells that are mostly transparent are given the transparent
+    /// key color as background, so lower layers show through.
+    #[must_use]
+    pub fn to_rexpaint(&self, options: &ImageOptions) -> XpFile {
+        let font = &options.font;
+        let (glyph_width, glyph_height) = (font.glyph_width(), font.glyph_height());
+        let columns = self.columns(options);
+        let rows = (self.scaled_height(columns * glyph_width) + glyph_height / 2) / glyph_height;
+        let rows = rows.max(1);
+        let image = self.resize(columns * glyph_width, rows * glyph_height);
+        let glyphs: Vec<GlyphShape> = (0..=u8::MAX)
+            .map(|glyph| GlyphShape::new(font, glyph))
+            .collect();
+
+        let mut xp = XpFile::new(columns, rows);
+        let mut pixels = Vec::with_capacity(glyph_width * glyph_height);
+        for row in 0..rows {
+            for column in 0..columns {
+                pixels.clear();
+                for y in row * glyph_height..(row + 1) * glyph_height {
+                    let start = y * image.width + column * glyph_width;
+                    pixels.extend_from_slice(&image.pixels[start..start + glyph_width]);
+                }
+                let Some(cell) = xp.layers[0].get_mut(column, row) else {
+                    continue;
+                };
+
+                let alpha = pixels.iter().map(|pixel| u32::from(pixel[3])).sum::<u32>();
+                #[allow(clippy::cast_possible_truncation)]
+                if alpha < u32::from(ALPHA_THRESHOLD) * pixels.len() as u32 {
+                    cell.ch = 0;
+                    cell.bg = XpColor::TRANSPARENT;
+                    continue;
+                }
+                let (glyph, fg, bg) = best_glyph(&glyphs, &pixels);
+                cell.ch = u32::from(glyph);
+                cell.fg = fg;
+                cell.bg = bg;
+            }
+        }
+        xp
+    }
+
+    /// Returns the number of columns to draw the image in: [`ImageOptions::columns`], but
+    /// at most one per pixel.
+    fn columns(&self, options: &ImageOptions) -> usize {
+        options.columns.min(self.width).max(1)
+    }
+
+    /// Returns the height in pixels of the image scaled to `width` pixels, keeping its
+    /// aspect ratio.
+    fn scaled_height(&self, width: usize) -> usize {
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let height = (self.height as f64 * width as f64 / self.width.max(1) as f64).round();
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let height = height as usize;
+        height.max(1)
+    }
+}
+
+/// How much of each pixel a glyph covers, with the sums needed to fit colors to it.
+struct GlyphShape {
+    /// Coverage of each pixel row by row, from 0 (background) to 1 (foreground).
+    coverage: Vec<f64>,
+    /// Sum of the squared foreground coverage.
+    fore_sq: f64,
+    /// Sum of the foreground coverage times the background coverage.
+    mixed: f64,
+    /// Sum of the squared background coverage.
+    back_sq: f64,
+}
+
+impl GlyphShape {
+    /// Measures `glyph` of `font`.
+    fn new(font: &FontAtlas, glyph: u8) -> Self {
+        let mut coverage = Vec::with_capacity(font.glyph_width() * font.glyph_height());
+        for y in 0..font.glyph_height() {
+            for x in 0..font.glyph_width() {
+                coverage.push(f64::from(font.coverage_at(glyph, x, y)) / 255.0);
+            }
+        }
+        let (mut fore_sq, mut mixed, mut back_sq) = (0.0, 0.0, 0.0);
+        for &fg in &coverage {
+            fore_sq += fg * fg;
+            mixed += fg * (1.0 - fg);
+            back_sq += (1.0 - fg) * (1.0 - fg);
+        }
+        GlyphShape {
+            coverage,
+            fore_sq,
+            mixed,
+            back_sq,
+        }
+    }
+}
+
+/// Returns the glyph and the foreground and background colors that draw `pixels`, the
+/// cell's pixels row by row, with the least squared error.
+///
+/// For each glyph, the best colors are found by least squares, and glyphs that are all
+/// foreground or all background are drawn in the average color. Ties go to the lowest
+/// glyph, so plain cells use a blank glyph.
+fn best_glyph(glyphs: &[GlyphShape], pixels: &[[u8; 4]]) -> (u8, XpColor, XpColor) {
+    let mut totals = [0.0; 3];
+    for pixel in pixels {
+        for (total, &channel) in totals.iter_mut().zip(pixel) {
+            *total += f64::from(channel);
+        }
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let count = pixels.len().max(1) as f64;
+
+    let mut best = (f64::INFINITY, 0, [0.0; 3], [0.0; 3]);
+    for (glyph, shape) in (0..=u8::MAX).zip(glyphs) {
+        let det = shape.fore_sq * shape.back_sq - shape.mixed * shape.mixed;
+        let (mut fg, mut bg, mut error) = ([0.0; 3], [0.0; 3], 0.0);
+        for channel in 0..3 {
+            let weighted_fore: f64 = pixels
+                .iter()
+                .zip(&shape.coverage)
+                .map(|(pixel, coverage)| coverage * f64::from(pixel[channel]))
+                .sum();
+            let weighted_back = totals[channel] - weighted_fore;
+            let (f, b) = if det.abs() < 1e-9 {
+                let mean = totals[channel] / count;
+                (mean, mean)
+            } else {
+                (
+                    ((weighted_fore * shape.back_sq - weighted_back * shape.mixed) / det)
+                        .clamp(0.0, 255.0),
+                    ((weighted_back * shape.fore_sq - weighted_fore * shape.mixed) / det)
+                        .clamp(0.0, 255.0),
+                )
+            };
+            // The squared error without the sum of squared pixels, which all glyphs share
+            error += f * f * shape.fore_sq + 2.0 * f * b * shape.mixed + b * b * shape.back_sq
+                - 2.0 * (f * weighted_fore + b * weighted_back);
+            (fg[channel], bg[channel]) = (f, b);
+        }
+        if error < best.0 - 1e-6 {
+            best = (error, glyph, fg, bg);
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let color =
+        |[r, g, b]: [f64; 3]| XpColor::new(r.round() as u8, g.round() as u8, b.round() as u8);
+    (best.1, color(best.2), color(best.3))
+}
+
+/// Returns the source pixels covered by pixel `idx` of `len` scaled from `source_len`.
+fn source_range(idx: usize, len: usize, source_len: usize) -> std::ops::Range<usize> {
+    let start = idx * source_len / len;
+    let end = ((idx + 1) * source_len / len).max(start + 1);
+    start..end.min(source_len)
+}
+
+/// Decodes a PNG image, expanding palettes and gray levels to RGBA.
+fn decode_png(data: &[u8]) -> Result<RgbaImage, Report<ImageError>> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .change_context(ImageError)
+        .attach("failed to read PNG header")?;
+    let mut buffer = vec![0; reader.output_buffer_size().unwrap_or(0)];
+    let info = reader
+        .next_frame(&mut buffer)
+        .change_context(ImageError)
+        .attach("failed to decode PNG")?;
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = info.color_type.samples();
+    let pixels = buffer[..info.line_size * height]
+        .chunks(info.line_size)
+        .flat_map(|row| row[..width * channels].chunks(channels))
+        .map(|pixel| match *pixel {
+            [gray] => [gray, gray, gray, 255],
+            [gray, alpha] => [gray, gray, gray, alpha],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, alpha, ..] => [r, g, b, alpha],
+            [] => [0, 0, 0, 0],
+        })
+        .collect();
+    Ok(RgbaImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Decodes a JPEG image to opaque RGBA.
+fn decode_jpeg(data: &[u8]) -> Result<RgbaImage, Report<ImageError>> {
+    let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(data));
+    let buffer = decoder
+        .decode()
+        .change_context(ImageError)
+        .attach("failed to decode JPEG")?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| Report::new(ImageError).attach("JPEG has no frame"))?;
+
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => buffer.iter().map(|&l| [l, l, l, 255]).collect(),
+        jpeg_decoder::PixelFormat::L16 => buffer
+            .chunks_exact(2)
+            .map(|l| {
+                let [l, _] = (u16::from_ne_bytes([l[0], l[1]])).to_be_bytes();
+                [l, l, l, 255]
+            })
+            .collect(),
+        jpeg_decoder::PixelFormat::RGB24 => buffer
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => buffer
+            .chunks_exact(4)
+            .map(|cmyk| {
+                let channel = |ink: u8| {
+                    let value = u16::from(255 - ink) * u16::from(255 - cmyk[3]) / 255;
+                    u8::try_from(value).unwrap_or(u8::MAX)
+                };
+                [channel(cmyk[0]), channel(cmyk[1]), channel(cmyk[2]), 255]
+            })
+            .collect(),
+    };
+    RgbaImage::new(usize::from(info.width), usize::from(info.height), pixels)
+        .ok_or_else(|| Report::new(ImageError).attach("JPEG data does not match its size"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn test_half_blocks() {
+        // A 2x3 image: red over blue, then a transparent column, then a red bottom row
+        #[rustfmt::skip]
+        let image = RgbaImage::new(2, 3, vec![
+            RED, CLEAR,
+            BLUE, CLEAR,
+            RED, RED,
+        ])
+        .unwrap();
+        let text = image.to_half_blocks(&ImageOptions::default());
+        assert_eq!(text.to_plain_text(), "▀ \n▀▀\n");
+        let style = &text.segments[0].style;
+        assert_eq!(
+            (style.fg_color.clone(), style.bg_color.clone()),
+            (Some(Color::Rgb(255, 0, 0)), Some(Color::Rgb(0, 0, 255)))
+        );
+        assert_eq!(text.segments[2].text, "▀▀");
+        assert_eq!(text.segments[2].style.bg_color, None);
+
+        let below = RgbaImage::new(1, 2, vec![CLEAR, BLUE]).unwrap();
+        assert_eq!(
+            below
+                .to_half_blocks(&ImageOptions::default())
+                .to_plain_text(),
+            "▄\n"
+        );
+    }
+
+    #[test]
+    fn test_resize() {
+        let image =
+            RgbaImage::new(4, 2, vec![RED, RED, BLUE, CLEAR, RED, RED, BLUE, CLEAR]).unwrap();
+        let small = image.resize(2, 1);
+        assert_eq!(small.pixel(0, 0), RED);
+        // Transparent pixels thin out the alpha but leave the color alone
+        assert_eq!(small.pixel(1, 0), [0, 0, 255, 127]);
+        // Shrinking to fit keeps the aspect ratio
+        assert_eq!(image.scaled_height(2), 1);
+        assert_eq!(image.scaled_height(4), 2);
+    }
+
+    #[test]
+    fn test_to_rexpaint() {
+        let font = FontAtlas::default();
+        let (width, height) = (font.glyph_width(), font.glyph_height());
+        let options = ImageOptions {
+            columns: 1,
+            ..Default::default()
+        };
+
+        // A drawing of `A` in white on blue comes back as that glyph and those colors
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let coverage = font.coverage_at(b'A', x, y);
+                pixels.push([coverage, coverage, 255, 255]);
+            }
+        }
+        let xp = RgbaImage::new(width, height, pixels)
+            .unwrap()
+            .to_rexpaint(&options);
+        assert_eq!((xp.layers[0].width, xp.layers[0].height), (1, 1));
+        let cell = xp.layers[0].get(0, 0).unwrap();
+        assert_eq!(u8::try_from(cell.ch), Ok(b'A'));
+        assert_eq!(cell.fg, XpColor::new(255, 255, 255));
+        assert_eq!(cell.bg, XpColor::new(0, 0, 255));
+
+        // A flat color is a blank glyph on that background
+        let flat = RgbaImage::new(width, height, vec![RED; width * height]).unwrap();
+        let cell = flat.to_rexpaint(&options).layers[0].cells[0];
+        assert_eq!((cell.ch, cell.bg), (0, XpColor::new(255, 0, 0)));
+
+        let clear = RgbaImage::new(2, 2, vec![CLEAR; 4]).unwrap();
+        assert_eq!(
+            clear.to_rexpaint(&options).layers[0].cells[0].bg,
+            XpColor::TRANSPARENT
+        );
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 0, 0, 255]).unwrap();
+        writer.finish().unwrap();
+
+        let image = RgbaImage::decode(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixel(1, 0), BLUE);
+        assert!(RgbaImage::decode(b"GIF89a").is_err());
+    }
+}
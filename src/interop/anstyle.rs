@@ -0,0 +1,121 @@
+use crate::{Color, Style};
+use anstyle::{Ansi256Color, Effects, RgbColor};
+
+impl From<&Color> for anstyle::Color {
+    /// Converts a color, using `anstyle`'s named colors for the first 16 indexes.
+    fn from(color: &Color) -> Self {
+        match color {
+            Color::Indexed(idx) => {
+                let color = Ansi256Color(*idx);
+                color
+                    .into_ansi()
+                    .map_or(anstyle::Color::Ansi256(color), anstyle::Color::Ansi)
+            }
+            Color::Rgb(r, g, b) => anstyle::Color::Rgb(RgbColor(*r, *g, *b)),
+        }
+    }
+}
+
+impl From<anstyle::Color> for Color {
+    fn from(color: anstyle::Color) -> Self {
+        match color {
+            anstyle::Color::Ansi(color) => Color::Indexed(Ansi256Color::from_ansi(color).0),
+            anstyle::Color::Ansi256(Ansi256Color(idx)) => Color::Indexed(idx),
+            anstyle::Color::Rgb(RgbColor(r, g, b)) => Color::Rgb(r, g, b),
+        }
+    }
+}
+
+/// Pairs each fromansi attribute with the `anstyle` effect that represents it.
+fn effects(style: &Style) -> [(bool, Effects); 8] {
+    [
+        (style.bold, Effects::BOLD),
+        (style.dim, Effects::DIMMED),
+        (style.italic, Effects::ITALIC),
+        (style.underline, Effects::UNDERLINE),
+        (style.blink, Effects::BLINK),
+        (style.reverse, Effects::INVERT),
+        (style.hidden, Effects::HIDDEN),
+        (style.strikethrough, Effects::STRIKETHROUGH),
+    ]
+}
+
+impl From<&Style> for anstyle::Style {
+    fn from(style: &Style) -> Self {
+        let effects = effects(style)
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .fold(Effects::new(), |all, (_, effect)| all | effect);
+        anstyle::Style::new()
+            .fg_color(style.fg_color.as_ref().map(anstyle::Color::from))
+            .bg_color(style.bg_color.as_ref().map(anstyle::Color::from))
+            .effects(effects)
+    }
+}
+
+impl From<anstyle::Style> for Style {
+    /// Converts an `anstyle` style, treating every underline variant as a plain
+    /// underline.
+    ///
+    /// Underline colors have no fromansi equivalent and are dropped.
+    fn from(style: anstyle::Style) -> Self {
+        let effects = style.get_effects();
+        let underline = [
+            Effects::UNDERLINE,
+            Effects::DOUBLE_UNDERLINE,
+            Effects::CURLY_UNDERLINE,
+            Effects::DOTTED_UNDERLINE,
+            Effects::DASHED_UNDERLINE,
+        ]
+        .into_iter()
+        .any(|effect| effects.contains(effect));
+        Style {
+            fg_color: style.get_fg_color().map(Color::from),
+            bg_color: style.get_bg_color().map(Color::from),
+            bold: effects.contains(Effects::BOLD),
+            dim: effects.contains(Effects::DIMMED),
+            italic: effects.contains(Effects::ITALIC),
+            underline,
+            blink: effects.contains(Effects::BLINK),
+            reverse: effects.contains(Effects::INVERT),
+            hidden: effects.contains(Effects::HIDDEN),
+            strikethrough: effects.contains(Effects::STRIKETHROUGH),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_anstyle_round_trip() {
+        let parsed = parse_ansi("\x1b[1;3;91;48;5;200mA\x1b[0m\x1b[7;38;2;1;2;3mB");
+        for segment in &parsed.segments {
+            let converted = anstyle::Style::from(&segment.style);
+            assert_eq!(Style::from(converted), segment.style);
+        }
+    }
+
+    #[test]
+    fn test_anstyle_rendering_matches() {
+        let style = Style {
+            fg_color: Some(Color::Indexed(1)),
+            bold: true,
+            ..Default::default()
+        };
+        let rendered = format!(
+            "{}x{}",
+            anstyle::Style::from(&style).render(),
+            anstyle::Reset.render()
+        );
+        assert_eq!(parse_ansi(&rendered).segments[0].style, style);
+    }
+
+    #[test]
+    fn test_anstyle_curly_underline() {
+        let style = anstyle::Style::new().effects(Effects::CURLY_UNDERLINE);
+        assert!(Style::from(style).underline);
+    }
+}
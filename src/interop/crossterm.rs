@@ -0,0 +1,133 @@
+use crate::palette::ColorDepth;
+use crate::{Color, Style, StyledText};
+use crossterm::QueueableCommand;
+use crossterm::style::{
+    Attribute, Color as CrosstermColor, Print, ResetColor, SetAttribute, SetBackgroundColor,
+    SetForegroundColor,
+};
+use std::io::{self, Write};
+
+impl StyledText {
+    /// Replays the styled text onto a terminal using crossterm commands.
+    ///
+    /// Colors are downgraded to what the terminal reports supporting through `COLORTERM`
+    /// and `TERM` (see `crossterm::style::available_color_count`). The style is reset
+    /// and the writer flushed once all text has been written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `writer` fails.
+    pub fn write_crossterm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_crossterm_with_depth(writer, ColorDepth::detect())
+    }
+
+    /// Replays the styled text onto a terminal using crossterm commands, converting colors
+    /// to `depth`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `writer` fails.
+    pub fn write_crossterm_with_depth<W: Write>(
+        &self,
+        writer: &mut W,
+        depth: ColorDepth,
+    ) -> io::Result<()> {
+        let default_style = Style::default();
+        let mut active = &default_style;
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            if segment.style != *active {
+                if *active != default_style {
+                    writer.queue(SetAttribute(Attribute::Reset))?;
+                    writer.queue(ResetColor)?;
+                }
+                queue_style(writer, &segment.style, depth)?;
+                active = &segment.style;
+            }
+            writer.queue(Print(&segment.text))?;
+        }
+
+        if *active != default_style {
+            writer.queue(SetAttribute(Attribute::Reset))?;
+            writer.queue(ResetColor)?;
+        }
+        writer.flush()
+    }
+}
+
+/// Queues the commands that select `style` from a reset state.
+fn queue_style<W: Write>(writer: &mut W, style: &Style, depth: ColorDepth) -> io::Result<()> {
+    let attributes = [
+        (style.bold, Attribute::Bold),
+        (style.dim, Attribute::Dim),
+        (style.italic, Attribute::Italic),
+        (style.underline, Attribute::Underlined),
+        (style.blink, Attribute::SlowBlink),
+        (style.reverse, Attribute::Reverse),
+        (style.hidden, Attribute::Hidden),
+        (style.strikethrough, Attribute::CrossedOut),
+    ];
+    for (enabled, attribute) in attributes {
+        if enabled {
+            writer.queue(SetAttribute(attribute))?;
+        }
+    }
+    if let Some(color) = &style.fg_color {
+        writer.queue(SetForegroundColor(to_crossterm_color(
+            &color.to_depth(depth),
+        )))?;
+    }
+    if let Some(color) = &style.bg_color {
+        writer.queue(SetBackgroundColor(to_crossterm_color(
+            &color.to_depth(depth),
+        )))?;
+    }
+    Ok(())
+}
+
+fn to_crossterm_color(color: &Color) -> CrosstermColor {
+    match color {
+        Color::Indexed(idx) => CrosstermColor::AnsiValue(*idx),
+        Color::Rgb(r, g, b) => CrosstermColor::Rgb {
+            r: *r,
+            g: *g,
+            b: *b,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_ansi, strip_ansi};
+
+    #[test]
+    fn test_write_crossterm_round_trip() {
+        let parsed = parse_ansi("plain \x1b[1;31mbold red\x1b[0m \x1b[48;2;1;2;3mrgb\x1b[0m");
+        let mut output = Vec::new();
+        parsed
+            .write_crossterm_with_depth(&mut output, ColorDepth::TrueColor)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(strip_ansi(&output), "plain bold red rgb");
+        assert_eq!(parse_ansi(&output).normalize(), parsed.normalize());
+    }
+
+    #[test]
+    fn test_write_crossterm_downgrades_colors() {
+        let parsed = parse_ansi("\x1b[38;2;255;0;0mA");
+        let mut output = Vec::new();
+        parsed
+            .write_crossterm_with_depth(&mut output, ColorDepth::Ansi16)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            parse_ansi(&output).segments[0].style.fg_color,
+            Some(Color::Indexed(9))
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{Color, StyledText};
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Stroke};
+
+impl StyledText {
+    /// Converts the styled text to an egui `LayoutJob` using `font_id` for every segment.
+    ///
+    /// Text without an explicit foreground uses `Color32::PLACEHOLDER`, so egui draws it
+    /// in the widget's normal text color. Reversed text without explicit colors falls back
+    /// to palette colors 7 and 0. egui cannot select a bold face per segment, so bold is
+    /// not represented; dim text is drawn at half opacity and hidden text is transparent.
+    #[must_use]
+    pub fn to_layout_job(&self, font_id: &FontId) -> LayoutJob {
+        let mut job = LayoutJob::default();
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            let style = &segment.style;
+            let (fg_color, bg_color) = if style.reverse {
+                (
+                    Some(style.bg_color.clone().unwrap_or(Color::Indexed(0))),
+                    Some(style.fg_color.clone().unwrap_or(Color::Indexed(7))),
+                )
+            } else {
+                (style.fg_color.clone(), style.bg_color.clone())
+            };
+
+            let mut color = fg_color.as_ref().map_or(Color32::PLACEHOLDER, to_color32);
+            if style.dim {
+                color = color.gamma_multiply(0.5);
+            }
+            if style.hidden {
+                color = Color32::TRANSPARENT;
+            }
+            let line = Stroke::new(1.0, color);
+
+            let format = TextFormat {
+                font_id: font_id.clone(),
+                color,
+                background: bg_color.as_ref().map_or(Color32::TRANSPARENT, to_color32),
+                italics: style.italic,
+                underline: if style.underline { line } else { Stroke::NONE },
+                strikethrough: if style.strikethrough {
+                    line
+                } else {
+                    Stroke::NONE
+                },
+                ..Default::default()
+            };
+            job.append(&segment.text, 0.0, format);
+        }
+        job
+    }
+}
+
+impl From<&StyledText> for LayoutJob {
+    /// Converts styled text using egui's default monospace font.
+    fn from(styled_text: &StyledText) -> Self {
+        styled_text.to_layout_job(&FontId::monospace(12.0))
+    }
+}
+
+fn to_color32(color: &Color) -> Color32 {
+    let (r, g, b) = color.to_rgb();
+    Color32::from_rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_layout_job_sections() {
+        let parsed = parse_ansi("plain \x1b[3;4;31mred\x1b[0m");
+        let job = LayoutJob::from(&parsed);
+
+        assert_eq!(job.text, "plain red");
+        assert_eq!(job.sections.len(), 2);
+        assert_eq!(job.sections[0].format.color, Color32::PLACEHOLDER);
+
+        let red = &job.sections[1];
+        assert_eq!(red.format.color, Color32::from_rgb(0x80, 0, 0));
+        assert!(red.format.italics);
+        assert_eq!(red.format.underline.color, red.format.color);
+        assert_eq!(red.format.font_id, FontId::monospace(12.0));
+    }
+
+    #[test]
+    fn test_layout_job_reverse_defaults() {
+        let job = LayoutJob::from(&parse_ansi("\x1b[7mr"));
+        let format = &job.sections[0].format;
+        assert_eq!(format.color, Color32::from_rgb(0, 0, 0));
+        assert_eq!(format.background, Color32::from_rgb(0xc0, 0xc0, 0xc0));
+    }
+}
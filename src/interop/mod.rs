@@ -0,0 +1,14 @@
+//! Conversions between fromansi's types and those of other terminal and GUI crates.
+//!
+//! Each integration is compiled only when its cargo feature is enabled.
+
+#[cfg(feature = "anstyle")]
+mod anstyle;
+#[cfg(feature = "crossterm")]
+mod crossterm;
+#[cfg(feature = "egui")]
+mod egui;
+#[cfg(feature = "ratatui")]
+mod ratatui;
+#[cfg(feature = "termcolor")]
+mod termcolor;
@@ -0,0 +1,203 @@
+use crate::{Color, Segment, Style, StyledText};
+use ratatui::style::{Color as RatatuiColor, Modifier, Style as RatatuiStyle};
+use ratatui::text::{Line, Span, Text};
+
+impl From<&Color> for RatatuiColor {
+    fn from(color: &Color) -> Self {
+        match color {
+            Color::Indexed(0) => RatatuiColor::Black,
+            Color::Indexed(1) => RatatuiColor::Red,
+            Color::Indexed(2) => RatatuiColor::Green,
+            Color::Indexed(3) => RatatuiColor::Yellow,
+            Color::Indexed(4) => RatatuiColor::Blue,
+            Color::Indexed(5) => RatatuiColor::Magenta,
+            Color::Indexed(6) => RatatuiColor::Cyan,
+            Color::Indexed(7) => RatatuiColor::Gray,
+            Color::Indexed(8) => RatatuiColor::DarkGray,
+            Color::Indexed(9) => RatatuiColor::LightRed,
+            Color::Indexed(10) => RatatuiColor::LightGreen,
+            Color::Indexed(11) => RatatuiColor::LightYellow,
+            Color::Indexed(12) => RatatuiColor::LightBlue,
+            Color::Indexed(13) => RatatuiColor::LightMagenta,
+            Color::Indexed(14) => RatatuiColor::LightCyan,
+            Color::Indexed(15) => RatatuiColor::White,
+            Color::Indexed(idx) => RatatuiColor::Indexed(*idx),
+            Color::Rgb(r, g, b) => RatatuiColor::Rgb(*r, *g, *b),
+        }
+    }
+}
+
+/// Converts a ratatui color, returning `None` for the terminal's default color.
+fn from_ratatui_color(color: RatatuiColor) -> Option<Color> {
+    let color = match color {
+        RatatuiColor::Reset => return None,
+        RatatuiColor::Black => Color::Indexed(0),
+        RatatuiColor::Red => Color::Indexed(1),
+        RatatuiColor::Green => Color::Indexed(2),
+        RatatuiColor::Yellow => Color::Indexed(3),
+        RatatuiColor::Blue => Color::Indexed(4),
+        RatatuiColor::Magenta => Color::Indexed(5),
+        RatatuiColor::Cyan => Color::Indexed(6),
+        RatatuiColor::Gray => Color::Indexed(7),
+        RatatuiColor::DarkGray => Color::Indexed(8),
+        RatatuiColor::LightRed => Color::Indexed(9),
+        RatatuiColor::LightGreen => Color::Indexed(10),
+        RatatuiColor::LightYellow => Color::Indexed(11),
+        RatatuiColor::LightBlue => Color::Indexed(12),
+        RatatuiColor::LightMagenta => Color::Indexed(13),
+        RatatuiColor::LightCyan => Color::Indexed(14),
+        RatatuiColor::White => Color::Indexed(15),
+        RatatuiColor::Indexed(idx) => Color::Indexed(idx),
+        RatatuiColor::Rgb(r, g, b) => Color::Rgb(r, g, b),
+    };
+    Some(color)
+}
+
+/// Pairs each fromansi attribute with the ratatui modifier that represents it.
+fn modifiers(style: &Style) -> [(bool, Modifier); 8] {
+    [
+        (style.bold, Modifier::BOLD),
+        (style.dim, Modifier::DIM),
+        (style.italic, Modifier::ITALIC),
+        (style.underline, Modifier::UNDERLINED),
+        (style.blink, Modifier::SLOW_BLINK),
+        (style.reverse, Modifier::REVERSED),
+        (style.hidden, Modifier::HIDDEN),
+        (style.strikethrough, Modifier::CROSSED_OUT),
+    ]
+}
+
+impl From<&Style> for RatatuiStyle {
+    fn from(style: &Style) -> Self {
+        let mut result = RatatuiStyle::default();
+        if let Some(color) = &style.fg_color {
+            result = result.fg(color.into());
+        }
+        if let Some(color) = &style.bg_color {
+            result = result.bg(color.into());
+        }
+        for (enabled, modifier) in modifiers(style) {
+            if enabled {
+                result = result.add_modifier(modifier);
+            }
+        }
+        result
+    }
+}
+
+impl From<RatatuiStyle> for Style {
+    /// Converts a ratatui style, treating both blink speeds as blinking.
+    ///
+    /// Underline colors have no fromansi equivalent and are dropped.
+    fn from(style: RatatuiStyle) -> Self {
+        let has = |modifier| style.add_modifier.contains(modifier);
+        Style {
+            fg_color: style.fg.and_then(from_ratatui_color),
+            bg_color: style.bg.and_then(from_ratatui_color),
+            bold: has(Modifier::BOLD),
+            dim: has(Modifier::DIM),
+            italic: has(Modifier::ITALIC),
+            underline: has(Modifier::UNDERLINED),
+            blink: has(Modifier::SLOW_BLINK) || has(Modifier::RAPID_BLINK),
+            reverse: has(Modifier::REVERSED),
+            hidden: has(Modifier::HIDDEN),
+            strikethrough: has(Modifier::CROSSED_OUT),
+        }
+    }
+}
+
+impl From<&StyledText> for Text<'static> {
+    /// Converts styled text to a ratatui `Text`, with one `Line` per line of text.
+    fn from(styled_text: &StyledText) -> Self {
+        styled_text
+            .split_lines()
+            .into_iter()
+            .map(|line| {
+                line.segments
+                    .into_iter()
+                    .map(|segment| Span::styled(segment.text, RatatuiStyle::from(&segment.style)))
+                    .collect::<Line>()
+            })
+            .collect()
+    }
+}
+
+impl From<StyledText> for Text<'static> {
+    fn from(styled_text: StyledText) -> Self {
+        Text::from(&styled_text)
+    }
+}
+
+impl From<&Text<'_>> for StyledText {
+    /// Converts a ratatui `Text`, joining its lines with newlines.
+    ///
+    /// Each span's style is layered on top of its line's and the text's styles, matching
+    /// how ratatui renders them. Line alignment is not represented.
+    fn from(text: &Text<'_>) -> Self {
+        let mut styled_text = StyledText::default();
+        for (idx, line) in text.lines.iter().enumerate() {
+            if idx > 0 {
+                styled_text.push_str_styled("\n", Style::default());
+            }
+            let line_style = text.style.patch(line.style);
+            for span in &line.spans {
+                styled_text.segments.push(Segment {
+                    text: span.content.to_string(),
+                    style: Style::from(line_style.patch(span.style)),
+                    source: None,
+                    escapes: None,
+                });
+            }
+        }
+        styled_text
+    }
+}
+
+impl From<Text<'_>> for StyledText {
+    fn from(text: Text<'_>) -> Self {
+        StyledText::from(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_styled_text_to_ratatui() {
+        let text = Text::from(parse_ansi("\x1b[1;31mred\x1b[0m plain\nnext"));
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(
+            text.lines[0].spans[0],
+            Span::styled("red", RatatuiStyle::new().fg(RatatuiColor::Red).bold())
+        );
+        assert_eq!(text.lines[1].spans[0].content, "next");
+    }
+
+    #[test]
+    fn test_ratatui_round_trip() {
+        let parsed = parse_ansi("\x1b[3;38;2;1;2;3mrgb\x1b[0m\n\x1b[48;5;200mbg\x1b[0m");
+        let text = Text::from(&parsed);
+        assert_eq!(StyledText::from(&text).normalize(), parsed.normalize());
+    }
+
+    #[test]
+    fn test_ratatui_line_style_is_inherited() {
+        let line = Line::from(vec![
+            Span::raw("a"),
+            Span::styled("b", RatatuiStyle::new().italic()),
+        ])
+        .style(RatatuiStyle::new().fg(RatatuiColor::Green));
+        let styled_text = StyledText::from(Text::from(line));
+        assert_eq!(
+            styled_text.segments[0].style.fg_color,
+            Some(Color::Indexed(2))
+        );
+        assert!(styled_text.segments[1].style.italic);
+        assert_eq!(
+            styled_text.segments[1].style.fg_color,
+            Some(Color::Indexed(2))
+        );
+    }
+}
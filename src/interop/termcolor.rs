@@ -0,0 +1,142 @@
+use crate::width::grapheme_width;
+use crate::{Color, Style, StyledText};
+use std::io;
+use termcolor::{Color as TermColor, ColorSpec, WriteColor};
+use unicode_segmentation::UnicodeSegmentation;
+
+impl StyledText {
+    /// Writes the styled text to a `termcolor` sink, such as `StandardStream`.
+    ///
+    /// The first 16 colors are written as named colors (using intensity for the bright
+    /// foreground variants) so they also work on legacy Windows consoles. `termcolor` has
+    /// no blink, reverse, or hidden attributes: blink is dropped, reverse swaps the colors,
+    /// and hidden text is written as spaces of the same width. The sink is reset and
+    /// flushed once all text has been written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `writer` fails.
+    pub fn write_color<W: WriteColor>(&self, writer: &mut W) -> io::Result<()> {
+        let default_style = Style::default();
+        let mut active = &default_style;
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            if segment.style != *active {
+                writer.set_color(&color_spec(&segment.style))?;
+                active = &segment.style;
+            }
+            if segment.style.hidden {
+                for grapheme in segment.text.graphemes(true) {
+                    if grapheme == "\n" {
+                        writer.write_all(b"\n")?;
+                    } else {
+                        write!(writer, "{:width$}", "", width = grapheme_width(grapheme))?;
+                    }
+                }
+            } else {
+                writer.write_all(segment.text.as_bytes())?;
+            }
+        }
+
+        if *active != default_style {
+            writer.reset()?;
+        }
+        writer.flush()
+    }
+}
+
+/// Builds the `termcolor` color specification for `style`.
+fn color_spec(style: &Style) -> ColorSpec {
+    let (fg_color, bg_color) = if style.reverse {
+        (style.bg_color.as_ref(), style.fg_color.as_ref())
+    } else {
+        (style.fg_color.as_ref(), style.bg_color.as_ref())
+    };
+
+    let mut spec = ColorSpec::new();
+    if let Some(color) = fg_color {
+        let (color, intense) = to_term_color(color);
+        spec.set_fg(Some(color)).set_intense(intense);
+    }
+    if let Some(color) = bg_color {
+        spec.set_bg(Some(match to_term_color(color) {
+            (_, true) => TermColor::Ansi256(color.to_indexed_if_possible().unwrap_or(0)),
+            (color, false) => color,
+        }));
+    }
+    spec.set_bold(style.bold)
+        .set_dimmed(style.dim)
+        .set_italic(style.italic)
+        .set_underline(style.underline)
+        .set_strikethrough(style.strikethrough);
+    spec
+}
+
+/// Converts a color, returning whether it is the intense variant of a named color.
+fn to_term_color(color: &Color) -> (TermColor, bool) {
+    const NAMED: [TermColor; 8] = [
+        TermColor::Black,
+        TermColor::Red,
+        TermColor::Green,
+        TermColor::Yellow,
+        TermColor::Blue,
+        TermColor::Magenta,
+        TermColor::Cyan,
+        TermColor::White,
+    ];
+    match color {
+        Color::Indexed(idx) if *idx < 8 => (NAMED[usize::from(*idx)], false),
+        Color::Indexed(idx) if *idx < 16 => (NAMED[usize::from(*idx - 8)], true),
+        Color::Indexed(idx) => (TermColor::Ansi256(*idx), false),
+        Color::Rgb(r, g, b) => (TermColor::Rgb(*r, *g, *b), false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_ansi, strip_ansi};
+    use termcolor::Ansi;
+
+    #[test]
+    fn test_write_color_round_trip() {
+        let parsed = parse_ansi("plain \x1b[1;31mbold red\x1b[0m \x1b[92;48;5;200mbright\x1b[0m");
+        let mut writer = Ansi::new(Vec::new());
+        parsed.write_color(&mut writer).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(strip_ansi(&output), "plain bold red bright");
+        let styles: Vec<_> = parse_ansi(&output)
+            .normalize()
+            .segments
+            .into_iter()
+            .map(|segment| (segment.style.fg_color, segment.style.bg_color))
+            .collect();
+        assert_eq!(
+            styles,
+            vec![
+                (None, None),
+                (Some(Color::Indexed(1)), None),
+                (None, None),
+                (Some(Color::Indexed(10)), Some(Color::Indexed(200))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_color_hidden_and_reverse() {
+        let parsed = parse_ansi("\x1b[8m日a\x1b[0m|\x1b[7;31mr\x1b[0m");
+        let mut writer = Ansi::new(Vec::new());
+        parsed.write_color(&mut writer).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(strip_ansi(&output), "   |r");
+        let parsed = parse_ansi(&output);
+        let reversed = parsed.segments.last().unwrap();
+        assert_eq!(reversed.style.bg_color, Some(Color::Indexed(1)));
+        assert_eq!(reversed.style.fg_color, None);
+    }
+}
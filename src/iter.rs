@@ -0,0 +1,167 @@
+use crate::width::grapheme_width;
+use crate::{Style, StyledText};
+use std::borrow::Cow;
+use unicode_normalization::char::is_combining_mark;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A grapheme cluster of a segment, as laid out in terminal columns by
+/// [`StyledText::styled_graphemes`].
+pub(crate) struct StyledGrapheme<'a> {
+    /// The text of the grapheme.
+    pub text: &'a str,
+    /// The style of the segment it is in.
+    pub style: &'a Style,
+    /// The index of the segment it is in.
+    pub segment: usize,
+    /// Its byte offset in the text of the segment.
+    pub offset: usize,
+    /// The columns it adds to the line.
+    pub width: usize,
+    /// Whether it continues the cluster of the grapheme before it, which a change of style
+    /// split into two segments, such as the skin tone of an emoji.
+    pub joins_previous: bool,
+}
+
+impl StyledText {
+    /// Returns an iterator over every character paired with its style.
+    pub fn chars_styled(&self) -> impl Iterator<Item = (char, &Style)> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.text.chars().map(move |ch| (ch, &segment.style)))
+    }
+
+    /// Returns an iterator over every grapheme cluster paired with its style.
+    ///
+    /// Each segment is split on its own, so a cluster that a change of style splits
+    /// between segments comes out in parts. Use
+    /// [`joined_graphemes_styled`](StyledText::joined_graphemes_styled) to keep it whole.
+    pub fn graphemes_styled(&self) -> impl Iterator<Item = (&str, &Style)> {
+        self.segments.iter().flat_map(|segment| {
+            segment
+                .text
+                .graphemes(true)
+                .map(move |grapheme| (grapheme, &segment.style))
+        })
+    }
+
+    /// Returns an iterator over every grapheme cluster paired with its style, keeping
+    /// clusters whole where a change of style splits them between segments.
+    ///
+    /// Such a cluster, like an emoji with its skin tone in another color, takes the style
+    /// of its first part. Only those clusters are copied.
+    pub fn joined_graphemes_styled(&self) -> impl Iterator<Item = (Cow<'_, str>, &Style)> {
+        let mut graphemes = self.styled_graphemes().peekable();
+        std::iter::from_fn(move || {
+            let first = graphemes.next()?;
+            let mut text = Cow::Borrowed(first.text);
+            while let Some(next) = graphemes.next_if(|next| next.joins_previous) {
+                text.to_mut().push_str(next.text);
+            }
+            Some((text, first.style))
+        })
+    }
+
+    /// Returns an iterator over the grapheme clusters of each segment with their widths,
+    /// marking those that continue a cluster split between segments.
+    ///
+    /// The width of a continuation is what it adds to the width of the cluster, which is
+    /// usually nothing.
+    pub(crate) fn styled_graphemes(&self) -> impl Iterator<Item = StyledGrapheme<'_>> {
+        let mut previous: Option<&str> = None;
+        self.segments
+            .iter()
+            .enumerate()
+            .flat_map(|(index, segment)| {
+                segment
+                    .text
+                    .grapheme_indices(true)
+                    .map(move |(offset, text)| (index, segment, offset, text))
+            })
+            .map(move |(index, segment, offset, text)| {
+                // Only the first grapheme of a segment can continue a cluster, and only
+                // when it starts with a character that extends one
+                let joined = previous
+                    .filter(|previous| offset == 0 && can_join(previous, text))
+                    .and_then(|previous| {
+                        let cluster = [previous, text].concat();
+                        (cluster.graphemes(true).nth(1).is_none()).then(|| {
+                            grapheme_width(&cluster).saturating_sub(grapheme_width(previous))
+                        })
+                    });
+                previous = Some(text);
+                StyledGrapheme {
+                    text,
+                    style: &segment.style,
+                    segment: index,
+                    offset,
+                    width: joined.unwrap_or_else(|| grapheme_width(text)),
+                    joins_previous: joined.is_some(),
+                }
+            })
+    }
+}
+
+/// Whether `text` might continue the grapheme cluster `previous` ends with, judged from
+/// the characters where they meet so that most segment boundaries need no copying.
+fn can_join(previous: &str, text: &str) -> bool {
+    let Some(first) = text.chars().next() else {
+        return false;
+    };
+    previous.ends_with('\u{200d}')
+        || is_combining_mark(first)
+        || matches!(
+            first,
+            '\u{200d}' | '\u{fe00}'..='\u{fe0f}' | '\u{1f3fb}'..='\u{1f3ff}' | '\u{1f1e6}'..='\u{1f1ff}'
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Style, parse_ansi};
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_chars_styled() {
+        let parsed = parse_ansi("a\x1b[1mb");
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        let chars: Vec<_> = parsed.chars_styled().collect();
+        assert_eq!(chars, vec![('a', &Style::default()), ('b', &bold)]);
+    }
+
+    #[test]
+    fn test_graphemes_styled() {
+        let parsed = parse_ansi("e\u{301}x");
+        let graphemes: Vec<_> = parsed.graphemes_styled().map(|(g, _)| g).collect();
+        assert_eq!(graphemes, vec!["e\u{301}", "x"]);
+    }
+
+    #[test]
+    fn test_graphemes_styled_joins_split_clusters() {
+        // A thumbs up with its skin tone in another color, then a flag
+        let parsed = parse_ansi("\x1b[33m👍\x1b[35m🏽\x1b[0m🇺🇸");
+        let graphemes: Vec<_> = parsed
+            .joined_graphemes_styled()
+            .map(|(g, style)| (g.into_owned(), style.fg_color.clone()))
+            .collect();
+        assert_eq!(
+            graphemes,
+            vec![
+                ("👍🏽".to_string(), Some(crate::Color::Indexed(3))),
+                ("🇺🇸".to_string(), None)
+            ]
+        );
+        let widths: Vec<_> = parsed.styled_graphemes().map(|g| g.width).collect();
+        assert_eq!(widths, vec![2, 0, 2]);
+
+        // Split in parts by the plain iterator, and joined across a zero width joiner
+        let parts: Vec<_> = parsed.graphemes_styled().map(|(g, _)| g).collect();
+        assert_eq!(parts, vec!["👍", "🏽", "🇺🇸"]);
+        let family = parse_ansi("👩\u{200d}\x1b[1m👧\x1b[0mok");
+        let graphemes: Vec<_> = family.joined_graphemes_styled().collect();
+        assert_eq!(graphemes.len(), 3);
+        assert!(matches!(graphemes[1].0, Cow::Borrowed("o")));
+    }
+}
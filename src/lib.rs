@@ -1,19 +1,95 @@
-use codepage_437::CP437_WINGDINGS;
 use error_stack::{Report, ResultExt};
 use regex::Regex;
-use rexpaint::XpFile;
+use renderers::ansi::write_sgr_params;
+use rexpaint::{XpCell, XpFile};
 use serde::{Deserialize, Serialize};
 use std::fmt::Write;
+use std::mem;
+use std::ops::Range;
 use std::sync::LazyLock;
 use wherror::Error;
 
+mod bbs;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod binary;
+mod bintext;
+mod cast;
+mod colorblind;
+mod compact;
+mod compose;
+mod contrast;
+mod detect;
+mod diagnostics;
+mod diff;
+mod filters;
+mod grid;
+#[cfg(feature = "image")]
+mod image;
+mod interop;
+mod iter;
+mod palette;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod recolor;
 mod renderers;
+mod sauce;
+mod screen;
+mod search;
+mod stats;
+mod styles;
+mod theme;
+mod tundra;
+mod width;
+
+pub use bbs::ColorCodes;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+pub use binary::SerializationError;
+pub use bintext::parse_bin;
+pub use cast::{CastError, Frame, Recording};
+pub use colorblind::ColorBlindness;
+pub use compact::CompactStyledText;
+pub use detect::{Encoding, InputError, InputFormat, decode_input, decode_input_as};
+pub use diagnostics::{
+    Diagnostic, DiagnosticKind, OutOfRangePolicy, ParseError, UnknownSequencePolicy,
+};
+pub use diff::{DiffChunk, DiffGranularity, DiffTag};
+#[cfg(feature = "image")]
+pub use image::{ImageError, ImageOptions, RgbaImage};
+pub use palette::ColorDepth;
+#[cfg(feature = "parallel")]
+pub use parallel::{
+    parse_ansi_parallel, parse_ansi_parallel_with_diagnostics, try_parse_ansi_parallel,
+};
+pub use recolor::ColorMap;
+pub use renderers::ansi::{AnsiOptions, ResetPlacement};
+#[cfg(feature = "gif")]
+pub use renderers::gif::{GifError, frames_to_gif};
+pub use renderers::html::{
+    ClassPrefix, ClassPrefixError, HtmlDocumentOptions, HtmlLayout, HtmlOptions, NewlineMode,
+    frames_to_html, xp_to_html, xp_to_html_with,
+};
+pub use renderers::json::JSON_SCHEMA_VERSION;
+#[cfg(feature = "png")]
+pub use renderers::png::{FontAtlas, PngError, PngOptions, xp_to_png, xp_to_png_with};
+pub use renderers::rexpaint::{
+    Codepage, CombiningPolicy, EmojiPolicy, LayerSelection, RexImportOptions, RexOptions,
+    TransparencyPolicy, WideCharPolicy,
+};
+pub use renderers::sections::{SectionMarker, SectionMarkerError};
+pub use renderers::svg::{SvgOptions, frames_to_svg};
+pub use sauce::Sauce;
+pub use screen::Screen;
+pub use search::{SegmentPosition, StyledMatch};
+pub use stats::{AttributeUsage, ColorUsage, TextStats};
+pub use styles::{StyleId, StyleTable};
+pub use theme::{Theme, ThemeError};
+pub use tundra::{TundraError, parse_tundra};
 
 /// Represents a color in ANSI escape sequences.
 ///
 /// This enum supports both indexed colors (0-255) from the standard ANSI palette
 /// and truecolor RGB values.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Color {
     /// An indexed color from the 256-color ANSI palette.
     Indexed(u8),
@@ -28,38 +104,36 @@ impl Color {
     /// For RGB colors, this formats the values as `#RRGGBB`.
     #[must_use]
     pub fn to_hex(&self) -> String {
-        match self {
-            Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
-            Color::Indexed(idx) => {
-                if *idx < 16 {
-                    let standard_colors = [
-                        "#000000", "#800000", "#008000", "#808000", "#000080", "#800080",
-                        "#008080", "#c0c0c0", "#808080", "#ff0000", "#00ff00", "#ffff00",
-                        "#0000ff", "#ff00ff", "#00ffff", "#ffffff",
-                    ];
-                    standard_colors[*idx as usize].to_string()
-                } else if *idx < 232 {
-                    let i = *idx as usize - 16;
-                    let r = (i / 36) * 51;
-                    let g = ((i % 36) / 6) * 51;
-                    let b = (i % 6) * 51;
-                    format!("#{r:02x}{g:02x}{b:02x}")
-                } else {
-                    let gray = 8 + (*idx as usize - 232) * 10;
-                    format!("#{gray:02x}{gray:02x}{gray:02x}")
-                }
-            }
+        let (r, g, b) = self.to_rgb();
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// Parses a color from a hex string in `#RRGGBB` format.
+    ///
+    /// The leading `#` is optional. Returns `None` if the string is not a valid hex color.
+    #[must_use]
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
         }
+        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+        Some(Color::Rgb(r, g, b))
     }
 
     /// Attempts to convert the color to an indexed color if it matches a palette entry.
     ///
-    /// This method checks if the color's hex representation matches any of the 256
-    /// standard ANSI colors. If a match is found, returns the index; otherwise, returns None.
+    /// Indexed colors keep their own index. An RGB color is checked against the 256
+    /// standard ANSI colors, and the index of the first match is returned; otherwise,
+    /// returns None.
     #[must_use]
     pub fn to_indexed_if_possible(&self) -> Option<u8> {
-        let hex = self.to_hex();
-        (0..=255).find(|&i| Color::Indexed(i).to_hex() == hex)
+        match *self {
+            Color::Indexed(idx) => Some(idx),
+            Color::Rgb(r, g, b) => (0..=255).find(|&idx| palette::palette_rgb(idx) == (r, g, b)),
+        }
     }
 }
 
@@ -67,7 +141,7 @@ impl Color {
 ///
 /// This struct contains all the formatting options that can be applied to text,
 /// including colors, font styles, and visibility settings.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct Style {
     /// Foreground color of the text.
@@ -96,19 +170,57 @@ pub struct Style {
 ///
 /// A segment is a contiguous piece of text that shares the same styling attributes.
 /// Styled text is composed of multiple segments, each with its own style.
+///
+/// Segments can gain fields in minor releases, so outside this crate they are created
+/// with [`Segment::new`] rather than a struct literal.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Segment {
     /// The text content of this segment.
     pub text: String,
     /// The styling attributes applied to this text.
     pub style: Style,
+    /// The byte range of the input this segment was parsed from, if recorded.
+    ///
+    /// This is only populated when parsing with [`ParseOptions::source_spans`] enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Range<usize>>,
+    /// The escape sequences of the input between the previous segment and this one, as
+    /// they were written, if recorded.
+    ///
+    /// This is only populated when parsing with [`ParseOptions::raw_escapes`] enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escapes: Option<String>,
+}
+
+impl Segment {
+    /// Creates a segment of `text` in `style`, with no source span or escapes recorded.
+    #[must_use]
+    pub fn new(text: impl Into<String>, style: Style) -> Self {
+        Segment {
+            text: text.into(),
+            style,
+            source: None,
+            escapes: None,
+        }
+    }
+
+    /// Returns the source span covered by the byte range `range` of this segment's text.
+    ///
+    /// Segment text is copied verbatim from the input, so offsets map directly onto the
+    /// recorded source span.
+    pub(crate) fn sub_source(&self, range: Range<usize>) -> Option<Range<usize>> {
+        self.source
+            .as_ref()
+            .map(|source| source.start + range.start..source.start + range.end)
+    }
 }
 
 /// A collection of styled text segments.
 ///
 /// This struct represents text that has been parsed from ANSI escape sequences,
 /// broken down into segments where each segment has consistent styling.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct StyledText {
     /// The list of text segments with their associated styles.
     pub segments: Vec<Segment>,
@@ -132,13 +244,17 @@ impl StyledText {
 
         for segment in &self.segments {
             let parts: Vec<&str> = segment.text.split('\n').collect();
+            let mut offset = 0;
             for (i, part) in parts.iter().enumerate() {
                 if !part.is_empty() {
                     current_line.push(Segment {
                         text: (*part).to_string(),
                         style: segment.style.clone(),
+                        source: segment.sub_source(offset..offset + part.len()),
+                        escapes: segment.escapes.clone().filter(|_| i == 0),
                     });
                 }
+                offset += part.len() + 1;
                 if i < parts.len() - 1 {
                     // end of line
                     lines.push(StyledText {
@@ -155,6 +271,91 @@ impl StyledText {
         }
         lines
     }
+
+    /// Returns the lines in `lines`, counted from zero, keeping their newlines.
+    ///
+    /// Styles set on earlier lines carry over, since every segment keeps its full style:
+    /// an excerpt starting inside a colored block is still colored.
+    #[must_use]
+    pub fn slice_lines(&self, lines: Range<usize>) -> StyledText {
+        let mut segments = Vec::new();
+        let mut line = 0;
+        for segment in &self.segments {
+            let mut kept: Option<Range<usize>> = None;
+            let mut offset = 0;
+            for part in segment.text.split_inclusive('\n') {
+                if lines.contains(&line) {
+                    let end = offset + part.len();
+                    kept = Some(kept.map_or(offset..end, |range| range.start..end));
+                }
+                offset += part.len();
+                if part.ends_with('\n') {
+                    line += 1;
+                }
+            }
+            if let Some(range) = kept {
+                segments.push(Segment {
+                    text: segment.text[range.clone()].to_string(),
+                    style: segment.style.clone(),
+                    escapes: segment.escapes.clone().filter(|_| range.start == 0),
+                    source: segment.sub_source(range),
+                });
+            }
+        }
+        StyledText { segments }
+    }
+
+    /// Returns the text content with all styling removed.
+    #[must_use]
+    pub fn to_plain_text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect()
+    }
+
+    /// Merges adjacent segments that share the same style and drops empty segments.
+    ///
+    /// Parsing and transforms can leave the text split into many small segments with
+    /// identical styling. Normalizing collapses them so renderers emit fewer spans.
+    /// Segments with recorded [`Segment::escapes`] are kept apart, so the escapes stay
+    /// where they were, as are segments whose [`Segment::source`] spans are not next to
+    /// each other in the input, so every span still maps onto its text.
+    #[must_use]
+    pub fn normalize(&self) -> StyledText {
+        let mut segments: Vec<Segment> = Vec::with_capacity(self.segments.len());
+
+        for segment in &self.segments {
+            if segment.text.is_empty() && segment.escapes.is_none() {
+                continue;
+            }
+            match segments.last_mut() {
+                Some(last)
+                    if last.style == segment.style
+                        && segment.escapes.is_none()
+                        && contiguous(last.source.as_ref(), segment.source.as_ref()) =>
+                {
+                    last.text.push_str(&segment.text);
+                    if let (Some(first), Some(next)) = (&mut last.source, &segment.source) {
+                        first.end = next.end;
+                    }
+                }
+                _ => segments.push(segment.clone()),
+            }
+        }
+
+        StyledText { segments }
+    }
+}
+
+/// Returns `true` if a segment with source span `next` continues one with `first` without
+/// a gap, such as the escape sequences between them, or if neither has a span.
+fn contiguous(first: Option<&Range<usize>>, next: Option<&Range<usize>>) -> bool {
+    match (first, next) {
+        (Some(first), Some(next)) => first.end == next.start,
+        (None, None) => true,
+        _ => false,
+    }
 }
 
 /// Type alias for styled text parsed from ANSI escape sequences.
@@ -162,154 +363,410 @@ impl StyledText {
 /// This represents the result of parsing ANSI-formatted text into structured segments.
 pub type ParsedData = StyledText;
 
+/// Options controlling how ANSI input is parsed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseOptions {
+    /// Record the byte range of the input each segment came from in [`Segment::source`].
+    pub source_spans: bool,
+    /// Show blinking text with a bright background, as [`StyledText::ice_colors`] does.
+    pub ice_colors: bool,
+    /// How [`parse_ansi_bytes`] decodes its input.
+    pub encoding: Encoding,
+    /// BBS color codes to read along with ANSI escape sequences.
+    pub color_codes: ColorCodes,
+    /// What happens to palette indexes and color channels above 255.
+    #[serde(default)]
+    pub out_of_range: OutOfRangePolicy,
+    /// What happens to CSI, OSC, and DCS escape sequences other than SGR.
+    #[serde(default)]
+    pub unknown_sequences: UnknownSequencePolicy,
+    /// Record the escape sequences before each segment, as written, in
+    /// [`Segment::escapes`], including unknown sequences left out of the text.
+    #[serde(default)]
+    pub raw_escapes: bool,
+}
+
 static ANSI_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[([0-9;]*)m").unwrap());
 
+/// Matches the CSI, OSC, and DCS sequences left in the text between SGR sequences.
+pub(crate) static UNKNOWN_SEQUENCE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\x1b\[[0-?]*[ -/]*[@-~]",
+        r"|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)",
+        r"|\x1bP[^\x1b]*\x1b\\",
+    ))
+    .unwrap()
+});
+
 /// Parses ANSI escape sequences from the input string into styled text.
 ///
-/// # Panics
-///
-/// Panics if the regex capture fails unexpectedly.
-#[allow(clippy::too_many_lines)]
+/// This uses the default [`ParseOptions`].
+#[must_use]
 pub fn parse_ansi(input: &str) -> ParsedData {
+    parse_ansi_with(input, &ParseOptions::default())
+}
+
+/// Parses ANSI text from bytes in the encoding `options` gives, such as CP437 for art and
+/// logs from DOS-era tools.
+///
+/// Source spans are byte ranges of the decoded text, not of `input`.
+#[must_use]
+pub fn parse_ansi_bytes(input: &[u8], options: &ParseOptions) -> ParsedData {
+    parse_ansi_with(&options.encoding.decode(input), options)
+}
+
+/// Parses ANSI escape sequences from the input string into styled text using `options`.
+#[must_use]
+pub fn parse_ansi_with(input: &str, options: &ParseOptions) -> ParsedData {
+    parse_ansi_with_diagnostics(input, options).0
+}
+
+/// Parses ANSI text like [`parse_ansi_with`], also returning the problems found in it, such
+/// as color parameters out of range, in the order they appear.
+#[must_use]
+pub fn parse_ansi_with_diagnostics(
+    input: &str,
+    options: &ParseOptions,
+) -> (ParsedData, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let (segments, _) = parse_segments(input, 0, Style::default(), options, &mut diagnostics);
+    let text = StyledText { segments };
+    let text = if options.ice_colors {
+        text.ice_colors()
+    } else {
+        text
+    };
+    (text, diagnostics)
+}
+
+/// Parses ANSI text like [`parse_ansi_with`], but fails on the first unknown escape
+/// sequence if [`ParseOptions::unknown_sequences`] is [`UnknownSequencePolicy::Error`].
+///
+/// # Errors
+///
+/// Returns an error naming the position of the first unknown sequence, if there is one
+/// and the policy asks for it.
+pub fn try_parse_ansi_with(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<ParsedData, Report<ParseError>> {
+    let (text, diagnostics) = parse_ansi_with_diagnostics(input, options);
+    check_unknown_sequences(&diagnostics, options)?;
+    Ok(text)
+}
+
+/// Fails on the first unknown escape sequence among `diagnostics` if
+/// [`ParseOptions::unknown_sequences`] is [`UnknownSequencePolicy::Error`].
+pub(crate) fn check_unknown_sequences(
+    diagnostics: &[Diagnostic],
+    options: &ParseOptions,
+) -> Result<(), Report<ParseError>> {
+    if options.unknown_sequences == UnknownSequencePolicy::Error
+        && let Some(diagnostic) = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.kind == DiagnosticKind::UnknownSequence)
+    {
+        return Err(Report::new(ParseError).attach(diagnostic.to_string()));
+    }
+    Ok(())
+}
+
+/// Parses `input` into segments, starting from `current_style`, and returns them with the
+/// style in effect at the end of the input.
+///
+/// `offset` is the position of `input` in the full text, added to recorded source spans and
+/// the ranges of problems pushed to `diagnostics`. Of `options`,
+/// [`ParseOptions::ice_colors`] and [`ParseOptions::encoding`] are not used.
+pub(crate) fn parse_segments(
+    input: &str,
+    offset: usize,
+    mut current_style: Style,
+    options: &ParseOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> (Vec<Segment>, Style) {
     let mut segments = Vec::new();
-    let mut current_style = Style::default();
     let mut last_end = 0;
+    // Escape sequences read since the last segment, when recording them
+    let mut escapes = String::new();
 
-    for cap in ANSI_REGEX.captures_iter(input) {
-        let full_match = cap.get(0).unwrap();
-        let params_str = cap.get(1).unwrap().as_str();
+    for cap in options.color_codes.regex().captures_iter(input) {
+        let Some(full_match) = cap.get(0) else {
+            continue;
+        };
 
         // Add text before this escape
         let text_before = &input[last_end..full_match.start()];
-        if !text_before.is_empty() {
-            segments.push(Segment {
-                text: text_before.to_string(),
-                style: current_style.clone(),
-            });
+        push_text(
+            &mut segments,
+            text_before,
+            offset + last_end,
+            &current_style,
+            options,
+            diagnostics,
+            &mut escapes,
+        );
+
+        if options.raw_escapes {
+            escapes.push_str(full_match.as_str());
         }
+        let range = offset + full_match.start()..offset + full_match.end();
+        apply_code(&mut current_style, &cap, options.out_of_range, |value| {
+            diagnostics.push(Diagnostic {
+                range: range.clone(),
+                kind: DiagnosticKind::ParamOutOfRange { value },
+            });
+        });
 
-        // Parse the parameters
-        let params: Vec<u32> = if params_str.is_empty() {
-            vec![0]
-        } else {
-            params_str
-                .split(';')
-                .filter_map(|s| s.parse().ok())
-                .collect()
+        last_end = full_match.end();
+    }
+
+    // Add remaining text
+    let remaining = &input[last_end..];
+    push_text(
+        &mut segments,
+        remaining,
+        offset + last_end,
+        &current_style,
+        options,
+        diagnostics,
+        &mut escapes,
+    );
+    // Escapes after the last text, such as a final reset, get a segment of their own
+    if !escapes.is_empty() {
+        segments.push(Segment {
+            text: String::new(),
+            style: current_style.clone(),
+            source: options
+                .source_spans
+                .then_some(offset + input.len()..offset + input.len()),
+            escapes: Some(escapes),
+        });
+    }
+
+    (segments, current_style)
+}
+
+/// Pushes segments for `text`, found at byte `start` of the input, handling the escape
+/// sequences left in it as [`ParseOptions::unknown_sequences`] says.
+///
+/// The first segment pushed takes the recorded `escapes`, and the sequences left out of
+/// the text are added to them.
+fn push_text(
+    segments: &mut Vec<Segment>,
+    text: &str,
+    start: usize,
+    style: &Style,
+    options: &ParseOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+    escapes: &mut String,
+) {
+    let push =
+        |segments: &mut Vec<Segment>, escapes: &mut String, text: &str, range: Range<usize>| {
+            if !text.is_empty() {
+                segments.push(Segment {
+                    text: text.to_string(),
+                    style: style.clone(),
+                    source: options.source_spans.then_some(range),
+                    escapes: (!escapes.is_empty()).then(|| mem::take(escapes)),
+                });
+            }
         };
 
-        let mut i = 0;
-        while i < params.len() {
-            let param = params[i];
-            match param {
-                0 => current_style = Style::default(), // reset
-                1 => current_style.bold = true,
-                2 => current_style.dim = true,
-                3 => current_style.italic = true,
-                4 => current_style.underline = true,
-                5 => current_style.blink = true,
-                7 => current_style.reverse = true,
-                8 => current_style.hidden = true,
-                9 => current_style.strikethrough = true,
-                22 => current_style.bold = false,
-                23 => current_style.italic = false,
-                24 => current_style.underline = false,
-                25 => current_style.blink = false,
-                27 => current_style.reverse = false,
-                28 => current_style.hidden = false,
-                29 => current_style.strikethrough = false,
-                30..=37 => {
-                    current_style.fg_color =
-                        Some(Color::Indexed(u8::try_from(param - 30).unwrap()));
-                }
-                40..=47 => {
-                    current_style.bg_color =
-                        Some(Color::Indexed(u8::try_from(param - 40).unwrap()));
+    let mut last_end = 0;
+    if text.contains('\x1b') {
+        for sequence in UNKNOWN_SEQUENCE_REGEX.find_iter(text) {
+            let range = start + sequence.start()..start + sequence.end();
+            diagnostics.push(Diagnostic {
+                range: range.clone(),
+                kind: DiagnosticKind::UnknownSequence,
+            });
+            let replacement = match options.unknown_sequences {
+                UnknownSequencePolicy::KeepRaw => continue,
+                UnknownSequencePolicy::ReplaceWith(ch) => Some(ch),
+                UnknownSequencePolicy::Drop | UnknownSequencePolicy::Error => None,
+            };
+            let before = &text[last_end..sequence.start()];
+            push(segments, escapes, before, start + last_end..range.start);
+            if options.raw_escapes {
+                escapes.push_str(sequence.as_str());
+            }
+            if let Some(ch) = replacement {
+                push(segments, escapes, ch.encode_utf8(&mut [0; 4]), range);
+            }
+            last_end = sequence.end();
+        }
+    }
+    let rest = &text[last_end..];
+    push(
+        segments,
+        escapes,
+        rest,
+        start + last_end..start + text.len(),
+    );
+}
+
+/// Returns the style in effect after the SGR sequences in `input`, starting from
+/// `current_style`, without building segments.
+#[cfg(feature = "parallel")]
+pub(crate) fn style_after(input: &str, mut current_style: Style, options: &ParseOptions) -> Style {
+    for cap in options.color_codes.regex().captures_iter(input) {
+        apply_code(&mut current_style, &cap, options.out_of_range, |_| {});
+    }
+    current_style
+}
+
+/// Applies an SGR sequence or color code matched by [`ColorCodes::regex`] to
+/// `current_style`, calling `out_of_range` with each color parameter above 255.
+fn apply_code(
+    current_style: &mut Style,
+    cap: &regex::Captures,
+    policy: OutOfRangePolicy,
+    out_of_range: impl FnMut(u32),
+) {
+    match cap.get(2) {
+        Some(attribute) => {
+            let attribute = u8::from_str_radix(attribute.as_str(), 16).unwrap();
+            bbs::apply_attribute(current_style, attribute);
+        }
+        None => apply_sgr(current_style, &captured_params(cap), policy, out_of_range),
+    }
+}
+
+/// Returns the parameters of an SGR sequence matched by [`ColorCodes::regex`], treating an
+/// empty parameter list as a reset.
+///
+/// Parameters too large for a `u32` become `u32::MAX`, so they are still out of range.
+fn captured_params(cap: &regex::Captures) -> Vec<u32> {
+    let params_str = cap.get(1).map_or("", |params| params.as_str());
+    if params_str.is_empty() {
+        vec![0]
+    } else {
+        params_str
+            .split(';')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().unwrap_or(u32::MAX))
+            .collect()
+    }
+}
+
+/// Applies the parameters of an SGR (`ESC [ ... m`) sequence to `current_style`.
+///
+/// Unknown parameters are ignored. Palette indexes and color channels above 255 are passed
+/// to `out_of_range` and then handled as `policy` says.
+pub(crate) fn apply_sgr(
+    current_style: &mut Style,
+    params: &[u32],
+    policy: OutOfRangePolicy,
+    mut out_of_range: impl FnMut(u32),
+) {
+    let mut channel = |value: u32| {
+        if value > u32::from(u8::MAX) {
+            out_of_range(value);
+        }
+        policy.apply(value)
+    };
+    let mut i = 0;
+    while i < params.len() {
+        let param = params[i];
+        match param {
+            0 => *current_style = Style::default(), // reset
+            1 => current_style.bold = true,
+            2 => current_style.dim = true,
+            3 => current_style.italic = true,
+            4 => current_style.underline = true,
+            5 => current_style.blink = true,
+            7 => current_style.reverse = true,
+            8 => current_style.hidden = true,
+            9 => current_style.strikethrough = true,
+            22 => current_style.bold = false,
+            23 => current_style.italic = false,
+            24 => current_style.underline = false,
+            25 => current_style.blink = false,
+            27 => current_style.reverse = false,
+            28 => current_style.hidden = false,
+            29 => current_style.strikethrough = false,
+            30..=37 => {
+                current_style.fg_color = Some(Color::Indexed(u8::try_from(param - 30).unwrap()));
+            }
+            40..=47 => {
+                current_style.bg_color = Some(Color::Indexed(u8::try_from(param - 40).unwrap()));
+            }
+            90..=97 => {
+                current_style.fg_color = Some(Color::Indexed(u8::try_from(param - 82).unwrap()));
+            } // bright
+            100..=107 => {
+                current_style.bg_color = Some(Color::Indexed(u8::try_from(param - 92).unwrap()));
+            } // bright
+            38 => {
+                // Extended foreground color
+                i += 1;
+                if i >= params.len() {
+                    break;
                 }
-                90..=97 => {
-                    current_style.fg_color =
-                        Some(Color::Indexed(u8::try_from(param - 82).unwrap()));
-                } // bright
-                100..=107 => {
-                    current_style.bg_color =
-                        Some(Color::Indexed(u8::try_from(param - 92).unwrap()));
-                } // bright
-                38 => {
-                    // Extended foreground color
+                let sub = params[i];
+                if sub == 5 {
+                    // 256 color
                     i += 1;
                     if i >= params.len() {
                         break;
                     }
-                    let sub = params[i];
-                    if sub == 5 {
-                        // 256 color
-                        i += 1;
-                        if i >= params.len() {
-                            break;
-                        }
-                        current_style.fg_color =
-                            Some(Color::Indexed(u8::try_from(params[i]).unwrap()));
-                    } else if sub == 2 {
-                        // Truecolor
-                        i += 1;
-                        if i + 2 >= params.len() {
-                            break;
-                        }
-                        current_style.fg_color = Some(Color::Rgb(
-                            u8::try_from(params[i]).unwrap(),
-                            u8::try_from(params[i + 1]).unwrap(),
-                            u8::try_from(params[i + 2]).unwrap(),
-                        ));
-                        i += 2;
+                    if let Some(idx) = channel(params[i]) {
+                        current_style.fg_color = Some(Color::Indexed(idx));
+                    }
+                } else if sub == 2 {
+                    // Truecolor
+                    i += 1;
+                    if i + 2 >= params.len() {
+                        break;
+                    }
+                    let [r, g, b] = [params[i], params[i + 1], params[i + 2]].map(&mut channel);
+                    if let (Some(r), Some(g), Some(b)) = (r, g, b) {
+                        current_style.fg_color = Some(Color::Rgb(r, g, b));
                     }
+                    i += 2;
+                }
+            }
+            48 => {
+                // Extended background color
+                i += 1;
+                if i >= params.len() {
+                    break;
                 }
-                48 => {
-                    // Extended background color
+                let sub = params[i];
+                if sub == 5 {
+                    // 256 color
                     i += 1;
                     if i >= params.len() {
                         break;
                     }
-                    let sub = params[i];
-                    if sub == 5 {
-                        // 256 color
-                        i += 1;
-                        if i >= params.len() {
-                            break;
-                        }
-                        current_style.bg_color =
-                            Some(Color::Indexed(u8::try_from(params[i]).unwrap()));
-                    } else if sub == 2 {
-                        // Truecolor
-                        i += 1;
-                        if i + 2 >= params.len() {
-                            break;
-                        }
-                        current_style.bg_color = Some(Color::Rgb(
-                            u8::try_from(params[i]).unwrap(),
-                            u8::try_from(params[i + 1]).unwrap(),
-                            u8::try_from(params[i + 2]).unwrap(),
-                        ));
-                        i += 2;
+                    if let Some(idx) = channel(params[i]) {
+                        current_style.bg_color = Some(Color::Indexed(idx));
+                    }
+                } else if sub == 2 {
+                    // Truecolor
+                    i += 1;
+                    if i + 2 >= params.len() {
+                        break;
+                    }
+                    let [r, g, b] = [params[i], params[i + 1], params[i + 2]].map(&mut channel);
+                    if let (Some(r), Some(g), Some(b)) = (r, g, b) {
+                        current_style.bg_color = Some(Color::Rgb(r, g, b));
                     }
+                    i += 2;
                 }
-                _ => {} // ignore unknown
             }
-            i += 1;
+            _ => {} // ignore unknown
         }
-
-        last_end = full_match.end();
-    }
-
-    // Add remaining text
-    let remaining = &input[last_end..];
-    if !remaining.is_empty() {
-        segments.push(Segment {
-            text: remaining.to_string(),
-            style: current_style,
-        });
+        i += 1;
     }
+}
 
-    StyledText { segments }
+/// Removes ANSI escape sequences from the input string, returning the plain text.
+///
+/// This is a convenience for `parse_ansi(input).to_plain_text()`.
+#[must_use]
+pub fn strip_ansi(input: &str) -> String {
+    parse_ansi(input).to_plain_text()
 }
 
 #[derive(Debug, Error)]
@@ -318,72 +775,137 @@ pub struct RexPaintError;
 
 /// Converts `RexPaint` file data to ANSI text.
 ///
+/// This is equivalent to [`rexpaint_to_ansi_with`] with the default options, which
+/// composite every layer.
+///
 /// # Errors
 ///
 /// Returns an error if the `RexPaint` data is invalid or cannot be read.
+pub fn rexpaint_to_ansi(data: &[u8]) -> Result<String, Report<RexPaintError>> {
+    rexpaint_to_ansi_with(data, &RexImportOptions::default())
+}
+
+/// Converts `RexPaint` file data to ANSI text, compositing the layers chosen by `options`.
+///
+/// By default every cell is written as its own escape sequence followed by a reset, using
+/// truecolor. `options` can lower the color depth, merge runs of cells with the same
+/// colors into one escape sequence, and leave out empty cells at the end of each line.
+/// Cells with the transparent key background are written as `options.transparency`
+/// says, which by default gives them the terminal's default background. Glyphs that
+/// decode to control characters are written as spaces, and glyph indexes past 255 as
+/// U+FFFD.
+///
+/// # Errors
+///
+/// Returns an error if the `RexPaint` data is invalid or cannot be read, or if a selected
+/// layer does not exist.
 ///
 /// # Panics
 ///
 /// Panics if accessing a cell in the layer fails (invalid dimensions).
-pub fn rexpaint_to_ansi(data: &[u8]) -> Result<String, Report<RexPaintError>> {
+pub fn rexpaint_to_ansi_with(
+    data: &[u8],
+    options: &RexImportOptions,
+) -> Result<String, Report<RexPaintError>> {
     use std::io::Cursor;
     let mut f = Cursor::new(data);
     let xp = XpFile::read(&mut f)
         .change_context(RexPaintError)
         .attach("failed to read data into virtual XpFile")?;
+    let layer = renderers::rexpaint::composite_layers(&xp, options)?;
     let mut output = String::new();
+    // Buffers reused for every row and cell, so large art does not spend its time allocating
+    let mut cells: Vec<&XpCell> = Vec::with_capacity(layer.width);
+    let (mut params, mut current) = (String::new(), String::new());
+
+    for y in 0..layer.height {
+        cells.clear();
+        cells.extend((0..layer.width).map(|x| layer.get(x, y).unwrap()));
+        let end = if options.trim_trailing {
+            cells
+                .iter()
+                .rposition(|cell| !renderers::rexpaint::is_empty_cell(cell, &options.transparency))
+                .map_or(0, |idx| idx + 1)
+        } else {
+            cells.len()
+        };
+
+        // Whether an escape sequence is in effect when coalescing, and its parameters
+        let mut active = false;
+        for cell in &cells[..end] {
+            let transparent = cell.bg.is_transparent();
+            if transparent && options.transparency == TransparencyPolicy::Skip {
+                if mem::take(&mut active) {
+                    output.push_str("\x1b[0m");
+                }
+                output.push(' ');
+                continue;
+            }
+            let ch = options.codepage.text_char(cell.ch);
+            let style = Style {
+                fg_color: Some(Color::Rgb(cell.fg.r, cell.fg.g, cell.fg.b)),
+                bg_color: match &options.transparency {
+                    TransparencyPolicy::Background(color) if transparent => Some(color.clone()),
+                    _ if transparent => None,
+                    _ => Some(Color::Rgb(cell.bg.r, cell.bg.g, cell.bg.b)),
+                },
+                ..Default::default()
+            };
+            params.clear();
+            write_sgr_params(&style, options.color_depth, &mut params);
 
-    for layer in &xp.layers {
-        for y in 0..layer.height {
-            for x in 0..layer.width {
-                let cell = layer.get(x, y).unwrap();
-                let ch = if cell.ch != 0 {
-                    CP437_WINGDINGS.decode(u8::try_from(cell.ch).unwrap())
-                } else {
-                    ' '
-                };
-                if cell.bg.is_transparent() {
-                    write!(
-                        &mut output,
-                        "\x1b[38;2;{};{};{}m{}\x1b[0m",
-                        cell.fg.r, cell.fg.g, cell.fg.b, ch
-                    )
-                    .unwrap();
-                } else {
-                    write!(
-                        &mut output,
-                        "\x1b[38;2;{};{};{};48;2;{};{};{}m{}\x1b[0m",
-                        cell.fg.r, cell.fg.g, cell.fg.b, cell.bg.r, cell.bg.g, cell.bg.b, ch
-                    )
-                    .unwrap();
+            if !options.coalesce {
+                write!(&mut output, "\x1b[{params}m{ch}\x1b[0m").unwrap();
+                continue;
+            }
+            if !active || params != current {
+                if active {
+                    output.push_str("\x1b[0m");
                 }
+                write!(&mut output, "\x1b[{params}m").unwrap();
+                mem::swap(&mut params, &mut current);
+                active = true;
             }
-            output.push('\n');
+            output.push(ch);
         }
+        if active {
+            output.push_str("\x1b[0m");
+        }
+        output.push('\n');
     }
     Ok(output)
 }
 
 /// Converts ANSI text to `RexPaint` file data.
 ///
+/// This is equivalent to [`ansi_to_rexpaint_with`] with the default options.
+///
 /// # Errors
 ///
 /// Returns an error if the `RexPaint` data cannot be written.
+pub fn ansi_to_rexpaint(input: &str) -> Result<Vec<u8>, Report<RexPaintError>> {
+    ansi_to_rexpaint_with(input, &RexOptions::default())
+}
+
+/// Converts ANSI text to `RexPaint` file data using `options`.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if writing to the in-memory buffer fails (should not happen in practice).
-pub fn ansi_to_rexpaint(input: &str) -> Result<Vec<u8>, Report<RexPaintError>> {
+/// Returns an error if the `RexPaint` data cannot be written.
+pub fn ansi_to_rexpaint_with(
+    input: &str,
+    options: &RexOptions,
+) -> Result<Vec<u8>, Report<RexPaintError>> {
     use std::io::Cursor;
-    
+
     let parsed = parse_ansi(input);
-    let xp = parsed.to_rexpaint();
-    
+    let xp = parsed.to_rexpaint_with(options);
+
     let mut buffer = Cursor::new(Vec::new());
     xp.write(&mut buffer)
         .change_context(RexPaintError)
         .attach("failed to write XpFile to buffer")?;
-    
+
     Ok(buffer.into_inner())
 }
 
@@ -393,23 +915,52 @@ pub fn ansi_to_rexpaint(input: &str) -> Result<Vec<u8>, Report<RexPaintError>> {
 /// including standard 16 colors, 256-color palette, and grayscale colors.
 #[must_use]
 pub fn generate_css() -> String {
+    generate_css_with_prefix("")
+}
+
+/// Generates CSS styles for HTML output whose class names start with `prefix`.
+///
+/// Use this together with [`HtmlOptions::class_prefix`], passing the same [`ClassPrefix`]
+/// so the class names are valid CSS.
+#[must_use]
+pub fn generate_css_with_prefix(prefix: &str) -> String {
     let mut css = String::new();
 
     // Header comment
     css.push_str("/* ANSI Color Styles for fromansi HTML output */\n\n");
 
     // Text styles
-    css.push_str(".bold { font-weight: bold; }\n");
-    css.push_str(".italic { font-style: italic; }\n");
-    css.push_str(".underline { text-decoration: underline; }\n");
-    css.push_str(".strikethrough { text-decoration: line-through; }\n");
-    css.push_str(".dim { opacity: 0.5; }\n");
-    css.push_str(".blink { animation: blink 1s infinite; }\n");
+    writeln!(&mut css, ".{prefix}bold {{ font-weight: bold; }}").unwrap();
+    writeln!(&mut css, ".{prefix}italic {{ font-style: italic; }}").unwrap();
+    writeln!(
+        &mut css,
+        ".{prefix}underline {{ text-decoration: underline; }}"
+    )
+    .unwrap();
+    writeln!(
+        &mut css,
+        ".{prefix}strikethrough {{ text-decoration: line-through; }}"
+    )
+    .unwrap();
+    writeln!(&mut css, ".{prefix}dim {{ opacity: 0.5; }}").unwrap();
+    writeln!(
+        &mut css,
+        ".{prefix}blink {{ animation: blink 1s infinite; }}"
+    )
+    .unwrap();
     css.push_str("@keyframes blink { 0%, 50% { opacity: 1; } 51%, 100% { opacity: 0; } }\n");
-    css.push_str(
-        ".reverse { /* Note: reverse is handled by swapping fg/bg in HTML generation */ }\n",
-    );
-    css.push_str(".hidden { visibility: hidden; }\n\n");
+    writeln!(
+        &mut css,
+        ".{prefix}reverse {{ /* Note: reverse is handled by swapping fg/bg in HTML generation */ }}"
+    )
+    .unwrap();
+    writeln!(&mut css, ".{prefix}hidden {{ visibility: hidden; }}").unwrap();
+    writeln!(
+        &mut css,
+        ".{prefix}line-number {{ {}; }}\n",
+        renderers::html::LINE_NUMBER_CSS
+    )
+    .unwrap();
 
     // Standard 16 colors
     let standard_colors = [
@@ -418,10 +969,15 @@ pub fn generate_css() -> String {
     ];
 
     (0..16).for_each(|i| {
-        writeln!(&mut css, ".fg{i} {{ color: {} }}", standard_colors[i]).unwrap();
         writeln!(
             &mut css,
-            ".bg{i} {{ background-color: {} }}",
+            ".{prefix}fg{i} {{ color: {} }}",
+            standard_colors[i]
+        )
+        .unwrap();
+        writeln!(
+            &mut css,
+            ".{prefix}bg{i} {{ background-color: {} }}",
             standard_colors[i]
         )
         .unwrap();
@@ -434,8 +990,8 @@ pub fn generate_css() -> String {
         let g = (((i - 16) % 36) / 6) * 51;
         let b = ((i - 16) % 6) * 51;
         let hex = format!("#{r:02x}{g:02x}{b:02x}");
-        writeln!(&mut css, ".fg{i} {{ color: {hex} }}").unwrap();
-        writeln!(&mut css, ".bg{i} {{ background-color: {hex} }}").unwrap();
+        writeln!(&mut css, ".{prefix}fg{i} {{ color: {hex} }}").unwrap();
+        writeln!(&mut css, ".{prefix}bg{i} {{ background-color: {hex} }}").unwrap();
     }
     css.push('\n');
 
@@ -443,8 +999,8 @@ pub fn generate_css() -> String {
     for i in 232..256 {
         let gray = 8 + (i - 232) * 10;
         let hex = format!("#{gray:02x}{gray:02x}{gray:02x}");
-        writeln!(&mut css, ".fg{i} {{ color: {hex} }}").unwrap();
-        writeln!(&mut css, ".bg{i} {{ background-color: {hex} }}").unwrap();
+        writeln!(&mut css, ".{prefix}fg{i} {{ color: {hex} }}").unwrap();
+        writeln!(&mut css, ".{prefix}bg{i} {{ background-color: {hex} }}").unwrap();
     }
 
     css
@@ -465,6 +1021,8 @@ mod tests {
                     fg_color: Some(Color::Indexed(1)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
@@ -481,11 +1039,40 @@ mod tests {
                     bg_color: Some(Color::Indexed(1)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_slice_lines_keeps_active_style() {
+        let input = "one\n\x1b[31mtwo\nthree\nfour\x1b[0m\nfive";
+        let result = parse_ansi(input).slice_lines(2..4);
+        let expected = StyledText {
+            segments: vec![
+                Segment {
+                    text: "three\nfour".to_string(),
+                    style: Style {
+                        fg_color: Some(Color::Indexed(1)),
+                        ..Default::default()
+                    },
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: "\n".to_string(),
+                    style: Style::default(),
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        assert_eq!(result, expected);
+        assert_eq!(parse_ansi(input).slice_lines(4..9).to_plain_text(), "five");
+    }
+
     #[test]
     fn test_basic_fg_bg_color() {
         let input = "\x1b[32;44mGreen on Blue\x1b[0m";
@@ -498,6 +1085,8 @@ mod tests {
                     bg_color: Some(Color::Indexed(4)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
@@ -516,6 +1105,8 @@ mod tests {
                     underline: true,
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
@@ -532,6 +1123,8 @@ mod tests {
                     fg_color: Some(Color::Indexed(196)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
@@ -548,6 +1141,8 @@ mod tests {
                     bg_color: Some(Color::Indexed(200)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
@@ -564,6 +1159,8 @@ mod tests {
                     fg_color: Some(Color::Rgb(255, 0, 0)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
@@ -580,6 +1177,8 @@ mod tests {
                     bg_color: Some(Color::Rgb(0, 255, 128)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
@@ -598,11 +1197,270 @@ mod tests {
                     bg_color: Some(Color::Indexed(0)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_out_of_range_params() {
+        let input = "\x1b[32ma\x1b[38;5;300;1mb\x1b[48;2;10;999;20mc";
+        let (parsed, diagnostics) = parse_ansi_with_diagnostics(input, &ParseOptions::default());
+        let style = &parsed.segments[1].style;
+        assert_eq!(
+            (style.fg_color.clone(), style.bold),
+            (Some(Color::Indexed(255)), true)
+        );
+        assert_eq!(
+            parsed.segments[2].style.bg_color,
+            Some(Color::Rgb(10, 255, 20))
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    range: 6..19,
+                    kind: DiagnosticKind::ParamOutOfRange { value: 300 },
+                },
+                Diagnostic {
+                    range: 20..37,
+                    kind: DiagnosticKind::ParamOutOfRange { value: 999 },
+                },
+            ]
+        );
+
+        // Rejected colors leave the color in effect, and the rest of the sequence applies
+        let options = ParseOptions {
+            out_of_range: OutOfRangePolicy::Reject,
+            ..Default::default()
+        };
+        let parsed = parse_ansi_with(input, &options);
+        let style = &parsed.segments[1].style;
+        assert_eq!(
+            (style.fg_color.clone(), style.bold),
+            (Some(Color::Indexed(2)), true)
+        );
+        assert_eq!(parsed.segments[2].style.bg_color, None);
+        assert_eq!(
+            parse_ansi("\x1b[38;5;99999999999mx").segments[0]
+                .style
+                .fg_color,
+            Some(Color::Indexed(255))
+        );
+    }
+
+    #[test]
+    fn test_unknown_sequence_policy() {
+        let input = "a\x1b[2Kb\x1b]0;title\x07c\x1bP1q\x1b\\\x1b[31md\x1b[?25le";
+        let parse = |policy| {
+            let options = ParseOptions {
+                unknown_sequences: policy,
+                source_spans: true,
+                ..Default::default()
+            };
+            parse_ansi_with_diagnostics(input, &options)
+        };
+
+        let (parsed, diagnostics) = parse(UnknownSequencePolicy::KeepRaw);
+        assert_eq!(
+            parsed.to_plain_text(),
+            "a\x1b[2Kb\x1b]0;title\x07c\x1bP1q\x1b\\d\x1b[?25le"
+        );
+        let ranges: Vec<_> = diagnostics.iter().map(|d| d.range.clone()).collect();
+        assert_eq!(ranges, vec![1..5, 6..16, 17..23, 29..35]);
+        assert_eq!(
+            parse(UnknownSequencePolicy::Drop).0.to_plain_text(),
+            "abcde"
+        );
+
+        let (parsed, _) = parse(UnknownSequencePolicy::ReplaceWith('?'));
+        assert_eq!(parsed.to_plain_text(), "a?b?c?d?e");
+        // Replacements keep the style and source of what they replace
+        let last = &parsed.segments[parsed.segments.len() - 2];
+        assert_eq!(
+            (last.text.as_str(), last.source.clone()),
+            ("?", Some(29..35))
+        );
+        assert_eq!(last.style.fg_color, Some(Color::Indexed(1)));
+
+        let options = ParseOptions {
+            unknown_sequences: UnknownSequencePolicy::Error,
+            ..Default::default()
+        };
+        assert!(try_parse_ansi_with(input, &options).is_err());
+        assert!(try_parse_ansi_with("\x1b[1mfine", &options).is_ok());
+        assert_eq!(parse_ansi_with(input, &options).to_plain_text(), "abcde");
+    }
+
+    #[test]
+    fn test_parse_raw_escapes() {
+        let input = "a\x1b[1m\x1b[31mb\nc\x1b[0m";
+        let options = ParseOptions {
+            raw_escapes: true,
+            ..Default::default()
+        };
+        let parsed = parse_ansi_with(input, &options);
+        let escapes: Vec<_> = parsed
+            .segments
+            .iter()
+            .map(|s| s.escapes.as_deref())
+            .collect();
+        assert_eq!(
+            escapes,
+            vec![None, Some("\x1b[1m\x1b[31m"), Some("\x1b[0m")]
+        );
+        assert_eq!(parsed.segments[2].text, "");
+
+        // The escapes stay with the first line of the segment they came before
+        let lines = parsed.split_lines();
+        assert_eq!(
+            lines[0].segments[1].escapes.as_deref(),
+            Some("\x1b[1m\x1b[31m")
+        );
+        assert_eq!(lines[1].segments[0].escapes, None);
+        assert!(
+            parse_ansi(input)
+                .segments
+                .iter()
+                .all(|s| s.escapes.is_none())
+        );
+    }
+
+    #[test]
+    fn test_color_from_hex() {
+        assert_eq!(Color::from_hex("#ff8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(Color::from_hex("00ff00"), Some(Color::Rgb(0, 255, 0)));
+        assert_eq!(Color::from_hex("#fff"), None);
+        assert_eq!(Color::from_hex("#gg0000"), None);
+        assert_eq!(Color::from_hex("#+f+f+f"), None);
+    }
+
+    #[test]
+    fn test_generate_css_prefix() {
+        assert_eq!(generate_css(), generate_css_with_prefix(""));
+        let css = generate_css_with_prefix("ansi-");
+        assert!(css.contains(".ansi-bold { font-weight: bold; }"));
+        assert!(css.contains(".ansi-fg196 { color: #ff0000 }"));
+        assert!(!css.contains(".bold"));
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        let input = "\x1b[1;31mError:\x1b[0m something \x1b[38;5;200mfailed\x1b[0m\n";
+        assert_eq!(strip_ansi(input), "Error: something failed\n");
+    }
+
+    #[test]
+    fn test_parse_source_spans() {
+        let input = "ab\x1b[31mcd\nef\x1b[0m";
+        let options = ParseOptions {
+            source_spans: true,
+            ..Default::default()
+        };
+        let parsed = parse_ansi_with(input, &options);
+
+        let sources: Vec<_> = parsed.segments.iter().map(|s| s.source.clone()).collect();
+        assert_eq!(sources, vec![Some(0..2), Some(7..12)]);
+
+        let lines = parsed.split_lines();
+        assert_eq!(lines[1].segments[0].text, "ef");
+        assert_eq!(lines[1].segments[0].source, Some(10..12));
+        assert_eq!(&input[10..12], "ef");
+
+        assert!(
+            parse_ansi(input)
+                .segments
+                .iter()
+                .all(|s| s.source.is_none())
+        );
+    }
+
+    #[test]
+    fn test_parse_ansi_bytes() {
+        let input = b"\x1b[31m\xc9\xcd\xbb\x1b[0m \xb0\xb1\xb2";
+        let options = ParseOptions {
+            encoding: Encoding::Cp437,
+            ..Default::default()
+        };
+        let parsed = parse_ansi_bytes(input, &options);
+        assert_eq!(parsed.to_plain_text(), "╔═╗ ░▒▓");
+        assert_eq!(parsed.segments[0].style.fg_color, Some(Color::Indexed(1)));
+
+        let utf8 = parse_ansi_bytes(input, &ParseOptions::default());
+        assert!(utf8.to_plain_text().contains('\u{fffd}'));
+    }
+
+    #[test]
+    fn test_normalize_merges_and_drops_empty() {
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        let styled_text = StyledText {
+            segments: vec![
+                Segment {
+                    text: "Hello".to_string(),
+                    style: Style::default(),
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: String::new(),
+                    style: bold.clone(),
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: " World".to_string(),
+                    style: Style::default(),
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: "!".to_string(),
+                    style: bold.clone(),
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        let expected = StyledText {
+            segments: vec![
+                Segment {
+                    text: "Hello World".to_string(),
+                    style: Style::default(),
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: "!".to_string(),
+                    style: bold,
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        assert_eq!(styled_text.normalize(), expected);
+    }
+
+    #[test]
+    fn test_normalize_keeps_source_spans() {
+        let options = ParseOptions {
+            source_spans: true,
+            ..Default::default()
+        };
+        let lines = parse_ansi_with("ab\x1b[0mcd\nef", &options)
+            .normalize()
+            .split_lines();
+        let spans: Vec<Vec<_>> = lines
+            .iter()
+            .map(|line| line.segments.iter().map(|s| s.source.clone()).collect())
+            .collect();
+        assert_eq!(spans, vec![vec![Some(0..2), Some(6..8)], vec![Some(9..11)]]);
+    }
+
     #[test]
     fn test_rexpaint_to_ansi_conversion() {
         let xp_data = include_bytes!("test-dedup.xp");
@@ -624,4 +1482,37 @@ mod tests {
 
         assert_eq!(actual_bytes, expected_bytes.as_slice());
     }
+
+    #[test]
+    fn test_rexpaint_to_ansi_output_options() {
+        let xp_data = include_bytes!("test-dedup.xp");
+        let options = RexImportOptions {
+            color_depth: ColorDepth::Ansi256,
+            coalesce: true,
+            trim_trailing: true,
+            ..Default::default()
+        };
+        let ansi = rexpaint_to_ansi_with(xp_data, &options).unwrap();
+        assert_eq!(
+            ansi,
+            "\x1b[30m \x1b[0m\x1b[38;5;31;48;5;31m \x1b[0m\x1b[91;101m \x1b[0m\n\
+             \x1b[30m    \x1b[0m\x1b[38;5;90;48;5;90m \x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_rexpaint_to_ansi_transparency_policy() {
+        let xp_data = include_bytes!("test-dedup.xp");
+        let mut options = RexImportOptions {
+            transparency: TransparencyPolicy::Skip,
+            coalesce: true,
+            ..Default::default()
+        };
+        let ansi = rexpaint_to_ansi_with(xp_data, &options).unwrap();
+        assert!(ansi.starts_with(" \x1b[38;2;0;89;178;48;2;0;89;178m"));
+
+        options.transparency = TransparencyPolicy::Background(Color::Rgb(1, 2, 3));
+        let ansi = rexpaint_to_ansi_with(xp_data, &options).unwrap();
+        assert!(ansi.starts_with("\x1b[38;2;0;0;0;48;2;1;2;3m \x1b[0m"));
+    }
 }
@@ -0,0 +1,214 @@
+use crate::{Color, StyledText};
+use serde::{Deserialize, Serialize};
+
+/// The number of colors a terminal or output format can represent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorDepth {
+    /// The standard 16-color ANSI palette.
+    Ansi16,
+    /// The 256-color extended palette.
+    Ansi256,
+    /// Truecolor (24-bit RGB).
+    #[default]
+    TrueColor,
+}
+
+#[cfg(feature = "crossterm")]
+impl ColorDepth {
+    /// Returns the color depth the current terminal reports supporting through `COLORTERM`
+    /// and `TERM`.
+    #[must_use]
+    pub fn detect() -> ColorDepth {
+        match crossterm::style::available_color_count() {
+            u16::MAX => ColorDepth::TrueColor,
+            count if count >= 256 => ColorDepth::Ansi256,
+            _ => ColorDepth::Ansi16,
+        }
+    }
+}
+
+impl Color {
+    /// Returns the color as an `(r, g, b)` triple.
+    ///
+    /// Indexed colors are resolved using the ANSI palette.
+    #[must_use]
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb(r, g, b) => (*r, *g, *b),
+            Color::Indexed(idx) => palette_rgb(*idx),
+        }
+    }
+
+    /// Converts the color so it can be represented at the given color depth.
+    ///
+    /// Colors that already fit are returned unchanged; otherwise the nearest palette entry
+    /// is chosen by RGB distance.
+    #[must_use]
+    pub fn to_depth(&self, depth: ColorDepth) -> Color {
+        match (depth, self) {
+            (ColorDepth::TrueColor, _) | (ColorDepth::Ansi256, Color::Indexed(_)) => self.clone(),
+            (ColorDepth::Ansi16, Color::Indexed(idx)) if *idx < 16 => self.clone(),
+            (ColorDepth::Ansi256, _) => Color::Indexed(nearest_indexed(self, 0..=255)),
+            (ColorDepth::Ansi16, _) => Color::Indexed(nearest_indexed(self, 0..=15)),
+        }
+    }
+}
+
+impl StyledText {
+    /// Converts every color to the nearest one available at `depth`, as
+    /// [`Color::to_depth`] does.
+    ///
+    /// Useful before rendering to formats or terminals with a limited palette, or to shrink
+    /// output by merging near-identical colors.
+    #[must_use]
+    pub fn quantize(&self, depth: ColorDepth) -> StyledText {
+        let mut styled_text = self.clone();
+        for segment in &mut styled_text.segments {
+            for color in [&mut segment.style.fg_color, &mut segment.style.bg_color] {
+                if let Some(quantized) = color.as_ref().map(|color| color.to_depth(depth)) {
+                    *color = Some(quantized);
+                }
+            }
+        }
+        styled_text
+    }
+
+    /// Shows blinking text with a bright background instead, as DOS displays in iCE colors
+    /// mode did.
+    ///
+    /// ANSI art drawn for iCE colors uses the blink attribute to reach the eight bright
+    /// background colors, so without this it renders with dark, blinking backgrounds. A
+    /// default background becomes bright black.
+    #[must_use]
+    pub fn ice_colors(&self) -> StyledText {
+        let mut styled_text = self.clone();
+        for segment in &mut styled_text.segments {
+            let style = &mut segment.style;
+            if !style.blink {
+                continue;
+            }
+            style.blink = false;
+            style.bg_color = match style.bg_color.take() {
+                Some(Color::Indexed(idx)) if idx < 8 => Some(Color::Indexed(idx + 8)),
+                None => Some(Color::Indexed(8)),
+                bright => bright,
+            };
+        }
+        styled_text
+    }
+}
+
+/// Returns entry `idx` of the 256-color palette as an `(r, g, b)` triple.
+pub(crate) fn palette_rgb(idx: u8) -> (u8, u8, u8) {
+    const STANDARD: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00),
+        (0x80, 0x00, 0x00),
+        (0x00, 0x80, 0x00),
+        (0x80, 0x80, 0x00),
+        (0x00, 0x00, 0x80),
+        (0x80, 0x00, 0x80),
+        (0x00, 0x80, 0x80),
+        (0xc0, 0xc0, 0xc0),
+        (0x80, 0x80, 0x80),
+        (0xff, 0x00, 0x00),
+        (0x00, 0xff, 0x00),
+        (0xff, 0xff, 0x00),
+        (0x00, 0x00, 0xff),
+        (0xff, 0x00, 0xff),
+        (0x00, 0xff, 0xff),
+        (0xff, 0xff, 0xff),
+    ];
+    match idx {
+        0..=15 => STANDARD[usize::from(idx)],
+        // The 6x6x6 color cube
+        16..=231 => {
+            let cube = idx - 16;
+            (cube / 36 * 51, cube % 36 / 6 * 51, cube % 6 * 51)
+        }
+        // The grayscale ramp
+        _ => {
+            let gray = 8 + (idx - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// Finds the palette index in `candidates` closest to `color` by squared RGB distance.
+fn nearest_indexed(color: &Color, candidates: std::ops::RangeInclusive<u8>) -> u8 {
+    let rgb = color.to_rgb();
+    candidates
+        .min_by_key(|&idx| rgb_distance(rgb, Color::Indexed(idx).to_rgb()))
+        .unwrap_or(0)
+}
+
+/// Returns the squared Euclidean distance between two RGB colors.
+pub(crate) fn rgb_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> i32 {
+    let dr = i32::from(a.0) - i32::from(b.0);
+    let dg = i32::from(a.1) - i32::from(b.1);
+    let db = i32::from(a.2) - i32::from(b.2);
+    dr * dr + dg * dg + db * db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_rgb_indexed() {
+        assert_eq!(Color::Indexed(9).to_rgb(), (255, 0, 0));
+        assert_eq!(Color::Indexed(232).to_rgb(), (8, 8, 8));
+    }
+
+    #[test]
+    fn test_to_depth_ansi256_exact_match() {
+        let color = Color::Rgb(255, 0, 0);
+        assert_eq!(color.to_depth(ColorDepth::Ansi256), Color::Indexed(9));
+    }
+
+    #[test]
+    fn test_to_depth_ansi16_from_extended() {
+        let color = Color::Indexed(196); // #ff0000
+        assert_eq!(color.to_depth(ColorDepth::Ansi16), Color::Indexed(9));
+    }
+
+    #[test]
+    fn test_quantize_styled_text() {
+        let styled_text = crate::parse_ansi("\x1b[38;2;250;5;5;48;5;196mred\x1b[0m plain");
+        let quantized = styled_text.quantize(ColorDepth::Ansi16);
+        assert_eq!(
+            quantized.segments[0].style.fg_color,
+            Some(Color::Indexed(9))
+        );
+        assert_eq!(
+            quantized.segments[0].style.bg_color,
+            Some(Color::Indexed(9))
+        );
+        assert_eq!(quantized.segments[1].style.fg_color, None);
+        assert_eq!(styled_text.quantize(ColorDepth::TrueColor), styled_text);
+    }
+
+    #[test]
+    fn test_ice_colors() {
+        let styled_text = crate::parse_ansi("\x1b[5;44mblue\x1b[0;5m black\x1b[0;5;104m bright");
+        let ice = styled_text.ice_colors();
+        let backgrounds: Vec<_> = ice
+            .segments
+            .iter()
+            .map(|segment| (segment.style.blink, segment.style.bg_color.clone()))
+            .collect();
+        assert_eq!(
+            backgrounds,
+            vec![
+                (false, Some(Color::Indexed(12))),
+                (false, Some(Color::Indexed(8))),
+                (false, Some(Color::Indexed(12))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_depth_truecolor_unchanged() {
+        let color = Color::Rgb(1, 2, 3);
+        assert_eq!(color.to_depth(ColorDepth::TrueColor), color);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{
+    Color, Diagnostic, ParseError, ParseOptions, ParsedData, Segment, Style, StyledText,
+    UNKNOWN_SEQUENCE_REGEX, check_unknown_sequences, parse_segments, style_after,
+};
+use error_stack::Report;
+use rayon::prelude::*;
+
+/// Inputs are split into chunks of at least this many bytes, so small inputs are parsed in
+/// one piece.
+const MIN_CHUNK_BYTES: usize = 1 << 20;
+
+/// Parses ANSI escape sequences like [`parse_ansi_with`](crate::parse_ansi_with), splitting
+/// large inputs on line boundaries and parsing the pieces on several threads.
+///
+/// The result is the same as parsing the input in one piece: styles set before a line
+/// boundary carry over to the next chunk, and text split by a boundary is joined back into
+/// one segment.
+#[must_use]
+pub fn parse_ansi_parallel(input: &str, options: &ParseOptions) -> ParsedData {
+    parse_ansi_parallel_with_diagnostics(input, options).0
+}
+
+/// Parses ANSI text like [`parse_ansi_parallel`], also returning the problems found in it,
+/// as [`parse_ansi_with_diagnostics`](crate::parse_ansi_with_diagnostics) does.
+#[must_use]
+pub fn parse_ansi_parallel_with_diagnostics(
+    input: &str,
+    options: &ParseOptions,
+) -> (ParsedData, Vec<Diagnostic>) {
+    let chunk_bytes = (input.len() / rayon::current_num_threads()).max(MIN_CHUNK_BYTES);
+    parse_in_chunks(input, options, chunk_bytes)
+}
+
+/// Parses ANSI text like [`parse_ansi_parallel`], but fails on the first unknown escape
+/// sequence as [`try_parse_ansi_with`](crate::try_parse_ansi_with) does.
+///
+/// # Errors
+///
+/// Returns an error naming the position of the first unknown sequence, if there is one
+/// and the policy asks for it.
+pub fn try_parse_ansi_parallel(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<ParsedData, Report<ParseError>> {
+    let (text, diagnostics) = parse_ansi_parallel_with_diagnostics(input, options);
+    check_unknown_sequences(&diagnostics, options)?;
+    Ok(text)
+}
+
+/// Parses `input` in chunks of about `chunk_bytes` on the rayon thread pool.
+fn parse_in_chunks(
+    input: &str,
+    options: &ParseOptions,
+    chunk_bytes: usize,
+) -> (ParsedData, Vec<Diagnostic>) {
+    let chunks = split_chunks(input, chunk_bytes);
+
+    // Find the style each chunk leaves behind, independent of the style it starts with, by
+    // running its escapes from two styles that differ in every attribute: the attributes
+    // it changes end up the same from both
+    let changes: Vec<(Style, Style)> = chunks
+        .par_iter()
+        .map(|(_, chunk)| {
+            (
+                style_after(chunk, Style::default(), options),
+                style_after(chunk, marked_style(), options),
+            )
+        })
+        .collect();
+    let mut start_styles = Vec::with_capacity(chunks.len());
+    let mut style = Style::default();
+    for (from_default, from_marked) in &changes {
+        let next = carry_style(&style, from_default, from_marked);
+        start_styles.push(style);
+        style = next;
+    }
+
+    // Source spans are always recorded to find segments split by a chunk boundary
+    let chunk_options = ParseOptions {
+        source_spans: true,
+        ..options.clone()
+    };
+    let parsed: Vec<(Vec<Segment>, Vec<Diagnostic>)> = chunks
+        .par_iter()
+        .zip(start_styles)
+        .map(|((offset, chunk), style)| {
+            let mut diagnostics = Vec::new();
+            let (segments, _) =
+                parse_segments(chunk, *offset, style, &chunk_options, &mut diagnostics);
+            (segments, diagnostics)
+        })
+        .collect();
+    let (parsed, diagnostics): (Vec<_>, Vec<_>) = parsed.into_iter().unzip();
+    let diagnostics = diagnostics.into_iter().flatten().collect();
+
+    let mut segments: Vec<Segment> = Vec::new();
+    for chunk in parsed {
+        let mut chunk = chunk.into_iter();
+        if let Some(first) = chunk.next() {
+            if let Some(last) = segments.last_mut()
+                && let (Some(last_source), Some(source)) = (&mut last.source, &first.source)
+                && last_source.end == source.start
+                && last_source.len() == last.text.len()
+                && source.len() == first.text.len()
+            {
+                // Both are text as written with no escape between them, so the boundary
+                // split one run of text
+                last.text.push_str(&first.text);
+                last_source.end = source.end;
+            } else {
+                segments.push(first);
+            }
+        }
+        segments.extend(chunk);
+    }
+    if !options.source_spans {
+        for segment in &mut segments {
+            segment.source = None;
+        }
+    }
+    let text = StyledText { segments };
+    let text = if options.ice_colors {
+        text.ice_colors()
+    } else {
+        text
+    };
+    (text, diagnostics)
+}
+
+/// Splits `input` into chunks of whole lines at least `chunk_bytes` long, except for the
+/// last, returning each with its byte offset.
+///
+/// OSC and DCS sequences can contain newlines, so chunks only end at newlines outside
+/// them, and no sequence is split between chunks.
+fn split_chunks(input: &str, chunk_bytes: usize) -> Vec<(usize, &str)> {
+    let target = chunk_bytes.max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < input.len() {
+        let mut search = start.saturating_add(target);
+        let end = loop {
+            // Searching bytes, since the target may fall inside a multi-byte character
+            let Some(newline) = input
+                .as_bytes()
+                .get(search..)
+                .and_then(|rest| rest.iter().position(|&byte| byte == b'\n'))
+                .map(|newline| search + newline)
+            else {
+                break input.len();
+            };
+            match sequence_around(input, start, newline) {
+                Some(sequence_end) => search = sequence_end,
+                None => break newline + 1,
+            }
+        };
+        chunks.push((start, &input[start..end]));
+        start = end;
+    }
+    chunks
+}
+
+/// Returns the end of the escape sequence the newline at `newline` is part of, if any,
+/// looking for where it starts no further back than `start`.
+///
+/// OSC and DCS sequences cannot contain an escape character before their end, so only the
+/// last one before the newline can be around it.
+fn sequence_around(input: &str, start: usize, newline: usize) -> Option<usize> {
+    let escape = start
+        + input.as_bytes()[start..newline]
+            .iter()
+            .rposition(|&byte| byte == 0x1b)?;
+    let sequence = UNKNOWN_SEQUENCE_REGEX.find_at(input, escape)?;
+    (sequence.start() == escape && sequence.end() > newline).then_some(sequence.end())
+}
+
+/// Returns a style that differs from the default style in every attribute.
+fn marked_style() -> Style {
+    Style {
+        fg_color: Some(Color::Rgb(0, 0, 0)),
+        bg_color: Some(Color::Rgb(0, 0, 0)),
+        bold: true,
+        dim: true,
+        italic: true,
+        underline: true,
+        blink: true,
+        reverse: true,
+        hidden: true,
+        strikethrough: true,
+    }
+}
+
+/// Returns the style in effect after a chunk that starts with `start`, given the styles it
+/// leaves behind when starting from the default style and from [`marked_style`].
+///
+/// Attributes the chunk sets are the same in both; the others keep their value from
+/// `start`.
+fn carry_style(start: &Style, from_default: &Style, from_marked: &Style) -> Style {
+    fn pick<T: Clone + PartialEq>(start: &T, from_default: &T, from_marked: &T) -> T {
+        if from_default == from_marked {
+            from_default.clone()
+        } else {
+            start.clone()
+        }
+    }
+    Style {
+        fg_color: pick(
+            &start.fg_color,
+            &from_default.fg_color,
+            &from_marked.fg_color,
+        ),
+        bg_color: pick(
+            &start.bg_color,
+            &from_default.bg_color,
+            &from_marked.bg_color,
+        ),
+        bold: pick(&start.bold, &from_default.bold, &from_marked.bold),
+        dim: pick(&start.dim, &from_default.dim, &from_marked.dim),
+        italic: pick(&start.italic, &from_default.italic, &from_marked.italic),
+        underline: pick(
+            &start.underline,
+            &from_default.underline,
+            &from_marked.underline,
+        ),
+        blink: pick(&start.blink, &from_default.blink, &from_marked.blink),
+        reverse: pick(&start.reverse, &from_default.reverse, &from_marked.reverse),
+        hidden: pick(&start.hidden, &from_default.hidden, &from_marked.hidden),
+        strikethrough: pick(
+            &start.strikethrough,
+            &from_default.strikethrough,
+            &from_marked.strikethrough,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorCodes, UnknownSequencePolicy, parse_ansi_with_diagnostics};
+    use std::fmt::Write;
+
+    #[test]
+    fn test_parse_ansi_parallel_matches_sequential() {
+        let mut input = String::new();
+        for line in 0..200 {
+            match line % 5 {
+                0 => input.push_str("\x1b[1;31mbold red "),
+                1 => input.push_str("\x1b[22mplain red "),
+                2 => input.push_str("\x1b[4;48;5;200munderlined "),
+                3 => input.push_str("\x1b[0m\x1bPq#0\n#1\n\x1b\\\x1b]0;two\nlines\x07"),
+                _ => input.push_str("@X1Fwhite on blue \x1b[2K"),
+            }
+            writeln!(&mut input, "line {line}").unwrap();
+        }
+
+        for (source_spans, color_codes, unknown_sequences) in [
+            (false, ColorCodes::None, UnknownSequencePolicy::KeepRaw),
+            (true, ColorCodes::None, UnknownSequencePolicy::KeepRaw),
+            (true, ColorCodes::PcBoard, UnknownSequencePolicy::KeepRaw),
+            (true, ColorCodes::None, UnknownSequencePolicy::Drop),
+            (
+                false,
+                ColorCodes::None,
+                UnknownSequencePolicy::ReplaceWith('?'),
+            ),
+            (true, ColorCodes::None, UnknownSequencePolicy::Error),
+        ] {
+            let options = ParseOptions {
+                source_spans,
+                color_codes,
+                unknown_sequences,
+                ..Default::default()
+            };
+            let sequential = parse_ansi_with_diagnostics(&input, &options);
+            for chunk_bytes in [1, 40, 1000, usize::MAX] {
+                assert_eq!(parse_in_chunks(&input, &options, chunk_bytes), sequential);
+            }
+            assert_eq!(
+                parse_ansi_parallel_with_diagnostics(&input, &options),
+                sequential
+            );
+        }
+
+        let options = ParseOptions {
+            unknown_sequences: UnknownSequencePolicy::Error,
+            ..Default::default()
+        };
+        assert!(try_parse_ansi_parallel(&input, &options).is_err());
+    }
+
+    #[test]
+    fn test_split_chunks() {
+        let input = "ab\ncd\nef";
+        assert_eq!(split_chunks("é\né", 1), vec![(0, "é\n"), (3, "é")]);
+        assert_eq!(
+            split_chunks(input, 1),
+            vec![(0, "ab\n"), (3, "cd\n"), (6, "ef")]
+        );
+        assert_eq!(split_chunks(input, 4), vec![(0, "ab\ncd\n"), (6, "ef")]);
+        assert!(split_chunks("", 1).is_empty());
+        // Newlines inside an OSC or DCS sequence do not end a chunk
+        assert_eq!(
+            split_chunks("a\x1b]0;b\nc\x07\nd", 1),
+            vec![(0, "a\x1b]0;b\nc\x07\n"), (10, "d")]
+        );
+    }
+}
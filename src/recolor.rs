@@ -0,0 +1,114 @@
+use crate::{Color, StyledText, Theme};
+use serde::{Deserialize, Serialize};
+
+/// A mapping from source colors to replacement colors.
+///
+/// Colors are compared by their hex value, so an indexed color and an RGB color that
+/// render identically are treated as the same color.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ColorMap {
+    entries: Vec<(Color, Color)>,
+}
+
+impl ColorMap {
+    /// Creates an empty color map.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mapping from `from` to `to`, replacing any existing mapping for `from`.
+    pub fn insert(&mut self, from: Color, to: Color) {
+        let hex = from.to_hex();
+        self.entries
+            .retain(|(existing, _)| existing.to_hex() != hex);
+        self.entries.push((from, to));
+    }
+
+    /// Returns the replacement for `color`, if one is mapped.
+    #[must_use]
+    pub fn get(&self, color: &Color) -> Option<&Color> {
+        let hex = color.to_hex();
+        self.entries
+            .iter()
+            .find(|(from, _)| from.to_hex() == hex)
+            .map(|(_, to)| to)
+    }
+
+    /// Returns `true` if the map contains no mappings.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl StyledText {
+    /// Replaces foreground and background colors according to `map`.
+    ///
+    /// Colors without a mapping are left unchanged.
+    #[must_use]
+    pub fn remap_colors(&self, map: &ColorMap) -> StyledText {
+        let mut styled_text = self.clone();
+        for segment in &mut styled_text.segments {
+            for color in [&mut segment.style.fg_color, &mut segment.style.bg_color] {
+                if let Some(to) = color.as_ref().and_then(|c| map.get(c)) {
+                    *color = Some(to.clone());
+                }
+            }
+        }
+        styled_text
+    }
+
+    /// Replaces indexed colors 0-15 with the colors `theme` draws them with.
+    ///
+    /// This bakes a theme into renderers that only know the default palette.
+    #[must_use]
+    pub fn resolve_colors(&self, theme: &Theme) -> StyledText {
+        let mut styled_text = self.clone();
+        for segment in &mut styled_text.segments {
+            for color in [&mut segment.style.fg_color, &mut segment.style.bg_color] {
+                if let Some(resolved) = color.as_ref().map(|color| theme.resolve(color)) {
+                    *color = Some(resolved);
+                }
+            }
+        }
+        styled_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Segment, Style};
+
+    #[test]
+    fn test_remap_colors_matches_by_hex() {
+        let mut map = ColorMap::new();
+        map.insert(Color::Rgb(128, 128, 128), Color::Indexed(0));
+
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "gray".to_string(),
+                style: Style {
+                    fg_color: Some(Color::Indexed(8)),
+                    bg_color: Some(Color::Indexed(4)),
+                    ..Default::default()
+                },
+                source: None,
+                escapes: None,
+            }],
+        };
+        let remapped = styled_text.remap_colors(&map);
+
+        assert_eq!(remapped.segments[0].style.fg_color, Some(Color::Indexed(0)));
+        assert_eq!(remapped.segments[0].style.bg_color, Some(Color::Indexed(4)));
+    }
+
+    #[test]
+    fn test_color_map_insert_replaces() {
+        let mut map = ColorMap::new();
+        map.insert(Color::Indexed(1), Color::Indexed(2));
+        map.insert(Color::Indexed(1), Color::Indexed(3));
+        assert_eq!(map.get(&Color::Indexed(1)), Some(&Color::Indexed(3)));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::palette::ColorDepth;
+use crate::{Color, Style, StyledText};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Write};
+
+/// Where reset sequences are placed when rendering ANSI output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ResetPlacement {
+    /// Every segment is wrapped in its own style and reset sequence.
+    EverySegment,
+    /// Escape sequences are only emitted when the style changes, with a single reset at
+    /// the end.
+    #[default]
+    OnChange,
+    /// Like `OnChange`, but the style is reset before every newline and re-applied after
+    /// it, so each line can be displayed on its own.
+    EndOfLine,
+}
+
+/// Options for rendering styled text back to ANSI escape sequences.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnsiOptions {
+    /// The color depth to emit; colors are downgraded to the nearest palette entry.
+    pub color_depth: ColorDepth,
+    /// Where reset sequences are placed.
+    pub reset: ResetPlacement,
+    /// Write the escape sequences recorded in [`Segment::escapes`](crate::Segment::escapes)
+    /// as they were, in place of generated ones, so sequences this crate does not model
+    /// survive a round trip.
+    pub raw_escapes: bool,
+}
+
+impl StyledText {
+    /// Converts the styled text back into a string containing ANSI escape sequences.
+    ///
+    /// Colors are converted to fit `options.color_depth` and resets are placed according
+    /// to `options.reset`. Unstyled text is emitted without any escape sequences.
+    #[must_use]
+    pub fn to_ansi(&self, options: &AnsiOptions) -> String {
+        let mut output = String::new();
+        let default_style = Style::default();
+        let mut active = &default_style;
+
+        for segment in &self.segments {
+            if options.raw_escapes
+                && let Some(escapes) = &segment.escapes
+            {
+                output.push_str(escapes);
+                output.push_str(&segment.text);
+                active = &segment.style;
+                continue;
+            }
+            if segment.text.is_empty() {
+                continue;
+            }
+            let sgr = sgr_sequence(&segment.style, options.color_depth);
+
+            match options.reset {
+                ResetPlacement::EverySegment => {
+                    if segment.style == default_style {
+                        output.push_str(&segment.text);
+                    } else {
+                        write!(&mut output, "{sgr}{}\x1b[0m", segment.text).unwrap();
+                    }
+                }
+                ResetPlacement::OnChange | ResetPlacement::EndOfLine => {
+                    if segment.style != *active {
+                        output.push_str(&sgr);
+                        active = &segment.style;
+                    }
+                    if options.reset == ResetPlacement::EndOfLine && *active != default_style {
+                        let text = segment.text.replace('\n', &format!("\x1b[0m\n{sgr}"));
+                        output.push_str(&text);
+                    } else {
+                        output.push_str(&segment.text);
+                    }
+                }
+            }
+        }
+
+        if *active != default_style {
+            output.push_str("\x1b[0m");
+        }
+        output
+    }
+}
+
+impl Display for StyledText {
+    /// Renders the styled text as ANSI escape sequences using the default options.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ansi(&AnsiOptions::default()))
+    }
+}
+
+/// Builds the SGR escape sequence that selects `style` from a reset state.
+fn sgr_sequence(style: &Style, depth: ColorDepth) -> String {
+    let mut sequence = String::from("\x1b[0");
+    let start = sequence.len();
+    write_sgr_params(style, depth, &mut sequence);
+    if sequence.len() > start {
+        sequence.insert(start, ';');
+    }
+    sequence.push('m');
+    sequence
+}
+
+/// Writes the SGR parameters that select `style` from a reset state to `output`,
+/// separated by `;` and without the leading reset. Nothing is written for the default
+/// style.
+pub(crate) fn write_sgr_params(style: &Style, depth: ColorDepth, output: &mut String) {
+    let start = output.len();
+    let separate = |output: &mut String| {
+        if output.len() > start {
+            output.push(';');
+        }
+    };
+
+    let flags = [
+        (style.bold, "1"),
+        (style.dim, "2"),
+        (style.italic, "3"),
+        (style.underline, "4"),
+        (style.blink, "5"),
+        (style.reverse, "7"),
+        (style.hidden, "8"),
+        (style.strikethrough, "9"),
+    ];
+    for (enabled, code) in flags {
+        if enabled {
+            separate(output);
+            output.push_str(code);
+        }
+    }
+
+    if let Some(color) = &style.fg_color {
+        separate(output);
+        write_color_params(&color.to_depth(depth), false, output);
+    }
+    if let Some(color) = &style.bg_color {
+        separate(output);
+        write_color_params(&color.to_depth(depth), true, output);
+    }
+}
+
+/// Writes the SGR parameters that select `color` as a foreground or background color.
+fn write_color_params(color: &Color, background: bool, output: &mut String) {
+    let base = if background { 40 } else { 30 };
+    match color {
+        Color::Indexed(idx) if *idx < 8 => write!(output, "{}", base + u32::from(*idx)),
+        Color::Indexed(idx) if *idx < 16 => {
+            write!(output, "{}", base + 60 + u32::from(*idx) - 8)
+        }
+        Color::Indexed(idx) => write!(output, "{};5;{idx}", base + 8),
+        Color::Rgb(r, g, b) => write!(output, "{};2;{r};{g};{b}", base + 8),
+    }
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParseOptions, UnknownSequencePolicy, parse_ansi, parse_ansi_with};
+
+    #[test]
+    fn test_to_ansi_round_trip() {
+        let input = "plain \x1b[1;31mbold red\x1b[0m \x1b[38;2;1;2;3;48;5;200mrgb\x1b[0m";
+        let parsed = parse_ansi(input);
+        let rendered = parsed.to_ansi(&AnsiOptions::default());
+        assert_eq!(parse_ansi(&rendered).normalize(), parsed.normalize());
+    }
+
+    #[test]
+    fn test_to_ansi_on_change() {
+        let parsed = parse_ansi("\x1b[31mA\x1b[31mB\x1b[0mC");
+        let rendered = parsed.to_ansi(&AnsiOptions::default());
+        assert_eq!(rendered, "\x1b[0;31mAB\x1b[0mC");
+    }
+
+    #[test]
+    fn test_to_ansi_every_segment() {
+        let parsed = parse_ansi("\x1b[31mA\x1b[32mB");
+        let options = AnsiOptions {
+            reset: ResetPlacement::EverySegment,
+            ..Default::default()
+        };
+        assert_eq!(
+            parsed.to_ansi(&options),
+            "\x1b[0;31mA\x1b[0m\x1b[0;32mB\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_to_ansi_end_of_line() {
+        let parsed = parse_ansi("\x1b[1mA\nB\x1b[0m");
+        let options = AnsiOptions {
+            reset: ResetPlacement::EndOfLine,
+            ..Default::default()
+        };
+        assert_eq!(
+            parsed.to_ansi(&options),
+            "\x1b[0;1mA\x1b[0m\n\x1b[0;1mB\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_to_ansi_raw_escapes() {
+        let input = "\x1b[31mA\x1b[31m\x1b]0;title\x07B\x1b[2K\x1b[0mC\x1b[0m";
+        let parse_options = ParseOptions {
+            unknown_sequences: UnknownSequencePolicy::Drop,
+            raw_escapes: true,
+            ..Default::default()
+        };
+        let parsed = parse_ansi_with(input, &parse_options).normalize();
+        let options = AnsiOptions {
+            raw_escapes: true,
+            ..Default::default()
+        };
+        assert_eq!(parsed.to_ansi(&options), input);
+        assert_eq!(
+            parsed.to_ansi(&AnsiOptions::default()),
+            "\x1b[0;31mAB\x1b[0mC"
+        );
+    }
+
+    #[test]
+    fn test_display_renders_ansi() {
+        let parsed = parse_ansi("\x1b[4mA\x1b[0mB");
+        assert_eq!(parsed.to_string(), "\x1b[0;4mA\x1b[0mB");
+    }
+
+    #[test]
+    fn test_to_ansi_downgrades_colors() {
+        let parsed = parse_ansi("\x1b[38;2;255;0;0mA");
+        let options = AnsiOptions {
+            color_depth: ColorDepth::Ansi16,
+            ..Default::default()
+        };
+        assert_eq!(parsed.to_ansi(&options), "\x1b[0;91mA\x1b[0m");
+    }
+}
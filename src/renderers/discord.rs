@@ -0,0 +1,137 @@
+use crate::palette::rgb_distance;
+use crate::width::grapheme_width;
+use crate::{Color, Style, StyledText};
+use std::fmt::Write;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The foreground colors Discord renders for SGR 30-37.
+const FOREGROUNDS: [(u8, u8, u8); 8] = [
+    (0x4f, 0x54, 0x5c),
+    (0xdc, 0x32, 0x2f),
+    (0x85, 0x99, 0x00),
+    (0xb5, 0x89, 0x00),
+    (0x26, 0x8b, 0xd2),
+    (0xd3, 0x36, 0x82),
+    (0x2a, 0xa1, 0x98),
+    (0xff, 0xff, 0xff),
+];
+
+/// The background colors Discord renders for SGR 40-47.
+const BACKGROUNDS: [(u8, u8, u8); 8] = [
+    (0x00, 0x2b, 0x36),
+    (0xcb, 0x4b, 0x16),
+    (0x58, 0x6e, 0x75),
+    (0x65, 0x7b, 0x83),
+    (0x83, 0x94, 0x96),
+    (0x6c, 0x71, 0xc4),
+    (0x93, 0xa1, 0xa1),
+    (0xfd, 0xf6, 0xe3),
+];
+
+impl StyledText {
+    /// Converts the styled text to a Discord ```` ```ansi ```` code block.
+    ///
+    /// Discord only understands bold, underline, and the eight foreground (30-37) and
+    /// background (40-47) colors, which it draws with its own palette. The first 16
+    /// palette colors map to the color of the same name; other colors are quantized to
+    /// the nearest color Discord actually displays. Reverse is applied by swapping colors,
+    /// hidden text becomes spaces, and other attributes are dropped. Backtick runs are
+    /// broken up with zero-width spaces so they cannot close the block.
+    #[must_use]
+    pub fn to_discord(&self) -> String {
+        let mut output = String::from("```ansi\n");
+        let mut active = String::new();
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            let params = discord_params(&segment.style);
+            if params != active {
+                write!(&mut output, "\x1b[0{params}m").unwrap();
+                active = params;
+            }
+            if segment.style.hidden {
+                for grapheme in segment.text.graphemes(true) {
+                    if grapheme == "\n" {
+                        output.push('\n');
+                    } else {
+                        output.push_str(&" ".repeat(grapheme_width(grapheme)));
+                    }
+                }
+            } else {
+                output.push_str(&segment.text.replace('`', "`\u{200b}"));
+            }
+        }
+
+        if !active.is_empty() {
+            output.push_str("\x1b[0m");
+        }
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str("```");
+        output
+    }
+}
+
+/// Returns the SGR parameters, each preceded by `;`, that select the Discord rendering of
+/// `style` from a reset state.
+fn discord_params(style: &Style) -> String {
+    let (fg_color, bg_color) = if style.reverse {
+        (style.bg_color.as_ref(), style.fg_color.as_ref())
+    } else {
+        (style.fg_color.as_ref(), style.bg_color.as_ref())
+    };
+
+    let mut params = String::new();
+    if style.bold {
+        params.push_str(";1");
+    }
+    if style.underline {
+        params.push_str(";4");
+    }
+    if let Some(color) = fg_color {
+        write!(&mut params, ";{}", 30 + nearest(color, &FOREGROUNDS)).unwrap();
+    }
+    if let Some(color) = bg_color {
+        write!(&mut params, ";{}", 40 + nearest(color, &BACKGROUNDS)).unwrap();
+    }
+    params
+}
+
+/// Returns the index of the Discord color used for `color`.
+fn nearest(color: &Color, palette: &[(u8, u8, u8); 8]) -> usize {
+    match color {
+        Color::Indexed(idx) if *idx < 16 => usize::from(idx % 8),
+        _ => {
+            let rgb = color.to_rgb();
+            (0..palette.len())
+                .min_by_key(|&idx| rgb_distance(rgb, palette[idx]))
+                .unwrap_or(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_discord_restricted_sgr() {
+        let parsed = parse_ansi("\x1b[1;3;91mhot\x1b[0m \x1b[38;2;40;140;210;7mblue\x1b[0m");
+        assert_eq!(
+            parsed.to_discord(),
+            "```ansi\n\x1b[0;1;31mhot\x1b[0m \x1b[0;45mblue\x1b[0m\n```"
+        );
+    }
+
+    #[test]
+    fn test_discord_escapes_backticks() {
+        let parsed = parse_ansi("```\n");
+        assert_eq!(
+            parsed.to_discord(),
+            "```ansi\n`\u{200b}`\u{200b}`\u{200b}\n```"
+        );
+    }
+}
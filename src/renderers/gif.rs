@@ -0,0 +1,112 @@
+use crate::cast::frame_durations;
+use crate::renderers::png::draw_layer;
+use crate::{Frame, PngOptions, RexOptions, TransparencyPolicy};
+use error_stack::{Report, ResultExt};
+use gif::{Encoder, Repeat};
+use wherror::Error;
+
+/// Error raised when frames cannot be rendered to a GIF animation.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct GifError;
+
+/// Renders the frames of a recording as a GIF animation that loops forever.
+///
+/// Every frame is drawn like [`StyledText::to_png_with`](crate::StyledText::to_png_with)
+/// on a canvas the size of the terminal, with the cursor shown as a reversed cell, and is
+/// shown for as long as the recording did, rounded to GIF's hundredths of a second.
+///
+/// # Errors
+///
+/// Returns an error if the image is too large for GIF or cannot be encoded.
+pub fn frames_to_gif(frames: &[Frame], options: &PngOptions) -> Result<Vec<u8>, Report<GifError>> {
+    let theme = &options.theme;
+    let size = |value: usize| {
+        u16::try_from(value)
+            .change_context(GifError)
+            .attach("image is too large for GIF")
+    };
+
+    let mut encoder: Option<Encoder<Vec<u8>>> = None;
+    for (frame, duration) in frames.iter().zip(frame_durations(frames)) {
+        let xp = frame
+            .text_with_cursor()
+            .resolve_colors(theme)
+            .to_rexpaint_with(&RexOptions {
+                default_fg: theme.foreground.clone(),
+                default_bg: theme.background.clone(),
+                width: Some(frame.width),
+                height: Some(frame.height),
+                ..Default::default()
+            });
+        let (mut pixels, width, height) = draw_layer(
+            &xp.layers[0],
+            &TransparencyPolicy::default(),
+            &options.font,
+            options.scale.max(1),
+        );
+        let (width, height) = (size(width)?, size(height)?);
+
+        let encoder = if let Some(encoder) = &mut encoder {
+            encoder
+        } else {
+            let mut new_encoder = Encoder::new(Vec::new(), width, height, &[])
+                .change_context(GifError)
+                .attach("failed to encode GIF")?;
+            new_encoder
+                .set_repeat(Repeat::Infinite)
+                .change_context(GifError)
+                .attach("failed to encode GIF")?;
+            encoder.insert(new_encoder)
+        };
+        let mut image = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let delay = (duration * 100.0).round().min(f64::from(u16::MAX)) as u16;
+        image.delay = delay;
+        encoder
+            .write_frame(&image)
+            .change_context(GifError)
+            .attach("failed to encode GIF")?;
+    }
+
+    match encoder {
+        Some(encoder) => encoder
+            .into_inner()
+            .change_context(GifError)
+            .attach("failed to encode GIF"),
+        None => Err(Report::new(GifError).attach("there are no frames to render")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ansi;
+    use gif::DecodeOptions;
+
+    #[test]
+    fn test_frames_to_gif() {
+        let frame = |time, text| Frame {
+            time,
+            text: parse_ansi(text),
+            width: 3,
+            height: 2,
+            cursor: Some((0, 1)),
+        };
+        let data = frames_to_gif(
+            &[frame(0.0, ""), frame(0.5, "\x1b[31mab\x1b[0m")],
+            &PngOptions::default(),
+        )
+        .unwrap();
+
+        let mut decoder = DecodeOptions::new().read_info(data.as_slice()).unwrap();
+        // 3x2 cells of the 14x14 font
+        assert_eq!((decoder.width(), decoder.height()), (42, 28));
+        let mut delays = Vec::new();
+        while let Some(image) = decoder.read_next_frame().unwrap() {
+            delays.push(image.delay);
+        }
+        assert_eq!(delays, vec![50, 200]);
+        assert!(frames_to_gif(&[], &PngOptions::default()).is_err());
+    }
+}
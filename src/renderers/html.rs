@@ -1,5 +1,244 @@
-use crate::{Color, StyledText};
-use std::fmt::Write;
+use crate::cast::frame_durations;
+use crate::grid::Grid;
+use crate::palette::ColorDepth;
+use crate::renderers::ansi::{AnsiOptions, write_sgr_params};
+use crate::renderers::escape_xml;
+use crate::renderers::rexpaint::{is_blank_glyph, select_layers};
+use crate::renderers::sections::{SectionLine, Sections};
+use crate::{
+    Color, Frame, RexImportOptions, RexPaintError, SectionMarker, Segment, Style, StyledText,
+    Theme, TransparencyPolicy, generate_css_with_prefix,
+};
+use error_stack::{Report, ResultExt};
+use regex::Regex;
+use rexpaint::XpFile;
+use serde::{Deserialize, Deserializer, Serialize, de};
+use smart_default::SmartDefault;
+use std::fmt::{self, Write};
+use std::mem;
+use std::ops::Range;
+use std::sync::LazyLock;
+use wherror::Error;
+
+/// Matches references to a line of a source file, such as `src/main.rs:12:5`: a path
+/// ending in a file extension, a line number, and optionally a column.
+static FILE_REFERENCE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?<path>(?:[A-Za-z]:[\\/])?[\w.~/\\-]*[\w-]\.[A-Za-z0-9]+):(?<line>\d+)(?::(?<column>\d+))?",
+    )
+    .unwrap()
+});
+
+/// Error raised when a [`ClassPrefix`] would not make valid CSS class names.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct ClassPrefixError;
+
+/// A prefix for the class names and ids of HTML output, checked so that every name made
+/// with it is a valid CSS identifier.
+///
+/// A prefix is letters, digits, `-`, `_`, and non-ASCII characters, and does not start
+/// with a digit or with `-` and a digit. It may be empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ClassPrefix(String);
+
+impl ClassPrefix {
+    /// Checks that `prefix` makes valid CSS identifiers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `prefix` has characters CSS identifiers can't contain, or
+    /// starts like a number.
+    pub fn new(prefix: impl Into<String>) -> Result<Self, Report<ClassPrefixError>> {
+        let prefix = prefix.into();
+        let valid_chars = prefix
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_') || !ch.is_ascii());
+        let number = prefix
+            .strip_prefix('-')
+            .unwrap_or(&prefix)
+            .starts_with(|ch: char| ch.is_ascii_digit());
+        if !valid_chars || number {
+            return Err(Report::new(ClassPrefixError).attach(format!(
+                "invalid class prefix '{prefix}', expected letters, digits, '-', and '_' \
+                 not starting with a digit"
+            )));
+        }
+        Ok(ClassPrefix(prefix))
+    }
+
+    /// Returns the prefix as a string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for ClassPrefix {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ClassPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ClassPrefix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let prefix = String::deserialize(deserializer)?;
+        ClassPrefix::new(prefix.clone())
+            .map_err(|_| de::Error::custom(format!("invalid class prefix '{prefix}'")))
+    }
+}
+
+/// Options controlling HTML rendering.
+#[derive(Debug, Clone, PartialEq, Eq, SmartDefault, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct HtmlOptions {
+    /// Hex color (e.g., "#000000") whose space-only segments are replaced with `&nbsp;`
+    /// and trimmed from line ends.
+    pub filter: Option<String>,
+    /// Whether to HTML-escape segment text. Disable only for trusted input containing
+    /// markup that should be rendered.
+    #[default(true)]
+    pub escape: bool,
+    /// Emit every color and attribute as an inline `style` attribute instead of classes,
+    /// so the output does not depend on external CSS.
+    pub inline_styles: bool,
+    /// Prefix prepended to every generated class name and id, to avoid clashing with the
+    /// page's own. Use the same prefix with `generate_css_with_prefix`.
+    pub class_prefix: ClassPrefix,
+    /// Element wrapping the output, or `None` to emit only the spans.
+    #[default(Some("pre".to_string()))]
+    pub wrapper: Option<String>,
+    /// How newlines in the text are written.
+    pub newlines: NewlineMode,
+    /// Wrap each line in an element with `id="L<N>"` (numbered from 1) so individual
+    /// lines can be linked to.
+    pub line_anchors: bool,
+    /// Render a gutter of line numbers linking to each line's anchor. Implies
+    /// `line_anchors`.
+    pub line_numbers: bool,
+    /// Number of the first line for `line_anchors` and `line_numbers`, so an excerpt keeps
+    /// the line numbers it has in the full text.
+    #[default(1)]
+    pub first_line_number: usize,
+    /// Merge adjacent segments that render with identical attributes into one span, and
+    /// write unstyled text without a wrapping span.
+    pub compact: bool,
+    /// Annotate each styled span with a `data-sgr` attribute holding the SGR parameters
+    /// (e.g. `1;38;5;196`) that select its style, to help debug color output.
+    pub data_sgr: bool,
+    /// How the text is laid out.
+    pub layout: HtmlLayout,
+    /// Fold the groups of CI logs into collapsible `<details>` elements, titled with the
+    /// group's name: `::group::` to `::endgroup::` from GitHub Actions (`##[group]` in
+    /// downloaded logs), and Azure Pipelines' `##[group]` and `##[section]` markers. The
+    /// marker lines themselves are not shown.
+    pub fold_groups: bool,
+    /// Patterns of lines that start and end collapsible `<details>` sections. The line
+    /// starting a section is its summary, and is shown when the section is collapsed.
+    pub sections: Vec<SectionMarker>,
+    /// Put a table of contents linking to every collapsible section, from `fold_groups`
+    /// and `sections`, before the output.
+    pub table_of_contents: bool,
+    /// Link references to source files, such as `src/main.rs:12:5` in a compiler error,
+    /// to this URL template. `{path}`, `{line}`, and `{column}` are replaced by the parts
+    /// of the reference, with the column 1 if it has none: for example
+    /// `vscode://file/{path}:{line}:{column}` or
+    /// `https://github.com/OWNER/REPO/blob/main/{path}#L{line}`. The path is
+    /// percent-encoded, apart from its separators. A reference must be within one segment,
+    /// and text is only linked when `escape` is set.
+    pub file_links: Option<String>,
+    /// Annotate each line with its line number in `data-line`, counted like
+    /// `line_anchors`, and the byte offset of its first character in the input, or where
+    /// it starts if it is empty, in `data-offset`, so other tools can link from a position
+    /// in a log to the rendered page. Offsets need text parsed with
+    /// [`ParseOptions::source_spans`](crate::ParseOptions::source_spans), and are left out
+    /// of lines without them.
+    pub source_positions: bool,
+}
+
+/// Options controlling standalone HTML documents.
+#[derive(Debug, Clone, PartialEq, SmartDefault, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct HtmlDocumentOptions {
+    /// Theme used by default, and whenever the reader prefers a light color scheme.
+    pub light: Theme,
+    /// Theme used when the reader prefers a dark color scheme, or `None` to always use
+    /// `light`.
+    #[default(Some(Theme::dark()))]
+    pub dark: Option<Theme>,
+    /// Title of the page, which screen readers also announce as the name of the output.
+    #[default("Terminal output".to_string())]
+    pub label: String,
+    /// Language of the page as a BCP 47 tag, which screen readers use to pick a voice.
+    #[default("en".to_string())]
+    pub lang: String,
+    /// Add a link to download the output as plain text, which is easier to follow with a
+    /// screen reader than styled markup. The text is embedded in the page a second time.
+    pub plain_text_link: bool,
+    /// Add a toolbar, shown when scripting is on, to find text in the output, show only
+    /// lines with errors, warnings, or text of one palette color, and jump to the next
+    /// error. Lines get elements with anchors as with
+    /// [`HtmlOptions::line_anchors`](crate::HtmlOptions::line_anchors), so the toolbar
+    /// only works with the flow layout. Recordings from [`frames_to_html`] never get one.
+    pub toolbar: bool,
+    /// Add buttons, shown when scripting is on, to copy the plain text of a line, or of the
+    /// selection or whole output. Lines get elements with anchors as with `toolbar`.
+    pub copy_buttons: bool,
+    /// Add a link to download the output as ANSI escape sequences, to view it in a
+    /// terminal again. The text is embedded in the page once more, so with both download
+    /// links it is there three times. Escape sequences recorded with
+    /// [`ParseOptions::raw_escapes`](crate::ParseOptions::raw_escapes) are written as they
+    /// were; the rest are generated as [`StyledText::to_ansi`] does, which looks the same as
+    /// the input but may not match it byte for byte.
+    pub ansi_link: bool,
+    /// Add a button, shown when scripting is on, that switches between the `light` and
+    /// `dark` themes and remembers the choice in the browser's local storage. Until one is
+    /// chosen the page follows the reader's preference. Has no effect without `dark`.
+    pub theme_toggle: bool,
+}
+
+impl HtmlDocumentOptions {
+    /// Returns `options` with line elements turned on if the page controls need them.
+    fn line_options(&self, options: &HtmlOptions) -> HtmlOptions {
+        HtmlOptions {
+            line_anchors: options.line_anchors || self.toolbar || self.copy_buttons,
+            ..options.clone()
+        }
+    }
+}
+
+/// How text is laid out in HTML output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HtmlLayout {
+    /// Flowing text inside the wrapper element.
+    #[default]
+    Flow,
+    /// A fixed-size character grid with one element per cell, padded to the widest line.
+    ///
+    /// Every cell is exactly one column wide (two for wide graphemes), so ANSI art keeps
+    /// its shape and backgrounds have no gaps regardless of font metrics. The grid is its
+    /// own container, so `filter`, `wrapper`, `newlines`, line anchors, `compact`,
+    /// sections, and file links do not apply.
+    Grid,
+}
+
+/// How newlines are written in HTML output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NewlineMode {
+    /// Keep newlines as-is, relying on a whitespace-preserving wrapper such as `<pre>`.
+    #[default]
+    Preserve,
+    /// Replace newlines with `<br>` elements.
+    LineBreak,
+}
 
 impl StyledText {
     /// Converts the styled text to HTML format.
@@ -28,153 +267,1198 @@ impl StyledText {
     /// Panics if the regex for filtering fails to compile.
     #[must_use]
     pub fn to_html_with_filter(&self, filter_hex: Option<&str>) -> String {
-        if filter_hex.is_none() {
-            // No filter, use original logic
-            format!("<pre>{}</pre>", self.generate_html_spans(filter_hex))
-        } else {
-            // With filter, process line by line
-            use regex::Regex;
+        self.to_html_with(&HtmlOptions {
+            filter: filter_hex.map(str::to_string),
+            ..Default::default()
+        })
+    }
 
+    /// Converts the styled text to HTML format without escaping segment text.
+    ///
+    /// This behaves like `to_html_with_filter`, except that `<`, `>`, and `&` in the text
+    /// are passed through verbatim. Only use this when the input is trusted and is known
+    /// to contain markup that should be rendered.
+    #[must_use]
+    pub fn to_html_raw(&self, filter_hex: Option<&str>) -> String {
+        self.to_html_with(&HtmlOptions {
+            filter: filter_hex.map(str::to_string),
+            escape: false,
+            ..Default::default()
+        })
+    }
+
+    /// Converts the styled text to HTML format using `options`.
+    ///
+    /// Returns a string containing the HTML representation wrapped in the element given by
+    /// `options.wrapper` (a `<pre>` tag by default). The other `to_html*` methods are thin
+    /// wrappers around this one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the regex for filtering fails to compile.
+    #[must_use]
+    pub fn to_html_with(&self, options: &HtmlOptions) -> String {
+        if options.layout == HtmlLayout::Grid {
+            return self.generate_html_grid(options);
+        }
+
+        let numbered = options.line_anchors || options.line_numbers || options.source_positions;
+        let mut sections = Sections::new(options);
+        let body = if options.filter.is_none() && !numbered && !sections.is_enabled() {
+            // No per-line processing needed, use original logic
+            self.generate_html_spans(options)
+        } else {
+            // Process line by line
             let lines = self.split_lines();
-            let mut result = String::new();
-            let re = Regex::new(r"(<span[^>]*>(&nbsp;)+</span>\s*)+$").unwrap();
+            let re = if options.compact {
+                // Filtered text is unstyled, so it may have been merged into bare text
+                Regex::new(r"((<span[^>]*>(&nbsp;)+</span>|&nbsp;)\s*)+$").unwrap()
+            } else {
+                Regex::new(r"(<span[^>]*>(&nbsp;)+</span>\s*)+$").unwrap()
+            };
+
+            let offsets = if options.source_positions {
+                line_offsets(self)
+            } else {
+                Vec::new()
+            };
+
+            let mut body = String::new();
+            // Whether a line was written since the last section opened or closed, so the
+            // next one goes on a new line
+            let mut after_line = false;
+            for (idx, line) in lines.iter().enumerate() {
+                let section_line = if sections.is_enabled() {
+                    sections.line(&line.to_plain_text())
+                } else {
+                    SectionLine::Plain
+                };
+                if let SectionLine::Replace(markup) = section_line {
+                    body.push_str(&markup);
+                    after_line = false;
+                    continue;
+                }
 
-            for line in lines {
-                let mut line_html = line.generate_html_spans(filter_hex);
+                let mut line_html = line.generate_html_spans(options);
 
-                // Trim trailing spans containing only &nbsp;
-                line_html = re.replace_all(&line_html, "").to_string();
+                if options.filter.is_some() {
+                    // Trim trailing spans containing only &nbsp;
+                    line_html = re.replace_all(&line_html, "").to_string();
+                    let plain = matches!(section_line, SectionLine::Plain);
+                    if line_html.is_empty() && !numbered && plain {
+                        continue;
+                    }
+                }
 
-                if !line_html.is_empty() {
-                    result.push_str(&line_html);
-                    result.push('\n');
+                if numbered {
+                    let number = idx + options.first_line_number;
+                    let offset = offsets.get(idx).copied().flatten();
+                    line_html = wrap_line(number, offset, &line_html, options);
+                }
+                match section_line {
+                    SectionLine::Start(markup) => {
+                        write!(&mut body, "{markup}{line_html}</summary>").unwrap();
+                        after_line = false;
+                    }
+                    SectionLine::End(markup) => {
+                        if after_line {
+                            body.push_str(newline(options.newlines));
+                        }
+                        write!(&mut body, "{line_html}{markup}").unwrap();
+                        after_line = false;
+                    }
+                    _ => {
+                        if after_line {
+                            body.push_str(newline(options.newlines));
+                        }
+                        body.push_str(&line_html);
+                        after_line = true;
+                    }
                 }
             }
+            body.push_str(&sections.finish());
+            body
+        };
+        let table_of_contents = if options.table_of_contents {
+            sections.table_of_contents()
+        } else {
+            String::new()
+        };
 
-            if result.ends_with('\n') {
-                result.pop();
-            }
+        match &options.wrapper {
+            Some(element) => format!("{table_of_contents}<{element}>{body}</{element}>"),
+            None => table_of_contents + &body,
+        }
+    }
+
+    /// Converts the styled text to a complete HTML page with embedded CSS.
+    ///
+    /// The page colors and 16-color palette follow `document.light`, switching to
+    /// `document.dark` through a `prefers-color-scheme` media query when one is given, or
+    /// with the button of [`HtmlDocumentOptions::theme_toggle`].
+    ///
+    /// The output is a `role="log"` landmark labelled with `document.label`, which a link
+    /// at the top of the page skips to, so screen readers can find and follow it.
+    #[must_use]
+    pub fn to_html_document(
+        &self,
+        options: &HtmlOptions,
+        document: &HtmlDocumentOptions,
+    ) -> String {
+        let prefix = &options.class_prefix;
+        let css = document_css(prefix, document);
+        let options = document.line_options(options);
+        let html = main_region(prefix, document, "log", &self.to_html_with(&options));
+        let html = with_controls(prefix, document, &html);
+        html_page(&css, prefix, document, &html, self)
+    }
+
+    /// Splits the text into pages of `lines_per_page` lines and renders each as a complete
+    /// HTML page like [`to_html_document`](StyledText::to_html_document), for text too
+    /// large for a browser to show at once.
+    ///
+    /// Every page links to the previous and next pages, using the link `page_href` gives
+    /// for each zero-based page index. Styles carry over from one page to the next, and
+    /// line anchors and numbers continue across pages.
+    #[must_use]
+    pub fn to_html_pages(
+        &self,
+        lines_per_page: usize,
+        page_href: impl Fn(usize) -> String,
+        options: &HtmlOptions,
+        document: &HtmlDocumentOptions,
+    ) -> Vec<String> {
+        let prefix = &options.class_prefix;
+        let css = document_css(prefix, document)
+            + &format!(
+                ".{prefix}pages {{ margin: 0.5em 0; }}\n.{prefix}pages a {{ margin: 0 0.5em; }}\n"
+            );
+
+        let lines_per_page = lines_per_page.max(1);
+        let pages = split_pages(self, lines_per_page);
+        let page_count = pages.len();
+
+        pages
+            .iter()
+            .enumerate()
+            .map(|(page, page_text)| {
+                let start = page * lines_per_page;
+                let page_options = HtmlOptions {
+                    first_line_number: options.first_line_number + start,
+                    ..document.line_options(options)
+                };
+                let html = page_text.to_html_with(&page_options);
+
+                let mut nav = format!("<nav class=\"{prefix}pages\" aria-label=\"Pages\">");
+                if page > 0 {
+                    nav.push_str("<a href=\"");
+                    escape_href(&page_href(page - 1), &mut nav);
+                    nav.push_str("\" rel=\"prev\">Previous</a>");
+                }
+                write!(&mut nav, "Page {} of {page_count}", page + 1).unwrap();
+                if page + 1 < page_count {
+                    nav.push_str("<a href=\"");
+                    escape_href(&page_href(page + 1), &mut nav);
+                    nav.push_str("\" rel=\"next\">Next</a>");
+                }
+                nav.push_str("</nav>");
+                let html = main_region(prefix, document, "log", &html);
+                let html = with_controls(prefix, document, &html);
+                html_page(
+                    &css,
+                    prefix,
+                    document,
+                    &format!("{nav}{html}{nav}"),
+                    page_text,
+                )
+            })
+            .collect()
+    }
 
-            format!("<pre>{result}</pre>")
+    fn generate_html_grid(&self, options: &HtmlOptions) -> String {
+        let mut grid = Grid::from_styled(self);
+        let width = grid.width();
+        grid.pad_to(width);
+
+        let mut html = format!(
+            "<div class=\"{}grid\" style=\"display: inline-grid; \
+             grid-template-columns: repeat({width}, 1ch); white-space: pre; \
+             font-family: monospace\">",
+            options.class_prefix
+        );
+        let (mut classes, mut styles) = (String::new(), String::new());
+        for row in &grid.rows {
+            for (column, cell) in row.iter().enumerate() {
+                if cell.is_continuation() {
+                    continue;
+                }
+                classes.clear();
+                styles.clear();
+                style_attributes(&cell.style, options, &mut classes, &mut styles);
+                let columns = 1 + row[column + 1..]
+                    .iter()
+                    .take_while(|next| next.is_continuation())
+                    .count();
+                if columns > 1 {
+                    push_item(
+                        &mut styles,
+                        "; ",
+                        format_args!("grid-column: span {columns}"),
+                    );
+                }
+
+                html.push_str("<span");
+                write_attributes(&classes, &styles, &mut html);
+                html.push('>');
+                if options.escape {
+                    escape_xml(&cell.text, &mut html);
+                } else {
+                    html.push_str(&cell.text);
+                }
+                html.push_str("</span>");
+            }
         }
+        html.push_str("</div>");
+        html
     }
 
-    #[allow(clippy::match_like_matches_macro)]
-    fn generate_html_spans(&self, filter_hex: Option<&str>) -> String {
+    fn generate_html_spans(&self, options: &HtmlOptions) -> String {
+        let filter_hex = options.filter.as_deref();
         let mut html = String::new();
+        // Buffers reused for every segment, so long output does not spend its time allocating
+        let (mut classes, mut styles) = (String::new(), String::new());
+        let (mut attrs, mut open_attrs, mut text) = (String::new(), String::new(), String::new());
+        // Whether a span with `open_attrs` is waiting for more text, when compacting
+        let mut is_open = false;
         for segment in &self.segments {
             if segment.text.is_empty() {
                 continue;
             }
-            let mut classes = Vec::new();
-            let mut inline_styles = Vec::new();
-
-            // Handle colors, considering reverse
-            let (fg_color, bg_color) = if segment.style.reverse {
-                (
-                    segment.style.bg_color.as_ref(),
-                    segment.style.fg_color.as_ref(),
-                )
+            let fg_color = if segment.style.reverse {
+                segment.style.bg_color.as_ref()
             } else {
-                (
-                    segment.style.fg_color.as_ref(),
-                    segment.style.bg_color.as_ref(),
-                )
+                segment.style.fg_color.as_ref()
             };
 
-            if let Some(color) = fg_color {
-                if let Some(idx) = color.to_indexed_if_possible() {
-                    classes.push(format!("fg{idx}"));
-                } else {
-                    match color {
-                        Color::Indexed(_) => unreachable!(), // since to_indexed_if_possible would return Some
-                        Color::Rgb(r, g, b) => {
-                            inline_styles.push(format!("color: rgb({r}, {g}, {b})"));
-                        }
+            // Spaces drawn in the filtered color are rendered unstyled
+            let is_filtered = filter_hex.is_some_and(|filter| {
+                fg_color.is_some_and(|color| color.to_hex() == filter)
+                    && segment.text.chars().all(|c| c == ' ')
+            });
+
+            attrs.clear();
+            text.clear();
+            if is_filtered {
+                for _ in 0..segment.text.len() {
+                    text.push_str("&nbsp;");
+                }
+            } else {
+                classes.clear();
+                styles.clear();
+                style_attributes(&segment.style, options, &mut classes, &mut styles);
+                write_attributes(&classes, &styles, &mut attrs);
+                if options.data_sgr {
+                    let start = attrs.len();
+                    attrs.push_str(" data-sgr=\"");
+                    let params_start = attrs.len();
+                    write_sgr_params(&segment.style, ColorDepth::TrueColor, &mut attrs);
+                    if attrs.len() == params_start {
+                        attrs.truncate(start);
+                    } else {
+                        attrs.push('"');
                     }
                 }
-            }
 
-            if let Some(color) = bg_color {
-                if let Some(idx) = color.to_indexed_if_possible() {
-                    classes.push(format!("bg{idx}"));
-                } else {
-                    match color {
-                        Color::Indexed(_) => unreachable!(),
-                        Color::Rgb(r, g, b) => {
-                            inline_styles.push(format!("background-color: rgb({r}, {g}, {b})"));
+                if options.escape {
+                    match &options.file_links {
+                        Some(template) => {
+                            link_file_references(&segment.text, template, &mut text);
                         }
+                        None => escape_xml(&segment.text, &mut text),
                     }
+                } else {
+                    text.push_str(&segment.text);
                 }
             }
 
-            // Add style classes
-            if segment.style.bold {
-                classes.push("bold".to_string());
-            }
-            if segment.style.dim {
-                classes.push("dim".to_string());
+            if !(is_open && options.compact && attrs == open_attrs) {
+                close_span(&mut html, is_open, options.compact, &open_attrs);
+                if !(options.compact && attrs.is_empty()) {
+                    write!(&mut html, "<span{attrs}>").unwrap();
+                }
+                mem::swap(&mut attrs, &mut open_attrs);
+                is_open = true;
             }
-            if segment.style.italic {
-                classes.push("italic".to_string());
+            match options.newlines {
+                NewlineMode::Preserve => html.push_str(&text),
+                NewlineMode::LineBreak => {
+                    for (index, line) in text.split('\n').enumerate() {
+                        if index > 0 {
+                            html.push_str(newline(options.newlines));
+                        }
+                        html.push_str(line);
+                    }
+                }
             }
-            if segment.style.underline {
-                classes.push("underline".to_string());
+            if !options.compact {
+                html.push_str("</span>");
+                is_open = false;
             }
-            if segment.style.blink {
-                classes.push("blink".to_string());
+        }
+        close_span(&mut html, is_open, options.compact, &open_attrs);
+        html
+    }
+}
+
+/// Closes the span left open by [`StyledText::generate_html_spans`], unless there is none or
+/// its text was written bare.
+fn close_span(html: &mut String, is_open: bool, compact: bool, attrs: &str) {
+    if is_open && !(compact && attrs.is_empty()) {
+        html.push_str("</span>");
+    }
+}
+
+/// Renders the frames of a recording as a complete HTML page that plays them in a loop.
+///
+/// Each frame is rendered with `options`, with the cursor shown as a reversed cell, and a
+/// small script shows the frames in turn for as long as the recording did. Without
+/// scripting the page shows the last frame. Colors and accessibility features follow
+/// `document` like [`StyledText::to_html_document`], with the plain text that of the last
+/// frame.
+#[must_use]
+pub fn frames_to_html(
+    frames: &[Frame],
+    options: &HtmlOptions,
+    document: &HtmlDocumentOptions,
+) -> String {
+    let prefix = &options.class_prefix;
+    let mut css = document_css(prefix, document);
+    if let Some(frame) = frames.first() {
+        // Every frame gets the size of the terminal, so the page does not jump around
+        #[allow(clippy::cast_precision_loss)]
+        let height = frame.height as f64 * 1.2;
+        writeln!(
+            &mut css,
+            ".{prefix}frame > * {{ margin: 0; line-height: 1.2; min-width: {}ch; \
+             min-height: {height:.1}em; }}",
+            frame.width
+        )
+        .unwrap();
+    }
+
+    let mut body = String::new();
+    let durations = frame_durations(frames);
+    for (idx, (frame, duration)) in frames.iter().zip(durations).enumerate() {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let duration = (duration * 1000.0).round() as u64;
+        let hidden = if idx + 1 == frames.len() {
+            ""
+        } else {
+            " hidden"
+        };
+        write!(
+            &mut body,
+            "<div class=\"{prefix}frame\" data-duration=\"{duration}\"{hidden}>{}</div>",
+            frame.text_with_cursor().to_html_with(options)
+        )
+        .unwrap();
+    }
+
+    let body = format!(
+        "{body}<script>(() => {{ const frames = document.querySelectorAll(\".{prefix}frame\"); \
+         let current = 0; const show = () => {{ frames.forEach((frame, idx) => \
+         {{ frame.hidden = idx !== current; }}); const duration = Number(frames[current]\
+         .dataset.duration); current = (current + 1) % frames.length; \
+         setTimeout(show, duration); }}; if (frames.length > 1) {{ show(); }} }})();\
+         </script>"
+    );
+    // A region rather than a log, so screen readers do not announce every frame
+    let body = main_region(prefix, document, "region", &body);
+    let text = frames
+        .last()
+        .map(|frame| frame.text.clone())
+        .unwrap_or_default();
+    html_page(&css, prefix, document, &body, &text)
+}
+
+/// Wraps `body` in a complete HTML page with `css` embedded, titled `document.label`.
+///
+/// The page starts with a link that skips to the [`main_region`], and ends with the links
+/// to download `text` that `document` asks for.
+fn html_page(
+    css: &str,
+    prefix: &str,
+    document: &HtmlDocumentOptions,
+    body: &str,
+    text: &StyledText,
+) -> String {
+    let mut title = String::new();
+    escape_xml(&document.label, &mut title);
+    let mut lang = String::new();
+    escape_xml(&document.lang, &mut lang);
+    let toggle = document.theme_toggle && document.dark.is_some();
+    let (head_script, toggle_button) = if toggle {
+        (THEME_SCRIPT, THEME_TOGGLE.replace("{prefix}", prefix))
+    } else {
+        ("", String::new())
+    };
+    let mut page = format!(
+        "<!DOCTYPE html><html lang=\"{lang}\"><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <style>{css}</style>{head_script}</head><body><a class=\"{prefix}skip-link\" \
+         href=\"#{prefix}content\">Skip to content</a>{toggle_button}{body}"
+    );
+    let mut downloads = Vec::new();
+    if document.plain_text_link {
+        downloads.push(format!(
+            "<a download=\"output.txt\" href=\"data:text/plain;charset=utf-8,{}\">\
+             Download as plain text</a>",
+            percent_encode(&text.to_plain_text())
+        ));
+    }
+    if document.ansi_link {
+        downloads.push(format!(
+            "<a download=\"output.ans\" href=\"data:text/plain;charset=utf-8,{}\">\
+             Download as ANSI</a>",
+            percent_encode(&text.to_ansi(&AnsiOptions {
+                raw_escapes: true,
+                ..Default::default()
+            }))
+        ));
+    }
+    if !downloads.is_empty() {
+        write!(&mut page, "<p>{}</p>", downloads.join(" ")).unwrap();
+    }
+    if toggle {
+        page.push_str(&THEME_TOGGLE_SCRIPT.replace("{prefix}", prefix));
+    }
+    page.push_str("</body></html>");
+    page
+}
+
+/// Wraps `html` in the page's `<main>` landmark, inside an element with the ARIA `role`
+/// and `document.label` as its name. The landmark keeps its implicit `main` role.
+fn main_region(prefix: &str, document: &HtmlDocumentOptions, role: &str, html: &str) -> String {
+    let mut label = String::new();
+    escape_xml(&document.label, &mut label);
+    format!(
+        "<main id=\"{prefix}content\"><div role=\"{role}\" aria-label=\"{label}\">{html}</div>\
+         </main>"
+    )
+}
+
+/// Splits `text` into pages of `lines_per_page` lines in a single pass, keeping each
+/// line's newline like [`StyledText::slice_lines`]. There is always at least one page.
+fn split_pages(text: &StyledText, lines_per_page: usize) -> Vec<StyledText> {
+    let mut pages = vec![StyledText::default()];
+    let mut line = 0;
+    for segment in &text.segments {
+        // The part of this segment on the page being filled, as (page, byte range)
+        let mut kept: Option<(usize, Range<usize>)> = None;
+        let mut offset = 0;
+        for part in segment.text.split_inclusive('\n') {
+            let page = line / lines_per_page;
+            let end = offset + part.len();
+            kept = match kept {
+                Some((kept_page, range)) if kept_page == page => Some((page, range.start..end)),
+                Some((kept_page, range)) => {
+                    push_page_part(&mut pages, kept_page, segment, range);
+                    Some((page, offset..end))
+                }
+                None => Some((page, offset..end)),
+            };
+            offset = end;
+            if part.ends_with('\n') {
+                line += 1;
             }
-            if segment.style.strikethrough {
-                classes.push("strikethrough".to_string());
+        }
+        if let Some((page, range)) = kept {
+            push_page_part(&mut pages, page, segment, range);
+        }
+    }
+    pages
+}
+
+/// Adds the `range` of `segment` to the end of page `page`.
+fn push_page_part(
+    pages: &mut Vec<StyledText>,
+    page: usize,
+    segment: &Segment,
+    range: Range<usize>,
+) {
+    if pages.len() <= page {
+        pages.resize_with(page + 1, StyledText::default);
+    }
+    pages[page].segments.push(Segment {
+        text: segment.text[range.clone()].to_string(),
+        style: segment.style.clone(),
+        escapes: segment.escapes.clone().filter(|_| range.start == 0),
+        source: segment.sub_source(range),
+    });
+}
+
+/// Writes the link `href` as a quoted attribute value, percent-encoding the characters
+/// a URL can't contain and escaping the rest for XML.
+fn escape_href(href: &str, output: &mut String) {
+    let mut encoded = String::with_capacity(href.len());
+    for byte in href.bytes() {
+        if byte.is_ascii_graphic()
+            && !matches!(
+                byte,
+                b'"' | b'<' | b'>' | b'\\' | b'^' | b'`' | b'{' | b'|' | b'}'
+            )
+        {
+            encoded.push(char::from(byte));
+        } else {
+            write!(&mut encoded, "%{byte:02X}").unwrap();
+        }
+    }
+    escape_xml(&encoded, output);
+}
+
+/// Percent-encodes `text` for a URL, leaving only unreserved characters as they are.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            write!(&mut encoded, "%{byte:02X}").unwrap();
+        }
+    }
+    encoded
+}
+
+/// Percent-encodes each part of the file path `path` for a URL, keeping the `/` and `\\`
+/// separators and a drive letter's colon as they are.
+fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for part in path.split_inclusive(['/', '\\']) {
+        let (name, separator) = match part.strip_suffix(['/', '\\']) {
+            Some(name) => (name, &part[name.len()..]),
+            None => (part, ""),
+        };
+        match name.strip_suffix(':') {
+            Some(drive) if encoded.is_empty() && drive.len() == 1 => encoded.push_str(name),
+            _ => encoded.push_str(&percent_encode(name)),
+        }
+        encoded.push_str(separator);
+    }
+    encoded
+}
+
+/// The toolbar of [`HtmlDocumentOptions::toolbar`], hidden until its script runs.
+const TOOLBAR: &str = "<div class=\"{prefix}toolbar\" role=\"search\" hidden>\
+<input id=\"{prefix}search\" type=\"search\" placeholder=\"Find\" aria-label=\"Find\">\
+<button id=\"{prefix}previous\" type=\"button\" aria-label=\"Previous match\">&uarr;</button>\
+<button id=\"{prefix}next\" type=\"button\" aria-label=\"Next match\">&darr;</button>\
+<output id=\"{prefix}count\" aria-live=\"polite\"></output>\
+<select id=\"{prefix}severity\" aria-label=\"Lines shown\"><option value=\"\">All lines</option>\
+<option value=\"warning\">Warnings and errors</option><option value=\"error\">Errors</option>\
+</select><select id=\"{prefix}color\" aria-label=\"Text color\">\
+<option value=\"\">Any color</option><option value=\"1\">Red</option>\
+<option value=\"2\">Green</option><option value=\"3\">Yellow</option>\
+<option value=\"4\">Blue</option><option value=\"5\">Magenta</option>\
+<option value=\"6\">Cyan</option></select>\
+<button id=\"{prefix}next-error\" type=\"button\">Next error</button></div>";
+
+/// The script behind [`TOOLBAR`].
+///
+/// Matches are highlighted with the CSS Custom Highlight API where the browser has it, and
+/// lines with matches are marked in any case. Lines are errors or warnings if their text
+/// has a word such as "error" or "warning" in it.
+const TOOLBAR_SCRIPT: &str = r#"<script>(() => {
+  const prefix = "{prefix}";
+  const byId = (id) => document.getElementById(prefix + id);
+  const [search, count, severity, color] = ["search", "count", "severity", "color"].map(byId);
+  const lines = [...document.querySelectorAll("." + prefix + "line")];
+  const errorPattern = /\b(error|fatal|panic|panicked|failed|failure)\b/i;
+  const warningPattern = /\bwarn(ing)?\b/i;
+  const textNodes = (line) => {
+    const walker = document.createTreeWalker(line, NodeFilter.SHOW_TEXT, {
+      acceptNode: (node) => node.parentElement.closest("." + prefix + "line-number")
+        ? NodeFilter.FILTER_REJECT : NodeFilter.FILTER_ACCEPT,
+    });
+    const nodes = [];
+    while (walker.nextNode()) { nodes.push(walker.currentNode); }
+    return nodes;
+  };
+  for (const line of lines) {
+    // The newline after a line is moved into it, so hiding the line hides the newline too
+    const next = line.nextSibling;
+    if (next && (next.nodeName === "BR" || next.nodeValue === "\n")) {
+      line.append(next);
+    }
+    const text = textNodes(line).map((node) => node.data).join("");
+    if (errorPattern.test(text)) {
+      line.dataset.severity = "error";
+    } else if (warningPattern.test(text)) {
+      line.dataset.severity = "warning";
+    }
+  }
+
+  const shown = (line) => {
+    if (severity.value === "error" && line.dataset.severity !== "error") { return false; }
+    if (severity.value === "warning" && !line.dataset.severity) { return false; }
+    const bright = Number(color.value) + 8;
+    return !color.value || line.querySelector(
+      "." + prefix + "fg" + color.value + ", ." + prefix + "fg" + bright) !== null;
+  };
+  const rangeOf = (nodes, start, end) => {
+    const range = document.createRange();
+    let offset = 0;
+    for (const node of nodes) {
+      const next = offset + node.data.length;
+      if (offset <= start && start < next) { range.setStart(node, start - offset); }
+      if (offset < end && end <= next) { range.setEnd(node, end - offset); break; }
+      offset = next;
+    }
+    return range;
+  };
+  const reveal = (line) => {
+    for (let details = line.closest("details"); details;
+      details = details.parentElement.closest("details")) {
+      details.open = true;
+    }
+    lines.forEach((other) => other.classList.toggle(prefix + "current", other === line));
+    line.scrollIntoView({ block: "center" });
+  };
+
+  let matches = [];
+  let current = -1;
+  const paint = () => {
+    count.textContent = !search.value ? ""
+      : matches.length ? (current + 1) + " of " + matches.length : "No matches";
+    if ("highlights" in CSS) {
+      CSS.highlights.set(prefix + "match", new Highlight(...matches.map((match) => match.range)));
+      CSS.highlights.set(prefix + "current",
+        new Highlight(...(current >= 0 ? [matches[current].range] : [])));
+    }
+    if (current >= 0) {
+      reveal(matches[current].line);
+    } else {
+      lines.forEach((line) => line.classList.remove(prefix + "current"));
+    }
+  };
+  const update = () => {
+    const needle = search.value.toLowerCase();
+    matches = [];
+    for (const line of lines) {
+      line.hidden = !shown(line);
+      const nodes = textNodes(line);
+      const text = nodes.map((node) => node.data).join("").toLowerCase();
+      let found = needle && !line.hidden ? text.indexOf(needle) : -1;
+      line.classList.toggle(prefix + "match", found !== -1);
+      while (found !== -1) {
+        matches.push({ line, range: rangeOf(nodes, found, found + needle.length) });
+        found = text.indexOf(needle, found + needle.length);
+      }
+    }
+    current = matches.length ? 0 : -1;
+    paint();
+  };
+  const step = (delta) => {
+    if (matches.length) {
+      current = (current + delta + matches.length) % matches.length;
+      paint();
+    }
+  };
+
+  let lastError = -1;
+  const isError = (line) => line.dataset.severity === "error" && !line.hidden;
+  byId("next-error").addEventListener("click", () => {
+    let next = lines.findIndex((line, idx) => idx > lastError && isError(line));
+    if (next === -1) { next = lines.findIndex(isError); }
+    if (next !== -1) { lastError = next; reveal(lines[next]); }
+  });
+  search.addEventListener("input", update);
+  search.addEventListener("keydown", (event) => {
+    if (event.key === "Enter") { event.preventDefault(); step(event.shiftKey ? -1 : 1); }
+  });
+  byId("previous").addEventListener("click", () => step(-1));
+  byId("next").addEventListener("click", () => step(1));
+  severity.addEventListener("change", update);
+  color.addEventListener("change", update);
+  document.querySelector("." + prefix + "toolbar").hidden = false;
+})();</script>"#;
+
+/// The button of [`HtmlDocumentOptions::copy_buttons`] that copies the selection or the
+/// whole output, hidden until its script runs.
+const COPY_BUTTON: &str = "<button class=\"{prefix}copy\" type=\"button\" hidden>Copy</button>";
+
+/// The script behind [`COPY_BUTTON`], which also adds a button to the end of every line.
+///
+/// Copied text leaves out line numbers and the buttons themselves.
+const COPY_SCRIPT: &str = r#"<script>(() => {
+  const prefix = "{prefix}";
+  const output = document.getElementById(prefix + "content");
+  const plainText = (node) => {
+    const copy = node.cloneNode(true);
+    copy.querySelectorAll("." + prefix + "line-number, ." + prefix + "copy-line")
+      .forEach((element) => element.remove());
+    copy.querySelectorAll("br").forEach((br) => br.replaceWith("\n"));
+    return copy.textContent;
+  };
+  const copy = async (text, button) => {
+    await navigator.clipboard.writeText(text);
+    button.dataset.copied = "";
+    setTimeout(() => { delete button.dataset.copied; }, 1500);
+  };
+
+  for (const line of document.querySelectorAll("." + prefix + "line")) {
+    const button = document.createElement("button");
+    button.type = "button";
+    button.className = prefix + "copy-line";
+    button.setAttribute("aria-label", "Copy line " + line.id.slice(1));
+    button.addEventListener("click", () => copy(plainText(line).replace(/\n$/, ""), button));
+    // Before the newline, if another script moved it into the line
+    const last = line.lastChild;
+    const newline = last && (last.nodeName === "BR" || last.nodeValue === "\n");
+    line.insertBefore(button, newline ? last : null);
+  }
+
+  const all = document.querySelector("." + prefix + "copy");
+  all.addEventListener("click", () => {
+    const selection = getSelection();
+    const selected = !selection.isCollapsed && output.contains(selection.anchorNode);
+    copy(plainText(selected ? selection.getRangeAt(0).cloneContents() : output), all);
+  });
+  all.hidden = false;
+})();</script>"#;
+
+/// Applies the theme stored by [`THEME_TOGGLE_SCRIPT`] before the page is drawn, so it
+/// does not flash the other one.
+const THEME_SCRIPT: &str = "<script>try { \
+const theme = localStorage.getItem(\"fromansi-theme\"); \
+if (theme) { document.documentElement.dataset.theme = theme; } } catch (err) {}</script>";
+
+/// The button of [`HtmlDocumentOptions::theme_toggle`], hidden until its script runs.
+const THEME_TOGGLE: &str = "<button class=\"{prefix}theme-toggle\" type=\"button\" \
+aria-pressed=\"false\" hidden>Dark theme</button>";
+
+/// The script behind [`THEME_TOGGLE`], which is pressed while the dark theme shows.
+const THEME_TOGGLE_SCRIPT: &str = r#"<script>(() => {
+  const root = document.documentElement;
+  const toggle = document.querySelector(".{prefix}theme-toggle");
+  const isDark = () => root.dataset.theme
+    ? root.dataset.theme === "dark" : matchMedia("(prefers-color-scheme: dark)").matches;
+  const show = () => toggle.setAttribute("aria-pressed", String(isDark()));
+  toggle.addEventListener("click", () => {
+    root.dataset.theme = isDark() ? "light" : "dark";
+    try { localStorage.setItem("fromansi-theme", root.dataset.theme); } catch (err) {}
+    show();
+  });
+  matchMedia("(prefers-color-scheme: dark)").addEventListener("change", show);
+  show();
+  toggle.hidden = false;
+})();</script>"#;
+
+/// Puts the controls `document` asks for around `html`: the toolbar and copy button before
+/// it, and their scripts after it.
+fn with_controls(prefix: &str, document: &HtmlDocumentOptions, html: &str) -> String {
+    let (mut before, mut after) = (String::new(), String::new());
+    if document.toolbar {
+        before.push_str(&TOOLBAR.replace("{prefix}", prefix));
+        after.push_str(&TOOLBAR_SCRIPT.replace("{prefix}", prefix));
+    }
+    if document.copy_buttons {
+        before.push_str(&COPY_BUTTON.replace("{prefix}", prefix));
+        after.push_str(&COPY_SCRIPT.replace("{prefix}", prefix));
+    }
+    format!("{before}{html}{after}")
+}
+
+/// Returns the CSS for a standalone document: the style classes, plus the page colors and
+/// palette of the themes in `document`.
+fn document_css(prefix: &str, document: &HtmlDocumentOptions) -> String {
+    let mut css = generate_css_with_prefix(prefix);
+    // The skip link stays out of sight until it is reached with the keyboard
+    writeln!(
+        &mut css,
+        ".{prefix}skip-link {{ position: absolute; left: -10000px; }}\n\
+         .{prefix}skip-link:focus {{ position: static; }}"
+    )
+    .unwrap();
+    if document.toolbar {
+        // The toolbar stays in view, on the page background
+        writeln!(
+            &mut css,
+            ".{prefix}toolbar {{ position: sticky; top: 0; display: flex; flex-wrap: wrap; \
+             gap: 0.5em; align-items: center; padding: 0.5em 0; background-color: inherit; }}\n\
+             .{prefix}toolbar[hidden], .{prefix}line[hidden] {{ display: none; }}\n\
+             .{prefix}match {{ background-color: rgba(255, 200, 0, 0.15); }}\n\
+             .{prefix}current {{ outline: 2px solid #f68c1e; }}\n\
+             ::highlight({prefix}match) {{ background-color: #ffd33d; color: #000; }}\n\
+             ::highlight({prefix}current) {{ background-color: #f68c1e; color: #000; }}"
+        )
+        .unwrap();
+    }
+    if document.copy_buttons {
+        // Line buttons show up on hover or focus, and say when they have copied
+        writeln!(
+            &mut css,
+            ".{prefix}copy-line {{ margin-left: 1ch; padding: 0 0.25em; font: inherit; \
+             font-size: 0.8em; opacity: 0; user-select: none; cursor: pointer; }}\n\
+             .{prefix}line:hover .{prefix}copy-line, .{prefix}copy-line:focus {{ opacity: 1; }}\n\
+             .{prefix}copy-line::before {{ content: \"Copy\"; }}\n\
+             .{prefix}copy-line[data-copied]::before {{ content: \"Copied\"; }}\n\
+             .{prefix}copy[data-copied]::after {{ content: \" \\2713\"; }}"
+        )
+        .unwrap();
+    }
+    match &document.dark {
+        Some(dark) if document.theme_toggle => {
+            // A theme chosen with the toggle wins over the reader's preference
+            css.push_str(":root { color-scheme: light dark; }\n");
+            css.push_str(&document.light.css(prefix));
+            css.push_str("@media (prefers-color-scheme: dark) {\n");
+            css.push_str(&scope_css(
+                &dark.css(prefix),
+                ":root:not([data-theme=\"light\"])",
+            ));
+            css.push_str("}\n");
+            css.push_str(&scope_css(&dark.css(prefix), ":root[data-theme=\"dark\"]"));
+            css.push_str(
+                ":root[data-theme=\"light\"] { color-scheme: light; }\n\
+                 :root[data-theme=\"dark\"] { color-scheme: dark; }\n",
+            );
+            writeln!(&mut css, ".{prefix}theme-toggle {{ float: right; }}").unwrap();
+        }
+        Some(dark) => {
+            css.push_str(":root { color-scheme: light dark; }\n");
+            css.push_str(&document.light.css(prefix));
+            css.push_str("@media (prefers-color-scheme: dark) {\n");
+            css.push_str(&dark.css(prefix));
+            css.push_str("}\n");
+        }
+        None => css.push_str(&document.light.css(prefix)),
+    }
+    css
+}
+
+/// Limits every rule of `css`, one per line as [`Theme::css`] writes them, to elements
+/// inside `scope`.
+fn scope_css(css: &str, scope: &str) -> String {
+    let mut scoped = String::new();
+    for rule in css.lines() {
+        writeln!(&mut scoped, "{scope} {rule}").unwrap();
+    }
+    scoped
+}
+
+/// Renders a `RexPaint` file as HTML, with the layers selected by the default
+/// [`RexImportOptions`].
+///
+/// See [`xp_to_html_with`].
+///
+/// # Errors
+///
+/// Returns an error if the `RexPaint` data is invalid or cannot be read.
+pub fn xp_to_html(data: &[u8], options: &HtmlOptions) -> Result<String, Report<RexPaintError>> {
+    xp_to_html_with(data, options, &RexImportOptions::default())
+}
+
+/// Renders the layers of a `RexPaint` file selected by `rex_options` as HTML.
+///
+/// The output is a grid of cells like [`HtmlLayout::Grid`], read straight from the file
+/// rather than through ANSI text. Each layer is a `layer` element with a `data-layer`
+/// index whose cells share the grid, so higher layers are drawn over lower ones. Cells
+/// with the transparent key background are drawn as `rex_options.transparency` says; by
+/// default blank ones are left out, letting the layer below (or the page) show through.
+/// Of `options`, only `class_prefix`, `inline_styles`, and `escape` apply.
+///
+/// # Errors
+///
+/// Returns an error if the `RexPaint` data is invalid or cannot be read, or if a selected
+/// layer does not exist.
+pub fn xp_to_html_with(
+    data: &[u8],
+    options: &HtmlOptions,
+    rex_options: &RexImportOptions,
+) -> Result<String, Report<RexPaintError>> {
+    let xp = XpFile::read(&mut std::io::Cursor::new(data))
+        .change_context(RexPaintError)
+        .attach("failed to read data into virtual XpFile")?;
+    let layers = select_layers(&xp, rex_options)?;
+    let width = layers
+        .iter()
+        .map(|(_, layer)| layer.width)
+        .max()
+        .unwrap_or(0);
+    let height = layers
+        .iter()
+        .map(|(_, layer)| layer.height)
+        .max()
+        .unwrap_or(0);
+
+    let prefix = &options.class_prefix;
+    let mut html = format!(
+        "<div class=\"{prefix}grid\" style=\"display: inline-grid; \
+         grid-template-columns: repeat({width}, 1ch); grid-template-rows: repeat({height}, 1lh); \
+         white-space: pre; font-family: monospace\">"
+    );
+    let (mut classes, mut styles) = (String::new(), String::new());
+    for (idx, layer) in layers {
+        write!(
+            &mut html,
+            "<div class=\"{prefix}layer\" data-layer=\"{idx}\" style=\"display: contents\">"
+        )
+        .unwrap();
+        for y in 0..layer.height {
+            for x in 0..layer.width {
+                let Some(cell) = layer.get(x, y) else {
+                    continue;
+                };
+                let bg_color = if cell.bg.is_transparent() {
+                    match &rex_options.transparency {
+                        TransparencyPolicy::Background(color) => Some(color.clone()),
+                        TransparencyPolicy::TerminalDefault if !is_blank_glyph(cell.ch) => None,
+                        _ => continue,
+                    }
+                } else {
+                    Some(Color::Rgb(cell.bg.r, cell.bg.g, cell.bg.b))
+                };
+                let style = Style {
+                    fg_color: Some(Color::Rgb(cell.fg.r, cell.fg.g, cell.fg.b)),
+                    bg_color,
+                    ..Default::default()
+                };
+                classes.clear();
+                styles.clear();
+                write!(&mut styles, "grid-area: {} / {}", y + 1, x + 1).unwrap();
+                style_attributes(&style, options, &mut classes, &mut styles);
+
+                let ch = rex_options.codepage.text_char(cell.ch);
+                html.push_str("<span");
+                write_attributes(&classes, &styles, &mut html);
+                html.push('>');
+                let mut buf = [0; 4];
+                if options.escape {
+                    escape_xml(ch.encode_utf8(&mut buf), &mut html);
+                } else {
+                    html.push(ch);
+                }
+                html.push_str("</span>");
             }
-            if segment.style.hidden {
-                classes.push("hidden".to_string());
+        }
+        html.push_str("</div>");
+    }
+    html.push_str("</div>");
+    Ok(html)
+}
+
+/// Appends the classes and inline declarations that render `style` to `classes` and
+/// `styles`, separated by spaces and semicolons.
+fn style_attributes(
+    style: &Style,
+    options: &HtmlOptions,
+    classes: &mut String,
+    styles: &mut String,
+) {
+    let prefix = &options.class_prefix;
+
+    // Handle colors, considering reverse
+    let (fg_color, bg_color) = if style.reverse {
+        (style.bg_color.as_ref(), style.fg_color.as_ref())
+    } else {
+        (style.fg_color.as_ref(), style.bg_color.as_ref())
+    };
+
+    let colors = [
+        (fg_color, "fg", "color"),
+        (bg_color, "bg", "background-color"),
+    ];
+    for (color, class, property) in colors {
+        let Some(color) = color else {
+            continue;
+        };
+        if options.inline_styles {
+            push_item(styles, "; ", format_args!("{property}: {}", color.to_hex()));
+        } else if let Color::Indexed(idx) = color {
+            // Truecolor values stay inline even when they match a palette entry, so that
+            // themes only recolor the palette
+            push_item(classes, " ", format_args!("{prefix}{class}{idx}"));
+        } else {
+            let (r, g, b) = color.to_rgb();
+            push_item(styles, "; ", format_args!("{property}: rgb({r}, {g}, {b})"));
+        }
+    }
+
+    // Add style classes, or the equivalent inline declarations
+    if options.inline_styles {
+        if style.bold {
+            push_item(styles, "; ", format_args!("font-weight: bold"));
+        }
+        if style.dim {
+            push_item(styles, "; ", format_args!("opacity: 0.5"));
+        }
+        if style.italic {
+            push_item(styles, "; ", format_args!("font-style: italic"));
+        }
+        let decorations = [
+            (style.underline, "underline"),
+            (style.strikethrough, "line-through"),
+            (style.blink, "blink"),
+        ];
+        if decorations.iter().any(|(enabled, _)| *enabled) {
+            push_item(styles, "; ", format_args!("text-decoration:"));
+            for (_, decoration) in decorations.iter().filter(|(enabled, _)| *enabled) {
+                write!(styles, " {decoration}").unwrap();
             }
+        }
+        if style.hidden {
+            push_item(styles, "; ", format_args!("visibility: hidden"));
+        }
+    } else {
+        let flags = [
+            (style.bold, "bold"),
+            (style.dim, "dim"),
+            (style.italic, "italic"),
+            (style.underline, "underline"),
+            (style.blink, "blink"),
+            (style.strikethrough, "strikethrough"),
+            (style.hidden, "hidden"),
+        ];
+        for (_, class) in flags.iter().filter(|(enabled, _)| *enabled) {
+            push_item(classes, " ", format_args!("{prefix}{class}"));
+        }
+    }
+}
 
-            // Check if segment should be filtered
-            let fg_hex = fg_color.map(Color::to_hex);
-            let is_filtered = match (fg_hex, filter_hex) {
-                (Some(fh), Some(filt)) if fh == filt && segment.text.chars().all(|c| c == ' ') => {
-                    true
-                }
-                _ => false,
-            };
+/// Appends `item` to the list in `list`, after `separator` unless it is the first.
+fn push_item(list: &mut String, separator: &str, item: fmt::Arguments) {
+    if !list.is_empty() {
+        list.push_str(separator);
+    }
+    list.write_fmt(item).unwrap();
+}
 
-            // For filtered segments, don't apply styling
-            let (final_classes, final_styles) = if is_filtered {
-                (Vec::new(), Vec::new())
-            } else {
-                (classes, inline_styles)
-            };
+/// Writes `class` and `style` attributes to `output`, omitting any that would be empty.
+fn write_attributes(classes: &str, styles: &str, output: &mut String) {
+    if !classes.is_empty() {
+        output.push_str(" class=\"");
+        escape_xml(classes, output);
+        output.push('"');
+    }
+    if !styles.is_empty() {
+        output.push_str(" style=\"");
+        escape_xml(styles, output);
+        output.push('"');
+    }
+}
 
-            // Build span
-            let class_attr = if final_classes.is_empty() {
-                String::new()
-            } else {
-                format!(" class=\"{}\"", final_classes.join(" "))
-            };
+/// Declarations applied to the line-number gutter.
+pub(crate) const LINE_NUMBER_CSS: &str = "display: inline-block; min-width: 4ch; \
+padding-right: 1ch; text-align: right; opacity: 0.5; color: inherit; \
+text-decoration: none; user-select: none";
 
-            let style_attr = if final_styles.is_empty() {
-                String::new()
-            } else {
-                format!(" style=\"{}\"", final_styles.join("; "))
-            };
+/// Returns the byte offset in the input of each line of `text`: where its first character
+/// is, or for an empty line, just after the newline before it. Lines are `None` without
+/// source spans.
+fn line_offsets(text: &StyledText) -> Vec<Option<usize>> {
+    // The offsets of each line's first character and of the end of the newline before it
+    let mut lines: Vec<(Option<usize>, Option<usize>)> = vec![(None, None)];
+    for segment in &text.segments {
+        let mut offset = 0;
+        for (idx, part) in segment.text.split('\n').enumerate() {
+            let start = segment
+                .sub_source(offset..offset)
+                .map(|source| source.start);
+            if idx > 0 {
+                lines.push((None, start));
+            }
+            let line = lines.last_mut().expect("lines is never empty");
+            if !part.is_empty() && line.0.is_none() {
+                line.0 = start;
+            }
+            offset += part.len() + 1;
+        }
+    }
+    lines
+        .into_iter()
+        .map(|(first_char, after_newline)| first_char.or(after_newline))
+        .collect()
+}
 
-            let text = if is_filtered {
-                "&nbsp;".repeat(segment.text.len())
-            } else {
-                segment.text.clone()
-            };
+/// Wraps the rendered markup of line `number`, starting at byte `offset` of the input, in
+/// its line element, adding a gutter link if line numbers are enabled.
+fn wrap_line(
+    number: usize,
+    offset: Option<usize>,
+    line_html: &str,
+    options: &HtmlOptions,
+) -> String {
+    let prefix = &options.class_prefix;
+    let mut html = String::from("<span");
+    if options.line_anchors || options.line_numbers {
+        write!(&mut html, " id=\"L{number}\"").unwrap();
+    }
+    write!(&mut html, " class=\"{prefix}line\"").unwrap();
+    if options.source_positions {
+        write!(&mut html, " data-line=\"{number}\"").unwrap();
+        if let Some(offset) = offset {
+            write!(&mut html, " data-offset=\"{offset}\"").unwrap();
+        }
+    }
+    html.push('>');
+    if options.line_numbers {
+        let style_attr = if options.inline_styles {
+            format!(" style=\"{LINE_NUMBER_CSS}\"")
+        } else {
+            String::new()
+        };
+        write!(
+            &mut html,
+            "<a class=\"{prefix}line-number\" href=\"#L{number}\"{style_attr}>{number}</a>"
+        )
+        .unwrap();
+    }
+    html.push_str(line_html);
+    html.push_str("</span>");
+    html
+}
 
-            write!(&mut html, "<span{class_attr}{style_attr}>{text}</span>").unwrap();
+/// Writes `text` to `output`, escaped, with each reference to a line of a source file
+/// linked to `template` filled in with its path, line, and column.
+fn link_file_references(text: &str, template: &str, output: &mut String) {
+    let mut last = 0;
+    for captures in FILE_REFERENCE_REGEX.captures_iter(text) {
+        let reference = captures.get(0).unwrap();
+        // A host and port in a URL looks like a reference, but follows the scheme's colon,
+        // and a match starting mid-word is a drive letter that is not one
+        if text[..reference.start()].ends_with(|ch: char| ch == ':' || ch.is_alphanumeric()) {
+            continue;
         }
-        html
+        escape_xml(&text[last..reference.start()], output);
+        let href = template
+            .replace("{path}", &percent_encode_path(&captures["path"]))
+            .replace("{line}", &captures["line"])
+            .replace(
+                "{column}",
+                captures
+                    .name("column")
+                    .map_or("1", |column| column.as_str()),
+            );
+        output.push_str("<a href=\"");
+        escape_xml(&href, output);
+        output.push_str("\">");
+        escape_xml(reference.as_str(), output);
+        output.push_str("</a>");
+        last = reference.end();
+    }
+    escape_xml(&text[last..], output);
+}
+
+/// Returns the markup used for a line break in the given mode.
+fn newline(mode: NewlineMode) -> &'static str {
+    match mode {
+        NewlineMode::Preserve => "\n",
+        NewlineMode::LineBreak => "<br>",
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Segment, Style};
+    use crate::{ParseOptions, Segment, Style, parse_ansi, parse_ansi_with};
 
     use super::*;
 
@@ -184,6 +1468,8 @@ mod tests {
             segments: vec![Segment {
                 text: "Hello World".to_string(),
                 style: Style::default(),
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -199,6 +1485,8 @@ mod tests {
                     fg_color: Some(Color::Indexed(1)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -214,6 +1502,8 @@ mod tests {
                     bg_color: Some(Color::Indexed(4)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -229,6 +1519,8 @@ mod tests {
                     fg_color: Some(Color::Rgb(255, 0, 128)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -247,6 +1539,8 @@ mod tests {
                     bg_color: Some(Color::Rgb(128, 255, 0)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -265,6 +1559,8 @@ mod tests {
                     bold: true,
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -282,6 +1578,8 @@ mod tests {
                     underline: true,
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -302,6 +1600,8 @@ mod tests {
                     reverse: true,
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -315,6 +1615,8 @@ mod tests {
                 Segment {
                     text: "Normal".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "Bold".to_string(),
@@ -322,6 +1624,8 @@ mod tests {
                         bold: true,
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
@@ -339,6 +1643,8 @@ mod tests {
                 Segment {
                     text: "Text".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: String::new(),
@@ -346,10 +1652,14 @@ mod tests {
                         bold: true,
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "More".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
@@ -363,6 +1673,8 @@ mod tests {
             segments: vec![Segment {
                 text: "hello         world".to_string(),
                 style: Style::default(),
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -375,6 +1687,8 @@ mod tests {
             segments: vec![Segment {
                 text: "line1\nline2\nline3".to_string(),
                 style: Style::default(),
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -387,6 +1701,8 @@ mod tests {
             segments: vec![Segment {
                 text: "This is a test\nwith    multiple   spaces\nand\nnewlines".to_string(),
                 style: Style::default(),
+                source: None,
+                escapes: None,
             }],
         };
         let html = styled_text.to_html();
@@ -403,6 +1719,8 @@ mod tests {
                 Segment {
                     text: "Normal text   with spaces".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "Bold    text".to_string(),
@@ -410,6 +1728,8 @@ mod tests {
                         bold: true,
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "\nRed     text".to_string(),
@@ -417,6 +1737,8 @@ mod tests {
                         fg_color: Some(Color::Indexed(1)),
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "   Underlined".to_string(),
@@ -424,6 +1746,8 @@ mod tests {
                         underline: true,
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
@@ -434,6 +1758,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_html_escapes_text() {
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "<script>alert(\"x\") && 1</script>".to_string(),
+                style: Style::default(),
+                source: None,
+                escapes: None,
+            }],
+        };
+        let html = styled_text.to_html();
+        assert_eq!(
+            html,
+            "<pre><span>&lt;script&gt;alert(&quot;x&quot;) &amp;&amp; 1&lt;/script&gt;</span></pre>"
+        );
+    }
+
+    #[test]
+    fn test_html_raw_passthrough() {
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "<b>bold</b>".to_string(),
+                style: Style::default(),
+                source: None,
+                escapes: None,
+            }],
+        };
+        let html = styled_text.to_html_raw(None);
+        assert_eq!(html, "<pre><span><b>bold</b></span></pre>");
+    }
+
+    #[test]
+    fn test_html_inline_styles() {
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "Alert".to_string(),
+                style: Style {
+                    fg_color: Some(Color::Indexed(1)),
+                    bg_color: Some(Color::Rgb(1, 2, 3)),
+                    bold: true,
+                    underline: true,
+                    strikethrough: true,
+                    ..Default::default()
+                },
+                source: None,
+                escapes: None,
+            }],
+        };
+        let html = styled_text.to_html_with(&HtmlOptions {
+            inline_styles: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<pre><span style=\"color: #800000; background-color: #010203; font-weight: bold; text-decoration: underline line-through\">Alert</span></pre>"
+        );
+    }
+
+    #[test]
+    fn test_html_with_prefix_wrapper_and_line_breaks() {
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "a\nb".to_string(),
+                style: Style {
+                    fg_color: Some(Color::Indexed(1)),
+                    bold: true,
+                    ..Default::default()
+                },
+                source: None,
+                escapes: None,
+            }],
+        };
+        let html = styled_text.to_html_with(&HtmlOptions {
+            class_prefix: ClassPrefix::new("ansi-").unwrap(),
+            wrapper: Some("code".to_string()),
+            newlines: NewlineMode::LineBreak,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<code><span class=\"ansi-fg1 ansi-bold\">a<br>b</span></code>"
+        );
+    }
+
+    #[test]
+    fn test_html_without_wrapper() {
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "x".to_string(),
+                style: Style::default(),
+                source: None,
+                escapes: None,
+            }],
+        };
+        let html = styled_text.to_html_with(&HtmlOptions {
+            wrapper: None,
+            ..Default::default()
+        });
+        assert_eq!(html, "<span>x</span>");
+    }
+
     #[test]
     fn test_html_filter_spaces_with_matching_fg() {
         let styled_text = StyledText {
@@ -441,6 +1866,8 @@ mod tests {
                 Segment {
                     text: "Data".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "   ".to_string(),
@@ -448,10 +1875,14 @@ mod tests {
                         fg_color: Some(Color::Indexed(0)), // #000000
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "More".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
@@ -469,6 +1900,8 @@ mod tests {
                 Segment {
                     text: "Data".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "   ".to_string(),
@@ -476,6 +1909,8 @@ mod tests {
                         fg_color: Some(Color::Indexed(0)), // #000000
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
@@ -490,6 +1925,8 @@ mod tests {
                 Segment {
                     text: "Data".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "XXX".to_string(),
@@ -497,10 +1934,14 @@ mod tests {
                         fg_color: Some(Color::Indexed(0)), // #000000
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "More".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
@@ -510,4 +1951,530 @@ mod tests {
             "<pre><span>Data</span><span class=\"fg0\">XXX</span><span>More</span></pre>"
         );
     }
+
+    #[test]
+    fn test_html_document_color_schemes() {
+        let styled_text = parse_ansi("\x1b[31mred\x1b[0m");
+        let document =
+            styled_text.to_html_document(&HtmlOptions::default(), &HtmlDocumentOptions::default());
+        assert!(document.starts_with("<!DOCTYPE html>"));
+        assert!(document.contains("body { background-color: #ffffff; color: #000000; }"));
+        assert!(
+            document.contains(
+                "@media (prefers-color-scheme: dark) {\nbody { background-color: #1e1e1e;"
+            )
+        );
+        assert!(document.contains(
+            "<main id=\"content\"><div role=\"log\" aria-label=\"Terminal output\">\
+             <pre><span class=\"fg1\">red</span></pre></div></main>"
+        ));
+
+        let light_only = HtmlDocumentOptions {
+            dark: None,
+            ..Default::default()
+        };
+        let document = styled_text.to_html_document(&HtmlOptions::default(), &light_only);
+        assert!(!document.contains("prefers-color-scheme"));
+        // Only the 16 palette colors are themed: 256-color and truecolor values keep
+        // their own, even where they match a palette entry
+        let styled_text = parse_ansi("\x1b[38;5;196ma\x1b[38;2;255;0;0mb");
+        let html = styled_text.to_html_with(&HtmlOptions::default());
+        assert!(html.contains("<span class=\"fg196\">a</span>"));
+        assert!(html.contains("<span style=\"color: rgb(255, 0, 0)\">b</span>"));
+    }
+
+    #[test]
+    fn test_html_document_theme_toggle() {
+        let styled_text = parse_ansi("\x1b[31mred\x1b[0m");
+        let toggle = HtmlDocumentOptions {
+            theme_toggle: true,
+            ..Default::default()
+        };
+        let document = styled_text.to_html_document(&HtmlOptions::default(), &toggle);
+        // The dark theme applies when chosen, or when preferred unless light was chosen
+        assert!(document.contains(
+            "@media (prefers-color-scheme: dark) {\n\
+             :root:not([data-theme=\"light\"]) body { background-color: #1e1e1e;"
+        ));
+        assert!(document.contains(":root[data-theme=\"dark\"] .fg1 { color: "));
+        assert!(document.contains("localStorage.getItem(\"fromansi-theme\")"));
+        assert!(document.contains(
+            "Skip to content</a><button class=\"theme-toggle\" type=\"button\" \
+             aria-pressed=\"false\" hidden>Dark theme</button>"
+        ));
+
+        let light_only = HtmlDocumentOptions {
+            dark: None,
+            ..toggle
+        };
+        let document = styled_text.to_html_document(&HtmlOptions::default(), &light_only);
+        assert!(!document.contains("theme-toggle") && !document.contains("<script>"));
+    }
+
+    #[test]
+    fn test_html_document_accessibility() {
+        let styled_text = parse_ansi("\x1b[1mok\x1b[0m 100%\n");
+        let document = HtmlDocumentOptions {
+            label: "Build <log>".to_string(),
+            lang: "de-CH".to_string(),
+            plain_text_link: true,
+            ..Default::default()
+        };
+        let options = HtmlOptions {
+            class_prefix: ClassPrefix::new("ansi-").unwrap(),
+            ..Default::default()
+        };
+        let html = styled_text.to_html_document(&options, &document);
+        assert!(html.contains("<html lang=\"de-CH\">"));
+        assert!(html.contains("<title>Build &lt;log&gt;</title>"));
+        assert!(html.contains(
+            "<body><a class=\"ansi-skip-link\" href=\"#ansi-content\">Skip to content</a>\
+             <main id=\"ansi-content\"><div role=\"log\" aria-label=\"Build &lt;log&gt;\">"
+        ));
+        assert!(html.contains(".ansi-skip-link:focus { position: static; }"));
+        assert!(html.contains(
+            "<a download=\"output.txt\" href=\"data:text/plain;charset=utf-8,ok%20100%25%0A\">"
+        ));
+
+        let html = styled_text.to_html_document(&options, &HtmlDocumentOptions::default());
+        assert!(html.contains("<html lang=\"en\">"));
+        assert!(!html.contains("download="));
+    }
+
+    #[test]
+    fn test_html_document_toolbar() {
+        let styled_text = parse_ansi("ok\n\x1b[31merror: failed\x1b[0m\n");
+        let options = HtmlOptions::default();
+        let html = styled_text.to_html_document(&options, &HtmlDocumentOptions::default());
+        assert!(!html.contains("toolbar") && !html.contains("<script>"));
+
+        let document = HtmlDocumentOptions {
+            toolbar: true,
+            ..Default::default()
+        };
+        let html = styled_text.to_html_document(&options, &document);
+        // The toolbar comes before the output, which gets line elements for it to work on
+        assert!(html.contains(
+            "Skip to content</a><div class=\"toolbar\" role=\"search\" hidden>\
+             <input id=\"search\""
+        ));
+        assert!(html.contains("<span id=\"L2\" class=\"line\"><span class=\"fg1\">error"));
+        assert!(html.contains("</main><script>(() => {\n  const prefix = \"\";"));
+        assert!(html.contains(".toolbar { position: sticky;"));
+    }
+
+    #[test]
+    fn test_html_document_copy_buttons_and_ansi_link() {
+        let styled_text = parse_ansi("\x1b[1mok\x1b[0m\n");
+        let document = HtmlDocumentOptions {
+            copy_buttons: true,
+            plain_text_link: true,
+            ansi_link: true,
+            ..Default::default()
+        };
+        let html = styled_text.to_html_document(&HtmlOptions::default(), &document);
+        assert!(html.contains(
+            "<button class=\"copy\" type=\"button\" hidden>Copy</button>\
+             <main id=\"content\"><div role=\"log\" aria-label=\"Terminal output\"><pre>\
+             <span id=\"L1\" class=\"line\">"
+        ));
+        assert!(html.contains("button.className = prefix + \"copy-line\";"));
+        assert!(html.contains(
+            "Download as plain text</a> <a download=\"output.ans\" \
+             href=\"data:text/plain;charset=utf-8,%1B%5B0%3B1mok%1B%5B0m%0A\">Download as ANSI</a>"
+        ));
+
+        // Escape sequences recorded while parsing are downloaded as they were
+        let parse_options = ParseOptions {
+            raw_escapes: true,
+            ..Default::default()
+        };
+        let styled_text = parse_ansi_with("\x1b[1mok\x1b[m\n", &parse_options);
+        let html = styled_text.to_html_document(&HtmlOptions::default(), &document);
+        assert!(html.contains("href=\"data:text/plain;charset=utf-8,%1B%5B1mok%1B%5Bm%0A\">"));
+    }
+
+    #[test]
+    fn test_html_fold_groups() {
+        let log = parse_ansi(
+            "start\n::group::Run <tests>\n\x1b[32mok\x1b[0m\nok\n::endgroup::\ndone\n\
+             ##[section]Starting: Build\n##[group]Compile\ncc\n##[section]Finishing: Build\nend",
+        );
+        let options = HtmlOptions {
+            fold_groups: true,
+            line_anchors: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            log.to_html_with(&options),
+            "<pre><span id=\"L1\" class=\"line\"><span>start</span></span>\
+             <details id=\"section-1\" class=\"group\"><summary>Run &lt;tests&gt;</summary>\
+             <span id=\"L3\" class=\"line\"><span class=\"fg2\">ok</span></span>\n\
+             <span id=\"L4\" class=\"line\"><span>ok</span></span></details>\
+             <span id=\"L6\" class=\"line\"><span>done</span></span>\
+             <details id=\"section-2\" class=\"section\"><summary>Starting: Build</summary>\
+             <details id=\"section-3\" class=\"group\"><summary>Compile</summary>\
+             <span id=\"L9\" class=\"line\"><span>cc</span></span></details></details>\
+             <span id=\"L11\" class=\"line\"><span>end</span></span></pre>"
+        );
+
+        // Groups left open at the end are closed, and timestamps are skipped
+        let log = parse_ansi("2024-05-01T10:00:00.1234567Z ##[group]Setup\nstep");
+        let html = log.to_html_with(&HtmlOptions {
+            fold_groups: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<pre><details id=\"section-1\" class=\"group\"><summary>Setup</summary>\
+             <span>step</span></details></pre>"
+        );
+    }
+
+    #[test]
+    fn test_class_prefix_is_validated() {
+        for prefix in ["", "ansi-", "-x", "--", "_a1", "pré-"] {
+            assert!(ClassPrefix::new(prefix).is_ok(), "{prefix}");
+        }
+        for prefix in ["1a", "-1", "a b", "a\"", "a.b", "a#"] {
+            assert!(ClassPrefix::new(prefix).is_err(), "{prefix}");
+        }
+        let prefix: ClassPrefix = serde_json::from_str(r#""ansi-""#).unwrap();
+        assert_eq!(prefix.as_str(), "ansi-");
+        assert!(serde_json::from_str::<ClassPrefix>(r#""a\"b""#).is_err());
+    }
+
+    #[test]
+    fn test_section_markers_are_validated() {
+        assert!(SectionMarker::new("(", None).is_err());
+        assert!(SectionMarker::new("^BEGIN", Some("[")).is_err());
+
+        let marker = SectionMarker::new("^BEGIN", Some("^END$")).unwrap();
+        let json = serde_json::to_string(&marker).unwrap();
+        assert_eq!(json, r#"{"start":"^BEGIN","end":"^END$"}"#);
+        assert_eq!(
+            serde_json::from_str::<SectionMarker>(&json).unwrap(),
+            marker
+        );
+        assert!(serde_json::from_str::<SectionMarker>(r#"{"start":"("}"#).is_err());
+    }
+
+    #[test]
+    fn test_html_sections() {
+        let log = parse_ansi(
+            "== \x1b[1mBuild\x1b[0m ==\ncompiling\nBEGIN lint\nok\nEND\n== Test ==\npassed",
+        );
+        let options = HtmlOptions {
+            sections: vec![
+                SectionMarker::new("^== (?<name>.*) ==$", None).unwrap(),
+                SectionMarker::new("^BEGIN", Some("^END$")).unwrap(),
+            ],
+            table_of_contents: true,
+            class_prefix: ClassPrefix::new("ansi-").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            log.to_html_with(&options),
+            "<nav class=\"ansi-toc\" aria-label=\"Sections\"><ol>\
+             <li><a href=\"#ansi-section-1\">Build</a><ol>\
+             <li><a href=\"#ansi-section-2\">BEGIN lint</a></li></ol></li>\
+             <li><a href=\"#ansi-section-3\">Test</a></li></ol></nav>\
+             <pre><details id=\"ansi-section-1\" class=\"ansi-section\"><summary>\
+             <span>== </span><span class=\"ansi-bold\">Build</span><span> ==</span></summary>\
+             <span>compiling</span>\
+             <details id=\"ansi-section-2\" class=\"ansi-section\"><summary><span>BEGIN lint</span>\
+             </summary><span>ok</span>\n<span>END</span></details></details>\
+             <details id=\"ansi-section-3\" class=\"ansi-section\"><summary><span>== Test ==</span>\
+             </summary><span>passed</span></details></pre>"
+        );
+    }
+
+    #[test]
+    fn test_html_file_links() {
+        let log = parse_ansi(
+            "\x1b[34m  --> \x1b[0msrc/main.rs:12:5\nsee lib/a-b.rs:7 & http://example.com:8080",
+        );
+        let html = log.to_html_with(&HtmlOptions {
+            file_links: Some("vscode://file/{path}:{line}:{column}".to_string()),
+            compact: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<pre><span class=\"fg4\">  --&gt; </span>\
+             <a href=\"vscode://file/src/main.rs:12:5\">src/main.rs:12:5</a>\n\
+             see <a href=\"vscode://file/lib/a-b.rs:7:1\">lib/a-b.rs:7</a> &amp; \
+             http://example.com:8080</pre>"
+        );
+
+        // Characters that are not allowed in URLs are encoded in paths
+        let log = parse_ansi("at src/ünï~c.rs:3 and C:\\w-x\\a.b.rs:1");
+        let html = log.to_html_with(&HtmlOptions {
+            file_links: Some("file:///{path}#{line}".to_string()),
+            ..Default::default()
+        });
+        assert!(html.contains("<a href=\"file:///src/%C3%BCn%C3%AF~c.rs#3\">"));
+        assert!(html.contains("<a href=\"file:///C:\\w-x\\a.b.rs#1\">"));
+    }
+
+    #[test]
+    fn test_html_source_positions() {
+        let input = "one\n\x1b[1mtwo\x1b[0m\n\nfour";
+        let parse_options = crate::ParseOptions {
+            source_spans: true,
+            ..Default::default()
+        };
+        let text = crate::parse_ansi_with(input, &parse_options);
+        let html = text.slice_lines(1..4).to_html_with(&HtmlOptions {
+            source_positions: true,
+            first_line_number: 2,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<pre><span class=\"line\" data-line=\"2\" data-offset=\"8\">\
+             <span class=\"bold\">two</span></span>\n\
+             <span class=\"line\" data-line=\"3\" data-offset=\"16\"></span>\n\
+             <span class=\"line\" data-line=\"4\" data-offset=\"17\"><span>four</span></span></pre>"
+        );
+        assert_eq!(&input[17..], "four");
+
+        // Merging segments keeps the offsets of lines after the merge
+        let text = crate::parse_ansi_with("ab\x1b[0mcd\nef", &parse_options).normalize();
+        let html = text.to_html_with(&HtmlOptions {
+            source_positions: true,
+            ..Default::default()
+        });
+        assert!(html.contains("data-line=\"2\" data-offset=\"9\""));
+    }
+
+    #[test]
+    fn test_html_line_anchors_and_numbers() {
+        let styled_text = parse_ansi("one\n\x1b[1mtwo\x1b[0m");
+        let html = styled_text.to_html_with(&HtmlOptions {
+            line_anchors: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<pre><span id=\"L1\" class=\"line\"><span>one</span></span>\n\
+             <span id=\"L2\" class=\"line\"><span class=\"bold\">two</span></span></pre>"
+        );
+
+        let html = styled_text.to_html_with(&HtmlOptions {
+            line_numbers: true,
+            class_prefix: ClassPrefix::new("ansi-").unwrap(),
+            ..Default::default()
+        });
+        assert!(html.contains(
+            "<span id=\"L2\" class=\"ansi-line\"><a class=\"ansi-line-number\" href=\"#L2\">2</a>"
+        ));
+    }
+
+    #[test]
+    fn test_html_compact_merges_spans() {
+        let styled_text = parse_ansi("a\x1b[31mb\x1b[0m\x1b[31mc\x1b[1md\x1b[0m e");
+        let options = HtmlOptions {
+            compact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            styled_text.to_html_with(&options),
+            "<pre>a<span class=\"fg1\">bc</span><span class=\"fg1 bold\">d</span> e</pre>"
+        );
+    }
+
+    #[test]
+    fn test_html_compact_filter_trims_merged_spaces() {
+        let styled_text = StyledText {
+            segments: vec![
+                Segment {
+                    text: "Data".to_string(),
+                    style: Style::default(),
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: "   ".to_string(),
+                    style: Style {
+                        fg_color: Some(Color::Indexed(0)),
+                        ..Default::default()
+                    },
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        let html = styled_text.to_html_with(&HtmlOptions {
+            filter: Some("#000000".to_string()),
+            compact: true,
+            ..Default::default()
+        });
+        assert_eq!(html, "<pre>Data</pre>");
+    }
+
+    #[test]
+    fn test_html_data_sgr() {
+        let styled_text = parse_ansi("\x1b[1;38;5;196mhot\x1b[0m cold");
+        let html = styled_text.to_html_with(&HtmlOptions {
+            data_sgr: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<pre><span class=\"fg196 bold\" data-sgr=\"1;38;5;196\">hot</span>\
+             <span> cold</span></pre>"
+        );
+    }
+
+    #[test]
+    fn test_html_grid_layout() {
+        let styled_text = parse_ansi("\x1b[41ma\x1b[0m日\nb");
+        let html = styled_text.to_html_with(&HtmlOptions {
+            layout: HtmlLayout::Grid,
+            ..Default::default()
+        });
+        assert_eq!(
+            html,
+            "<div class=\"grid\" style=\"display: inline-grid; \
+             grid-template-columns: repeat(3, 1ch); white-space: pre; \
+             font-family: monospace\">\
+             <span class=\"bg1\">a</span><span style=\"grid-column: span 2\">日</span>\
+             <span>b</span><span> </span><span> </span></div>"
+        );
+    }
+
+    #[test]
+    fn test_xp_to_html_layers() {
+        use rexpaint::{XpColor, XpLayer};
+
+        let mut xp = XpFile::new(2, 1);
+        let bottom = &mut xp.layers[0];
+        bottom.get_mut(0, 0).unwrap().ch = u32::from(b'a');
+        bottom.get_mut(0, 0).unwrap().bg = XpColor::new(0, 0, 128);
+        bottom.get_mut(1, 0).unwrap().bg = XpColor::TRANSPARENT;
+        let mut top = XpLayer::new(2, 1);
+        top.get_mut(0, 0).unwrap().bg = XpColor::TRANSPARENT;
+        *top.get_mut(1, 0).unwrap() = rexpaint::XpCell {
+            ch: 3,
+            fg: XpColor::new(255, 0, 0),
+            bg: XpColor::new(1, 2, 3),
+        };
+        xp.layers.push(top);
+        let mut data = Vec::new();
+        xp.write(&mut data).unwrap();
+
+        let options = HtmlOptions {
+            inline_styles: true,
+            ..Default::default()
+        };
+        let html = xp_to_html(&data, &options).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"grid\" style=\"display: inline-grid; \
+             grid-template-columns: repeat(2, 1ch); grid-template-rows: repeat(1, 1lh); \
+             white-space: pre; font-family: monospace\">\
+             <div class=\"layer\" data-layer=\"0\" style=\"display: contents\">\
+             <span style=\"grid-area: 1 / 1; color: #000000; background-color: #000080\">a</span>\
+             </div>\
+             <div class=\"layer\" data-layer=\"1\" style=\"display: contents\">\
+             <span style=\"grid-area: 1 / 2; color: #ff0000; background-color: #010203\">♥</span>\
+             </div></div>"
+        );
+
+        let only_top = RexImportOptions {
+            layers: crate::LayerSelection::Only(1),
+            ..Default::default()
+        };
+        let html = xp_to_html_with(&data, &options, &only_top).unwrap();
+        assert!(!html.contains("data-layer=\"0\""));
+
+        let filled = RexImportOptions {
+            transparency: TransparencyPolicy::Background(Color::Rgb(1, 1, 1)),
+            ..Default::default()
+        };
+        let html = xp_to_html_with(&data, &options, &filled).unwrap();
+        assert!(html.contains(
+            "<span style=\"grid-area: 1 / 2; color: #000000; background-color: #010101\"> </span>"
+        ));
+        assert!(xp_to_html(b"not a rexpaint file", &options).is_err());
+    }
+
+    #[test]
+    fn test_frames_to_html_plays_frames() {
+        let frame = |time, text| Frame {
+            time,
+            text: parse_ansi(text),
+            width: 4,
+            height: 2,
+            cursor: Some((1, 0)),
+        };
+        let html = frames_to_html(
+            &[frame(0.0, ""), frame(0.25, "a")],
+            &HtmlOptions::default(),
+            &HtmlDocumentOptions::default(),
+        );
+        assert!(html.contains(
+            ".frame > * { margin: 0; line-height: 1.2; min-width: 4ch; min-height: 2.4em; }"
+        ));
+        // Only the last frame shows without scripting, and it has a reversed cursor cell
+        assert!(html.contains("<div class=\"frame\" data-duration=\"250\" hidden><pre>"));
+        assert!(html.contains(
+            "<div class=\"frame\" data-duration=\"2000\"><pre><span>a</span>\
+             <span class=\"fg0 bg7\"> </span>"
+        ));
+        assert!(html.contains("document.querySelectorAll(\".frame\")"));
+    }
+
+    #[test]
+    fn test_html_pages() {
+        let text = parse_ansi("one\n\x1b[31mtwo\nthree\x1b[0m\nfour\nfive");
+        let options = HtmlOptions {
+            line_anchors: true,
+            ..Default::default()
+        };
+        let pages = text.to_html_pages(
+            2,
+            |page| format!("page{page}.html"),
+            &options,
+            &HtmlDocumentOptions::default(),
+        );
+        assert_eq!(pages.len(), 3);
+        assert!(pages[0].contains("Page 1 of 3<a href=\"page1.html\" rel=\"next\">Next</a>"));
+        assert!(!pages[0].contains("rel=\"prev\""));
+        // Red carries over to the second page, and its lines keep their numbers
+        assert!(pages[1].contains("<a href=\"page0.html\" rel=\"prev\">Previous</a>"));
+        assert!(
+            pages[1].contains("<span id=\"L3\" class=\"line\"><span class=\"fg1\">three</span>")
+        );
+        assert!(!pages[2].contains("rel=\"next\""));
+        assert!(pages[2].contains("<span id=\"L5\" class=\"line\"><span>five</span></span>"));
+        assert!(pages[2].starts_with("<!DOCTYPE html>"));
+
+        let empty = StyledText::default().to_html_pages(
+            10,
+            |_| String::new(),
+            &options,
+            &HtmlDocumentOptions::default(),
+        );
+        assert_eq!(empty.len(), 1);
+    }
+
+    #[test]
+    fn test_html_pages_split_and_hrefs() {
+        let text = parse_ansi("one\n\x1b[31mtwo\nthree\x1b[0m\n\nfour\nfive\n");
+        let pages = split_pages(&text, 2);
+        assert_eq!(pages.len(), 3);
+        for (page, page_text) in pages.iter().enumerate() {
+            assert_eq!(*page_text, text.slice_lines(page * 2..page * 2 + 2));
+        }
+
+        let pages = text.to_html_pages(
+            2,
+            |page| format!("my log \"{page}\"&.html"),
+            &HtmlOptions::default(),
+            &HtmlDocumentOptions::default(),
+        );
+        assert!(pages[0].contains("<a href=\"my%20log%20%221%22&amp;.html\" rel=\"next\">"));
+    }
 }
@@ -0,0 +1,233 @@
+use crate::{Color, Segment, StyledText};
+use serde::Serialize;
+
+/// The version of the JSON document produced by [`StyledText::to_json`].
+///
+/// The version is only incremented for incompatible changes. New optional fields may be
+/// added without a version change, so consumers should ignore fields they do not know.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// The top-level JSON document.
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    version: u32,
+    segments: Vec<JsonSegment<'a>>,
+}
+
+/// The top-level JSON document with segments grouped by line.
+#[derive(Serialize)]
+struct JsonLinesDocument<'a> {
+    version: u32,
+    lines: Vec<Vec<JsonSegment<'a>>>,
+}
+
+#[derive(Serialize)]
+struct JsonSegment<'a> {
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fg: Option<JsonColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bg: Option<JsonColor>,
+    attributes: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<[usize; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    escapes: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonColor {
+    hex: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rgb: Option<[u8; 3]>,
+}
+
+impl From<&Color> for JsonColor {
+    fn from(color: &Color) -> Self {
+        JsonColor {
+            hex: color.to_hex(),
+            index: match color {
+                Color::Indexed(idx) => Some(*idx),
+                Color::Rgb(..) => None,
+            },
+            rgb: match color {
+                Color::Indexed(_) => None,
+                Color::Rgb(r, g, b) => Some([*r, *g, *b]),
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a Segment> for JsonSegment<'a> {
+    fn from(segment: &'a Segment) -> Self {
+        let style = &segment.style;
+        let attributes = [
+            (style.bold, "bold"),
+            (style.dim, "dim"),
+            (style.italic, "italic"),
+            (style.underline, "underline"),
+            (style.blink, "blink"),
+            (style.reverse, "reverse"),
+            (style.hidden, "hidden"),
+            (style.strikethrough, "strikethrough"),
+        ];
+        JsonSegment {
+            text: &segment.text,
+            fg: style.fg_color.as_ref().map(JsonColor::from),
+            bg: style.bg_color.as_ref().map(JsonColor::from),
+            attributes: attributes
+                .iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, name)| *name)
+                .collect(),
+            source: segment
+                .source
+                .as_ref()
+                .map(|range| [range.start, range.end]),
+            escapes: segment.escapes.as_deref(),
+        }
+    }
+}
+
+impl StyledText {
+    /// Converts the styled text to JSON using a stable, versioned schema.
+    ///
+    /// Unlike the `serde` representation of [`StyledText`], which follows the crate's
+    /// internal types, this format is kept compatible across releases (see
+    /// [`JSON_SCHEMA_VERSION`]). The document looks like:
+    ///
+    /// ```json
+    /// {
+    ///   "version": 1,
+    ///   "segments": [
+    ///     {
+    ///       "text": "Error",
+    ///       "fg": { "hex": "#800000", "index": 1 },
+    ///       "bg": { "hex": "#010203", "rgb": [1, 2, 3] },
+    ///       "attributes": ["bold"],
+    ///       "source": [7, 12]
+    ///     }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// - `fg` and `bg` are omitted for the terminal's default colors. `hex` is always
+    ///   present; `index` is present for palette colors and `rgb` for truecolor.
+    /// - `attributes` lists any of `bold`, `dim`, `italic`, `underline`, `blink`,
+    ///   `reverse`, `hidden`, and `strikethrough`, in that order.
+    /// - `source` is the segment's byte range in the input, present only when parsed
+    ///   with [`ParseOptions::source_spans`](crate::ParseOptions::source_spans).
+    /// - `escapes` holds the escape sequences written before the segment, present only
+    ///   when parsed with [`ParseOptions::raw_escapes`](crate::ParseOptions::raw_escapes).
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.json_document()).unwrap_or_default()
+    }
+
+    /// Like [`to_json`](Self::to_json), but indented for readability.
+    #[must_use]
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.json_document()).unwrap_or_default()
+    }
+
+    /// Converts the styled text to JSON like [`to_json`](Self::to_json), with the segments
+    /// grouped by line.
+    ///
+    /// The document has a `lines` array in place of `segments`, where each line is an
+    /// array of segments without the newline:
+    ///
+    /// ```json
+    /// { "version": 1, "lines": [[{ "text": "a", "attributes": [] }], []] }
+    /// ```
+    #[must_use]
+    pub fn to_json_lines(&self) -> String {
+        let lines = self.split_lines();
+        serde_json::to_string(&json_lines_document(&lines)).unwrap_or_default()
+    }
+
+    /// Like [`to_json_lines`](Self::to_json_lines), but indented for readability.
+    #[must_use]
+    pub fn to_json_lines_pretty(&self) -> String {
+        let lines = self.split_lines();
+        serde_json::to_string_pretty(&json_lines_document(&lines)).unwrap_or_default()
+    }
+
+    fn json_document(&self) -> JsonDocument<'_> {
+        JsonDocument {
+            version: JSON_SCHEMA_VERSION,
+            segments: self.segments.iter().map(JsonSegment::from).collect(),
+        }
+    }
+}
+
+fn json_lines_document(lines: &[StyledText]) -> JsonLinesDocument<'_> {
+    JsonLinesDocument {
+        version: JSON_SCHEMA_VERSION,
+        lines: lines
+            .iter()
+            .map(|line| line.segments.iter().map(JsonSegment::from).collect())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParseOptions, parse_ansi, parse_ansi_with};
+    use serde_json::json;
+
+    #[test]
+    fn test_json_schema() {
+        let parsed = parse_ansi("a\x1b[1;31;48;2;1;2;3mb");
+        let value: serde_json::Value = serde_json::from_str(&parsed.to_json()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "version": 1,
+                "segments": [
+                    { "text": "a", "attributes": [] },
+                    {
+                        "text": "b",
+                        "fg": { "hex": "#800000", "index": 1 },
+                        "bg": { "hex": "#010203", "rgb": [1, 2, 3] },
+                        "attributes": ["bold"]
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_source_spans() {
+        let options = ParseOptions {
+            source_spans: true,
+            raw_escapes: true,
+            ..Default::default()
+        };
+        let parsed = parse_ansi_with("\x1b[4mab", &options);
+        let value: serde_json::Value = serde_json::from_str(&parsed.to_json_pretty()).unwrap();
+        assert_eq!(value["segments"][0]["source"], json!([4, 6]));
+        assert_eq!(value["segments"][0]["escapes"], json!("\u{1b}[4m"));
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let parsed = parse_ansi("a\x1b[1mb\n\nc");
+        let value: serde_json::Value = serde_json::from_str(&parsed.to_json_lines()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "version": 1,
+                "lines": [
+                    [
+                        { "text": "a", "attributes": [] },
+                        { "text": "b", "attributes": ["bold"] }
+                    ],
+                    [],
+                    [{ "text": "c", "attributes": ["bold"] }]
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::StyledText;
+use std::fmt::Write;
+
+impl StyledText {
+    /// Converts the styled text to a block that can be pasted into Markdown documents.
+    ///
+    /// The output is a `<pre>` element, which GitHub, mdBook, and other `CommonMark`
+    /// engines treat as a raw HTML block that preserves whitespace, just like a fenced code
+    /// block. Attributes use semantic tags (`<b>`, `<i>`, `<u>`, `<s>`) that survive HTML
+    /// sanitizers, and colors are applied with inline `style` attributes.
+    ///
+    /// Engines that render raw HTML as-is, such as mdBook, show the colors. GitHub's
+    /// sanitizer removes `style` attributes, so there only the text and the semantic tags
+    /// remain.
+    ///
+    /// Characters that are significant to HTML or Markdown are written as numeric entities
+    /// so they are not mangled by the engine.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::from("<pre>");
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            let style = &segment.style;
+            let (fg_color, bg_color) = if style.reverse {
+                (style.bg_color.as_ref(), style.fg_color.as_ref())
+            } else {
+                (style.fg_color.as_ref(), style.bg_color.as_ref())
+            };
+
+            let mut css = Vec::new();
+            if let Some(color) = fg_color {
+                css.push(format!("color: {}", color.to_hex()));
+            }
+            if let Some(color) = bg_color {
+                css.push(format!("background-color: {}", color.to_hex()));
+            }
+            if style.dim {
+                css.push("opacity: 0.5".to_string());
+            }
+            if style.hidden {
+                css.push("visibility: hidden".to_string());
+            }
+
+            let tags = [
+                (style.bold, "b"),
+                (style.italic, "i"),
+                (style.underline, "u"),
+                (style.strikethrough, "s"),
+            ];
+            let tags: Vec<&str> = tags
+                .iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, tag)| *tag)
+                .collect();
+
+            if !css.is_empty() {
+                write!(&mut output, "<span style=\"{}\">", css.join("; ")).unwrap();
+            }
+            for tag in &tags {
+                write!(&mut output, "<{tag}>").unwrap();
+            }
+            escape_markdown(&segment.text, &mut output);
+            for tag in tags.iter().rev() {
+                write!(&mut output, "</{tag}>").unwrap();
+            }
+            if !css.is_empty() {
+                output.push_str("</span>");
+            }
+        }
+
+        output.push_str("</pre>");
+        output
+    }
+}
+
+/// Writes `text` to `output`, replacing characters that HTML or Markdown would interpret.
+fn escape_markdown(text: &str, output: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '~' | '|' | '#' => {
+                write!(output, "&#{};", u32::from(ch)).unwrap();
+            }
+            _ => output.push(ch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_markdown_plain_text() {
+        let parsed = parse_ansi("hello\n\nworld");
+        assert_eq!(parsed.to_markdown(), "<pre>hello\n\nworld</pre>");
+    }
+
+    #[test]
+    fn test_markdown_styles_and_colors() {
+        let parsed = parse_ansi("\x1b[1;4;31mError\x1b[0m");
+        assert_eq!(
+            parsed.to_markdown(),
+            "<pre><span style=\"color: #800000\"><b><u>Error</u></b></span></pre>"
+        );
+    }
+
+    #[test]
+    fn test_markdown_escapes_special_characters() {
+        let parsed = parse_ansi("a <b> & *c* `d`");
+        assert_eq!(
+            parsed.to_markdown(),
+            "<pre>a &lt;b&gt; &amp; &#42;c&#42; &#96;d&#96;</pre>"
+        );
+    }
+}
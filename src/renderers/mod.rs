@@ -1,2 +1,31 @@
+pub mod ansi;
+pub mod discord;
+#[cfg(feature = "gif")]
+pub mod gif;
 pub mod html;
-pub mod rexpaint;
\ No newline at end of file
+pub mod json;
+pub mod markdown;
+pub mod pango;
+#[cfg(feature = "png")]
+pub mod png;
+pub mod rexpaint;
+pub mod sections;
+pub mod svg;
+pub mod typst;
+pub mod xml;
+
+/// Writes `text` to `output`, escaping characters that are significant in XML markup.
+///
+/// This covers element content and quoted attribute values.
+pub(crate) fn escape_xml(text: &str, output: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(ch),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::StyledText;
+use crate::renderers::escape_xml;
+use std::fmt::Write;
+
+impl StyledText {
+    /// Converts the styled text to Pango markup.
+    ///
+    /// Each styled segment becomes a `<span>` with `foreground`, `background`, `weight`,
+    /// `style`, `underline`, and `strikethrough` attributes as needed, for use with GTK and
+    /// tools that render Pango markup. Text is escaped so it cannot inject markup.
+    ///
+    /// Dim text is drawn at half opacity and hidden text is fully transparent. Blink has
+    /// no Pango equivalent and is ignored.
+    #[must_use]
+    pub fn to_pango(&self) -> String {
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            let style = &segment.style;
+            let (fg_color, bg_color) = if style.reverse {
+                (style.bg_color.as_ref(), style.fg_color.as_ref())
+            } else {
+                (style.fg_color.as_ref(), style.bg_color.as_ref())
+            };
+
+            let mut attributes = Vec::new();
+            if let Some(color) = fg_color {
+                attributes.push(format!("foreground=\"{}\"", color.to_hex()));
+            }
+            if let Some(color) = bg_color {
+                attributes.push(format!("background=\"{}\"", color.to_hex()));
+            }
+            if style.bold {
+                attributes.push("weight=\"bold\"".to_string());
+            }
+            if style.italic {
+                attributes.push("style=\"italic\"".to_string());
+            }
+            if style.underline {
+                attributes.push("underline=\"single\"".to_string());
+            }
+            if style.strikethrough {
+                attributes.push("strikethrough=\"true\"".to_string());
+            }
+            if style.hidden {
+                attributes.push("alpha=\"1\"".to_string());
+            } else if style.dim {
+                attributes.push("alpha=\"50%\"".to_string());
+            }
+
+            if attributes.is_empty() {
+                escape_xml(&segment.text, &mut output);
+            } else {
+                write!(&mut output, "<span {}>", attributes.join(" ")).unwrap();
+                escape_xml(&segment.text, &mut output);
+                output.push_str("</span>");
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_pango_plain_text_is_escaped() {
+        let parsed = parse_ansi("a < b & c");
+        assert_eq!(parsed.to_pango(), "a &lt; b &amp; c");
+    }
+
+    #[test]
+    fn test_pango_attributes() {
+        let parsed = parse_ansi("\x1b[1;3;31;44mX\x1b[0m");
+        assert_eq!(
+            parsed.to_pango(),
+            "<span foreground=\"#800000\" background=\"#000080\" weight=\"bold\" style=\"italic\">X</span>"
+        );
+    }
+
+    #[test]
+    fn test_pango_reverse() {
+        let parsed = parse_ansi("\x1b[7;31;44mX\x1b[0m");
+        assert_eq!(
+            parsed.to_pango(),
+            "<span foreground=\"#000080\" background=\"#800000\">X</span>"
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use crate::renderers::rexpaint::composite_layers;
+use crate::{
+    CombiningPolicy, RexImportOptions, RexOptions, RexPaintError, StyledText, Theme,
+    TransparencyPolicy,
+};
+use error_stack::{Report, ResultExt};
+use rexpaint::{XpFile, XpLayer};
+use smart_default::SmartDefault;
+use std::io::Cursor;
+use wherror::Error;
+
+/// `RexPaint`'s default 14x14 font.
+const DEFAULT_ATLAS: &[u8] = include_bytes!("../../static/cp437_14x14.png");
+
+/// A bitmap font laid out as a 16x16 grid of glyphs, in glyph index order.
+///
+/// This is the layout `RexPaint` uses for its font images: glyph 0 is at the top left and
+/// glyph 255 at the bottom right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontAtlas {
+    glyph_width: usize,
+    glyph_height: usize,
+    /// How much of each atlas pixel is covered by its glyph, row by row, from 0 (background)
+    /// to 255 (foreground).
+    coverage: Vec<u8>,
+}
+
+impl Default for FontAtlas {
+    /// Returns `RexPaint`'s default 14x14 CP437 font.
+    fn default() -> Self {
+        FontAtlas::from_png(DEFAULT_ATLAS).expect("bundled font atlas is a valid PNG")
+    }
+}
+
+impl FontAtlas {
+    /// Loads a font atlas from a PNG image.
+    ///
+    /// Glyphs are drawn with light pixels on a dark background, or on a transparent
+    /// background. Each glyph is a sixteenth of the image's width and height.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be decoded, or if its size is not a multiple of
+    /// 16 in each direction.
+    pub fn from_png(data: &[u8]) -> Result<Self, Report<RexPaintError>> {
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .change_context(RexPaintError)
+            .attach("failed to read font atlas")?;
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap_or(0)];
+        let info = reader
+            .next_frame(&mut pixels)
+            .change_context(RexPaintError)
+            .attach("failed to decode font atlas")?;
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        if width == 0 || height == 0 || width % 16 != 0 || height % 16 != 0 {
+            return Err(Report::new(RexPaintError).attach(format!(
+                "font atlas is {width}x{height}, which is not a 16x16 grid of glyphs"
+            )));
+        }
+
+        let channels = info.color_type.samples();
+        let coverage = pixels[..info.line_size * height]
+            .chunks(info.line_size)
+            .flat_map(|row| row[..width * channels].chunks(channels))
+            .map(|pixel| {
+                let (color, alpha) = match pixel {
+                    [gray] => (*gray, 255),
+                    [gray, alpha] => (*gray, *alpha),
+                    [r, g, b] => (*r.max(g).max(b), 255),
+                    [r, g, b, alpha, ..] => (*r.max(g).max(b), *alpha),
+                    [] => (0, 0),
+                };
+                blend(0, color, alpha)
+            })
+            .collect();
+
+        Ok(FontAtlas {
+            glyph_width: width / 16,
+            glyph_height: height / 16,
+            coverage,
+        })
+    }
+
+    /// The width of each glyph in pixels.
+    #[must_use]
+    pub fn glyph_width(&self) -> usize {
+        self.glyph_width
+    }
+
+    /// The height of each glyph in pixels.
+    #[must_use]
+    pub fn glyph_height(&self) -> usize {
+        self.glyph_height
+    }
+
+    /// Returns the coverage of pixel (`x`, `y`) of `glyph`.
+    pub(crate) fn coverage_at(&self, glyph: u8, x: usize, y: usize) -> u8 {
+        let column = usize::from(glyph % 16) * self.glyph_width + x;
+        let row = usize::from(glyph / 16) * self.glyph_height + y;
+        self.coverage[row * self.glyph_width * 16 + column]
+    }
+}
+
+/// Renders a `RexPaint` file to a PNG image with `RexPaint`'s default font.
+///
+/// See [`xp_to_png_with`].
+///
+/// # Errors
+///
+/// Returns an error if the `RexPaint` data is invalid or cannot be read, or if the image
+/// cannot be encoded.
+pub fn xp_to_png(data: &[u8]) -> Result<Vec<u8>, Report<RexPaintError>> {
+    xp_to_png_with(data, &RexImportOptions::default(), &FontAtlas::default())
+}
+
+/// Renders the layers of a `RexPaint` file selected by `options` to a PNG image, drawing
+/// each cell with a glyph from `atlas`.
+///
+/// Layers are composited as in [`rexpaint_to_ansi_with`](crate::rexpaint_to_ansi_with).
+/// Glyph pixels are blended between the cell's background and foreground colors by their
+/// coverage. Cells left with the transparent key background are drawn as
+/// `options.transparency` says, where the default leaves the background transparent.
+/// Glyph indexes are drawn as-is, so `options.codepage` does not apply.
+///
+/// # Errors
+///
+/// Returns an error if the `RexPaint` data is invalid or cannot be read, if a selected
+/// layer does not exist, or if the image cannot be encoded.
+pub fn xp_to_png_with(
+    data: &[u8],
+    options: &RexImportOptions,
+    atlas: &FontAtlas,
+) -> Result<Vec<u8>, Report<RexPaintError>> {
+    let xp = XpFile::read(&mut Cursor::new(data))
+        .change_context(RexPaintError)
+        .attach("failed to read data into virtual XpFile")?;
+    let layer = composite_layers(&xp, options)?;
+
+    let (pixels, width, height) = draw_layer(&layer, &options.transparency, atlas, 1);
+    encode_png(&pixels, width, height)
+        .change_context(RexPaintError)
+        .attach("failed to encode PNG")
+}
+
+/// Error raised when styled text cannot be rendered to a PNG image.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct PngError;
+
+/// Options for rendering styled text as a PNG image.
+#[derive(Debug, Clone, PartialEq, SmartDefault)]
+pub struct PngOptions {
+    /// Bitmap font to draw the text with. Defaults to `RexPaint`'s 14x14 CP437 font.
+    pub font: FontAtlas,
+    /// Size of each font pixel in image pixels.
+    #[default(1)]
+    pub scale: usize,
+    /// Page colors and palette. Defaults to the dark theme, like most terminals.
+    #[default(Theme::dark())]
+    pub theme: Theme,
+    /// How letters with combining marks are drawn, as in [`RexOptions::combining`].
+    pub combining: CombiningPolicy,
+}
+
+impl StyledText {
+    /// Renders the styled text as a PNG image with the default [`PngOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be encoded.
+    pub fn to_png(&self) -> Result<Vec<u8>, Report<PngError>> {
+        self.to_png_with(&PngOptions::default())
+    }
+
+    /// Renders the styled text as a PNG image, like a screenshot of a terminal.
+    ///
+    /// The text is laid out as in [`to_rexpaint`](StyledText::to_rexpaint) and drawn one
+    /// CP437 glyph per cell, so characters without a CP437 glyph are approximated or
+    /// replaced. Colors 0-15 and the page colors come from `options.theme`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be encoded.
+    pub fn to_png_with(&self, options: &PngOptions) -> Result<Vec<u8>, Report<PngError>> {
+        let theme = &options.theme;
+        let xp = self.resolve_colors(theme).to_rexpaint_with(&RexOptions {
+            default_fg: theme.foreground.clone(),
+            default_bg: theme.background.clone(),
+            combining: options.combining,
+            ..Default::default()
+        });
+        let (pixels, width, height) = draw_layer(
+            &xp.layers[0],
+            &TransparencyPolicy::default(),
+            &options.font,
+            options.scale.max(1),
+        );
+        encode_png(&pixels, width, height)
+            .change_context(PngError)
+            .attach("failed to encode PNG")
+    }
+}
+
+/// Draws `layer` with glyphs from `atlas` as 8-bit RGBA pixels, with each font pixel
+/// `scale` image pixels wide. Returns the pixels and the image's width and height.
+///
+/// Cells with the transparent key background are drawn as `transparency` says.
+pub(crate) fn draw_layer(
+    layer: &XpLayer,
+    transparency: &TransparencyPolicy,
+    atlas: &FontAtlas,
+    scale: usize,
+) -> (Vec<u8>, usize, usize) {
+    let cell_width = atlas.glyph_width * scale;
+    let cell_height = atlas.glyph_height * scale;
+    let width = layer.width * cell_width;
+    let height = layer.height * cell_height;
+    let mut pixels = vec![0; width * height * 4];
+
+    for y in 0..layer.height {
+        for x in 0..layer.width {
+            let Some(cell) = layer.get(x, y) else {
+                continue;
+            };
+            let background = if cell.bg.is_transparent() {
+                match transparency {
+                    TransparencyPolicy::Background(color) => Some(color.to_rgb()),
+                    TransparencyPolicy::TerminalDefault => None,
+                    TransparencyPolicy::Skip => continue,
+                }
+            } else {
+                Some((cell.bg.r, cell.bg.g, cell.bg.b))
+            };
+
+            let glyph = u8::try_from(cell.ch).unwrap_or(0);
+            for py in 0..cell_height {
+                for px in 0..cell_width {
+                    let coverage = atlas.coverage_at(glyph, px / scale, py / scale);
+                    let offset = ((y * cell_height + py) * width + x * cell_width + px) * 4;
+                    let pixel = match background {
+                        Some((r, g, b)) => [
+                            blend(r, cell.fg.r, coverage),
+                            blend(g, cell.fg.g, coverage),
+                            blend(b, cell.fg.b, coverage),
+                            255,
+                        ],
+                        // Glyph pixels over a transparent background
+                        None if coverage > 0 => [cell.fg.r, cell.fg.g, cell.fg.b, coverage],
+                        None => [0; 4],
+                    };
+                    pixels[offset..offset + 4].copy_from_slice(&pixel);
+                }
+            }
+        }
+    }
+
+    (pixels, width, height)
+}
+
+/// Mixes `from` and `to` by `amount`, where 0 is all `from` and 255 is all `to`.
+fn blend(from: u8, to: u8, amount: u8) -> u8 {
+    let (from, to, amount) = (u32::from(from), u32::from(to), u32::from(amount));
+    #[allow(clippy::cast_possible_truncation)]
+    let mixed = ((from * (255 - amount) + to * amount + 127) / 255) as u8;
+    mixed
+}
+
+/// Encodes 8-bit RGBA pixels as a PNG image.
+fn encode_png(pixels: &[u8], width: usize, height: usize) -> Result<Vec<u8>, png::EncodingError> {
+    let width = u32::try_from(width).map_err(|_| png::EncodingError::LimitsExceeded)?;
+    let height = u32::try_from(height).map_err(|_| png::EncodingError::LimitsExceeded)?;
+
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rexpaint::{XpCell, XpColor, XpLayer};
+
+    /// Decodes a PNG image to its width, height, and RGBA pixels.
+    fn decode(data: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(Cursor::new(data)).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        (info.width, info.height, pixels)
+    }
+
+    #[test]
+    fn test_default_atlas() {
+        let atlas = FontAtlas::default();
+        assert_eq!((atlas.glyph_width(), atlas.glyph_height()), (14, 14));
+        // The middle of the crossbar of 'A'
+        assert_eq!(atlas.coverage_at(b'A', 6, 8), 255);
+        assert_eq!(atlas.coverage_at(b' ', 6, 8), 0);
+        // Full block
+        assert_eq!(atlas.coverage_at(219, 0, 0), 255);
+    }
+
+    #[test]
+    fn test_xp_to_png() {
+        let mut xp = XpFile::new(2, 1);
+        *xp.layers[0].get_mut(0, 0).unwrap() = XpCell {
+            ch: 219,
+            fg: XpColor::new(255, 0, 0),
+            bg: XpColor::new(0, 0, 255),
+        };
+        xp.layers[0].get_mut(1, 0).unwrap().bg = XpColor::TRANSPARENT;
+        xp.layers.push(XpLayer::new(2, 1));
+        for cell in &mut xp.layers[1].cells {
+            cell.bg = XpColor::TRANSPARENT;
+        }
+        let mut data = Vec::new();
+        xp.write(&mut data).unwrap();
+
+        let (width, height, pixels) = decode(&xp_to_png(&data).unwrap());
+        assert_eq!((width, height), (28, 14));
+        assert_eq!(pixels[0..4], [255, 0, 0, 255]);
+        // The transparent cell
+        assert_eq!(pixels[14 * 4..15 * 4], [0, 0, 0, 0]);
+
+        let options = RexImportOptions {
+            transparency: TransparencyPolicy::Background(crate::Color::Rgb(1, 2, 3)),
+            ..Default::default()
+        };
+        let image = xp_to_png_with(&data, &options, &FontAtlas::default()).unwrap();
+        let (_, _, pixels) = decode(&image);
+        assert_eq!(pixels[14 * 4..15 * 4], [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn test_to_png() {
+        let text = crate::parse_ansi("\x1b[41m\u{2588}\x1b[0m ");
+        let (width, height, pixels) = decode(&text.to_png().unwrap());
+        assert_eq!((width, height), (28, 14));
+        // A full block in the theme's foreground, then the page background
+        assert_eq!(pixels[0..4], [0xcc, 0xcc, 0xcc, 255]);
+        assert_eq!(pixels[14 * 4..15 * 4], [0x1e, 0x1e, 0x1e, 255]);
+
+        let options = PngOptions {
+            scale: 2,
+            theme: Theme::light(),
+            ..Default::default()
+        };
+        let text = crate::parse_ansi("\x1b[31;47m ");
+        let (width, height, pixels) = decode(&text.to_png_with(&options).unwrap());
+        assert_eq!((width, height), (28, 28));
+        assert_eq!(pixels[0..4], [0xc0, 0xc0, 0xc0, 255]);
+    }
+
+    #[test]
+    fn test_invalid_atlas() {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 10, 10);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[0; 100])
+            .unwrap();
+        assert!(FontAtlas::from_png(&data).is_err());
+        assert!(FontAtlas::from_png(b"not a png").is_err());
+    }
+
+    #[test]
+    fn test_blend() {
+        assert_eq!(blend(0, 255, 0), 0);
+        assert_eq!(blend(0, 255, 255), 255);
+        assert_eq!(blend(100, 200, 128), 150);
+    }
+}
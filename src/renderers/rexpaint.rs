@@ -1,101 +1,584 @@
-use crate::{Color, StyledText};
-use codepage_437::CP437_WINGDINGS;
-use rexpaint::{XpColor, XpFile};
+use crate::palette::ColorDepth;
+use crate::width::grapheme_width;
+use crate::{Color, Diagnostic, DiagnosticKind, RexPaintError, Style, StyledText};
+use codepage_437::{CP437_CONTROL, CP437_WINGDINGS};
+use error_stack::Report;
+use rexpaint::{XpCell, XpColor, XpFile, XpLayer};
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+use std::borrow::Cow;
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Options for converting styled text to `RexPaint` files.
+#[derive(Debug, Clone, PartialEq, SmartDefault, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RexOptions {
+    /// Foreground color for text without one.
+    #[default(Color::Rgb(255, 255, 255))]
+    pub default_fg: Color,
+    /// Background color for text without one, and for empty cells.
+    #[default(Color::Rgb(0, 0, 0))]
+    pub default_bg: Color,
+    /// Write text without a background, and empty cells, with `RexPaint`'s transparent
+    /// key color instead of `default_bg`, so the layer can be placed over others.
+    pub transparent_background: bool,
+    /// Canvas width in cells, instead of the width of the longest line.
+    pub width: Option<usize>,
+    /// Write two layers: backgrounds in layer 0, and glyphs with their colors in layer 1,
+    /// where cells without a glyph are transparent. The image looks the same, but
+    /// backgrounds and text can be edited separately in `RexPaint`.
+    pub split_layers: bool,
+    /// Wrap lines longer than `width` onto the following rows instead of clipping them.
+    /// The canvas grows to fit the extra rows unless `height` is set.
+    pub wrap: bool,
+    /// Canvas height in cells, instead of the number of lines.
+    pub height: Option<usize>,
+    /// Glyph written in place of characters that have no equivalent in `codepage`.
+    #[default('?')]
+    pub replacement: char,
+    /// The mapping from characters to `RexPaint` glyph indexes.
+    pub codepage: Codepage,
+    /// Write characters without an exact glyph, such as rounded box corners, accented
+    /// letters, and double arrows, as the closest CP437 glyph instead of `replacement`.
+    #[default(true)]
+    pub approximate_glyphs: bool,
+    /// How characters two columns wide, such as CJK ideographs, are written.
+    pub wide_chars: WideCharPolicy,
+    /// How emoji, including sequences such as flags and skin tones that make up one
+    /// character, are written.
+    pub emoji: EmojiPolicy,
+    /// How letters with combining marks, such as `e` followed by a combining acute accent,
+    /// are written.
+    pub combining: CombiningPolicy,
+}
+
+/// How characters two columns wide, such as CJK ideographs and most emoji, are written to
+/// `RexPaint`, where every glyph fills a single cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WideCharPolicy {
+    /// Write the glyph in the first of its two cells and leave the second blank, so the
+    /// columns after it line up as they do in a terminal.
+    #[default]
+    Pad,
+    /// Write the glyph in both of its cells, so text replaced with `?` keeps its width
+    /// and weight.
+    Fill,
+    /// Write the glyph in a single cell, moving the rest of the line left.
+    Narrow,
+}
+
+impl WideCharPolicy {
+    /// Returns the number of cells `grapheme` fills.
+    fn columns(self, grapheme: &str) -> usize {
+        let columns = grapheme_width(grapheme);
+        match self {
+            WideCharPolicy::Narrow => columns.min(1),
+            WideCharPolicy::Pad | WideCharPolicy::Fill => columns,
+        }
+    }
+}
+
+/// How emoji are written to `RexPaint`, which has no glyphs for them.
+///
+/// An emoji is written as one glyph even when it is a sequence of characters, such as a
+/// flag, a skin tone, or people joined into a family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EmojiPolicy {
+    /// Write the first character of the emoji, which is usually `replacement` unless it
+    /// is a symbol with a CP437 glyph, such as `☺` or `♥`.
+    #[default]
+    FirstChar,
+    /// Write this character in place of every emoji.
+    ReplaceWith(char),
+    /// Leave the cells of the emoji blank.
+    Blank,
+}
+
+/// How letters with combining marks are written to `RexPaint`, where every cell holds a
+/// single glyph.
+///
+/// Marks that are not written are reported by
+/// [`to_rexpaint_with_diagnostics`](StyledText::to_rexpaint_with_diagnostics).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CombiningPolicy {
+    /// Write the precomposed character (NFC), such as `é`, where Unicode has one, and
+    /// otherwise the letter without its marks.
+    #[default]
+    Precompose,
+    /// Write the letter without its marks.
+    Drop,
+}
+
+impl EmojiPolicy {
+    /// Returns the character written for `emoji`.
+    fn glyph(self, emoji: &str) -> char {
+        match self {
+            EmojiPolicy::FirstChar => emoji.chars().next().unwrap_or(' '),
+            EmojiPolicy::ReplaceWith(ch) => ch,
+            EmojiPolicy::Blank => ' ',
+        }
+    }
+}
+
+/// Returns the character written for `grapheme`, and whether combining marks were left
+/// out of it.
+fn glyph(grapheme: &str, options: &RexOptions) -> (char, bool) {
+    if is_emoji(grapheme) {
+        return (options.emoji.glyph(grapheme), false);
+    }
+    let composed: Cow<'_, str> = match options.combining {
+        CombiningPolicy::Precompose => Cow::Owned(grapheme.nfc().collect()),
+        CombiningPolicy::Drop => Cow::Borrowed(grapheme),
+    };
+    let mut chars = composed.chars();
+    match chars.next() {
+        // Marks with no letter to go on
+        Some(first) if is_combining_mark(first) => (' ', true),
+        Some(first) => (first, chars.any(is_combining_mark)),
+        None => (' ', false),
+    }
+}
+
+/// Returns `true` if `grapheme` is an emoji: a pictograph, or a sequence with an emoji
+/// presentation selector, keycap, or joiner.
+fn is_emoji(grapheme: &str) -> bool {
+    grapheme.chars().any(|ch| {
+        matches!(
+            ch,
+            '\u{fe0f}' | '\u{20e3}' | '\u{200d}' | '\u{1f000}'..='\u{1faff}'
+        )
+    })
+}
+
+/// The mapping between `RexPaint` glyph indexes and Unicode characters.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Codepage {
+    /// CP437 with the control range (0-31 and 127) mapped to the pictographs DOS and
+    /// `RexPaint` draw there, such as `☺` and `♥`.
+    #[default]
+    Wingdings,
+    /// CP437 with the control range mapped to the ASCII control characters.
+    Control,
+    /// A custom mapping, where the character at each position is the glyph with that index.
+    /// Indexes past the end of the list decode as spaces.
+    Custom(Vec<char>),
+}
+
+impl Codepage {
+    /// Returns the character for the glyph with index `code`.
+    #[must_use]
+    pub fn decode(&self, code: u8) -> char {
+        match self {
+            Codepage::Wingdings => CP437_WINGDINGS.decode(code),
+            Codepage::Control => CP437_CONTROL.decode(code),
+            Codepage::Custom(glyphs) => glyphs.get(usize::from(code)).copied().unwrap_or(' '),
+        }
+    }
+
+    /// Returns the index of the glyph for `ch`, or `None` if the codepage has no such glyph.
+    #[must_use]
+    pub fn encode(&self, ch: char) -> Option<u8> {
+        match self {
+            Codepage::Wingdings => CP437_WINGDINGS.encode(ch),
+            Codepage::Control => CP437_CONTROL.encode(ch),
+            Codepage::Custom(glyphs) => glyphs
+                .iter()
+                .position(|&glyph| glyph == ch)
+                .and_then(|idx| u8::try_from(idx).ok()),
+        }
+    }
+
+    /// Returns the index of the glyph written for characters with no glyph of their own
+    /// when the replacement has none either: `'?'` if the codepage has it, otherwise a
+    /// space, otherwise glyph 0, which `RexPaint` draws as an empty cell.
+    fn fallback(&self) -> u8 {
+        self.encode('?').or_else(|| self.encode(' ')).unwrap_or(0)
+    }
+
+    /// Returns the character written to text output for the glyph with index `code`.
+    ///
+    /// Glyph 0 and control characters, which [`Codepage::Control`] decodes its control
+    /// range to, become spaces. Indexes past the 256 glyphs of a codepage become U+FFFD.
+    pub(crate) fn text_char(&self, code: u32) -> char {
+        match u8::try_from(code) {
+            Ok(0) => ' ',
+            Ok(code) => Some(self.decode(code))
+                .filter(|ch| !ch.is_control())
+                .unwrap_or(' '),
+            Err(_) => char::REPLACEMENT_CHARACTER,
+        }
+    }
+}
+
+/// Which layers of a `RexPaint` file are converted.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LayerSelection {
+    /// Every layer.
+    #[default]
+    All,
+    /// Only the layer with this index, where 0 is the bottom layer.
+    Only(usize),
+    /// Every layer except those with these indexes.
+    Exclude(Vec<usize>),
+}
+
+/// How cells with `RexPaint`'s transparent key background are converted.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TransparencyPolicy {
+    /// Draw the glyph without a background, so the terminal's or page's default background
+    /// shows through. Blank glyphs draw nothing where the output format allows it.
+    #[default]
+    TerminalDefault,
+    /// Draw the cell with this background color.
+    Background(Color),
+    /// Leave the cell blank.
+    Skip,
+}
+
+/// Options for converting `RexPaint` files to other formats.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RexImportOptions {
+    /// The layers to convert.
+    pub layers: LayerSelection,
+    /// Composite the selected layers with layer 0 on top instead of at the bottom.
+    pub reverse_layers: bool,
+    /// The mapping from `RexPaint` glyph indexes to characters.
+    pub codepage: Codepage,
+    /// How cells left with the transparent key background after compositing are drawn.
+    pub transparency: TransparencyPolicy,
+    /// The color depth of ANSI output.
+    pub color_depth: ColorDepth,
+    /// Write runs of cells with the same colors as one escape sequence in ANSI output,
+    /// instead of an escape sequence and reset for every cell.
+    pub coalesce: bool,
+    /// Leave out cells at the end of each line of ANSI output that draw nothing: those
+    /// skipped for the transparent key background, or with a blank glyph on the terminal's
+    /// default or a black background.
+    pub trim_trailing: bool,
+}
+
+/// Returns `true` if `cell` draws nothing: it is skipped for its transparent key
+/// background, or it has a blank glyph on the terminal's default or a black background.
+pub(crate) fn is_empty_cell(cell: &XpCell, transparency: &TransparencyPolicy) -> bool {
+    match transparency {
+        TransparencyPolicy::Skip if cell.bg.is_transparent() => true,
+        TransparencyPolicy::TerminalDefault if cell.bg.is_transparent() => is_blank_glyph(cell.ch),
+        TransparencyPolicy::Background(_) if cell.bg.is_transparent() => false,
+        _ => is_blank_glyph(cell.ch) && cell.bg == XpColor::BLACK,
+    }
+}
+
+/// Returns `true` if glyph `ch` draws no pixels in a CP437 font.
+pub(crate) fn is_blank_glyph(ch: u32) -> bool {
+    matches!(ch, 0 | 32 | 255)
+}
+
+/// Returns the layers of `xp` selected by `options` with their indexes, bottom layer first.
+///
+/// Layers are stacked with higher indexes on top, or the reverse with
+/// `options.reverse_layers`. Returns an error if a selected layer does not exist.
+pub(crate) fn select_layers<'a>(
+    xp: &'a XpFile,
+    options: &RexImportOptions,
+) -> Result<Vec<(usize, &'a XpLayer)>, Report<RexPaintError>> {
+    let mut layers: Vec<(usize, &XpLayer)> = match &options.layers {
+        LayerSelection::All => xp.layers.iter().enumerate().collect(),
+        LayerSelection::Only(idx) => {
+            let layer = xp.layers.get(*idx).ok_or_else(|| {
+                Report::new(RexPaintError).attach(format!(
+                    "layer {idx} does not exist (the file has {} layers)",
+                    xp.layers.len()
+                ))
+            })?;
+            vec![(*idx, layer)]
+        }
+        LayerSelection::Exclude(excluded) => xp
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !excluded.contains(idx))
+            .collect(),
+    };
+    if options.reverse_layers {
+        layers.reverse();
+    }
+    Ok(layers)
+}
+
+/// Flattens the selected layers of `xp` into a single layer, the way `RexPaint` displays
+/// them.
+///
+/// Layers are stacked as described in [`select_layers`]. A cell whose background is the
+/// transparent key color lets the cell below it show through; where every layer is
+/// transparent, the topmost such cell is kept as-is. The result is as large as the largest
+/// selected layer.
+pub(crate) fn composite_layers(
+    xp: &XpFile,
+    options: &RexImportOptions,
+) -> Result<XpLayer, Report<RexPaintError>> {
+    let layers: Vec<&XpLayer> = select_layers(xp, options)?
+        .into_iter()
+        .map(|(_, layer)| layer)
+        .collect();
+    let width = layers.iter().map(|layer| layer.width).max().unwrap_or(0);
+    let height = layers.iter().map(|layer| layer.height).max().unwrap_or(0);
+    let mut result = XpLayer::new(width, height);
+    for cell in &mut result.cells {
+        cell.bg = XpColor::TRANSPARENT;
+    }
+
+    for layer in layers {
+        for x in 0..layer.width {
+            for y in 0..layer.height {
+                let Some(cell) = layer.get(x, y) else {
+                    continue;
+                };
+                if let Some(target) = result.get_mut(x, y)
+                    && (target.bg.is_transparent() || !cell.bg.is_transparent())
+                {
+                    *target = *cell;
+                }
+            }
+        }
+    }
+    Ok(result)
+}
 
 impl StyledText {
-    /// Converts the styled text to a RexPaint XpFile.
+    /// Converts the styled text to a `RexPaint` `XpFile`.
     ///
-    /// This method creates a RexPaint file with dimensions automatically calculated
+    /// This method creates a `RexPaint` file with dimensions automatically calculated
     /// from the input text (width = longest line, height = number of lines).
     ///
     /// # Returns
     ///
     /// An `XpFile` containing the styled text with colors and characters encoded
-    /// in the RexPaint format.
+    /// in the `RexPaint` format.
     ///
     /// # Note
     ///
-    /// - Text styles (bold, italic, underline, etc.) are not supported by RexPaint
+    /// - Text styles (bold, italic, underline, etc.) are not supported by `RexPaint`
     ///   and will be ignored
-    /// - Characters that cannot be encoded in CP437 will be replaced with '?'
+    /// - Characters that cannot be encoded in CP437 will be approximated with the closest
+    ///   glyph where possible, and otherwise replaced with '?'
     /// - Default colors are white foreground on black background
     #[must_use]
     pub fn to_rexpaint(&self) -> XpFile {
+        self.to_rexpaint_with(&RexOptions::default())
+    }
+
+    /// Converts the styled text to a `RexPaint` `XpFile` using `options`.
+    ///
+    /// Unless overridden by `options.width` and `options.height`, the canvas is as wide as
+    /// the longest line and as tall as the number of lines; text outside a fixed canvas is
+    /// clipped, unless `options.wrap` moves long lines onto extra rows. Cells past the end of
+    /// a line are left empty with the default background.
+    ///
+    /// With `options.transparent_background`, hidden text, padding, and text without a
+    /// background color are written with the transparent key color, so lower layers show
+    /// through when the result is placed over other layers.
+    #[must_use]
+    pub fn to_rexpaint_with(&self, options: &RexOptions) -> XpFile {
+        self.to_rexpaint_with_diagnostics(options).0
+    }
+
+    /// Converts the styled text to a `RexPaint` `XpFile` using `options`, like
+    /// [`to_rexpaint_with`](StyledText::to_rexpaint_with), and reports the characters that
+    /// lost combining marks no glyph could show.
+    ///
+    /// Each [`Diagnostic`] covers a whole character with its marks: its span of the input
+    /// if the text was parsed with [`ParseOptions::source_spans`](crate::ParseOptions),
+    /// and otherwise its byte range of [`to_plain_text`](StyledText::to_plain_text).
+    #[must_use]
+    #[allow(clippy::similar_names)]
+    pub fn to_rexpaint_with_diagnostics(&self, options: &RexOptions) -> (XpFile, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
         let lines = self.split_lines();
-        
+        let wrap_width = options.width.filter(|_| options.wrap);
+        let rows = layout_rows(&lines, wrap_width, options.wide_chars);
+
         // Calculate dimensions
-        let height = lines.len().max(1);
-        let width = lines
-            .iter()
-            .map(|line| calculate_line_width(line))
-            .max()
-            .unwrap_or(80)
+        let height = options.height.unwrap_or(rows.len()).max(1);
+        let width = options
+            .width
+            .unwrap_or_else(|| {
+                lines
+                    .iter()
+                    .map(|line| line_width(line, options.wide_chars))
+                    .max()
+                    .unwrap_or(80)
+            })
             .max(1);
 
         let mut xp = XpFile::new(width, height);
 
-        // Default colors (white on black)
-        let default_fg = XpColor::new(255, 255, 255);
-        let default_bg = XpColor::new(0, 0, 0);
+        let default_fg = color_to_xp_color(&options.default_fg);
+        let default_bg = if options.transparent_background {
+            XpColor::TRANSPARENT
+        } else {
+            color_to_xp_color(&options.default_bg)
+        };
+        for cell in &mut xp.layers[0].cells {
+            cell.bg = default_bg;
+        }
 
         // Fill the file with styled text
-        for (y, line) in lines.iter().enumerate() {
-            let mut x = 0;
-            
-            for segment in &line.segments {
-                if segment.style.hidden {
-                    // Skip hidden segments
-                    x += segment.text.chars().count();
+        for (y, row) in rows.iter().take(height).enumerate() {
+            for placed in row {
+                let PlacedGrapheme {
+                    x,
+                    text: ref grapheme,
+                    style,
+                    ..
+                } = *placed;
+                if x >= width {
+                    break; // Don't exceed calculated width
+                }
+                if style.hidden {
+                    // Skip hidden graphemes
                     continue;
                 }
 
                 // Determine colors, considering reverse
-                let (fg_color, bg_color) = if segment.style.reverse {
+                let (fg, bg) = if style.reverse {
                     (
-                        segment.style.bg_color.as_ref(),
-                        segment.style.fg_color.as_ref(),
+                        style
+                            .bg_color
+                            .as_ref()
+                            .map_or(color_to_xp_color(&options.default_bg), color_to_xp_color),
+                        style
+                            .fg_color
+                            .as_ref()
+                            .map_or(default_fg, color_to_xp_color),
                     )
                 } else {
                     (
-                        segment.style.fg_color.as_ref(),
-                        segment.style.bg_color.as_ref(),
+                        style
+                            .fg_color
+                            .as_ref()
+                            .map_or(default_fg, color_to_xp_color),
+                        style
+                            .bg_color
+                            .as_ref()
+                            .map_or(default_bg, color_to_xp_color),
                     )
                 };
 
-                let fg = fg_color.map_or(default_fg, color_to_xp_color);
-                let bg = bg_color.map_or(default_bg, color_to_xp_color);
-
-                // Write each character
-                for ch in segment.text.chars() {
-                    if x >= width {
-                        break; // Don't exceed calculated width
-                    }
-
-                    if let Some(cell) = xp.layers[0].get_mut(x, y) {
-                        cell.ch = encode_char(ch);
+                // Write the grapheme, padding wide graphemes as `options.wide_chars` says
+                let (ch, dropped_marks) = glyph(grapheme, options);
+                if dropped_marks {
+                    diagnostics.push(Diagnostic {
+                        range: placed.range.clone(),
+                        kind: DiagnosticKind::DroppedCombiningMarks,
+                    });
+                }
+                for offset in 0..options.wide_chars.columns(grapheme) {
+                    if let Some(cell) = xp.layers[0].get_mut(x + offset, y) {
+                        cell.ch = if offset == 0 || options.wide_chars == WideCharPolicy::Fill {
+                            encode_char(ch, options)
+                        } else {
+                            encode_char(' ', options)
+                        };
                         cell.fg = fg;
                         cell.bg = bg;
                     }
-                    x += 1;
                 }
             }
         }
 
-        xp
+        if options.split_layers {
+            let blank = encode_char(' ', options);
+            let mut glyphs = XpLayer::new(width, height);
+            for (background, glyph) in xp.layers[0].cells.iter_mut().zip(&mut glyphs.cells) {
+                if is_blank_glyph(background.ch) {
+                    glyph.bg = XpColor::TRANSPARENT;
+                } else {
+                    *glyph = *background;
+                    background.ch = blank;
+                }
+            }
+            xp.layers.push(glyphs);
+        }
+
+        (xp, diagnostics)
     }
 }
 
-/// Calculates the display width of a line of styled text.
+/// A grapheme cluster placed on a row.
+struct PlacedGrapheme<'a> {
+    /// The column it starts at.
+    x: usize,
+    text: Cow<'a, str>,
+    style: &'a Style,
+    /// Its span of the input if recorded, and otherwise its byte range of the plain text.
+    range: Range<usize>,
+}
+
+/// Lays out the graphemes of `lines` into rows of cells.
 ///
-/// This counts the number of visible characters in the line.
-fn calculate_line_width(line: &StyledText) -> usize {
-    line.segments
-        .iter()
-        .map(|seg| seg.text.chars().count())
+/// Each line starts a new row. With a `wrap_width`, graphemes that would extend past it
+/// continue on the next row, so wide graphemes are never split across rows. Graphemes
+/// fill as many cells as `wide_chars` says.
+fn layout_rows(
+    lines: &[StyledText],
+    wrap_width: Option<usize>,
+    wide_chars: WideCharPolicy,
+) -> Vec<Vec<PlacedGrapheme<'_>>> {
+    let mut rows = Vec::new();
+    // The byte offset of the current grapheme in the plain text
+    let mut position = 0;
+    for line in lines {
+        let mut row: Vec<PlacedGrapheme<'_>> = Vec::new();
+        let mut x = 0;
+        for grapheme in line.styled_graphemes() {
+            let end = grapheme.offset + grapheme.text.len();
+            let range = line.segments[grapheme.segment]
+                .sub_source(grapheme.offset..end)
+                .unwrap_or(position..position + grapheme.text.len());
+            position += grapheme.text.len();
+
+            // The rest of a cluster split between segments joins its first part
+            if grapheme.joins_previous
+                && let Some(last) = row.last_mut()
+            {
+                last.text.to_mut().push_str(grapheme.text);
+                last.range.end = range.end;
+                x = last.x + wide_chars.columns(&last.text);
+                continue;
+            }
+
+            let columns = wide_chars.columns(grapheme.text);
+            if wrap_width.is_some_and(|width| x > 0 && x + columns > width) {
+                rows.push(std::mem::take(&mut row));
+                x = 0;
+            }
+            row.push(PlacedGrapheme {
+                x,
+                text: Cow::Borrowed(grapheme.text),
+                style: grapheme.style,
+                range,
+            });
+            x += columns;
+        }
+        rows.push(row);
+        // The newline ending the line
+        position += 1;
+    }
+    rows
+}
+
+/// Calculates the number of cells a line of styled text fills.
+///
+/// Wide characters fill two cells, or one with [`WideCharPolicy::Narrow`].
+fn line_width(line: &StyledText, wide_chars: WideCharPolicy) -> usize {
+    line.joined_graphemes_styled()
+        .map(|(grapheme, _)| wide_chars.columns(&grapheme))
         .sum()
 }
 
-/// Converts a Color enum to an XpColor.
+/// Converts a `Color` enum to an `XpColor`.
 ///
 /// For RGB colors, this is a direct mapping.
 /// For indexed colors, this converts to RGB using the ANSI color palette.
@@ -114,22 +597,207 @@ fn color_to_xp_color(color: &Color) -> XpColor {
     }
 }
 
-/// Encodes a character to CP437 format for RexPaint.
+/// Encodes a character to a `RexPaint` glyph index using `options.codepage`.
 ///
-/// Characters that cannot be encoded in CP437 are replaced with '?' (character code 63).
-fn encode_char(ch: char) -> u32 {
+/// Characters that cannot be encoded are approximated (if `options.approximate_glyphs` is
+/// set) or replaced with `options.replacement`, or with the codepage's own '?' or space if
+/// the replacement cannot be encoded either.
+fn encode_char(ch: char, options: &RexOptions) -> u32 {
+    let codepage = &options.codepage;
     if ch == '\n' || ch == '\r' {
         // Newlines should not appear in individual cells
-        return u32::from(CP437_WINGDINGS.encode(' ').unwrap_or(32));
+        return u32::from(codepage.encode(' ').unwrap_or(32));
     }
-    
-    u32::from(CP437_WINGDINGS.encode(ch).unwrap_or(63)) // 63 is '?'
+
+    let code = codepage
+        .encode(ch)
+        .or_else(|| {
+            approximate_glyph(ch)
+                .filter(|_| options.approximate_glyphs)
+                .and_then(|approx| codepage.encode(approx))
+        })
+        .or_else(|| codepage.encode(options.replacement));
+    u32::from(code.unwrap_or_else(|| codepage.fallback()))
+}
+
+/// Returns the closest CP437 character to `ch`, for characters CP437 does not include.
+#[allow(clippy::match_same_arms)]
+fn approximate_glyph(ch: char) -> Option<char> {
+    let approx = match ch {
+        // Heavy and rounded box drawing
+        '━' | '╴' | '╶' | '╸' | '╺' | '┄' | '┈' | '╌' => '─',
+        '┃' | '╵' | '╷' | '╹' | '╻' | '┆' | '┊' | '╎' => '│',
+        '┅' | '┉' | '╍' => '═',
+        '┇' | '┋' | '╏' => '║',
+        '╭' | '┍' | '┎' => '┌',
+        '╮' | '┑' | '┒' => '┐',
+        '╰' | '┕' | '┖' => '└',
+        '╯' | '┙' | '┚' => '┘',
+        '┏' => '╔',
+        '┓' => '╗',
+        '┗' => '╚',
+        '┛' => '╝',
+        '┣' => '╠',
+        '┫' => '╣',
+        '┳' => '╦',
+        '┻' => '╩',
+        '╋' => '╬',
+        '╱' => '/',
+        '╲' => '\\',
+        '╳' => 'X',
+        // Block elements
+        '▔' | '▘' | '▝' => '▀',
+        '▁' | '▂' | '▃' | '▅' | '▖' | '▗' => '▄',
+        '▆' | '▇' | '▉' | '▊' | '▙' | '▛' | '▜' | '▟' => '█',
+        '▏' | '▎' | '▍' | '▋' => '▌',
+        '▕' => '▐',
+        '▚' | '▞' => '▒',
+        // Geometric shapes and arrows
+        '▶' | '▸' | '▹' | '▷' | '➜' | '➔' | '➤' => '►',
+        '◀' | '◂' | '◃' | '◁' => '◄',
+        '▴' | '△' | '▵' => '▲',
+        '▾' | '▽' | '▿' => '▼',
+        '⇐' | '⟵' | '⟸' | '↤' => '←',
+        '⇒' | '⟶' | '⟹' | '↦' => '→',
+        '⇑' => '↑',
+        '⇓' => '↓',
+        '⇔' | '⟷' | '⟺' => '↔',
+        '⇕' => '↕',
+        '●' | '⬤' => '•',
+        '◯' | '◦' => '○',
+        '□' | '▪' | '▫' | '◼' | '◻' => '■',
+        '◆' | '◇' => '♦',
+        '★' | '☆' => '*',
+        '✔' => '√',
+        '✗' | '✘' | '×' => 'x',
+        // Punctuation
+        '‘' | '’' | '‚' | '′' => '\'',
+        '“' | '”' | '„' | '″' => '"',
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => '-',
+        '…' | '‧' | '⋅' => '·',
+        '‹' => '<',
+        '›' => '>',
+        // Latin letters with accents CP437 lacks
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'ã' | 'ā' | 'ă' | 'ą' => 'a',
+        'Ć' | 'Ĉ' | 'Ċ' | 'Č' => 'C',
+        'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'Ď' | 'Đ' | 'Ð' => 'D',
+        'ď' | 'đ' => 'd',
+        'È' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'Ğ' | 'Ģ' => 'G',
+        'ğ' | 'ģ' => 'g',
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'Į' | 'İ' => 'I',
+        'ī' | 'į' | 'ı' => 'i',
+        'Ł' | 'Ľ' | 'Ĺ' => 'L',
+        'ł' | 'ľ' | 'ĺ' => 'l',
+        'Ń' | 'Ň' => 'N',
+        'ń' | 'ň' => 'n',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ō' | 'Ő' => 'O',
+        'õ' | 'ō' | 'ő' => 'o',
+        'Ŕ' | 'Ř' => 'R',
+        'ŕ' | 'ř' => 'r',
+        'Ś' | 'Š' | 'Ş' => 'S',
+        'ś' | 'š' | 'ş' => 's',
+        'Ť' | 'Ţ' => 'T',
+        'ť' | 'ţ' => 't',
+        'Ù' | 'Ú' | 'Û' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => 'U',
+        'ū' | 'ů' | 'ű' | 'ų' => 'u',
+        'Ý' | 'Ÿ' => 'Y',
+        'ý' => 'y',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        'ź' | 'ż' | 'ž' => 'z',
+        'Œ' => 'O',
+        'œ' => 'o',
+        _ => return None,
+    };
+    Some(approx)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Segment, Style};
+    use crate::{Segment, Style, parse_ansi};
+    use rexpaint::XpCell;
+
+    fn cell(ch: char, bg: XpColor) -> XpCell {
+        XpCell {
+            ch: u32::from(CP437_WINGDINGS.encode(ch).unwrap()),
+            fg: XpColor::new(255, 255, 255),
+            bg,
+        }
+    }
+
+    /// A 2x1 file whose bottom layer is `ab` and whose top layer covers only the second
+    /// cell with `X`.
+    fn two_layer_file() -> XpFile {
+        let mut xp = XpFile::new(2, 1);
+        xp.layers[0].cells = vec![cell('a', XpColor::BLACK), cell('b', XpColor::BLACK)];
+        let mut top = XpLayer::new(2, 1);
+        top.cells = vec![cell(' ', XpColor::TRANSPARENT), cell('X', XpColor::BLACK)];
+        xp.layers.push(top);
+        xp
+    }
+
+    fn composite_text(xp: &XpFile, options: &RexImportOptions) -> String {
+        let layer = composite_layers(xp, options).unwrap();
+        (0..layer.width)
+            .map(|x| CP437_WINGDINGS.decode(u8::try_from(layer.get(x, 0).unwrap().ch).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_composite_layers() {
+        let xp = two_layer_file();
+        assert_eq!(composite_text(&xp, &RexImportOptions::default()), "aX");
+
+        let reversed = RexImportOptions {
+            reverse_layers: true,
+            ..Default::default()
+        };
+        assert_eq!(composite_text(&xp, &reversed), "ab");
+
+        let only_top = RexImportOptions {
+            layers: LayerSelection::Only(1),
+            ..Default::default()
+        };
+        let layer = composite_layers(&xp, &only_top).unwrap();
+        assert!(layer.get(0, 0).unwrap().bg.is_transparent());
+
+        let without_top = RexImportOptions {
+            layers: LayerSelection::Exclude(vec![1]),
+            ..Default::default()
+        };
+        assert_eq!(composite_text(&xp, &without_top), "ab");
+    }
+
+    #[test]
+    fn test_is_empty_cell() {
+        let transparent = XpColor::TRANSPARENT;
+        let policy = TransparencyPolicy::TerminalDefault;
+        assert!(is_empty_cell(&cell(' ', transparent), &policy));
+        // A visible glyph on the terminal's default background still draws something
+        assert!(!is_empty_cell(&cell('A', transparent), &policy));
+        assert!(is_empty_cell(&cell(' ', XpColor::BLACK), &policy));
+        assert!(!is_empty_cell(&cell('A', XpColor::BLACK), &policy));
+
+        assert!(is_empty_cell(
+            &cell('A', transparent),
+            &TransparencyPolicy::Skip
+        ));
+        let background = TransparencyPolicy::Background(Color::Rgb(0, 0, 0));
+        assert!(!is_empty_cell(&cell(' ', transparent), &background));
+    }
+
+    #[test]
+    fn test_composite_missing_layer() {
+        let options = RexImportOptions {
+            layers: LayerSelection::Only(5),
+            ..Default::default()
+        };
+        assert!(composite_layers(&two_layer_file(), &options).is_err());
+    }
 
     #[test]
     fn test_rexpaint_plain_text() {
@@ -137,13 +805,15 @@ mod tests {
             segments: vec![Segment {
                 text: "Hello".to_string(),
                 style: Style::default(),
+                source: None,
+                escapes: None,
             }],
         };
         let xp = styled_text.to_rexpaint();
-        
+
         assert_eq!(xp.layers[0].width, 5);
         assert_eq!(xp.layers[0].height, 1);
-        
+
         // Check first character
         let cell = xp.layers[0].get(0, 0).unwrap();
         assert_eq!(cell.ch, u32::from(CP437_WINGDINGS.encode('H').unwrap()));
@@ -161,10 +831,12 @@ mod tests {
                     bg_color: Some(Color::Rgb(0, 0, 255)),
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let xp = styled_text.to_rexpaint();
-        
+
         let cell = xp.layers[0].get(0, 0).unwrap();
         assert_eq!(cell.fg, XpColor::new(255, 0, 0)); // red
         assert_eq!(cell.bg, XpColor::new(0, 0, 255)); // blue
@@ -180,10 +852,12 @@ mod tests {
                     bg_color: Some(Color::Indexed(4)), // blue
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let xp = styled_text.to_rexpaint();
-        
+
         let cell = xp.layers[0].get(0, 0).unwrap();
         // Indexed 1 is #800000 (dark red)
         assert_eq!(cell.fg, XpColor::new(128, 0, 0));
@@ -197,10 +871,12 @@ mod tests {
             segments: vec![Segment {
                 text: "Line1\nLine2\nLine3".to_string(),
                 style: Style::default(),
+                source: None,
+                escapes: None,
             }],
         };
         let xp = styled_text.to_rexpaint();
-        
+
         assert_eq!(xp.layers[0].height, 3);
         assert_eq!(xp.layers[0].width, 5); // "Line1" is longest
     }
@@ -216,10 +892,12 @@ mod tests {
                     reverse: true,
                     ..Default::default()
                 },
+                source: None,
+                escapes: None,
             }],
         };
         let xp = styled_text.to_rexpaint();
-        
+
         let cell = xp.layers[0].get(0, 0).unwrap();
         // Colors should be swapped
         assert_eq!(cell.fg, XpColor::new(0, 255, 0)); // green (was bg)
@@ -233,6 +911,8 @@ mod tests {
                 Segment {
                     text: "Visible".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "Hidden".to_string(),
@@ -240,11 +920,13 @@ mod tests {
                         hidden: true,
                         ..Default::default()
                     },
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
         let xp = styled_text.to_rexpaint();
-        
+
         // Width should only count visible text
         // But hidden text still takes up space in the calculation
         assert_eq!(xp.layers[0].width, 13); // "Visible" + "Hidden"
@@ -257,29 +939,314 @@ mod tests {
                 Segment {
                     text: "Short".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "\n".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
                 Segment {
                     text: "Much longer line".to_string(),
                     style: Style::default(),
+                    source: None,
+                    escapes: None,
                 },
             ],
         };
         let xp = styled_text.to_rexpaint();
-        
+
         assert_eq!(xp.layers[0].width, 16); // "Much longer line"
         assert_eq!(xp.layers[0].height, 2);
     }
 
+    #[test]
+    fn test_rexpaint_wide_characters() {
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "日A".to_string(),
+                style: Style::default(),
+                source: None,
+                escapes: None,
+            }],
+        };
+        let xp = styled_text.to_rexpaint();
+
+        assert_eq!(xp.layers[0].width, 3);
+        let cell = xp.layers[0].get(2, 0).unwrap();
+        assert_eq!(cell.ch, u32::from(CP437_WINGDINGS.encode('A').unwrap()));
+    }
+
+    #[test]
+    fn test_rexpaint_wide_char_policy() {
+        let text = parse_ansi("日本A\n1234");
+        let glyphs = |wide_chars| {
+            let xp = text.to_rexpaint_with(&RexOptions {
+                wide_chars,
+                ..Default::default()
+            });
+            let layer = &xp.layers[0];
+            let row: String = (0..layer.width)
+                .map(|x| layer.get(x, 0).unwrap().ch)
+                .map(|ch| CP437_WINGDINGS.decode(u8::try_from(ch).unwrap()))
+                .collect();
+            // Cells past the end of the line are left empty
+            (layer.width, row.trim_end_matches('\0').to_string())
+        };
+        assert_eq!(glyphs(WideCharPolicy::Pad), (5, "? ? A".to_string()));
+        assert_eq!(glyphs(WideCharPolicy::Fill), (5, "????A".to_string()));
+        // The canvas is as wide as the longest line once wide characters are narrowed
+        assert_eq!(glyphs(WideCharPolicy::Narrow), (4, "??A".to_string()));
+    }
+
+    #[test]
+    fn test_rexpaint_emoji_policy() {
+        // A family joined from three emoji, a flag, and a heart with a CP437 glyph
+        let text = parse_ansi("👨\u{200d}👩\u{200d}👧🇺🇸\x1b[31m♥\u{fe0f}\x1b[0m♥A");
+        let glyphs = |emoji| {
+            let xp = text.to_rexpaint_with(&RexOptions {
+                emoji,
+                wide_chars: WideCharPolicy::Narrow,
+                ..Default::default()
+            });
+            let layer = &xp.layers[0];
+            (0..layer.width)
+                .map(|x| layer.get(x, 0).unwrap().ch)
+                .map(|ch| CP437_WINGDINGS.decode(u8::try_from(ch).unwrap()))
+                .collect::<String>()
+        };
+        assert_eq!(glyphs(EmojiPolicy::FirstChar), "??♥♥A");
+        assert_eq!(glyphs(EmojiPolicy::ReplaceWith('*')), "***♥A");
+        assert_eq!(glyphs(EmojiPolicy::Blank), "   ♥A");
+    }
+
+    #[test]
+    fn test_rexpaint_combining_policy() {
+        // An e with an acute accent, which has a precomposed form, then a q with one
+        let text = parse_ansi("e\u{301}q\u{301}");
+        let convert = |combining| {
+            let (xp, diagnostics) = text.to_rexpaint_with_diagnostics(&RexOptions {
+                combining,
+                ..Default::default()
+            });
+            let layer = &xp.layers[0];
+            let row: String = (0..layer.width)
+                .map(|x| layer.get(x, 0).unwrap().ch)
+                .map(|ch| CP437_WINGDINGS.decode(u8::try_from(ch).unwrap()))
+                .collect();
+            let ranges: Vec<_> = diagnostics
+                .into_iter()
+                .map(|d| (d.range.start, d.range.end))
+                .collect();
+            (row, ranges)
+        };
+        assert_eq!(
+            convert(CombiningPolicy::Precompose),
+            ("éq".to_string(), vec![(3, 6)])
+        );
+        assert_eq!(
+            convert(CombiningPolicy::Drop),
+            ("eq".to_string(), vec![(0, 3), (3, 6)])
+        );
+    }
+
     #[test]
     fn test_encode_char() {
-        assert_eq!(encode_char('A'), u32::from(CP437_WINGDINGS.encode('A').unwrap()));
-        assert_eq!(encode_char(' '), u32::from(CP437_WINGDINGS.encode(' ').unwrap()));
+        let options = RexOptions::default();
+        assert_eq!(
+            encode_char('A', &options),
+            u32::from(CP437_WINGDINGS.encode('A').unwrap())
+        );
+        assert_eq!(
+            encode_char(' ', &options),
+            u32::from(CP437_WINGDINGS.encode(' ').unwrap())
+        );
         // Newlines should be converted to spaces
-        assert_eq!(encode_char('\n'), u32::from(CP437_WINGDINGS.encode(' ').unwrap()));
+        assert_eq!(
+            encode_char('\n', &options),
+            u32::from(CP437_WINGDINGS.encode(' ').unwrap())
+        );
+    }
+
+    #[test]
+    fn test_encode_char_replacement() {
+        let mut options = RexOptions {
+            replacement: '#',
+            ..Default::default()
+        };
+        assert_eq!(encode_char('字', &options), u32::from(b'#'));
+        // Unencodable replacements fall back to '?'
+        options.replacement = '字';
+        assert_eq!(encode_char('字', &options), u32::from(b'?'));
+    }
+
+    #[test]
+    fn test_encode_char_approximation() {
+        let mut options = RexOptions::default();
+        let encode = |ch| u32::from(CP437_WINGDINGS.encode(ch).unwrap());
+        assert_eq!(encode_char('╭', &options), encode('┌'));
+        assert_eq!(encode_char('━', &options), encode('─'));
+        assert_eq!(encode_char('⇒', &options), encode('→'));
+        assert_eq!(encode_char('Ã', &options), encode('A'));
+        assert_eq!(encode_char('—', &options), encode('-'));
+        // Exact glyphs are never approximated
+        assert_eq!(encode_char('é', &options), encode('é'));
+
+        options.approximate_glyphs = false;
+        assert_eq!(encode_char('╭', &options), u32::from(b'?'));
+    }
+
+    #[test]
+    fn test_codepages() {
+        assert_eq!(Codepage::Wingdings.decode(1), '☺');
+        assert_eq!(Codepage::Control.decode(1), '\u{1}');
+        assert_eq!(Codepage::Control.encode('☺'), None);
+        assert_eq!(Codepage::Wingdings.encode('é'), Some(0x82));
+
+        let custom = Codepage::Custom(vec!['.', '#', '@']);
+        assert_eq!(custom.decode(1), '#');
+        assert_eq!(custom.decode(200), ' ');
+        assert_eq!(custom.encode('@'), Some(2));
+
+        let options = RexOptions {
+            codepage: custom,
+            replacement: '.',
+            ..Default::default()
+        };
+        let xp = parse_ansi("#x@").to_rexpaint_with(&options);
+        let codes: Vec<u32> = xp.layers[0].cells.iter().map(|cell| cell.ch).collect();
+        assert_eq!(codes, vec![1, 0, 2]);
+
+        // Without a usable replacement, the codepage's own '?' or space is written
+        let options = RexOptions {
+            codepage: Codepage::Custom(vec!['.', ' ', '@']),
+            replacement: '字',
+            ..Default::default()
+        };
+        assert_eq!(encode_char('x', &options), 1);
+        let options = RexOptions {
+            codepage: Codepage::Custom(vec!['.', '?']),
+            ..options
+        };
+        assert_eq!(encode_char('x', &options), 1);
+
+        // Text output never gets control characters or glyphs a codepage lacks
+        assert_eq!(Codepage::Control.text_char(1), ' ');
+        assert_eq!(Codepage::Control.text_char(u32::from(b'A')), 'A');
+        assert_eq!(Codepage::Wingdings.text_char(1), '☺');
+        assert_eq!(Codepage::Wingdings.text_char(0), ' ');
+        assert_eq!(Codepage::Wingdings.text_char(300), '\u{fffd}');
+    }
+
+    #[test]
+    fn test_rexpaint_with_options() {
+        let styled_text = parse_ansi("abc\x1b[41md\x1b[0m\nsecond\nthird");
+        let options = RexOptions {
+            default_fg: Color::Rgb(1, 2, 3),
+            default_bg: Color::Rgb(4, 5, 6),
+            width: Some(4),
+            height: Some(2),
+            ..Default::default()
+        };
+        let xp = styled_text.to_rexpaint_with(&options);
+        let layer = &xp.layers[0];
+
+        assert_eq!((layer.width, layer.height), (4, 2));
+        let cell = layer.get(0, 0).unwrap();
+        assert_eq!(
+            (cell.fg, cell.bg),
+            (XpColor::new(1, 2, 3), XpColor::new(4, 5, 6))
+        );
+        assert_eq!(layer.get(3, 0).unwrap().bg, XpColor::new(128, 0, 0));
+        // "second" is clipped to the canvas width
+        assert_eq!(
+            layer.get(3, 1).unwrap().ch,
+            u32::from(CP437_WINGDINGS.encode('o').unwrap())
+        );
+    }
+
+    #[test]
+    fn test_rexpaint_transparent_background() {
+        let styled_text = parse_ansi("a\x1b[44mb\x1b[0m\nlonger");
+        let options = RexOptions {
+            transparent_background: true,
+            ..Default::default()
+        };
+        let xp = styled_text.to_rexpaint_with(&options);
+        let layer = &xp.layers[0];
+
+        assert!(layer.get(0, 0).unwrap().bg.is_transparent());
+        assert_eq!(layer.get(1, 0).unwrap().bg, XpColor::new(0, 0, 128));
+        // Padding past the end of the line
+        assert!(layer.get(5, 0).unwrap().bg.is_transparent());
+    }
+
+    #[test]
+    fn test_rexpaint_split_layers() {
+        let options = RexOptions {
+            split_layers: true,
+            ..Default::default()
+        };
+        let xp = parse_ansi("\x1b[31;44ma\x1b[0m ").to_rexpaint_with(&options);
+        assert_eq!(xp.layers.len(), 2);
+        let (background, glyphs) = (&xp.layers[0], &xp.layers[1]);
+
+        assert_eq!(background.get(0, 0).unwrap().ch, u32::from(b' '));
+        assert_eq!(background.get(0, 0).unwrap().bg, XpColor::new(0, 0, 128));
+        let glyph = glyphs.get(0, 0).unwrap();
+        assert_eq!(glyph.ch, u32::from(b'a'));
+        assert_eq!(glyph.fg, XpColor::new(128, 0, 0));
+        assert!(glyphs.get(1, 0).unwrap().bg.is_transparent());
+
+        // Compositing the layers gives back the single-layer image
+        let single = parse_ansi("\x1b[31;44ma\x1b[0m ").to_rexpaint();
+        let composite = composite_layers(&xp, &RexImportOptions::default()).unwrap();
+        assert_eq!(composite.cells, single.layers[0].cells);
+    }
+
+    #[test]
+    fn test_rexpaint_wrap() {
+        let options = RexOptions {
+            width: Some(3),
+            wrap: true,
+            ..Default::default()
+        };
+        let xp = parse_ansi("abcde日\nf").to_rexpaint_with(&options);
+        let layer = &xp.layers[0];
+        assert_eq!((layer.width, layer.height), (3, 4));
+
+        let row = |y| -> String {
+            (0..3)
+                .map(|x| CP437_WINGDINGS.decode(u8::try_from(layer.get(x, y).unwrap().ch).unwrap()))
+                .collect()
+        };
+        assert_eq!(row(0), "abc");
+        // The wide character moves to the next row rather than being split
+        assert_eq!(row(1), "de\0");
+        assert_eq!(row(2), "? \0");
+        assert_eq!(row(3), "f\0\0");
+    }
+
+    #[test]
+    fn test_rexpaint_transparent_cells_show_lower_layers() {
+        let options = RexOptions {
+            transparent_background: true,
+            ..Default::default()
+        };
+        let base = parse_ansi("\x1b[42ma\x1b[0m").to_rexpaint();
+        let top = parse_ansi("\x1b[8mx\x1b[0mYZ").to_rexpaint_with(&options);
+
+        let mut xp = base.clone();
+        xp.layers.push(top.layers[0].clone());
+        let layer = composite_layers(&xp, &RexImportOptions::default()).unwrap();
+
+        // Hidden text leaves the base layer visible
+        assert_eq!(layer.get(0, 0), base.layers[0].get(0, 0));
+        let covered = layer.get(1, 0).unwrap();
+        assert_eq!(covered.ch, u32::from(b'Y'));
+        assert!(covered.bg.is_transparent());
     }
 
     #[test]
@@ -294,7 +1261,7 @@ mod tests {
         let color = Color::Indexed(0); // black
         let xp_color = color_to_xp_color(&color);
         assert_eq!(xp_color, XpColor::new(0, 0, 0));
-        
+
         let color = Color::Indexed(15); // white
         let xp_color = color_to_xp_color(&color);
         assert_eq!(xp_color, XpColor::new(255, 255, 255));
@@ -0,0 +1,304 @@
+use crate::HtmlOptions;
+use crate::renderers::escape_xml;
+use error_stack::{Report, ResultExt};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::LazyLock;
+use wherror::Error;
+
+/// Matches the lines of a CI log that open or close a group or section, after the
+/// timestamp GitHub Actions puts on downloaded logs, with the title in group 3.
+static CI_MARKER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:\d{4}-\d\d-\d\dT\S*Z )?(?:::(group|endgroup)::|##\[(group|endgroup|section)\])(.*)$",
+    )
+    .unwrap()
+});
+
+/// Error raised when a pattern of a [`SectionMarker`] is not a valid regex.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct SectionMarkerError;
+
+/// Patterns marking collapsible sections in HTML output, for
+/// [`HtmlOptions::sections`](crate::HtmlOptions::sections).
+///
+/// Both patterns are regexes matched against the plain text of each line, compiled when
+/// the marker is created or deserialized so that rendering cannot fail on them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "SectionPatterns", into = "SectionPatterns")]
+pub struct SectionMarker {
+    start: Regex,
+    end: Option<Regex>,
+}
+
+/// The patterns of a [`SectionMarker`] as they are serialized.
+#[derive(Clone, Serialize, Deserialize)]
+struct SectionPatterns {
+    start: String,
+    #[serde(default)]
+    end: Option<String>,
+}
+
+impl SectionMarker {
+    /// Creates a marker for sections starting at lines matching `start`.
+    ///
+    /// The starting line becomes the summary of the section, which is named after the
+    /// `name` capture group, or the whole line if there is none. The section ends at the
+    /// line matching `end`, the last line inside it, or with `None` where the next one
+    /// from this marker starts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either pattern is not a valid regex.
+    pub fn new(start: &str, end: Option<&str>) -> Result<Self, Report<SectionMarkerError>> {
+        let compile = |pattern: &str| {
+            Regex::new(pattern)
+                .change_context(SectionMarkerError)
+                .attach_with(|| format!("invalid section pattern '{pattern}'"))
+        };
+        Ok(SectionMarker {
+            start: compile(start)?,
+            end: end.map(compile).transpose()?,
+        })
+    }
+
+    /// Returns the pattern of the line that starts a section.
+    #[must_use]
+    pub fn start(&self) -> &str {
+        self.start.as_str()
+    }
+
+    /// Returns the pattern of the line that ends a section, if it has one.
+    #[must_use]
+    pub fn end(&self) -> Option<&str> {
+        self.end.as_ref().map(Regex::as_str)
+    }
+}
+
+impl PartialEq for SectionMarker {
+    fn eq(&self, other: &Self) -> bool {
+        self.start() == other.start() && self.end() == other.end()
+    }
+}
+
+impl Eq for SectionMarker {}
+
+impl TryFrom<SectionPatterns> for SectionMarker {
+    type Error = regex::Error;
+
+    fn try_from(patterns: SectionPatterns) -> Result<Self, Self::Error> {
+        Ok(SectionMarker {
+            start: Regex::new(&patterns.start)?,
+            end: patterns.end.as_deref().map(Regex::new).transpose()?,
+        })
+    }
+}
+
+impl From<SectionMarker> for SectionPatterns {
+    fn from(marker: SectionMarker) -> Self {
+        SectionPatterns {
+            start: marker.start().to_string(),
+            end: marker.end().map(str::to_string),
+        }
+    }
+}
+
+/// How a collapsible section ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SectionEnd {
+    /// At an `endgroup` CI marker.
+    EndGroup,
+    /// At the next Azure Pipelines section.
+    NextCiSection,
+    /// At a line matching the end pattern of the marker with this index.
+    Pattern(usize),
+    /// At the next line matching the start pattern of the marker with this index.
+    NextStart(usize),
+}
+
+/// How a line is rendered, as decided by [`Sections::line`].
+pub(crate) enum SectionLine {
+    /// As usual.
+    Plain,
+    /// Not at all, with this markup in its place.
+    Replace(String),
+    /// After this markup, as the summary of a section, followed by `</summary>`.
+    Start(String),
+    /// As the last line of its section, followed by this markup.
+    End(String),
+}
+
+/// Tracks the collapsible sections of a text rendered line by line.
+pub(crate) struct Sections<'a> {
+    prefix: &'a str,
+    fold_groups: bool,
+    /// The markers of the sections that are not CI groups.
+    markers: &'a [SectionMarker],
+    /// How each open section ends, innermost last.
+    open: Vec<SectionEnd>,
+    /// The depth, id, and name of every section so far.
+    contents: Vec<(usize, String, String)>,
+}
+
+impl<'a> Sections<'a> {
+    /// Creates a tracker for the sections `options` asks for.
+    pub(crate) fn new(options: &'a HtmlOptions) -> Self {
+        Sections {
+            prefix: &options.class_prefix,
+            fold_groups: options.fold_groups,
+            markers: &options.sections,
+            open: Vec::new(),
+            contents: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if any kind of section is recognized.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.fold_groups || !self.markers.is_empty()
+    }
+
+    /// Decides how the line with plain text `text` is rendered, opening and closing
+    /// sections as it starts or ends them.
+    pub(crate) fn line(&mut self, text: &str) -> SectionLine {
+        let mut markup = String::new();
+        if self.fold_groups
+            && let Some(captures) = CI_MARKER_REGEX.captures(text)
+        {
+            let kind = captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .unwrap()
+                .as_str();
+            let title = captures[3].trim();
+            match kind {
+                "endgroup" => {
+                    if self.open.last() == Some(&SectionEnd::EndGroup) {
+                        self.close_from(&mut markup, self.open.len() - 1);
+                    }
+                    return SectionLine::Replace(markup);
+                }
+                "group" => self.open_section(&mut markup, kind, title, SectionEnd::EndGroup),
+                _ => {
+                    // Azure Pipelines ends each step with a `Finishing:` section, which only
+                    // closes the step's section
+                    self.close_through(&mut markup, SectionEnd::NextCiSection);
+                    if title.starts_with("Finishing:") {
+                        return SectionLine::Replace(markup);
+                    }
+                    self.open_section(&mut markup, kind, title, SectionEnd::NextCiSection);
+                }
+            }
+            escape_xml(title, &mut markup);
+            markup.push_str("</summary>");
+            return SectionLine::Replace(markup);
+        }
+
+        for (index, marker) in self.markers.iter().enumerate() {
+            let Some(captures) = marker.start.captures(text) else {
+                continue;
+            };
+            let name = captures
+                .name("name")
+                .map_or(text, |name| name.as_str())
+                .trim();
+            let name = name.to_string();
+            let section_end = if marker.end.is_some() {
+                SectionEnd::Pattern(index)
+            } else {
+                SectionEnd::NextStart(index)
+            };
+            self.close_through(&mut markup, SectionEnd::NextStart(index));
+            self.open_section(&mut markup, "section", &name, section_end);
+            return SectionLine::Start(markup);
+        }
+
+        let ended = self.open.iter().rposition(|end| match end {
+            SectionEnd::Pattern(index) => self.markers[*index]
+                .end
+                .as_ref()
+                .is_some_and(|end| end.is_match(text)),
+            _ => false,
+        });
+        match ended {
+            Some(pos) => {
+                self.close_from(&mut markup, pos);
+                SectionLine::End(markup)
+            }
+            None => SectionLine::Plain,
+        }
+    }
+
+    /// Returns the markup closing every section still open.
+    pub(crate) fn finish(&mut self) -> String {
+        let mut markup = String::new();
+        self.close_from(&mut markup, 0);
+        markup
+    }
+
+    /// Returns a `<nav>` element with nested lists linking to every section, or an empty
+    /// string if there are none.
+    pub(crate) fn table_of_contents(&self) -> String {
+        if self.contents.is_empty() {
+            return String::new();
+        }
+        let mut html = format!("<nav class=\"{}toc\" aria-label=\"Sections\">", self.prefix);
+        // The number of lists open
+        let mut depth = 0;
+        for (level, id, name) in &self.contents {
+            if level + 1 > depth {
+                while depth < level + 1 {
+                    html.push_str("<ol>");
+                    depth += 1;
+                }
+            } else {
+                html.push_str("</li>");
+                while depth > level + 1 {
+                    html.push_str("</ol></li>");
+                    depth -= 1;
+                }
+            }
+            write!(&mut html, "<li><a href=\"#{id}\">").unwrap();
+            escape_xml(name, &mut html);
+            html.push_str("</a>");
+        }
+        html.push_str("</li>");
+        while depth > 1 {
+            html.push_str("</ol></li>");
+            depth -= 1;
+        }
+        html.push_str("</ol></nav>");
+        html
+    }
+
+    /// Writes the opening of a section with class `kind` named `name` to `markup`, up to
+    /// and including its `<summary>` tag.
+    fn open_section(&mut self, markup: &mut String, kind: &str, name: &str, end: SectionEnd) {
+        let id = format!("{}section-{}", self.prefix, self.contents.len() + 1);
+        write!(
+            markup,
+            "<details id=\"{id}\" class=\"{}{kind}\"><summary>",
+            self.prefix
+        )
+        .unwrap();
+        self.contents.push((self.open.len(), id, name.to_string()));
+        self.open.push(end);
+    }
+
+    /// Closes the innermost open section ending with `end`, and the sections inside it,
+    /// if there is one.
+    fn close_through(&mut self, markup: &mut String, end: SectionEnd) {
+        if let Some(pos) = self.open.iter().rposition(|open| *open == end) {
+            self.close_from(markup, pos);
+        }
+    }
+
+    /// Closes the open sections from the one at `pos` inward.
+    fn close_from(&mut self, markup: &mut String, pos: usize) {
+        for _ in pos..self.open.len() {
+            markup.push_str("</details>");
+        }
+        self.open.truncate(pos);
+    }
+}
@@ -0,0 +1,427 @@
+use crate::cast::frame_durations;
+use crate::grid::{Cell, Grid};
+use crate::renderers::escape_xml;
+use crate::{Frame, Style, StyledText, Theme};
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+use std::fmt::Write;
+
+/// The advance width of a monospace glyph, relative to the font size.
+const GLYPH_ASPECT: f64 = 0.6;
+/// Seconds for blinking text or the cursor to go through one on and off cycle.
+const BLINK_PERIOD: f64 = 1.0;
+
+/// Options for rendering styled text as an SVG image.
+#[derive(Debug, Clone, PartialEq, SmartDefault, Serialize, Deserialize)]
+pub struct SvgOptions {
+    /// CSS font family list for the text.
+    #[default("ui-monospace, Menlo, Consolas, 'DejaVu Sans Mono', monospace".to_string())]
+    pub font_family: String,
+    /// Font size in pixels.
+    #[default(14.0)]
+    pub font_size: f64,
+    /// Line height, relative to the font size.
+    #[default(1.2)]
+    pub line_height: f64,
+    /// Space around the text in pixels.
+    #[default(16.0)]
+    pub padding: f64,
+    /// Page colors and palette. Defaults to the dark theme, like most terminals.
+    #[default(Theme::dark())]
+    pub theme: Theme,
+    /// Make blinking text and the cursor blink with SMIL animations. Otherwise blinking
+    /// text is drawn steadily and the cursor as a solid block.
+    pub animate_blink: bool,
+    /// Draw the cursor in this zero-based `(column, row)` cell, such as the final
+    /// [`Screen::cursor`](crate::Screen::cursor) of the session the text came from.
+    ///
+    /// Recordings rendered with [`frames_to_svg`] use the cursor of each frame instead.
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl StyledText {
+    /// Renders the styled text as an SVG image with the default [`SvgOptions`].
+    #[must_use]
+    pub fn to_svg(&self) -> String {
+        self.to_svg_with(&SvgOptions::default())
+    }
+
+    /// Renders the styled text as an SVG image, like a screenshot of a terminal.
+    ///
+    /// Text is laid out on a grid of cells, with wide characters taking two cells. Each
+    /// run of cells sharing a style becomes a `<text>` element stretched to exactly its
+    /// cells with `textLength`, so columns stay aligned whatever font the viewer picks.
+    /// Backgrounds are drawn as `<rect>` elements behind the text. The image is made large
+    /// enough to hold the cursor, if one is drawn.
+    #[must_use]
+    pub fn to_svg_with(&self, options: &SvgOptions) -> String {
+        let mut grid = Grid::from_styled(self);
+        if grid.trailing_newline && grid.rows.last().is_some_and(Vec::is_empty) {
+            grid.rows.pop();
+        }
+
+        let (mut columns, mut rows) = (grid.width(), grid.rows.len());
+        if let Some((column, row)) = options.cursor {
+            (columns, rows) = (columns.max(column + 1), rows.max(row + 1));
+        }
+        let mut svg = open_svg(columns, rows, options);
+        write_rows(&grid.rows, options, &mut svg);
+        if let Some(cursor) = options.cursor {
+            write_cursor(cursor, options, &mut svg);
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
+
+/// Renders the frames of a recording as an animated SVG image that loops forever.
+///
+/// Every frame is drawn like [`StyledText::to_svg_with`] on a canvas the size of the
+/// terminal, and SMIL `<animate>` elements show each one in turn for as long as the
+/// recording did. The cursor is drawn as a translucent block, which blinks if
+/// [`SvgOptions::animate_blink`] is set.
+#[must_use]
+pub fn frames_to_svg(frames: &[Frame], options: &SvgOptions) -> String {
+    let (columns, rows) = frames
+        .first()
+        .map_or((0, 0), |frame| (frame.width, frame.height));
+    let mut svg = open_svg(columns, rows, options);
+
+    let durations = frame_durations(frames);
+    let total: f64 = durations.iter().sum();
+    let mut start = 0.0;
+    for (frame, duration) in frames.iter().zip(durations) {
+        let (from, to) = (start / total, (start + duration) / total);
+        start += duration;
+
+        if frames.len() == 1 {
+            svg.push_str("<g>\n");
+        } else {
+            let (values, key_times) = if from <= 0.0 {
+                ("visible;hidden", format!("0;{}", key_time(to)))
+            } else if to >= 1.0 {
+                ("hidden;visible", format!("0;{}", key_time(from)))
+            } else {
+                (
+                    "hidden;visible;hidden",
+                    format!("0;{};{}", key_time(from), key_time(to)),
+                )
+            };
+            writeln!(
+                &mut svg,
+                "<g visibility=\"hidden\">\n<animate attributeName=\"visibility\" \
+                 values=\"{values}\" keyTimes=\"{key_times}\" dur=\"{}s\" \
+                 calcMode=\"discrete\" repeatCount=\"indefinite\"/>",
+                key_time(total)
+            )
+            .unwrap();
+        }
+
+        write_rows(&Grid::from_styled(&frame.text).rows, options, &mut svg);
+        if let Some(cursor) = frame.cursor {
+            write_cursor(cursor, options, &mut svg);
+        }
+        svg.push_str("</g>\n");
+    }
+
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// Starts an SVG document sized for `columns` by `rows` cells, with the page background
+/// drawn and a group setting the font left open.
+fn open_svg(columns: usize, rows: usize, options: &SvgOptions) -> String {
+    let cell_width = options.font_size * GLYPH_ASPECT;
+    let line_height = options.font_size * options.line_height;
+    #[allow(clippy::cast_precision_loss)]
+    let (width, height) = (
+        options.padding * 2.0 + cell_width * columns as f64,
+        options.padding * 2.0 + line_height * rows as f64,
+    );
+
+    let mut svg = String::new();
+    writeln!(
+        &mut svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\">",
+        w = px(width),
+        h = px(height)
+    )
+    .unwrap();
+    writeln!(
+        &mut svg,
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+        options.theme.background.to_hex()
+    )
+    .unwrap();
+    svg.push_str("<g font-family=\"");
+    escape_xml(&options.font_family, &mut svg);
+    writeln!(
+        &mut svg,
+        "\" font-size=\"{}\" style=\"white-space: pre\">",
+        px(options.font_size)
+    )
+    .unwrap();
+    svg
+}
+
+/// Draws the backgrounds and text of `rows` of cells, starting at the top left of the page.
+fn write_rows(rows: &[Vec<Cell>], options: &SvgOptions, svg: &mut String) {
+    let cell_width = options.font_size * GLYPH_ASPECT;
+    let line_height = options.font_size * options.line_height;
+    let theme = &options.theme;
+
+    for (row_idx, row) in rows.iter().enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let top = options.padding + line_height * row_idx as f64;
+        let baseline = top + (line_height - options.font_size) / 2.0 + options.font_size * 0.8;
+        for (start, cells) in style_runs(row) {
+            #[allow(clippy::cast_precision_loss)]
+            let (x, run_width) = (
+                options.padding + cell_width * start as f64,
+                cell_width * cells.len() as f64,
+            );
+            let style = &cells[0].style;
+            let (fg, bg) = colors(style, theme);
+
+            if let Some(bg) = bg {
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{bg}\"/>",
+                    px(x),
+                    px(top),
+                    px(run_width),
+                    px(line_height)
+                )
+                .unwrap();
+            }
+
+            let text: String = cells.iter().map(|cell| &*cell.text).collect();
+            if style.hidden || text.trim().is_empty() {
+                continue;
+            }
+            write!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" \
+                 fill=\"{fg}\"{}>",
+                px(x),
+                px(baseline),
+                px(run_width),
+                text_attributes(style)
+            )
+            .unwrap();
+            if style.blink && options.animate_blink {
+                svg.push_str(&blink_animation());
+            }
+            escape_xml(&text, svg);
+            svg.push_str("</text>\n");
+        }
+    }
+}
+
+/// Draws the cursor as a translucent block over the cell at `(column, row)`.
+fn write_cursor((column, row): (usize, usize), options: &SvgOptions, svg: &mut String) {
+    let cell_width = options.font_size * GLYPH_ASPECT;
+    let line_height = options.font_size * options.line_height;
+    #[allow(clippy::cast_precision_loss)]
+    let (x, y) = (
+        options.padding + cell_width * column as f64,
+        options.padding + line_height * row as f64,
+    );
+    write!(
+        svg,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" opacity=\"0.5\"",
+        px(x),
+        px(y),
+        px(cell_width),
+        px(line_height),
+        options.theme.foreground.to_hex()
+    )
+    .unwrap();
+    if options.animate_blink {
+        writeln!(svg, ">{}</rect>", blink_animation()).unwrap();
+    } else {
+        svg.push_str("/>\n");
+    }
+}
+
+/// Returns an `<animate>` element that hides its parent for the second half of every
+/// blink period.
+fn blink_animation() -> String {
+    format!(
+        "<animate attributeName=\"visibility\" values=\"visible;hidden\" keyTimes=\"0;0.5\" \
+         dur=\"{}s\" calcMode=\"discrete\" repeatCount=\"indefinite\"/>",
+        key_time(BLINK_PERIOD)
+    )
+}
+
+/// Splits a row into runs of cells that share a style, with the column each run starts at.
+fn style_runs(row: &[Cell]) -> Vec<(usize, &[Cell])> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for idx in 1..=row.len() {
+        if idx == row.len() || row[idx].style != row[start].style {
+            runs.push((start, &row[start..idx]));
+            start = idx;
+        }
+    }
+    runs
+}
+
+/// Returns the foreground color and, if it differs from the page, the background color for
+/// `style` under `theme`.
+fn colors(style: &Style, theme: &Theme) -> (String, Option<String>) {
+    let fg = style
+        .fg_color
+        .as_ref()
+        .map_or_else(|| theme.foreground.clone(), |color| theme.resolve(color));
+    let bg = style.bg_color.as_ref().map(|color| theme.resolve(color));
+    if style.reverse {
+        let bg = bg.unwrap_or_else(|| theme.background.clone());
+        (bg.to_hex(), Some(fg.to_hex()))
+    } else {
+        (fg.to_hex(), bg.map(|color| color.to_hex()))
+    }
+}
+
+/// Returns the presentation attributes for the text attributes of `style`.
+fn text_attributes(style: &Style) -> String {
+    let mut attributes = String::new();
+    if style.bold {
+        attributes.push_str(" font-weight=\"bold\"");
+    }
+    if style.italic {
+        attributes.push_str(" font-style=\"italic\"");
+    }
+    if style.dim {
+        attributes.push_str(" opacity=\"0.5\"");
+    }
+    let decorations: Vec<&str> = [
+        (style.underline, "underline"),
+        (style.strikethrough, "line-through"),
+    ]
+    .iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, name)| *name)
+    .collect();
+    if !decorations.is_empty() {
+        write!(attributes, " text-decoration=\"{}\"", decorations.join(" ")).unwrap();
+    }
+    attributes
+}
+
+/// Formats a length in pixels, rounded to two decimal places.
+fn px(value: f64) -> String {
+    format!("{}", (value * 100.0).round() / 100.0)
+}
+
+/// Formats a fraction of an animation or a time in seconds, rounded to four decimal places.
+fn key_time(value: f64) -> String {
+    format!("{}", (value * 10000.0).round() / 10000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_svg_layout() {
+        let svg = parse_ansi("ab\x1b[1;31;44mc\x1b[0m\n日\n").to_svg();
+        // 3 columns of 8.4px and 2 lines of 16.8px, plus 16px of padding on each side
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"57.2\" height=\"65.6\" \
+             viewBox=\"0 0 57.2 65.6\">\n<rect width=\"100%\" height=\"100%\" fill=\"#1e1e1e\"/>"
+        ));
+        assert!(svg.contains(
+            "<text x=\"16\" y=\"28.6\" textLength=\"16.8\" lengthAdjust=\"spacingAndGlyphs\" \
+             fill=\"#cccccc\">ab</text>"
+        ));
+        assert!(svg.contains(
+            "<rect x=\"32.8\" y=\"16\" width=\"8.4\" height=\"16.8\" fill=\"#2472c8\"/>"
+        ));
+        assert!(svg.contains("fill=\"#cd3131\" font-weight=\"bold\">c</text>"));
+        assert!(svg.contains(
+            "textLength=\"16.8\" lengthAdjust=\"spacingAndGlyphs\" fill=\"#cccccc\">日</text>"
+        ));
+    }
+
+    #[test]
+    fn test_svg_reverse_and_escaping() {
+        let options = SvgOptions {
+            font_family: "A&B".to_string(),
+            theme: Theme::light(),
+            ..Default::default()
+        };
+        let svg = parse_ansi("\x1b[7m<\x1b[0m").to_svg_with(&options);
+        assert!(svg.contains("font-family=\"A&amp;B\""));
+        assert!(svg.contains("fill=\"#000000\"/>"));
+        assert!(svg.contains("fill=\"#ffffff\">&lt;</text>"));
+    }
+
+    #[test]
+    fn test_frames_to_svg() {
+        let frame = |time, text| Frame {
+            time,
+            text: parse_ansi(text),
+            width: 4,
+            height: 2,
+            cursor: None,
+        };
+        let frames = [
+            frame(0.0, ""),
+            frame(1.0, "a"),
+            Frame {
+                cursor: Some((1, 0)),
+                ..frame(2.0, "ab")
+            },
+        ];
+        let svg = frames_to_svg(&frames, &SvgOptions::default());
+        // The canvas fits the terminal, and the frames share a 4 second loop: 1 second each
+        // and 2 seconds on the last one
+        assert!(svg.contains("width=\"65.6\" height=\"65.6\""));
+        assert!(svg.contains("values=\"visible;hidden\" keyTimes=\"0;0.25\" dur=\"4s\""));
+        assert!(svg.contains("values=\"hidden;visible;hidden\" keyTimes=\"0;0.25;0.5\""));
+        assert!(svg.contains("values=\"hidden;visible\" keyTimes=\"0;0.5\""));
+        assert!(svg.contains(
+            "<rect x=\"24.4\" y=\"16\" width=\"8.4\" height=\"16.8\" fill=\"#cccccc\" \
+             opacity=\"0.5\"/>"
+        ));
+        assert!(!frames_to_svg(&frames[..1], &SvgOptions::default()).contains("<animate"));
+    }
+
+    #[test]
+    fn test_svg_blink_and_cursor() {
+        let text = parse_ansi("\x1b[5mblink\x1b[0m\n");
+        let steady = text.to_svg_with(&SvgOptions {
+            cursor: Some((6, 1)),
+            ..Default::default()
+        });
+        assert!(!steady.contains("<animate"));
+        // The image grows to fit the cursor: 7 columns and 2 rows
+        assert!(steady.contains("width=\"90.8\" height=\"65.6\""));
+        assert!(steady.contains(
+            "<rect x=\"66.4\" y=\"32.8\" width=\"8.4\" height=\"16.8\" fill=\"#cccccc\" \
+             opacity=\"0.5\"/>"
+        ));
+
+        let animated = text.to_svg_with(&SvgOptions {
+            animate_blink: true,
+            cursor: Some((6, 1)),
+            ..Default::default()
+        });
+        let animation = "<animate attributeName=\"visibility\" values=\"visible;hidden\" \
+                         keyTimes=\"0;0.5\" dur=\"1s\" calcMode=\"discrete\" \
+                         repeatCount=\"indefinite\"/>";
+        assert!(animated.contains(&format!("fill=\"#cccccc\">{animation}blink</text>")));
+        assert!(animated.contains(&format!("opacity=\"0.5\">{animation}</rect>")));
+        assert!(
+            !parse_ansi("steady")
+                .to_svg_with(&SvgOptions {
+                    animate_blink: true,
+                    ..Default::default()
+                })
+                .contains("<animate")
+        );
+    }
+}
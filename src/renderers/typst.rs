@@ -0,0 +1,104 @@
+use crate::StyledText;
+use std::fmt::Write;
+
+impl StyledText {
+    /// Converts the styled text to Typst markup.
+    ///
+    /// The result is a `#block` set in a monospace font in which every segment is emitted
+    /// as a string literal, so whitespace is preserved and no Typst markup characters need
+    /// escaping. Styled segments are wrapped in `text(fill: rgb(...))` and, where needed,
+    /// `highlight`, `underline`, `strike`, and `hide`.
+    ///
+    /// Blink has no Typst equivalent and is ignored.
+    #[must_use]
+    pub fn to_typst(&self) -> String {
+        let mut output = String::from("#block[#set text(font: \"DejaVu Sans Mono\");");
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            let style = &segment.style;
+            let (fg_color, bg_color) = if style.reverse {
+                (style.bg_color.as_ref(), style.fg_color.as_ref())
+            } else {
+                (style.fg_color.as_ref(), style.bg_color.as_ref())
+            };
+
+            let mut arguments = Vec::new();
+            if let Some(color) = fg_color {
+                let alpha = if style.dim { "80" } else { "" };
+                arguments.push(format!("fill: rgb(\"{}{alpha}\")", color.to_hex()));
+            }
+            if style.bold {
+                arguments.push("weight: \"bold\"".to_string());
+            }
+            if style.italic {
+                arguments.push("style: \"italic\"".to_string());
+            }
+
+            let mut expression = typst_string(&segment.text);
+            if !arguments.is_empty() {
+                expression = format!("text({}, {expression})", arguments.join(", "));
+            }
+            if style.underline {
+                expression = format!("underline({expression})");
+            }
+            if style.strikethrough {
+                expression = format!("strike({expression})");
+            }
+            if let Some(color) = bg_color {
+                expression = format!("highlight(fill: rgb(\"{}\"), {expression})", color.to_hex());
+            }
+            if style.hidden {
+                expression = format!("hide({expression})");
+            }
+
+            write!(&mut output, "#{expression};").unwrap();
+        }
+
+        output.push(']');
+        output
+    }
+}
+
+/// Formats `text` as a Typst string literal.
+fn typst_string(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\r' => literal.push_str("\\r"),
+            _ => literal.push(ch),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_typst_plain_text() {
+        let parsed = parse_ansi("say \"hi\"\n#done");
+        assert_eq!(
+            parsed.to_typst(),
+            "#block[#set text(font: \"DejaVu Sans Mono\");#\"say \\\"hi\\\"\\n#done\";]"
+        );
+    }
+
+    #[test]
+    fn test_typst_styles() {
+        let parsed = parse_ansi("\x1b[1;4;31;44mX\x1b[0m");
+        assert_eq!(
+            parsed.to_typst(),
+            "#block[#set text(font: \"DejaVu Sans Mono\");#highlight(fill: rgb(\"#000080\"), underline(text(fill: rgb(\"#800000\"), weight: \"bold\", \"X\")));]"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::StyledText;
+use crate::renderers::escape_xml;
+use std::fmt::Write;
+
+impl StyledText {
+    /// Converts the styled text to a simple XML document with one element per segment.
+    ///
+    /// Each `<segment>` carries its colors as `fg` and `bg` hex attributes and its enabled
+    /// text attributes as `bold="true"`, `underline="true"`, and so on; attributes for
+    /// default colors and disabled styles are omitted. Segment text, including newlines,
+    /// is kept verbatim, while the whitespace between elements can be stripped.
+    ///
+    /// ```xml
+    /// <?xml version="1.0" encoding="UTF-8"?>
+    /// <styled-text>
+    /// <segment fg="#800000" bold="true">Error:</segment>
+    /// <segment> something failed
+    /// </segment>
+    /// </styled-text>
+    /// ```
+    #[must_use]
+    pub fn to_xml(&self) -> String {
+        let mut output =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<styled-text>\n");
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            let style = &segment.style;
+            output.push_str("<segment");
+            if let Some(color) = &style.fg_color {
+                write!(&mut output, " fg=\"{}\"", color.to_hex()).unwrap();
+            }
+            if let Some(color) = &style.bg_color {
+                write!(&mut output, " bg=\"{}\"", color.to_hex()).unwrap();
+            }
+            for (enabled, name) in [
+                (style.bold, "bold"),
+                (style.dim, "dim"),
+                (style.italic, "italic"),
+                (style.underline, "underline"),
+                (style.blink, "blink"),
+                (style.reverse, "reverse"),
+                (style.hidden, "hidden"),
+                (style.strikethrough, "strikethrough"),
+            ] {
+                if enabled {
+                    write!(&mut output, " {name}=\"true\"").unwrap();
+                }
+            }
+            output.push('>');
+            escape_xml(&segment.text, &mut output);
+            output.push_str("</segment>\n");
+        }
+
+        output.push_str("</styled-text>\n");
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_xml_segments() {
+        let parsed = parse_ansi("\x1b[1;31mError:\x1b[0m a < b\n");
+        assert_eq!(
+            parsed.to_xml(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<styled-text>\n\
+             <segment fg=\"#800000\" bold=\"true\">Error:</segment>\n\
+             <segment> a &lt; b\n</segment>\n\
+             </styled-text>\n"
+        );
+    }
+
+    #[test]
+    fn test_xml_empty() {
+        let parsed = parse_ansi("");
+        assert_eq!(
+            parsed.to_xml(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<styled-text>\n</styled-text>\n"
+        );
+    }
+}
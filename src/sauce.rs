@@ -0,0 +1,183 @@
+use codepage_437::{CP437_CONTROL, FromCp437};
+use serde::{Deserialize, Serialize};
+
+/// Size in bytes of a SAUCE record.
+const RECORD_LEN: usize = 128;
+/// Size in bytes of a SAUCE comment line.
+const COMMENT_LEN: usize = 64;
+
+/// The SAUCE record that DOS-era art tools append to a file to describe it.
+///
+/// The record follows the art, an end-of-file character (`0x1A`), and an optional comment
+/// block. Text fields are decoded from CP437 with trailing padding removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sauce {
+    /// Title of the work.
+    pub title: String,
+    /// Name or handle of the artist.
+    pub author: String,
+    /// Group or company the artist belongs to.
+    pub group: String,
+    /// Creation date as `CCYYMMDD`.
+    pub date: String,
+    /// Size of the art without the SAUCE record and comments, in bytes.
+    pub file_size: u32,
+    /// Kind of data, such as 1 for character art or 5 for binary text.
+    pub data_type: u8,
+    /// Format of the data within its kind, such as 1 for ANSI.
+    pub file_type: u8,
+    /// Type-dependent numbers, such as the width and height of character art.
+    pub t_info: [u16; 4],
+    /// Lines of the comment block.
+    pub comments: Vec<String>,
+    /// Type-dependent flags, such as iCE colors for character art.
+    pub flags: u8,
+    /// Name of the font the art was drawn in, such as `IBM VGA`.
+    pub font: String,
+}
+
+impl Sauce {
+    /// Reads the SAUCE record at the end of `data`, or returns `None` if there is none.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Sauce> {
+        let start = data.len().checked_sub(RECORD_LEN)?;
+        let record = &data[start..];
+        if !record.starts_with(b"SAUCE") {
+            return None;
+        }
+        let u16_at = |idx: usize| u16::from_le_bytes([record[idx], record[idx + 1]]);
+
+        let comment_lines = usize::from(record[104]);
+        let comments = start
+            .checked_sub(comment_lines * COMMENT_LEN + 5)
+            .map(|comment_start| &data[comment_start..start])
+            .filter(|block| comment_lines > 0 && block.starts_with(b"COMNT"))
+            .map(|block| block[5..].chunks(COMMENT_LEN).map(text).collect())
+            .unwrap_or_default();
+
+        Some(Sauce {
+            title: text(&record[7..42]),
+            author: text(&record[42..62]),
+            group: text(&record[62..82]),
+            date: text(&record[82..90]),
+            file_size: u32::from_le_bytes([record[90], record[91], record[92], record[93]]),
+            data_type: record[94],
+            file_type: record[95],
+            t_info: [u16_at(96), u16_at(98), u16_at(100), u16_at(102)],
+            comments,
+            flags: record[105],
+            font: text(&record[106..128]),
+        })
+    }
+
+    /// Returns the part of `data` before its SAUCE record, comments, and end-of-file
+    /// character, which is the art itself.
+    #[must_use]
+    pub fn strip(data: &[u8]) -> &[u8] {
+        let data = Sauce::strip_record(data);
+        let end = data
+            .iter()
+            .position(|&byte| byte == 0x1a)
+            .unwrap_or(data.len());
+        &data[..end]
+    }
+
+    /// Returns the part of `data` before its SAUCE record and comments, and the
+    /// end-of-file character just before them.
+    ///
+    /// Unlike [`Sauce::strip`], other `0x1A` bytes are kept, for binary formats where they
+    /// are part of the art.
+    #[must_use]
+    pub fn strip_record(data: &[u8]) -> &[u8] {
+        let end = match Sauce::parse(data) {
+            Some(sauce) => {
+                let comments = if sauce.comments.is_empty() {
+                    0
+                } else {
+                    sauce.comments.len() * COMMENT_LEN + 5
+                };
+                data.len() - RECORD_LEN - comments
+            }
+            None => data.len(),
+        };
+        let data = &data[..end];
+        data.strip_suffix(b"\x1a").unwrap_or(data)
+    }
+
+    /// Returns the width in columns the art was drawn for, if the record gives one.
+    ///
+    /// Character art stores its width in the first type-dependent number, and binary text
+    /// stores half its width in the file type.
+    #[must_use]
+    pub fn width(&self) -> Option<usize> {
+        let width = match (self.data_type, self.file_type) {
+            (1, 0 | 1 | 2 | 4 | 5 | 8) | (6, _) => usize::from(self.t_info[0]),
+            (5, half_width) => usize::from(half_width) * 2,
+            _ => 0,
+        };
+        (width > 0).then_some(width)
+    }
+
+    /// Returns the height in lines of the art, if the record gives one.
+    #[must_use]
+    pub fn height(&self) -> Option<usize> {
+        let height = match (self.data_type, self.file_type) {
+            (1, 0 | 1 | 2 | 4 | 5 | 8) | (6, _) => usize::from(self.t_info[1]),
+            _ => 0,
+        };
+        (height > 0).then_some(height)
+    }
+
+    /// Returns `true` if the art uses iCE colors, where blinking text is shown with a
+    /// bright background instead.
+    #[must_use]
+    pub fn ice_colors(&self) -> bool {
+        matches!(self.data_type, 1 | 5 | 6) && self.flags & 1 != 0
+    }
+}
+
+/// Decodes a CP437 text field, removing the spaces and NULs it is padded with.
+fn text(field: &[u8]) -> String {
+    String::from_cp437(field.to_vec(), &CP437_CONTROL)
+        .trim_end_matches([' ', '\0'])
+        .to_string()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a SAUCE record for ANSI art of the given size, with one comment line.
+    pub(crate) fn sauce_record(width: u16, height: u16, flags: u8) -> Vec<u8> {
+        let mut comment = b"COMNT".to_vec();
+        comment.extend(format!("{:<64}", "hello").bytes());
+        let mut record = b"SAUCE00".to_vec();
+        record.extend(format!("{:<35}{:<20}{:<20}19950101", "Title", "Artist", "Group").bytes());
+        record.extend(0u32.to_le_bytes());
+        record.extend([1, 1]);
+        for info in [width, height, 0, 0] {
+            record.extend(info.to_le_bytes());
+        }
+        record.extend([1, flags]);
+        record.extend(format!("{:\0<22}", "IBM VGA").bytes());
+        [b"\x1a".to_vec(), comment, record].concat()
+    }
+
+    #[test]
+    fn test_parse_sauce() {
+        let data = [b"art".to_vec(), sauce_record(40, 2, 1)].concat();
+        let sauce = Sauce::parse(&data).unwrap();
+        assert_eq!(sauce.title, "Title");
+        assert_eq!(sauce.author, "Artist");
+        assert_eq!(sauce.date, "19950101");
+        assert_eq!(sauce.comments, vec!["hello".to_string()]);
+        assert_eq!(sauce.font, "IBM VGA");
+        assert_eq!((sauce.width(), sauce.height()), (Some(40), Some(2)));
+        assert!(sauce.ice_colors());
+        assert_eq!(Sauce::strip(&data), b"art");
+        assert_eq!(Sauce::parse(b"no record"), None);
+        assert_eq!(Sauce::strip(b"art\x1ajunk"), b"art");
+        let binary = [b"\x1a\x07".to_vec(), sauce_record(1, 1, 0)].concat();
+        assert_eq!(Sauce::strip_record(&binary), b"\x1a\x07");
+    }
+}
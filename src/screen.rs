@@ -0,0 +1,671 @@
+use crate::grid::Cell;
+use crate::width::grapheme_width;
+use crate::{OutOfRangePolicy, Segment, Style, StyledText, apply_sgr};
+use std::borrow::Cow;
+use std::mem;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The most columns a screen has, however wide it is asked to be.
+const MAX_WIDTH: usize = 1000;
+
+/// The most cells a screen holds. A canvas grows until it reaches this many, after which
+/// it scrolls like a screen, so a cursor movement far down or a huge size in a header
+/// cannot allocate without limit.
+const MAX_CELLS: usize = 4_000_000;
+
+/// The longest cut-off escape sequence kept for the next feed. Past this, the sequence is
+/// taken to be unterminated and what follows its introducer is shown as text.
+const MAX_PENDING: usize = 4096;
+
+/// A virtual terminal screen that interprets output the way a terminal would.
+///
+/// Unlike [`parse_ansi`](crate::parse_ansi), which only understands SGR sequences, a screen
+/// follows cursor movement, erasing, scrolling, and the alternate screen, so the output of
+/// interactive programs such as progress bars and full-screen tools ends up as it was
+/// displayed. Text wraps at the right edge and scrolls off the top.
+///
+/// Sequences split across calls to [`Screen::feed`] are handled, so output can be fed in
+/// the chunks it was produced in. A sequence still unfinished after 4 KiB is taken to be
+/// unterminated, and what follows its introducer is shown as text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screen {
+    width: usize,
+    height: usize,
+    rows: Vec<Vec<Cell>>,
+    cursor_x: usize,
+    cursor_y: usize,
+    /// Set after writing to the last column; the next character wraps to a new line first.
+    pending_wrap: bool,
+    style: Style,
+    saved_cursor: (usize, usize),
+    cursor_visible: bool,
+    /// First and last rows of the scrolling region.
+    scroll_top: usize,
+    scroll_bottom: usize,
+    /// The main screen's rows and cursor while the alternate screen is shown.
+    saved_main: Option<(Vec<Vec<Cell>>, usize, usize)>,
+    /// The start of an escape sequence cut off at the end of the last feed.
+    pending: String,
+    /// Whether rows are added at the bottom instead of scrolling, as on a canvas.
+    grows: bool,
+}
+
+impl Screen {
+    /// Creates a blank screen of `width` columns and `height` rows, each at least 1.
+    ///
+    /// Screens are at most 1000 columns wide and 4 million cells in all, so larger sizes
+    /// are cut down to fit.
+    #[must_use]
+    pub fn new(width: usize, height: usize) -> Self {
+        let width = width.clamp(1, MAX_WIDTH);
+        let height = height.clamp(1, MAX_CELLS / width);
+        Screen {
+            width,
+            height,
+            rows: vec![vec![Cell::blank(); width]; height],
+            cursor_x: 0,
+            cursor_y: 0,
+            pending_wrap: false,
+            style: Style::default(),
+            saved_cursor: (0, 0),
+            cursor_visible: true,
+            scroll_top: 0,
+            scroll_bottom: height - 1,
+            saved_main: None,
+            pending: String::new(),
+            grows: false,
+        }
+    }
+
+    /// Creates a canvas of `width` columns that gets taller as text is written below its
+    /// last row instead of scrolling, for laying out art drawn for a fixed width.
+    #[must_use]
+    pub fn canvas(width: usize) -> Self {
+        Screen {
+            grows: true,
+            ..Screen::new(width, 1)
+        }
+    }
+
+    /// The width of the screen in columns.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the screen in rows.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The cursor position as a zero-based `(column, row)`.
+    #[must_use]
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_x, self.cursor_y)
+    }
+
+    /// Whether the cursor is shown, as set by `ESC [ ? 25 h` and `ESC [ ? 25 l`.
+    #[must_use]
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Interprets `data` as terminal output.
+    pub fn feed(&mut self, data: &str) {
+        let input = mem::take(&mut self.pending) + data;
+        let mut idx = 0;
+        while let Some(ch) = input[idx..].chars().next() {
+            let rest = &input[idx..];
+            match ch {
+                '\x1b' => {
+                    let Some(length) = escape_length(rest) else {
+                        if rest.len() <= MAX_PENDING {
+                            self.pending = rest.to_string();
+                            return;
+                        }
+                        idx += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+                        continue;
+                    };
+                    self.escape(&rest[..length]);
+                    idx += length;
+                }
+                '\n' | '\x0b' | '\x0c' => {
+                    self.line_feed();
+                    idx += 1;
+                }
+                '\r' => {
+                    self.move_to(0, self.cursor_y);
+                    idx += 1;
+                }
+                '\x08' => {
+                    self.move_to(self.cursor_x.saturating_sub(1), self.cursor_y);
+                    idx += 1;
+                }
+                '\t' => {
+                    let next_stop = (self.cursor_x / 8 + 1) * 8;
+                    self.move_to(next_stop.min(self.width - 1), self.cursor_y);
+                    idx += 1;
+                }
+                ch if ch.is_control() => idx += ch.len_utf8(),
+                _ => {
+                    let end = rest.find(|ch: char| ch.is_control()).unwrap_or(rest.len());
+                    for grapheme in rest[..end].graphemes(true) {
+                        self.print(grapheme);
+                    }
+                    idx += end;
+                }
+            }
+        }
+    }
+
+    /// Returns the contents of the screen as styled text, one line per row.
+    ///
+    /// Blank cells at the end of each row and blank rows at the bottom of the screen are
+    /// left out. Every row ends with a newline.
+    #[must_use]
+    pub fn to_styled_text(&self) -> StyledText {
+        let blank = Cell::blank();
+        let used_rows = self
+            .rows
+            .iter()
+            .rposition(|row| row.iter().any(|cell| *cell != blank))
+            .map_or(0, |idx| idx + 1);
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut push = |text: &str, style: &Style| match segments.last_mut() {
+            Some(last) if last.style == *style => last.text.push_str(text),
+            _ => segments.push(Segment {
+                text: text.to_string(),
+                style: style.clone(),
+                source: None,
+                escapes: None,
+            }),
+        };
+        for row in &self.rows[..used_rows] {
+            let used = row
+                .iter()
+                .rposition(|cell| *cell != blank)
+                .map_or(0, |idx| idx + 1);
+            for cell in &row[..used] {
+                push(&cell.text, &cell.style);
+            }
+            push("\n", &Style::default());
+        }
+        StyledText { segments }
+    }
+
+    /// Writes a grapheme at the cursor and advances it.
+    fn print(&mut self, grapheme: &str) {
+        let columns = grapheme_width(grapheme).min(self.width);
+        if columns == 0 {
+            // Combining marks join the grapheme before the cursor
+            let x = if self.pending_wrap {
+                self.cursor_x
+            } else {
+                self.cursor_x.saturating_sub(1)
+            };
+            let row = &mut self.rows[self.cursor_y];
+            let x = if row[x].is_continuation() {
+                x.saturating_sub(1)
+            } else {
+                x
+            };
+            row[x].text.to_mut().push_str(grapheme);
+            return;
+        }
+
+        if self.pending_wrap || self.cursor_x + columns > self.width {
+            self.pending_wrap = false;
+            self.cursor_x = 0;
+            self.line_feed();
+        }
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        for column in x..x + columns {
+            self.split_wide(column, y);
+        }
+        self.rows[y][x] = Cell {
+            text: Cow::Owned(grapheme.to_string()),
+            style: self.style.clone(),
+        };
+        for column in x + 1..x + columns {
+            self.rows[y][column] = Cell {
+                text: Cow::Borrowed(""),
+                style: self.style.clone(),
+            };
+        }
+
+        if x + columns >= self.width {
+            self.cursor_x = self.width - 1;
+            self.pending_wrap = true;
+        } else {
+            self.cursor_x = x + columns;
+        }
+    }
+
+    /// Blanks the rest of a wide grapheme that is partly overwritten at (`x`, `y`).
+    fn split_wide(&mut self, x: usize, y: usize) {
+        let row = &mut self.rows[y];
+        if row[x].is_continuation() && x > 0 {
+            row[x - 1] = Cell::blank();
+        }
+        let mut next = x + 1;
+        while next < row.len() && row[next].is_continuation() {
+            row[next] = Cell::blank();
+            next += 1;
+        }
+    }
+
+    /// Moves the cursor down a row, scrolling the region up at its bottom edge.
+    fn line_feed(&mut self) {
+        self.pending_wrap = false;
+        if self.grows && self.cursor_y + 1 == self.height && self.height < self.max_rows() {
+            self.grow_to(self.height + 1);
+            self.cursor_y += 1;
+        } else if self.cursor_y == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor_y + 1 < self.height {
+            self.cursor_y += 1;
+        }
+    }
+
+    /// Moves the cursor up a row, scrolling the region down at its top edge.
+    fn reverse_line_feed(&mut self) {
+        self.pending_wrap = false;
+        if self.cursor_y == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.cursor_y = self.cursor_y.saturating_sub(1);
+        }
+    }
+
+    /// Moves the cursor, keeping it on the screen.
+    fn move_to(&mut self, x: usize, y: usize) {
+        if self.grows && y >= self.height {
+            self.grow_to(y.saturating_add(1).min(self.max_rows()));
+        }
+        self.cursor_x = x.min(self.width - 1);
+        self.cursor_y = y.min(self.height - 1);
+        self.pending_wrap = false;
+    }
+
+    /// The most rows a canvas grows to.
+    fn max_rows(&self) -> usize {
+        MAX_CELLS / self.width
+    }
+
+    /// Adds blank rows to a canvas until it is `height` rows tall.
+    fn grow_to(&mut self, height: usize) {
+        if self.scroll_bottom + 1 == self.height {
+            self.scroll_bottom = height - 1;
+        }
+        self.rows.resize(height, vec![Cell::blank(); self.width]);
+        self.height = height;
+    }
+
+    /// A blank cell in the current background color, as left by erasing.
+    fn erased(&self) -> Cell {
+        Cell {
+            text: Cow::Borrowed(" "),
+            style: Style {
+                bg_color: self.style.bg_color.clone(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Scrolls the scrolling region up by `count` rows, adding blank rows at the bottom.
+    fn scroll_up(&mut self, count: usize) {
+        let count = count.min(self.scroll_bottom - self.scroll_top + 1);
+        let blank = vec![self.erased(); self.width];
+        self.rows
+            .splice(self.scroll_top..self.scroll_top + count, []);
+        for _ in 0..count {
+            self.rows
+                .insert(self.scroll_bottom + 1 - count, blank.clone());
+        }
+    }
+
+    /// Scrolls the scrolling region down by `count` rows, adding blank rows at the top.
+    fn scroll_down(&mut self, count: usize) {
+        let count = count.min(self.scroll_bottom - self.scroll_top + 1);
+        let blank = vec![self.erased(); self.width];
+        self.rows
+            .splice(self.scroll_bottom + 1 - count..=self.scroll_bottom, []);
+        for _ in 0..count {
+            self.rows.insert(self.scroll_top, blank.clone());
+        }
+    }
+
+    /// Erases the cells of row `y` in `columns`.
+    fn erase(&mut self, y: usize, columns: std::ops::Range<usize>) {
+        let erased = self.erased();
+        for cell in &mut self.rows[y][columns] {
+            *cell = erased.clone();
+        }
+    }
+
+    /// Handles a complete escape sequence.
+    fn escape(&mut self, sequence: &str) {
+        let mut chars = sequence[1..].chars();
+        match chars.next() {
+            Some('[') => self.control_sequence(&sequence[2..]),
+            Some('7') => self.saved_cursor = (self.cursor_x, self.cursor_y),
+            Some('8') => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            Some('D') => self.line_feed(),
+            Some('E') => {
+                self.cursor_x = 0;
+                self.line_feed();
+            }
+            Some('M') => self.reverse_line_feed(),
+            Some('c') if self.grows => *self = Screen::canvas(self.width),
+            Some('c') => *self = Screen::new(self.width, self.height),
+            // OSC, DCS, and charset designations have no effect on the screen
+            _ => {}
+        }
+    }
+
+    /// Handles a CSI sequence, given everything after `ESC [`.
+    #[allow(clippy::too_many_lines)]
+    fn control_sequence(&mut self, body: &str) {
+        let Some(action) = body.chars().last() else {
+            return;
+        };
+        let body = &body[..body.len() - action.len_utf8()];
+        let (private, body) = match body.strip_prefix(['?', '>', '=', '<']) {
+            Some(rest) => (body.chars().next(), rest),
+            None => (None, body),
+        };
+        let params: Vec<usize> = body
+            .split(';')
+            .map(|param| param.parse().unwrap_or(0))
+            .collect();
+        let param = |idx: usize| params.get(idx).copied().unwrap_or(0);
+        // Movement counts treat 0 and a missing parameter as 1
+        let count = param(0).max(1);
+        let (x, y) = (self.cursor_x, self.cursor_y);
+
+        if private == Some('?') {
+            if matches!(action, 'h' | 'l') {
+                self.set_private_modes(&params, action == 'h');
+            }
+            return;
+        }
+        if private.is_some() {
+            return;
+        }
+
+        match action {
+            'm' => {
+                let params: Vec<u32> = if body.is_empty() {
+                    vec![0]
+                } else {
+                    body.split([';', ':'])
+                        .filter_map(|param| param.parse().ok())
+                        .collect()
+                };
+                apply_sgr(&mut self.style, &params, OutOfRangePolicy::Clamp, |_| {});
+            }
+            'A' => self.move_to(x, y.saturating_sub(count)),
+            'B' | 'e' => self.move_to(x, y.saturating_add(count)),
+            'C' | 'a' => self.move_to(x.saturating_add(count), y),
+            'D' => self.move_to(x.saturating_sub(count), y),
+            'E' => self.move_to(0, y.saturating_add(count)),
+            'F' => self.move_to(0, y.saturating_sub(count)),
+            'G' | '`' => self.move_to(count - 1, y),
+            'd' => self.move_to(x, count - 1),
+            'H' | 'f' => self.move_to(param(1).max(1) - 1, count - 1),
+            'J' => match param(0) {
+                0 => {
+                    self.erase(y, x..self.width);
+                    for row in y + 1..self.height {
+                        self.erase(row, 0..self.width);
+                    }
+                }
+                1 => {
+                    for row in 0..y {
+                        self.erase(row, 0..self.width);
+                    }
+                    self.erase(y, 0..x + 1);
+                }
+                _ => {
+                    for row in 0..self.height {
+                        self.erase(row, 0..self.width);
+                    }
+                }
+            },
+            'K' => match param(0) {
+                0 => self.erase(y, x..self.width),
+                1 => self.erase(y, 0..x + 1),
+                _ => self.erase(y, 0..self.width),
+            },
+            'X' => self.erase(y, x..x.saturating_add(count).min(self.width)),
+            'P' => {
+                let erased = self.erased();
+                let row = &mut self.rows[y];
+                let count = count.min(self.width - x);
+                row.drain(x..x.saturating_add(count).min(self.width));
+                row.extend((0..count).map(|_| erased.clone()));
+            }
+            '@' => {
+                let erased = self.erased();
+                let row = &mut self.rows[y];
+                let count = count.min(self.width - x);
+                row.truncate(self.width - count);
+                row.splice(x..x, (0..count).map(|_| erased.clone()));
+            }
+            'L' | 'M' if (self.scroll_top..=self.scroll_bottom).contains(&y) => {
+                let top = self.scroll_top;
+                self.scroll_top = y;
+                if action == 'L' {
+                    self.scroll_down(count);
+                } else {
+                    self.scroll_up(count);
+                }
+                self.scroll_top = top;
+                self.move_to(0, y);
+            }
+            'S' => self.scroll_up(count),
+            'T' => self.scroll_down(count),
+            'r' => {
+                let top = count - 1;
+                let bottom = match param(1) {
+                    0 => self.height - 1,
+                    bottom => bottom.min(self.height) - 1,
+                };
+                if top < bottom {
+                    (self.scroll_top, self.scroll_bottom) = (top, bottom);
+                    self.move_to(0, 0);
+                }
+            }
+            's' => self.saved_cursor = (x, y),
+            'u' => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            _ => {}
+        }
+    }
+
+    /// Sets or resets DEC private modes.
+    fn set_private_modes(&mut self, modes: &[usize], enable: bool) {
+        for mode in modes {
+            match mode {
+                25 => self.cursor_visible = enable,
+                47 | 1047 | 1049 => {
+                    if enable && self.saved_main.is_none() {
+                        let blank = vec![vec![Cell::blank(); self.width]; self.height];
+                        let main = mem::replace(&mut self.rows, blank);
+                        self.saved_main = Some((main, self.cursor_x, self.cursor_y));
+                    } else if !enable && let Some((main, x, y)) = self.saved_main.take() {
+                        // A canvas may have grown on the alternate screen
+                        self.rows = main;
+                        self.height = self.rows.len();
+                        if self.scroll_bottom >= self.height {
+                            self.scroll_bottom = self.height - 1;
+                        }
+                        if self.scroll_top >= self.scroll_bottom {
+                            self.scroll_top = 0;
+                        }
+                        self.move_to(x, y);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the length in bytes of the escape sequence at the start of `input`, or `None`
+/// if it is cut off.
+fn escape_length(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    match bytes.get(1)? {
+        b'[' => bytes[2..]
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map(|idx| idx + 3),
+        // Strings end with BEL or ST (`ESC \`)
+        b']' | b'P' | b'_' | b'^' | b'X' => {
+            let end = bytes[2..].iter().enumerate().find(|&(idx, byte)| {
+                *byte == 0x07 || (*byte == 0x1b && bytes.get(idx + 3) == Some(&b'\\'))
+            })?;
+            Some(match end {
+                (idx, 0x07) => idx + 3,
+                (idx, _) => idx + 4,
+            })
+        }
+        // Charset designations take one more character
+        b'(' | b')' | b'*' | b'+' | b'#' | b'%' => {
+            input[2..].chars().next().map(|ch| 2 + ch.len_utf8())
+        }
+        _ => Some(1 + input[1..].chars().next()?.len_utf8()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn screen(width: usize, height: usize, data: &str) -> String {
+        let mut screen = Screen::new(width, height);
+        screen.feed(data);
+        screen.to_styled_text().to_plain_text()
+    }
+
+    #[test]
+    fn test_screen_overwrites_with_carriage_return() {
+        assert_eq!(screen(10, 3, "50%\r100%\r\ndone"), "100%\ndone\n");
+        assert_eq!(screen(10, 3, "abcdef\x1b[3D\x1b[K!"), "abc!\n");
+    }
+
+    #[test]
+    fn test_screen_cursor_movement_and_erase() {
+        assert_eq!(screen(10, 3, "\x1b[2;3Hx\x1b[1;1Hy"), "y\n  x\n");
+        assert_eq!(screen(10, 3, "one\r\ntwo\x1b[2J\x1b[Hz"), "z\n");
+        assert_eq!(screen(10, 3, "abc\x1b[2G\x1b[P"), "ac\n");
+        assert_eq!(screen(10, 3, "abc\x1b[2G\x1b[2@"), "a  bc\n");
+    }
+
+    #[test]
+    fn test_screen_wraps_and_scrolls() {
+        assert_eq!(screen(3, 2, "abcdef"), "abc\ndef\n");
+        assert_eq!(screen(3, 2, "1\r\n2\r\n3\r\n4"), "3\n4\n");
+        assert_eq!(screen(3, 2, "ab日"), "ab\n日\n");
+    }
+
+    #[test]
+    fn test_screen_keeps_styles() {
+        let mut screen = Screen::new(10, 2);
+        screen.feed("\x1b[31mred\x1b[0m plain");
+        let text = screen.to_styled_text();
+        assert_eq!(text.segments[0].text, "red");
+        assert_eq!(text.segments[0].style.fg_color, Some(Color::Indexed(1)));
+        assert_eq!(text.segments[1].text, " plain\n");
+    }
+
+    #[test]
+    fn test_screen_huge_counts() {
+        let count = u64::MAX;
+        assert_eq!(screen(10, 3, &format!("abc\x1b[{count}X")), "abc\n");
+        assert_eq!(screen(10, 3, &format!("abc\x1b[1G\x1b[{count}P")), "");
+        assert_eq!(screen(10, 3, &format!("a\x1b[{count}Bb")), "a\n\n b\n");
+        assert_eq!(screen(10, 3, &format!("a\x1b[{count}Cb")), "a        b\n");
+        assert_eq!(screen(10, 3, &format!("a\x1b[{count}Eb")), "a\n\nb\n");
+    }
+
+    #[test]
+    fn test_screen_unterminated_string() {
+        let mut screen = Screen::new(10, 2);
+        screen.feed("a\x1b]0;title");
+        screen.feed(&"\0".repeat(MAX_PENDING));
+        screen.feed("\r\nmore");
+        assert_eq!(screen.to_styled_text().to_plain_text(), "a0;title\nmore\n");
+    }
+
+    #[test]
+    fn test_screen_sequences_split_across_feeds() {
+        let mut screen = Screen::new(10, 2);
+        screen.feed("a\x1b[3");
+        screen.feed("1mb\x1b]0;title");
+        screen.feed("\x07c");
+        let text = screen.to_styled_text();
+        assert_eq!(text.to_plain_text(), "abc\n");
+        assert_eq!(text.segments[1].style.fg_color, Some(Color::Indexed(1)));
+    }
+
+    #[test]
+    fn test_screen_charset_designation_before_multibyte() {
+        assert_eq!(screen(10, 2, "a\x1b(éb"), "ab\n");
+        assert_eq!(screen(10, 2, "a\x1b(B\x1b)日b"), "ab\n");
+    }
+
+    #[test]
+    fn test_screen_alternate_screen_and_cursor() {
+        let mut screen = Screen::new(10, 2);
+        screen.feed("shell$ \x1b[?1049h\x1b[?25l\x1b[Hfull screen");
+        assert!(!screen.cursor_visible());
+        assert_eq!(screen.to_styled_text().to_plain_text(), "full scree\nn\n");
+        screen.feed("\x1b[?1049l\x1b[?25h");
+        assert_eq!(screen.to_styled_text().to_plain_text(), "shell$\n");
+        assert_eq!(screen.cursor(), (7, 0));
+    }
+
+    #[test]
+    fn test_screen_size_limits() {
+        let screen = Screen::new(65_535, 65_535);
+        assert_eq!(
+            (screen.width(), screen.height()),
+            (MAX_WIDTH, MAX_CELLS / MAX_WIDTH)
+        );
+        let mut canvas = Screen::canvas(80);
+        canvas.feed("\x1b[999999B");
+        assert_eq!(canvas.height() * canvas.width(), MAX_CELLS);
+    }
+
+    #[test]
+    fn test_canvas_grows() {
+        let mut canvas = Screen::canvas(3);
+        canvas.feed("abcdefg\r\n1\x1b[5;2H2");
+        assert_eq!(canvas.height(), 5);
+        canvas.feed("\x1b[999999999B");
+        assert_eq!(canvas.height(), MAX_CELLS / 3);
+        assert_eq!(
+            canvas.to_styled_text().to_plain_text(),
+            "abc\ndef\ng\n1\n 2\n"
+        );
+    }
+
+    #[test]
+    fn test_canvas_leaves_grown_alternate_screen() {
+        let mut canvas = Screen::canvas(10);
+        canvas.feed("\x1b[?1049h\n\n\x1b[?1049l");
+        assert_eq!(canvas.height(), 1);
+        canvas.feed("\nhi\n");
+        assert_eq!(canvas.to_styled_text().to_plain_text(), "\nhi\n");
+    }
+
+    #[test]
+    fn test_screen_scroll_region() {
+        let data = "top\r\n1\r\n2\r\nbottom\x1b[2;3r\x1b[3;1H\n3";
+        assert_eq!(screen(10, 4, data), "top\n2\n3\nbottom\n");
+    }
+}
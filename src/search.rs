@@ -0,0 +1,253 @@
+use crate::width::str_width;
+use crate::{Segment, Style, StyledText};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// A position within the segments of a [`StyledText`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentPosition {
+    /// Index of the segment.
+    pub segment: usize,
+    /// Byte offset into the segment's text.
+    pub offset: usize,
+}
+
+/// A regex match found in styled text, mapped back to segment positions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StyledMatch {
+    /// The matched text.
+    pub text: String,
+    /// Byte range of the match within the concatenated plain text.
+    pub range: Range<usize>,
+    /// Segment position where the match starts.
+    pub start: SegmentPosition,
+    /// Segment position where the match ends (exclusive).
+    pub end: SegmentPosition,
+    /// Zero-based line on which the match starts.
+    pub line: usize,
+    /// Zero-based display column at which the match starts.
+    pub column: usize,
+}
+
+impl StyledText {
+    /// Finds every match of `regex` in the plain text of the styled text.
+    ///
+    /// The regex runs over the concatenated segment text with all styling removed. Each
+    /// match range is then mapped back to the segments it covers and to a line and display
+    /// column, so callers can restyle or annotate the matched spans.
+    #[must_use]
+    pub fn find_all(&self, regex: &Regex) -> Vec<StyledMatch> {
+        let mut plain = String::new();
+        let mut starts = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            starts.push(plain.len());
+            plain.push_str(&segment.text);
+        }
+
+        regex
+            .find_iter(&plain)
+            .map(|m| {
+                let before = &plain[..m.start()];
+                let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+                StyledMatch {
+                    text: m.as_str().to_string(),
+                    range: m.range(),
+                    start: self.position_at(&starts, m.start(), false),
+                    end: self.position_at(&starts, m.end(), m.end() > m.start()),
+                    line: before.matches('\n').count(),
+                    column: str_width(&before[line_start..]),
+                }
+            })
+            .collect()
+    }
+
+    /// Restyles every match of `regex` in the plain text with `highlight`.
+    ///
+    /// The highlight is layered over the existing style: its colors replace the original
+    /// ones where set, and its attributes are added to the original attributes. Segments
+    /// are split at match boundaries, so text outside the matches keeps its style.
+    #[must_use]
+    pub fn highlight(&self, regex: &Regex, highlight: &Style) -> StyledText {
+        let ranges: Vec<Range<usize>> = self
+            .find_all(regex)
+            .into_iter()
+            .map(|m| m.range)
+            .filter(|range| !range.is_empty())
+            .collect();
+
+        let mut segments = Vec::with_capacity(self.segments.len());
+        let mut start = 0;
+        for segment in &self.segments {
+            let end = start + segment.text.len();
+            let mut cuts = vec![start, end];
+            for range in &ranges {
+                cuts.extend(
+                    [range.start, range.end]
+                        .into_iter()
+                        .filter(|&cut| start < cut && cut < end),
+                );
+            }
+            cuts.sort_unstable();
+            cuts.dedup();
+
+            for pair in cuts.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                let matched = ranges
+                    .iter()
+                    .any(|range| range.start <= from && to <= range.end);
+                segments.push(Segment {
+                    text: segment.text[from - start..to - start].to_string(),
+                    style: if matched {
+                        layer_style(&segment.style, highlight)
+                    } else {
+                        segment.style.clone()
+                    },
+                    source: segment.sub_source(from - start..to - start),
+                    escapes: None,
+                });
+            }
+            start = end;
+        }
+        StyledText { segments }
+    }
+
+    /// Maps a byte offset in the concatenated text to a segment position.
+    ///
+    /// When `inclusive_end` is set, an offset on a segment boundary resolves to the end of
+    /// the preceding segment rather than the start of the next one.
+    fn position_at(&self, starts: &[usize], offset: usize, inclusive_end: bool) -> SegmentPosition {
+        let mut position = SegmentPosition {
+            segment: self.segments.len(),
+            offset: 0,
+        };
+        for (idx, (start, segment)) in starts.iter().zip(&self.segments).enumerate() {
+            let end = start + segment.text.len();
+            let contains = if inclusive_end {
+                *start < offset && offset <= end
+            } else {
+                *start <= offset && offset < end
+            };
+            if contains {
+                position = SegmentPosition {
+                    segment: idx,
+                    offset: offset - start,
+                };
+                break;
+            }
+        }
+        position
+    }
+}
+
+/// Returns `base` with the colors and attributes set in `top` layered over it.
+fn layer_style(base: &Style, top: &Style) -> Style {
+    Style {
+        fg_color: top.fg_color.clone().or_else(|| base.fg_color.clone()),
+        bg_color: top.bg_color.clone().or_else(|| base.bg_color.clone()),
+        bold: base.bold || top.bold,
+        dim: base.dim || top.dim,
+        italic: base.italic || top.italic,
+        underline: base.underline || top.underline,
+        blink: base.blink || top.blink,
+        reverse: base.reverse || top.reverse,
+        hidden: base.hidden || top.hidden,
+        strikethrough: base.strikethrough || top.strikethrough,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_all_across_segments() {
+        let red = Style {
+            fg_color: Some(crate::Color::Indexed(1)),
+            ..Default::default()
+        };
+        let styled_text = StyledText {
+            segments: vec![
+                Segment {
+                    text: "ok\nan ER".to_string(),
+                    style: Style::default(),
+                    source: None,
+                    escapes: None,
+                },
+                Segment {
+                    text: "ROR here".to_string(),
+                    style: red,
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        let matches = styled_text.find_all(&Regex::new("ERROR").unwrap());
+
+        assert_eq!(matches.len(), 1);
+        let m = &matches[0];
+        assert_eq!(m.text, "ERROR");
+        assert_eq!(m.range, 6..11);
+        assert_eq!(
+            m.start,
+            SegmentPosition {
+                segment: 0,
+                offset: 6
+            }
+        );
+        assert_eq!(
+            m.end,
+            SegmentPosition {
+                segment: 1,
+                offset: 3
+            }
+        );
+        assert_eq!(m.line, 1);
+        assert_eq!(m.column, 3);
+    }
+
+    #[test]
+    fn test_find_all_no_matches() {
+        let styled_text = StyledText {
+            segments: vec![Segment {
+                text: "all good".to_string(),
+                style: Style::default(),
+                source: None,
+                escapes: None,
+            }],
+        };
+        assert!(
+            styled_text
+                .find_all(&Regex::new("ERROR").unwrap())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_highlight_layers_over_existing_style() {
+        let styled_text = crate::parse_ansi("an \x1b[44mERR\x1b[0mOR here");
+        let highlight = Style {
+            fg_color: Some(crate::Color::Indexed(1)),
+            bold: true,
+            ..Default::default()
+        };
+        let highlighted = styled_text.highlight(&Regex::new("ERROR").unwrap(), &highlight);
+
+        let texts: Vec<&str> = highlighted
+            .segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["an ", "ERR", "OR", " here"]);
+        assert_eq!(highlighted.segments[0].style, Style::default());
+        assert_eq!(
+            highlighted.segments[1].style,
+            Style {
+                bg_color: Some(crate::Color::Indexed(4)),
+                ..highlight.clone()
+            }
+        );
+        assert_eq!(highlighted.segments[2].style, highlight);
+        assert_eq!(highlighted.segments[3].style, Style::default());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::palette::ColorDepth;
+use crate::{Color, StyledText};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// The number of characters using a particular color.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorUsage {
+    /// The color.
+    pub color: Color,
+    /// The number of characters drawn with this color.
+    pub count: usize,
+}
+
+/// The number of characters using each text attribute.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct AttributeUsage {
+    /// Characters that are bold.
+    pub bold: usize,
+    /// Characters that are dimmed.
+    pub dim: usize,
+    /// Characters that are italic.
+    pub italic: usize,
+    /// Characters that are underlined.
+    pub underline: usize,
+    /// Characters that are blinking.
+    pub blink: usize,
+    /// Characters that are reversed.
+    pub reverse: usize,
+    /// Characters that are hidden.
+    pub hidden: usize,
+    /// Characters that are struck-through.
+    pub strikethrough: usize,
+}
+
+/// Summary statistics about the colors and styles used in styled text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextStats {
+    /// The number of non-empty segments.
+    pub segments: usize,
+    /// The number of lines.
+    pub lines: usize,
+    /// The display width of the longest line.
+    pub longest_line: usize,
+    /// Distinct foreground colors in order of first appearance.
+    pub fg_colors: Vec<ColorUsage>,
+    /// Distinct background colors in order of first appearance.
+    pub bg_colors: Vec<ColorUsage>,
+    /// How many characters use each attribute.
+    pub attributes: AttributeUsage,
+}
+
+impl TextStats {
+    /// Returns `true` if every color used can be represented exactly at `depth`.
+    ///
+    /// This tells whether downgrading to that color depth would change the output.
+    #[must_use]
+    pub fn is_lossless_at(&self, depth: ColorDepth) -> bool {
+        self.fg_colors
+            .iter()
+            .chain(&self.bg_colors)
+            .all(|usage| usage.color.to_depth(depth).to_hex() == usage.color.to_hex())
+    }
+}
+
+impl StyledText {
+    /// Collects statistics about the segments, colors, and attributes in the text.
+    ///
+    /// Color and attribute counts are measured in characters, excluding newlines.
+    #[must_use]
+    pub fn stats(&self) -> TextStats {
+        let mut stats = TextStats {
+            segments: 0,
+            lines: self.split_lines().len(),
+            longest_line: self.width(),
+            fg_colors: Vec::new(),
+            bg_colors: Vec::new(),
+            attributes: AttributeUsage::default(),
+        };
+
+        for segment in &self.segments {
+            if segment.text.is_empty() {
+                continue;
+            }
+            stats.segments += 1;
+
+            let count = segment.text.chars().filter(|&ch| ch != '\n').count();
+            if count == 0 {
+                continue;
+            }
+
+            let style = &segment.style;
+            if let Some(color) = &style.fg_color {
+                add_color(&mut stats.fg_colors, color, count);
+            }
+            if let Some(color) = &style.bg_color {
+                add_color(&mut stats.bg_colors, color, count);
+            }
+
+            let attributes = &mut stats.attributes;
+            for (enabled, total) in [
+                (style.bold, &mut attributes.bold),
+                (style.dim, &mut attributes.dim),
+                (style.italic, &mut attributes.italic),
+                (style.underline, &mut attributes.underline),
+                (style.blink, &mut attributes.blink),
+                (style.reverse, &mut attributes.reverse),
+                (style.hidden, &mut attributes.hidden),
+                (style.strikethrough, &mut attributes.strikethrough),
+            ] {
+                if enabled {
+                    *total += count;
+                }
+            }
+        }
+
+        stats
+    }
+}
+
+/// Adds `count` characters to the usage entry for `color`, creating it if needed.
+fn add_color(usages: &mut Vec<ColorUsage>, color: &Color, count: usize) {
+    match usages.iter_mut().find(|usage| usage.color == *color) {
+        Some(usage) => usage.count += count,
+        None => usages.push(ColorUsage {
+            color: color.clone(),
+            count,
+        }),
+    }
+}
+
+impl Display for TextStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "segments: {}", self.segments)?;
+        writeln!(f, "lines: {}", self.lines)?;
+        writeln!(f, "longest line: {}", self.longest_line)?;
+
+        for (label, usages) in [
+            ("foreground", &self.fg_colors),
+            ("background", &self.bg_colors),
+        ] {
+            writeln!(f, "{label} colors: {}", usages.len())?;
+            for usage in usages {
+                writeln!(f, "  {} {}", usage.color.to_hex(), usage.count)?;
+            }
+        }
+
+        let attributes = &self.attributes;
+        writeln!(f, "attributes:")?;
+        for (name, count) in [
+            ("bold", attributes.bold),
+            ("dim", attributes.dim),
+            ("italic", attributes.italic),
+            ("underline", attributes.underline),
+            ("blink", attributes.blink),
+            ("reverse", attributes.reverse),
+            ("hidden", attributes.hidden),
+            ("strikethrough", attributes.strikethrough),
+        ] {
+            writeln!(f, "  {name} {count}")?;
+        }
+
+        writeln!(
+            f,
+            "lossless at 16 colors: {}",
+            self.is_lossless_at(ColorDepth::Ansi16)
+        )?;
+        write!(
+            f,
+            "lossless at 256 colors: {}",
+            self.is_lossless_at(ColorDepth::Ansi256)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ansi;
+
+    #[test]
+    fn test_stats_counts_colors_and_attributes() {
+        let parsed = parse_ansi("\x1b[1;31mab\x1b[0m\ncde\x1b[31mf\x1b[0m\n");
+        let stats = parsed.stats();
+
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.longest_line, 4);
+        assert_eq!(
+            stats.fg_colors,
+            vec![ColorUsage {
+                color: Color::Indexed(1),
+                count: 3,
+            }]
+        );
+        assert!(stats.bg_colors.is_empty());
+        assert_eq!(stats.attributes.bold, 2);
+        assert!(stats.is_lossless_at(ColorDepth::Ansi16));
+    }
+
+    #[test]
+    fn test_stats_lossless_check() {
+        let parsed = parse_ansi("\x1b[38;2;1;2;3mx");
+        let stats = parsed.stats();
+        assert!(!stats.is_lossless_at(ColorDepth::Ansi256));
+        assert!(stats.is_lossless_at(ColorDepth::TrueColor));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{Style, StyledText};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Index;
+
+/// The position of a style in a [`StyleTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct StyleId(u32);
+
+impl StyleId {
+    /// Returns the position of the style in its table.
+    #[must_use]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A table of distinct styles, each stored once and referred to by a [`StyleId`].
+///
+/// Logs tend to use a handful of styles across millions of segments, so storing an id per
+/// segment and looking styles up here keeps one copy of each.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<Style>", into = "Vec<Style>")]
+pub struct StyleTable {
+    styles: Vec<Style>,
+    ids: HashMap<Style, StyleId>,
+}
+
+impl StyleTable {
+    /// Creates an empty table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the id of `style`, adding it to the table if it is not there yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table already holds `u32::MAX` styles.
+    pub fn intern(&mut self, style: &Style) -> StyleId {
+        if let Some(&id) = self.ids.get(style) {
+            return id;
+        }
+        let id = StyleId(u32::try_from(self.styles.len()).expect("too many distinct styles"));
+        self.styles.push(style.clone());
+        self.ids.insert(style.clone(), id);
+        id
+    }
+
+    /// Returns the id of `style` if it is in the table.
+    #[must_use]
+    pub fn id(&self, style: &Style) -> Option<StyleId> {
+        self.ids.get(style).copied()
+    }
+
+    /// Returns the style with the given id, or `None` if it is not from this table.
+    #[must_use]
+    pub fn get(&self, id: StyleId) -> Option<&Style> {
+        self.styles.get(id.index())
+    }
+
+    /// Returns the styles in the order they were added, so a style's id is its index.
+    #[must_use]
+    pub fn styles(&self) -> &[Style] {
+        &self.styles
+    }
+
+    /// Returns the number of distinct styles.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.styles.len()
+    }
+
+    /// Returns `true` if the table holds no styles.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+}
+
+impl Index<StyleId> for StyleTable {
+    type Output = Style;
+
+    fn index(&self, id: StyleId) -> &Style {
+        &self.styles[id.index()]
+    }
+}
+
+impl PartialEq for StyleTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.styles == other.styles
+    }
+}
+
+impl From<Vec<Style>> for StyleTable {
+    /// Builds a table from a list of styles, keeping the first id of any repeated style.
+    fn from(styles: Vec<Style>) -> Self {
+        let mut table = StyleTable::new();
+        for style in &styles {
+            table.intern(style);
+        }
+        table
+    }
+}
+
+impl From<StyleTable> for Vec<Style> {
+    fn from(table: StyleTable) -> Self {
+        table.styles
+    }
+}
+
+impl StyledText {
+    /// Returns the distinct styles of the text in order of first use, along with the id of
+    /// each segment's style.
+    #[must_use]
+    pub fn intern_styles(&self) -> (StyleTable, Vec<StyleId>) {
+        let mut table = StyleTable::new();
+        let ids = self
+            .segments
+            .iter()
+            .map(|segment| table.intern(&segment.style))
+            .collect();
+        (table, ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, parse_ansi};
+
+    #[test]
+    fn test_intern_styles() {
+        let text = parse_ansi("a\x1b[31mb\x1b[0mc\x1b[31md");
+        let (table, ids) = text.intern_styles();
+        assert_eq!(table.len(), 2);
+        assert_eq!(ids, vec![StyleId(0), StyleId(1), StyleId(0), StyleId(1)]);
+        assert_eq!(table[ids[1]].fg_color, Some(Color::Indexed(1)));
+        assert_eq!(table.id(&Style::default()), Some(StyleId(0)));
+        assert_eq!(table.get(StyleId(2)), None);
+    }
+
+    #[test]
+    fn test_style_table_serde_round_trip() {
+        let (table, _) = parse_ansi("\x1b[1mbold\x1b[0m plain").intern_styles();
+        let json = serde_json::to_string(&table).unwrap();
+        let restored: StyleTable = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, table);
+        assert_eq!(restored.id(&table.styles()[1]), Some(StyleId(1)));
+    }
+}
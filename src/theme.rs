@@ -0,0 +1,227 @@
+use crate::Color;
+use crate::contrast::AAA_CONTRAST;
+use error_stack::Report;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use wherror::Error;
+
+/// Error raised when a theme file cannot be parsed.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct ThemeError;
+
+/// The page colors and 16-color palette used when rendering standalone documents.
+///
+/// Indexed colors 0-15 are drawn from `palette`, while the 256-color cube, grayscale ramp,
+/// and truecolor values are left untouched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    /// Color of text without an explicit foreground.
+    pub foreground: Color,
+    /// Page background, also used for text without an explicit background.
+    pub background: Color,
+    /// Colors for indexes 0-15, in standard ANSI order.
+    pub palette: [Color; 16],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+impl Theme {
+    /// A light theme: black text on a white page using the classic VGA-style palette.
+    #[must_use]
+    pub fn light() -> Self {
+        Theme {
+            foreground: Color::Rgb(0x00, 0x00, 0x00),
+            background: Color::Rgb(0xff, 0xff, 0xff),
+            palette: std::array::from_fn(|idx| {
+                #[allow(clippy::cast_possible_truncation)]
+                let (r, g, b) = Color::Indexed(idx as u8).to_rgb();
+                Color::Rgb(r, g, b)
+            }),
+        }
+    }
+
+    /// A dark theme: light gray text on a near-black page with a brighter palette that
+    /// stays readable on dark backgrounds.
+    #[must_use]
+    pub fn dark() -> Self {
+        let palette = [
+            0x00_0000, 0xcd_3131, 0x0d_bc79, 0xe5_e510, 0x24_72c8, 0xbc_3fbc, 0x11_a8cd, 0xe5_e5e5,
+            0x66_6666, 0xf1_4c4c, 0x23_d18b, 0xf5_f543, 0x3b_8eea, 0xd6_70d6, 0x29_b8db, 0xff_ffff,
+        ];
+        Theme {
+            foreground: Color::Rgb(0xcc, 0xcc, 0xcc),
+            background: Color::Rgb(0x1e, 0x1e, 0x1e),
+            palette: palette.map(rgb),
+        }
+    }
+
+    /// A high-contrast theme: white text on a black page, with every palette color
+    /// saturated and lightened to at least 7:1 contrast, as WCAG level AAA asks.
+    ///
+    /// Use with [`StyledText::high_contrast`](crate::StyledText::high_contrast) so that
+    /// colors outside the palette meet the same contrast.
+    #[must_use]
+    pub fn high_contrast_dark() -> Self {
+        Theme::high_contrast(Color::Rgb(0x00, 0x00, 0x00), Color::Rgb(0xff, 0xff, 0xff))
+    }
+
+    /// A high-contrast theme: black text on a white page, with every palette color
+    /// saturated and darkened to at least 7:1 contrast, as WCAG level AAA asks.
+    #[must_use]
+    pub fn high_contrast_light() -> Self {
+        Theme::high_contrast(Color::Rgb(0xff, 0xff, 0xff), Color::Rgb(0x00, 0x00, 0x00))
+    }
+
+    /// Returns a theme with the classic palette adjusted for high contrast on `background`.
+    fn high_contrast(background: Color, foreground: Color) -> Self {
+        let palette = Theme::light()
+            .palette
+            .map(|color| color.with_contrast(&background, AAA_CONTRAST));
+        Theme {
+            foreground,
+            background,
+            palette,
+        }
+    }
+
+    /// Parses a theme from a terminal color scheme.
+    ///
+    /// Each line sets one color as `name #RRGGBB`, in the style of kitty and similar
+    /// terminals; `name = #RRGGBB` and `name: #RRGGBB` also work. The names are
+    /// `foreground`, `background`, and `color0` through `color15`. Blank lines, lines
+    /// starting with `#` or `;`, and unknown names are ignored, and colors that are not
+    /// set are taken from [`Theme::dark`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a known name is set to something other than a hex color.
+    pub fn parse(text: &str) -> Result<Self, Report<ThemeError>> {
+        let mut theme = Theme::dark();
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once(['=', ':'])
+                .or_else(|| line.split_once(char::is_whitespace))
+                .unwrap_or((line, ""));
+            let (name, value) = (name.trim(), value.trim());
+            let slot = match name {
+                "foreground" => &mut theme.foreground,
+                "background" => &mut theme.background,
+                _ => match name
+                    .strip_prefix("color")
+                    .and_then(|idx| idx.parse::<usize>().ok())
+                {
+                    Some(idx) if idx < 16 => &mut theme.palette[idx],
+                    _ => continue,
+                },
+            };
+            *slot = Color::from_hex(value).ok_or_else(|| {
+                Report::new(ThemeError).attach(format!(
+                    "line {}: invalid color '{value}' for '{name}', expected #RRGGBB",
+                    line_idx + 1
+                ))
+            })?;
+        }
+        Ok(theme)
+    }
+
+    /// Returns the color `color` is drawn with under this theme.
+    ///
+    /// Indexed colors 0-15 are looked up in the palette; every other color is unchanged.
+    #[must_use]
+    pub fn resolve(&self, color: &Color) -> Color {
+        match color {
+            Color::Indexed(idx) if *idx < 16 => self.palette[usize::from(*idx)].clone(),
+            _ => color.clone(),
+        }
+    }
+
+    /// Generates CSS applying this theme's page colors and palette.
+    ///
+    /// Class names start with `prefix`, matching
+    /// [`generate_css_with_prefix`](crate::generate_css_with_prefix).
+    #[must_use]
+    pub fn css(&self, prefix: &str) -> String {
+        let mut css = String::new();
+        writeln!(
+            &mut css,
+            "body {{ background-color: {}; color: {}; }}",
+            self.background.to_hex(),
+            self.foreground.to_hex()
+        )
+        .unwrap();
+        for (idx, color) in self.palette.iter().enumerate() {
+            let hex = color.to_hex();
+            writeln!(&mut css, ".{prefix}fg{idx} {{ color: {hex} }}").unwrap();
+            writeln!(&mut css, ".{prefix}bg{idx} {{ background-color: {hex} }}").unwrap();
+        }
+        css
+    }
+}
+
+/// Converts a `0xRRGGBB` literal to a color.
+fn rgb(value: u32) -> Color {
+    let [_, r, g, b] = value.to_be_bytes();
+    Color::Rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_theme_matches_default_palette() {
+        let theme = Theme::light();
+        assert_eq!(theme.palette[1].to_hex(), "#800000");
+        assert_eq!(theme.palette[15].to_hex(), "#ffffff");
+    }
+
+    #[test]
+    fn test_high_contrast_themes() {
+        for theme in [Theme::high_contrast_dark(), Theme::high_contrast_light()] {
+            for color in theme.palette.iter().chain([&theme.foreground]) {
+                assert!(color.contrast_ratio(&theme.background) >= AAA_CONTRAST);
+            }
+        }
+        // Red stays a pure hue, lightened just enough on black
+        assert_eq!(Theme::high_contrast_dark().palette[9].to_rgb().0, 255);
+    }
+
+    #[test]
+    fn test_theme_resolve() {
+        let theme = Theme::dark();
+        assert_eq!(theme.resolve(&Color::Indexed(1)).to_hex(), "#cd3131");
+        assert_eq!(theme.resolve(&Color::Indexed(196)), Color::Indexed(196));
+    }
+
+    #[test]
+    fn test_theme_parse() {
+        let theme = Theme::parse(
+            "# My scheme\nforeground #ffffff\nbackground = #000000\ncolor1: #ff0000\n\
+             cursor #123456\ncolor16 #123456\n",
+        )
+        .unwrap();
+        assert_eq!(theme.foreground, Color::Rgb(0xff, 0xff, 0xff));
+        assert_eq!(theme.background, Color::Rgb(0, 0, 0));
+        assert_eq!(theme.palette[1], Color::Rgb(0xff, 0, 0));
+        assert_eq!(theme.palette[2], Theme::dark().palette[2]);
+
+        assert!(Theme::parse("color3 yellow").is_err());
+    }
+
+    #[test]
+    fn test_theme_css() {
+        let css = Theme::dark().css("ansi-");
+        assert!(css.contains("body { background-color: #1e1e1e; color: #cccccc; }"));
+        assert!(css.contains(".ansi-fg1 { color: #cd3131 }"));
+        assert!(css.contains(".ansi-bg4 { background-color: #2472c8 }"));
+    }
+}
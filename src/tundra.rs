@@ -0,0 +1,162 @@
+use crate::{Codepage, Color, Sauce, Segment, Style, StyledText};
+use error_stack::Report;
+use wherror::Error;
+
+/// The version byte and signature a `TundraDraw` file starts with.
+const HEADER: &[u8] = b"\x18TUNDRA24";
+/// Width in columns of `TundraDraw` art without a SAUCE width.
+const DEFAULT_TUNDRA_WIDTH: usize = 80;
+/// Rows past this are dropped, so a stray cursor position cannot allocate without bound.
+const MAX_TUNDRA_ROWS: usize = 100_000;
+
+/// Command byte that moves the cursor to a row and column.
+const POSITION: u8 = 1;
+/// Command byte that sets the foreground color and draws a character.
+const FOREGROUND: u8 = 2;
+/// Command byte that sets the background color and draws a character.
+const BACKGROUND: u8 = 4;
+/// Command byte that sets both colors and draws a character.
+const BOTH: u8 = 6;
+
+/// Error raised when a `TundraDraw` file cannot be read.
+#[derive(Debug, Error)]
+#[error(debug)]
+pub struct TundraError;
+
+/// Reads a `TundraDraw` (`.tnd`) file, ANSI art with a 24-bit color for each character.
+///
+/// The art is drawn from commands that move the cursor or set colors, with every other
+/// byte a CP437 character drawn at the cursor. Rows are as wide as the SAUCE record
+/// declares, or 80 columns, and cells nothing was drawn in are left blank.
+///
+/// # Errors
+///
+/// Returns an error if the data does not start with the `TundraDraw` header or a command
+/// is cut off.
+pub fn parse_tundra(data: &[u8]) -> Result<StyledText, Report<TundraError>> {
+    let columns = Sauce::parse(data)
+        .as_ref()
+        .and_then(Sauce::width)
+        .unwrap_or(DEFAULT_TUNDRA_WIDTH);
+    let body = Sauce::strip_record(data)
+        .strip_prefix(HEADER)
+        .ok_or_else(|| Report::new(TundraError).attach("missing TundraDraw header"))?;
+
+    let mut rows: Vec<Vec<(u8, Style)>> = Vec::new();
+    let mut style = Style::default();
+    let (mut x, mut y) = (0, 0);
+    let mut pos = 0;
+    while pos < body.len() {
+        let command = body[pos];
+        let argument_len = match command {
+            POSITION => 8,
+            FOREGROUND | BACKGROUND => 5,
+            BOTH => 9,
+            _ => 0,
+        };
+        let arguments = body.get(pos + 1..=pos + argument_len).ok_or_else(|| {
+            Report::new(TundraError).attach(format!("command at byte {pos} is cut off"))
+        })?;
+        pos += 1 + argument_len;
+
+        let character = match command {
+            POSITION => {
+                (y, x) = (be_u32(&arguments[0..4]), be_u32(&arguments[4..8]));
+                continue;
+            }
+            FOREGROUND => {
+                style.fg_color = Some(rgb(&arguments[1..5]));
+                arguments[0]
+            }
+            BACKGROUND => {
+                style.bg_color = Some(rgb(&arguments[1..5]));
+                arguments[0]
+            }
+            BOTH => {
+                style.fg_color = Some(rgb(&arguments[1..5]));
+                style.bg_color = Some(rgb(&arguments[5..9]));
+                arguments[0]
+            }
+            character => character,
+        };
+
+        if y < MAX_TUNDRA_ROWS && x < columns {
+            if rows.len() <= y {
+                rows.resize_with(y + 1, Vec::new);
+            }
+            let row = &mut rows[y];
+            if row.len() <= x {
+                row.resize(x + 1, (b' ', Style::default()));
+            }
+            row[x] = (character, style.clone());
+        }
+        x += 1;
+        if x >= columns {
+            (x, y) = (0, y + 1);
+        }
+    }
+
+    let mut segments = Vec::new();
+    for row in rows {
+        for (character, style) in row {
+            segments.push(Segment {
+                text: Codepage::Wingdings.decode(character).to_string(),
+                style,
+                source: None,
+                escapes: None,
+            });
+        }
+        segments.push(Segment {
+            text: "\n".to_string(),
+            style: Style::default(),
+            source: None,
+            escapes: None,
+        });
+    }
+    Ok(StyledText { segments }.normalize())
+}
+
+/// Reads a big-endian number, saturating on targets where it does not fit in `usize`.
+fn be_u32(bytes: &[u8]) -> usize {
+    let value = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    usize::try_from(value).unwrap_or(usize::MAX)
+}
+
+/// Reads a color stored as four bytes, an unused one followed by red, green, and blue.
+fn rgb(bytes: &[u8]) -> Color {
+    Color::Rgb(bytes[1], bytes[2], bytes[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tundra() {
+        let mut data = HEADER.to_vec();
+        data.extend([FOREGROUND, b'A', 0, 255, 0, 0]);
+        data.extend(b"B");
+        data.extend([BOTH, 0xdb, 0, 0, 0, 255, 0, 1, 2, 3]);
+        data.extend([POSITION, 0, 0, 0, 2, 0, 0, 0, 1]);
+        data.extend([BACKGROUND, b'C', 0, 9, 9, 9]);
+        let text = parse_tundra(&data).unwrap();
+        assert_eq!(text.to_plain_text(), "AB█\n\n C\n");
+
+        let red = &text.segments[0];
+        assert_eq!(red.text, "AB");
+        assert_eq!(red.style.fg_color, Some(Color::Rgb(255, 0, 0)));
+        let block = &text.segments[1].style;
+        assert_eq!(
+            (block.fg_color.clone(), block.bg_color.clone()),
+            (Some(Color::Rgb(0, 0, 255)), Some(Color::Rgb(1, 2, 3)))
+        );
+        // The cursor moved to row 2, column 1, and colors carry over
+        let c = &text.segments[text.segments.len() - 2];
+        assert_eq!(c.text, "C");
+        assert_eq!(c.style.fg_color, Some(Color::Rgb(0, 0, 255)));
+        assert_eq!(c.style.bg_color, Some(Color::Rgb(9, 9, 9)));
+
+        assert!(parse_tundra(b"plain text").is_err());
+        assert!(parse_tundra(&[HEADER, &[FOREGROUND, b'A', 0]].concat()).is_err());
+    }
+}
@@ -0,0 +1,399 @@
+use crate::iter::StyledGrapheme;
+use crate::{Segment, Style, StyledText};
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+impl Segment {
+    /// Returns the display width of the segment in terminal columns.
+    ///
+    /// Width is measured per grapheme cluster, so wide CJK characters take two columns
+    /// and combining marks take none. If the text spans multiple lines, the width of the
+    /// widest line is returned.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.text.split('\n').map(str_width).max().unwrap_or(0)
+    }
+}
+
+impl StyledText {
+    /// Returns the display width of the styled text in terminal columns.
+    ///
+    /// If the text spans multiple lines, the width of the widest line is returned. Grapheme
+    /// clusters split between segments by a change of style, such as an emoji and its skin
+    /// tone, are measured whole.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        let (mut widest, mut line) = (0, 0);
+        for grapheme in self.styled_graphemes() {
+            if grapheme.text.contains('\n') {
+                widest = widest.max(line);
+                line = 0;
+            } else {
+                line += grapheme.width;
+            }
+        }
+        widest.max(line)
+    }
+
+    /// Truncates the styled text to at most `width` display columns.
+    ///
+    /// Graphemes are never split; a wide character that would straddle the boundary is
+    /// dropped instead. If the text is cut and an `ellipsis` is given, it is appended using
+    /// the style of the last retained segment and counts towards `width`.
+    ///
+    /// This operates on a single line; use [`StyledText::split_lines`] first for
+    /// multi-line text.
+    #[must_use]
+    pub fn truncate(&self, width: usize, ellipsis: Option<&str>) -> StyledText {
+        if self.width() <= width {
+            return self.clone();
+        }
+
+        let ellipsis = ellipsis.unwrap_or("");
+        let ellipsis_width = str_width(ellipsis);
+        let available = width.saturating_sub(ellipsis_width);
+
+        let mut segments = self.slice_columns(0..available).segments;
+
+        if !ellipsis.is_empty() && ellipsis_width <= width {
+            let style = segments
+                .last()
+                .or(self.segments.first())
+                .map(|segment| segment.style.clone())
+                .unwrap_or_default();
+            segments.push(Segment {
+                text: ellipsis.to_string(),
+                style,
+                source: None,
+                escapes: None,
+            });
+        }
+
+        StyledText { segments }
+    }
+
+    /// Returns the styled content between two display columns.
+    ///
+    /// Segments are split as needed so that only graphemes lying entirely within
+    /// `columns` are kept; a wide character straddling either edge is dropped.
+    ///
+    /// This operates on a single line; use [`StyledText::split_lines`] first for
+    /// multi-line text.
+    #[must_use]
+    pub fn slice_columns(&self, columns: Range<usize>) -> StyledText {
+        // The byte range kept of each segment with any kept
+        let mut kept: Vec<(usize, Range<usize>)> = Vec::new();
+        let mut column = 0;
+        // Whether the last grapheme was kept, which decides for the rest of its cluster
+        let mut kept_last = false;
+
+        for grapheme in self.styled_graphemes() {
+            let start = column;
+            column += grapheme.width;
+            if start >= columns.end && !grapheme.joins_previous {
+                break;
+            }
+            kept_last = if grapheme.joins_previous {
+                kept_last
+            } else {
+                start >= columns.start && column <= columns.end
+            };
+            if !kept_last {
+                continue;
+            }
+            let end = grapheme.offset + grapheme.text.len();
+            match kept.last_mut() {
+                Some((segment, range)) if *segment == grapheme.segment => range.end = end,
+                _ => kept.push((grapheme.segment, grapheme.offset..end)),
+            }
+        }
+
+        let segments = kept
+            .into_iter()
+            .map(|(index, range)| {
+                let segment = &self.segments[index];
+                Segment {
+                    text: segment.text[range.clone()].to_string(),
+                    style: segment.style.clone(),
+                    source: segment.sub_source(range),
+                    escapes: None,
+                }
+            })
+            .collect();
+        StyledText { segments }
+    }
+
+    /// Reflows the styled text so no line is wider than `width` display columns.
+    ///
+    /// Lines are broken at whitespace where possible, and the whitespace at a break is
+    /// dropped; words longer than `width` are broken between graphemes. Styles carry over
+    /// to the continued lines, and existing line breaks are kept.
+    #[must_use]
+    pub fn wrap(&self, width: usize) -> StyledText {
+        let width = width.max(1);
+        let newline = || Segment {
+            text: "\n".to_string(),
+            style: Style::default(),
+            source: None,
+            escapes: None,
+        };
+
+        let mut segments = Vec::new();
+        for (line_idx, line) in self.split_lines().iter().enumerate() {
+            if line_idx > 0 {
+                segments.push(newline());
+            }
+            for (row_idx, row) in wrap_line(line, width).into_iter().enumerate() {
+                if row_idx > 0 {
+                    segments.push(newline());
+                }
+                segments.extend(row.into_iter().map(|(grapheme, style, _)| Segment {
+                    text: grapheme.to_string(),
+                    style: style.clone(),
+                    source: None,
+                    escapes: None,
+                }));
+            }
+        }
+        if self.to_plain_text().ends_with('\n') {
+            segments.push(newline());
+        }
+        StyledText { segments }.normalize()
+    }
+}
+
+/// Breaks a single line into rows of styled graphemes, with their widths, no wider than
+/// `width`.
+///
+/// The parts of a grapheme cluster split between segments stay on the same row.
+fn wrap_line(line: &StyledText, width: usize) -> Vec<Vec<(&str, &Style, usize)>> {
+    let is_whitespace = |grapheme: &str| grapheme.chars().all(char::is_whitespace);
+
+    let mut rows = vec![Vec::new()];
+    let mut row_width = 0;
+    // Where the current row can be broken: just after its last whitespace
+    let mut break_at = None;
+    for StyledGrapheme {
+        text: grapheme,
+        style,
+        width: width_of,
+        joins_previous,
+        ..
+    } in line.styled_graphemes()
+    {
+        let row: &mut Vec<(&str, &Style, usize)> = rows.last_mut().expect("rows is never empty");
+        if joins_previous && !row.is_empty() {
+            row.push((grapheme, style, width_of));
+            row_width += width_of;
+            continue;
+        }
+        let whitespace = is_whitespace(grapheme);
+
+        if row_width + width_of > width && !row.is_empty() {
+            let carried = if whitespace {
+                Vec::new()
+            } else {
+                break_at.map_or_else(Vec::new, |idx| row.split_off(idx))
+            };
+            while row
+                .last()
+                .is_some_and(|(grapheme, ..)| is_whitespace(grapheme))
+            {
+                row.pop();
+            }
+            row_width = carried.iter().map(|(.., width)| width).sum();
+            rows.push(carried);
+            break_at = None;
+        }
+
+        let continued = rows.len() > 1;
+        let row = rows.last_mut().expect("rows is never empty");
+        if whitespace && row.is_empty() && continued {
+            // Whitespace at a break is dropped
+            continue;
+        }
+        row.push((grapheme, style, width_of));
+        row_width += width_of;
+        if whitespace {
+            break_at = Some(row.len());
+        }
+    }
+    rows
+}
+
+/// Returns the display width of a single line of text.
+pub(crate) fn str_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Returns the display width of a single grapheme cluster.
+pub(crate) fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Style;
+
+    fn segment(text: &str) -> Segment {
+        Segment {
+            text: text.to_string(),
+            style: Style::default(),
+            source: None,
+            escapes: None,
+        }
+    }
+
+    #[test]
+    fn test_width_ascii() {
+        assert_eq!(segment("Hello").width(), 5);
+    }
+
+    #[test]
+    fn test_width_cjk() {
+        assert_eq!(segment("日本語").width(), 6);
+    }
+
+    #[test]
+    fn test_width_combining() {
+        assert_eq!(segment("e\u{301}").width(), 1);
+    }
+
+    #[test]
+    fn test_wrap_at_whitespace() {
+        let styled_text =
+            crate::parse_ansi("one \x1b[31mtwo three\x1b[0m four\n\nsupercalifragilistic\n");
+        let wrapped = styled_text.wrap(9);
+        assert_eq!(
+            wrapped.to_plain_text(),
+            "one two\nthree\nfour\n\nsupercali\nfragilist\nic\n"
+        );
+        // The style of "two three" carries over the break
+        let red: Vec<&str> = wrapped
+            .segments
+            .iter()
+            .filter(|segment| segment.style.fg_color.is_some())
+            .map(|segment| segment.text.as_str())
+            .collect();
+        assert_eq!(red, vec!["two", "three"]);
+    }
+
+    #[test]
+    fn test_wrap_wide_chars() {
+        let styled_text = StyledText {
+            segments: vec![segment("日本語")],
+        };
+        assert_eq!(styled_text.wrap(5).to_plain_text(), "日本\n語");
+    }
+
+    #[test]
+    fn test_truncate_fits() {
+        let styled_text = StyledText {
+            segments: vec![segment("Hello")],
+        };
+        assert_eq!(styled_text.truncate(5, Some("…")), styled_text);
+    }
+
+    #[test]
+    fn test_truncate_with_ellipsis() {
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        let styled_text = StyledText {
+            segments: vec![
+                segment("Hel"),
+                Segment {
+                    text: "lo World".to_string(),
+                    style: bold.clone(),
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        let expected = StyledText {
+            segments: vec![
+                segment("Hel"),
+                Segment {
+                    text: "lo…".to_string(),
+                    style: bold,
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        assert_eq!(styled_text.truncate(6, Some("…")).normalize(), expected);
+    }
+
+    #[test]
+    fn test_truncate_does_not_split_wide_chars() {
+        let styled_text = StyledText {
+            segments: vec![segment("日本語")],
+        };
+        let truncated = styled_text.truncate(3, None);
+        assert_eq!(truncated.segments, vec![segment("日")]);
+        assert_eq!(truncated.width(), 2);
+    }
+
+    #[test]
+    fn test_slice_columns_splits_segments() {
+        let bold = Style {
+            bold: true,
+            ..Default::default()
+        };
+        let styled_text = StyledText {
+            segments: vec![
+                segment("abc"),
+                Segment {
+                    text: "def".to_string(),
+                    style: bold.clone(),
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        let expected = StyledText {
+            segments: vec![
+                segment("bc"),
+                Segment {
+                    text: "d".to_string(),
+                    style: bold,
+                    source: None,
+                    escapes: None,
+                },
+            ],
+        };
+        assert_eq!(styled_text.slice_columns(1..4), expected);
+    }
+
+    #[test]
+    fn test_slice_columns_drops_straddling_wide_chars() {
+        let styled_text = StyledText {
+            segments: vec![segment("a日本b")],
+        };
+        assert_eq!(
+            styled_text.slice_columns(2..6).segments,
+            vec![segment("本b")]
+        );
+    }
+
+    #[test]
+    fn test_split_emoji_clusters() {
+        // A thumbs up with its skin tone in another color, then a flag
+        let styled_text = crate::parse_ansi("a\x1b[33m👍\x1b[35m🏽\x1b[0m🇺🇸b");
+        assert_eq!(styled_text.width(), 6);
+        assert_eq!(styled_text.slice_columns(1..3).to_plain_text(), "👍🏽");
+        assert_eq!(styled_text.slice_columns(2..5).to_plain_text(), "🇺🇸");
+        assert_eq!(styled_text.wrap(3).to_plain_text(), "a👍🏽\n🇺🇸b");
+    }
+
+    #[test]
+    fn test_width_widest_line() {
+        let styled_text = StyledText {
+            segments: vec![segment("ab\n"), segment("日本"), segment("c")],
+        };
+        assert_eq!(styled_text.width(), 5);
+    }
+}